@@ -0,0 +1,46 @@
+//! 文件列表缓存加载性能：JSON 与二进制格式对比（10 万条目）
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::collections::HashMap;
+use synctools_lib::core::{CacheFormat, FileListCache};
+use synctools_lib::storage::FileInfo;
+
+const ENTRY_COUNT: usize = 100_000;
+
+fn build_tree() -> HashMap<String, FileInfo> {
+    (0..ENTRY_COUNT)
+        .map(|i| {
+            let path = format!("photos/{}/{}/IMG_{:06}.jpg", i % 100, i % 37, i);
+            let info = FileInfo {
+                path: path.clone(),
+                size: (i as u64) * 1024,
+                modified_time: 1_700_000_000 + i as i64,
+                is_dir: false,
+                checksum: Some(format!("{:032x}", i)),
+                hidden: false,
+            };
+            (path, info)
+        })
+        .collect()
+}
+
+fn bench_cache_load(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("synctools-bench-{}", std::process::id()));
+    let tree = build_tree();
+
+    let mut group = c.benchmark_group("cache_load_100k");
+    group.sample_size(10);
+    for (name, format) in [("json", CacheFormat::Json), ("binary", CacheFormat::Binary)] {
+        let cache = FileListCache::new(dir.clone()).with_format(format);
+        cache.save(name, "source", "cfg", &tree).unwrap();
+        group.bench_function(name, |b| {
+            b.iter(|| cache.load(name, "source", "cfg").unwrap());
+        });
+    }
+    group.finish();
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+criterion_group!(benches, bench_cache_load);
+criterion_main!(benches);
@@ -0,0 +1,11 @@
+-- 本地文件校验和缓存（大小和修改时间未变化时复用，避免重复计算）
+CREATE TABLE IF NOT EXISTS file_checksums (
+    job_id TEXT NOT NULL,
+    side TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    file_size INTEGER NOT NULL,
+    modified_time INTEGER NOT NULL,
+    checksum TEXT NOT NULL,
+    PRIMARY KEY (job_id, side, file_path),
+    FOREIGN KEY (job_id) REFERENCES sync_jobs(id) ON DELETE CASCADE
+);
@@ -0,0 +1,4 @@
+-- 任务串联：当前任务在 run_after 指定的任务完成后自动执行
+ALTER TABLE sync_jobs ADD COLUMN run_after TEXT;
+-- 前置任务失败时是否仍然执行
+ALTER TABLE sync_jobs ADD COLUMN run_after_always BOOLEAN DEFAULT 0 NOT NULL;
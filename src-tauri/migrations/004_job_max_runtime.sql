@@ -0,0 +1,2 @@
+-- 任务最长运行时间（秒），超时后自动取消
+ALTER TABLE sync_jobs ADD COLUMN max_runtime_secs INTEGER;
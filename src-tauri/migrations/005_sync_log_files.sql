@@ -0,0 +1,10 @@
+-- 同步失败文件记录（用于只重试失败的文件）
+CREATE TABLE IF NOT EXISTS sync_log_files (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    log_id INTEGER NOT NULL,
+    file_path TEXT NOT NULL,
+    error_message TEXT,
+    FOREIGN KEY (log_id) REFERENCES sync_logs(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_log_files_log ON sync_log_files(log_id);
@@ -0,0 +1,2 @@
+-- 附加源（JSON 数组），挂载到主源命名空间的子路径下合并同步
+ALTER TABLE sync_jobs ADD COLUMN additional_sources TEXT;
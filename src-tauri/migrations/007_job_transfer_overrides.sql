@@ -0,0 +1,3 @@
+-- 任务级传输参数（MB），为空时使用全局传输配置
+ALTER TABLE sync_jobs ADD COLUMN chunk_size_mb INTEGER;
+ALTER TABLE sync_jobs ADD COLUMN stream_threshold_mb INTEGER;
@@ -0,0 +1,3 @@
+-- 任务级排除规则：预设名称和自定义规则（JSON 数组），为空时使用 code 预设
+ALTER TABLE sync_jobs ADD COLUMN exclude_preset TEXT;
+ALTER TABLE sync_jobs ADD COLUMN exclude_patterns TEXT;
@@ -0,0 +1,2 @@
+-- 仅追加模式：目标文件是源文件的前缀时只上传新增部分
+ALTER TABLE sync_jobs ADD COLUMN append_only BOOLEAN DEFAULT 0 NOT NULL;
@@ -0,0 +1,3 @@
+-- 双向同步按方向记录复制的文件数（旧记录为 NULL）
+ALTER TABLE sync_logs ADD COLUMN files_copied_to_source INTEGER;
+ALTER TABLE sync_logs ADD COLUMN files_copied_to_dest INTEGER;
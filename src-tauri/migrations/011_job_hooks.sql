@@ -0,0 +1,3 @@
+-- 任务钩子：同步前后执行的命令
+ALTER TABLE sync_jobs ADD COLUMN pre_command TEXT;
+ALTER TABLE sync_jobs ADD COLUMN post_command TEXT;
@@ -0,0 +1,2 @@
+-- 两边都是 S3 兼容存储时直接比较 ETag
+ALTER TABLE sync_jobs ADD COLUMN compare_etags BOOLEAN DEFAULT 0 NOT NULL;
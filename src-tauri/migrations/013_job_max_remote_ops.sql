@@ -0,0 +1,2 @@
+-- 扫描和传输共享的远程操作并发上限，为空时不限制
+ALTER TABLE sync_jobs ADD COLUMN max_remote_ops INTEGER;
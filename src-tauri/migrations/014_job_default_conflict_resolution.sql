@@ -0,0 +1,2 @@
+-- 冲突的默认解决方式（keep_source / keep_dest / keep_both / skip），为空时需要手动处理
+ALTER TABLE sync_jobs ADD COLUMN default_conflict_resolution TEXT;
@@ -0,0 +1,3 @@
+-- 任务级读取/写入并发数，为空时使用传输并发数
+ALTER TABLE sync_jobs ADD COLUMN read_concurrency INTEGER;
+ALTER TABLE sync_jobs ADD COLUMN write_concurrency INTEGER;
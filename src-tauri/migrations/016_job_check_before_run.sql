@@ -0,0 +1,2 @@
+-- 定时触发时先检查存储连接，失败时稍后重试而不是直接记录失败
+ALTER TABLE sync_jobs ADD COLUMN check_before_run BOOLEAN DEFAULT 0 NOT NULL;
@@ -0,0 +1,2 @@
+-- 目标路径大小写转换（preserve / lower / upper），为空时保持原样
+ALTER TABLE sync_jobs ADD COLUMN case_fold TEXT;
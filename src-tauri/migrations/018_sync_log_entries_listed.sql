@@ -0,0 +1,2 @@
+-- 本次同步扫描时列出的原始条目数（过滤前，用于估计下次扫描的剩余时间；有一侧未扫描时为 NULL）
+ALTER TABLE sync_logs ADD COLUMN entries_listed INTEGER;
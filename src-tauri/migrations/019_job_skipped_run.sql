@@ -0,0 +1,2 @@
+-- 被跳过的定时触发时间（Unix 时间戳，秒），重启后继续跳过
+ALTER TABLE sync_jobs ADD COLUMN skipped_run_at INTEGER;
@@ -0,0 +1,183 @@
+//! 存储性能测试命令
+
+use crate::db::StorageConfig;
+use futures::stream;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// 默认测试数据大小（MB）
+const DEFAULT_BENCHMARK_SIZE_MB: u64 = 16;
+/// 测试数据大小上限（MB）
+const MAX_BENCHMARK_SIZE_MB: u64 = 1024;
+/// 上传时每块的大小
+const BENCHMARK_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+/// 测试文件所在目录名前缀（目录名附加随机后缀，避免与已有文件冲突）
+const BENCHMARK_DIR_PREFIX: &str = ".synctools-benchmark-";
+
+/// 存储性能测试结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkResult {
+    pub storage: String,
+    pub size_bytes: u64,
+    /// 列出测试目录的耗时（毫秒）
+    pub list_ms: u64,
+    pub upload_ms: u64,
+    /// 上传速度（MB/s）
+    pub upload_mbps: f64,
+    pub download_ms: u64,
+    /// 下载速度（MB/s）
+    pub download_mbps: f64,
+    pub delete_ms: u64,
+}
+
+fn mbps(bytes: u64, elapsed: Duration) -> f64 {
+    bytes as f64 / 1024.0 / 1024.0 / elapsed.as_secs_f64().max(0.001)
+}
+
+/// 按块生成不可压缩的测试数据（BLAKE3 XOF 输出），相同种子得到相同内容
+///
+/// 上传和校验下载时各自重新生成，测试文件不需要整个放在内存中
+fn benchmark_chunks(seed: &str, size: u64) -> impl Iterator<Item = Vec<u8>> + Send {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(seed.as_bytes());
+    let mut reader = hasher.finalize_xof();
+    let mut remaining = size;
+    std::iter::from_fn(move || {
+        if remaining == 0 {
+            return None;
+        }
+        let len = remaining.min(BENCHMARK_CHUNK_SIZE as u64) as usize;
+        remaining -= len as u64;
+        let mut chunk = vec![0u8; len];
+        reader.fill(&mut chunk);
+        Some(chunk)
+    })
+}
+
+/// 测试存储的吞吐量：上传、列出、下载并删除一个随机内容的测试文件
+///
+/// 测试文件写入存储根目录下的临时目录，结束后（包括失败时）尽量删除
+#[tauri::command]
+pub async fn benchmark_storage(
+    config: StorageConfig,
+    size_mb: Option<u64>,
+) -> Result<BenchmarkResult, String> {
+    if config.typ.is_read_only() {
+        return Err("只读存储无法测试上传".to_string());
+    }
+    let size_mb = size_mb
+        .unwrap_or(DEFAULT_BENCHMARK_SIZE_MB)
+        .clamp(1, MAX_BENCHMARK_SIZE_MB);
+    let size = size_mb * 1024 * 1024;
+
+    let storage = crate::storage::create_storage(&config)
+        .await
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let dir = format!("{}{}/", BENCHMARK_DIR_PREFIX, &id[..8]);
+    let path = format!("{}data.bin", dir);
+    tracing::info!("开始存储性能测试: {} ({} MB)", storage.name(), size_mb);
+
+    let result = async {
+        storage
+            .create_dir(&dir)
+            .await
+            .map_err(|e| format!("创建测试目录失败: {}", e))?;
+
+        let chunks = benchmark_chunks(&id, size).map(anyhow::Ok);
+        let start = Instant::now();
+        storage
+            .write_stream(&path, Box::pin(stream::iter(chunks)), Some(size))
+            .await
+            .map_err(|e| format!("上传失败: {}", e))?;
+        let upload = start.elapsed();
+
+        let start = Instant::now();
+        storage
+            .list_files(Some(&dir))
+            .await
+            .map_err(|e| format!("列出目录失败: {}", e))?;
+        let list = start.elapsed();
+
+        // 按块下载并与重新生成的数据比较，只统计下载耗时
+        let mut download = Duration::ZERO;
+        let mut offset = 0u64;
+        for expected in benchmark_chunks(&id, size) {
+            let start = Instant::now();
+            let chunk = storage
+                .read_range(&path, offset, expected.len() as u64)
+                .await
+                .map_err(|e| format!("下载失败: {}", e))?;
+            download += start.elapsed();
+            if chunk != expected {
+                return Err(format!(
+                    "下载的内容与上传的不一致（偏移 {}，{} / {} 字节）",
+                    offset,
+                    chunk.len(),
+                    expected.len()
+                ));
+            }
+            offset += expected.len() as u64;
+        }
+
+        Ok((upload, list, download))
+    }
+    .await;
+
+    let start = Instant::now();
+    let cleanup = async {
+        storage.delete(&path).await?;
+        storage.delete(&dir).await
+    }
+    .await;
+    let delete = start.elapsed();
+    if let Err(e) = &cleanup {
+        tracing::warn!("删除测试文件失败: {} - {}", path, e);
+    }
+
+    let (upload, list, download) = result?;
+    cleanup.map_err(|e| format!("删除测试文件失败: {}", e))?;
+
+    let result = BenchmarkResult {
+        storage: storage.name().to_string(),
+        size_bytes: size,
+        list_ms: list.as_millis() as u64,
+        upload_ms: upload.as_millis() as u64,
+        upload_mbps: mbps(size, upload),
+        download_ms: download.as_millis() as u64,
+        download_mbps: mbps(size, download),
+        delete_ms: delete.as_millis() as u64,
+    };
+    tracing::info!(
+        "存储性能测试完成: {} 上传 {:.2} MB/s, 下载 {:.2} MB/s, 列出 {} ms",
+        result.storage,
+        result.upload_mbps,
+        result.download_mbps,
+        result.list_ms
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_benchmark_chunks_match_single_output() {
+        let size = BENCHMARK_CHUNK_SIZE as u64 * 2 + 123;
+        let chunks: Vec<_> = benchmark_chunks("seed", size).collect();
+        assert_eq!(
+            chunks.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![BENCHMARK_CHUNK_SIZE, BENCHMARK_CHUNK_SIZE, 123]
+        );
+
+        // 分块生成与一次生成的内容相同
+        let mut whole = vec![0u8; size as usize];
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(b"seed");
+        hasher.finalize_xof().fill(&mut whole);
+        assert_eq!(chunks.concat(), whole);
+    }
+}
@@ -1,28 +1,100 @@
 //! 缓存相关命令
 
-use crate::config::CacheConfig;
+use crate::config::{CacheConfig, TransferConfig};
+use crate::core::{CacheStatus, FileListCache, FileStateManager, TransferManager};
+use crate::db::SyncJob;
 use crate::AppState;
+use serde::Serialize;
 use tauri::State;
 
+/// 孤立数据清理结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcReport {
+    /// 已删除任务残留的缓存文件所属任务 ID
+    pub cache_job_ids: Vec<String>,
+    pub file_states_removed: u64,
+    pub transfer_states_removed: u64,
+    /// 删除的遗留传输临时文件数
+    pub temp_files_removed: usize,
+}
+
 /// 获取缓存配置
 #[tauri::command]
 pub async fn get_cache_config(state: State<'_, AppState>) -> Result<CacheConfig, String> {
-    Ok(CacheConfig::load(&state.config_dir))
+    Ok(CacheConfig::load(&state.config_dir()))
 }
 
 /// 设置缓存配置
 #[tauri::command]
 pub async fn set_cache_config(
     remote_ttl: Option<u64>,
+    format: Option<crate::core::CacheFormat>,
     state: State<'_, AppState>,
 ) -> Result<CacheConfig, String> {
-    let mut config = CacheConfig::load(&state.config_dir);
+    let mut config = CacheConfig::load(&state.config_dir());
     
     if let Some(ttl) = remote_ttl {
         config.remote_ttl = ttl;
     }
+    if let Some(f) = format {
+        config.format = f;
+    }
     
-    config.save(&state.config_dir).map_err(|e| e.to_string())?;
+    config.save(&state.config_dir()).map_err(|e| e.to_string())?;
     
     Ok(config)
 }
+
+/// 获取缓存目录状态（目录不可写时缓存被禁用）
+#[tauri::command]
+pub async fn get_cache_status(state: State<'_, AppState>) -> Result<CacheStatus, String> {
+    Ok(FileListCache::status(&state.config_dir().join("cache")))
+}
+
+/// 清理任务已不存在的扫描缓存文件和同步状态记录
+#[tauri::command]
+pub async fn gc_orphans(state: State<'_, AppState>) -> Result<GcReport, String> {
+    let db = state.db();
+    let job_ids = SyncJob::load_ids(&db).await.map_err(|e| e.to_string())?;
+
+    let cache = FileListCache::new(state.config_dir().join("cache"));
+    let mut cache_job_ids: Vec<String> = cache
+        .cached_job_ids()
+        .into_iter()
+        .filter(|id| !job_ids.contains(id))
+        .collect();
+    cache_job_ids.sort();
+    for id in &cache_job_ids {
+        cache.clear(id, None);
+    }
+
+    let file_states_removed = FileStateManager::new(db.clone())
+        .delete_orphan_states()
+        .await
+        .map_err(|e| e.to_string())?;
+    let orphan_transfers = TransferManager::new(db)
+        .cleanup_orphans()
+        .await
+        .map_err(|e| e.to_string())?;
+    let transfer_states_removed = orphan_transfers.len() as u64;
+    // 孤立传输记录的临时文件直接删除，其余遗留的临时文件按修改时间清理
+    let naming = TransferConfig::load(&state.config_dir()).temp_file_naming();
+    let temp_files_removed = naming.remove_temp_files(&orphan_transfers)
+        + super::transfer::sweep_temp_files(&state).await;
+
+    tracing::info!(
+        "已清理孤立数据: {} 个任务的缓存, {} 条文件状态, {} 条传输记录, {} 个临时文件",
+        cache_job_ids.len(),
+        file_states_removed,
+        transfer_states_removed,
+        temp_files_removed
+    );
+
+    Ok(GcReport {
+        cache_job_ids,
+        file_states_removed,
+        transfer_states_removed,
+        temp_files_removed,
+    })
+}
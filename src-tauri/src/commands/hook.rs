@@ -0,0 +1,36 @@
+//! 任务钩子配置相关命令
+
+use crate::config::HookConfig;
+use crate::AppState;
+use tauri::State;
+
+/// 钩子命令超时时间的上限（秒）
+const MAX_HOOK_TIMEOUT_SECS: u64 = 24 * 3600;
+
+/// 获取钩子配置
+#[tauri::command]
+pub async fn get_hook_config(state: State<'_, AppState>) -> Result<HookConfig, String> {
+    Ok(HookConfig::load(&state.config_dir()))
+}
+
+/// 设置钩子配置（下次同步生效）
+#[tauri::command]
+pub async fn set_hook_config(
+    allow_hooks: Option<bool>,
+    timeout_secs: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<HookConfig, String> {
+    let mut config = HookConfig::load(&state.config_dir());
+
+    if let Some(allow) = allow_hooks {
+        config.allow_hooks = allow;
+    }
+    if let Some(secs) = timeout_secs {
+        config.timeout_secs = secs.clamp(1, MAX_HOOK_TIMEOUT_SECS);
+    }
+
+    config.save(&state.config_dir()).map_err(|e| e.to_string())?;
+    tracing::info!("任务钩子: 允许={}, 超时={}秒", config.allow_hooks, config.timeout_secs);
+
+    Ok(config)
+}
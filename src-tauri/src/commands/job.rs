@@ -1,29 +1,93 @@
 #![allow(non_snake_case)]
 #![allow(clippy::too_many_arguments)]
 
-use crate::db::{StorageConfig, SyncJob, SyncMode};
+use crate::core::{
+    scheduler, CaseFold, ConflictResolution, ExcludePreset, FileStateManager, TransferManager,
+};
+use crate::db::{AdditionalSource, StorageConfig, SyncJob, SyncMode};
 use crate::AppState;
 use tauri::State;
 
 /// 解析同步模式
-fn parse_sync_mode(mode: &str) -> Result<SyncMode, String> {
+pub(crate) fn parse_sync_mode(mode: &str) -> Result<SyncMode, String> {
     match mode {
         "bidirectional" => Ok(SyncMode::Bidirectional),
         "mirror" => Ok(SyncMode::Mirror),
         "backup" => Ok(SyncMode::Backup),
+        "seed" => Ok(SyncMode::Seed),
         _ => Err(format!("无效的同步模式: {}", mode)),
     }
 }
 
+/// 解析排除规则预设
+fn parse_exclude_preset(name: &str) -> Result<ExcludePreset, String> {
+    ExcludePreset::parse(name).ok_or_else(|| format!("无效的排除规则预设: {}", name))
+}
+
+/// 解析目标路径大小写转换
+fn parse_case_fold(name: &str) -> Result<CaseFold, String> {
+    CaseFold::parse(name).ok_or_else(|| format!("无效的大小写转换: {}", name))
+}
+
+/// 解析冲突解决方式（空字符串表示不设置默认方式）
+fn parse_conflict_resolution(name: &str) -> Result<Option<ConflictResolution>, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Ok(None);
+    }
+    ConflictResolution::parse(name)
+        .map(Some)
+        .ok_or_else(|| format!("无效的冲突解决方式: {}", name))
+}
+
 /// 解析存储配置
 fn parse_storage_config(config: serde_json::Value, name: &str) -> Result<StorageConfig, String> {
     serde_json::from_value(config).map_err(|e| format!("无效的{}配置: {}", name, e))
 }
 
+/// 解析附加源列表（null 表示没有附加源），挂载路径统一规范化
+fn parse_additional_sources(value: serde_json::Value) -> Result<Vec<AdditionalSource>, String> {
+    let sources: Option<Vec<AdditionalSource>> =
+        serde_json::from_value(value).map_err(|e| format!("无效的附加源配置: {}", e))?;
+    Ok(sources
+        .unwrap_or_default()
+        .into_iter()
+        .map(|s| AdditionalSource {
+            prefix: crate::storage::normalize_path(&s.prefix)
+                .trim_end_matches('/')
+                .to_string(),
+            ..s
+        })
+        .collect())
+}
+
+/// 校验定时表达式（空表达式表示不定时）
+fn validate_schedule_expr(schedule: &Option<String>) -> Result<(), String> {
+    match schedule.as_deref().map(str::trim) {
+        Some(expr) if !expr.is_empty() => scheduler::parse_schedule(expr)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// 钩子命令去除首尾空白，空命令视为未设置
+fn normalize_command(command: Option<String>) -> Option<String> {
+    command
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+}
+
+/// 校验 cron 表达式并返回接下来几次的触发时间（Unix 时间戳，秒）
+#[tauri::command]
+pub async fn validate_schedule(cron: String) -> Result<Vec<i64>, String> {
+    scheduler::next_fire_times(&cron, scheduler::PREVIEW_FIRE_COUNT).map_err(|e| e.to_string())
+}
+
 /// 获取所有同步任务
 #[tauri::command]
 pub async fn get_jobs(state: State<'_, AppState>) -> Result<Vec<SyncJob>, String> {
-    SyncJob::load_all(&state.db).await.map_err(|e| e.to_string())
+    SyncJob::load_all(&state.db()).await.map_err(|e| e.to_string())
 }
 
 /// 创建新的同步任务
@@ -34,14 +98,66 @@ pub async fn create_job(
     destConfig: serde_json::Value,
     syncMode: String,
     schedule: Option<String>,
+    runAfter: Option<String>,
+    runAfterAlways: Option<bool>,
+    maxRuntimeSecs: Option<u64>,
+    additionalSources: Option<serde_json::Value>,
+    chunkSizeMb: Option<u64>,
+    streamThresholdMb: Option<u64>,
+    excludePreset: Option<String>,
+    excludePatterns: Option<Vec<String>>,
+    appendOnly: Option<bool>,
+    preCommand: Option<String>,
+    postCommand: Option<String>,
+    compareEtags: Option<bool>,
+    maxRemoteOps: Option<u32>,
+    defaultConflictResolution: Option<String>,
+    readConcurrency: Option<u32>,
+    writeConcurrency: Option<u32>,
+    checkBeforeRun: Option<bool>,
+    caseFold: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<SyncJob, String> {
     let source = parse_storage_config(sourceConfig, "源存储")?;
     let dest = parse_storage_config(destConfig, "目标存储")?;
     let mode = parse_sync_mode(&syncMode)?;
+    validate_schedule_expr(&schedule)?;
 
-    let job = SyncJob::new(name, source, dest, mode, schedule);
-    job.save(&state.db).await.map_err(|e| e.to_string())?;
+    let mut job = SyncJob::new(name, source, dest, mode, schedule);
+    if let Some(prev) = &runAfter {
+        SyncJob::validate_run_after(&state.db(), &job.id, prev)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    job.runAfter = runAfter;
+    job.runAfterAlways = runAfterAlways.unwrap_or(false);
+    job.maxRuntimeSecs = maxRuntimeSecs.filter(|s| *s > 0);
+    if let Some(sources) = additionalSources {
+        job.additionalSources = parse_additional_sources(sources)?;
+    }
+    job.chunkSizeMb = chunkSizeMb.filter(|v| *v > 0);
+    job.streamThresholdMb = streamThresholdMb.filter(|v| *v > 0);
+    if let Some(preset) = &excludePreset {
+        job.excludePreset = parse_exclude_preset(preset)?;
+    }
+    job.excludePatterns = excludePatterns.unwrap_or_default();
+    job.appendOnly = appendOnly.unwrap_or(false);
+    job.preCommand = normalize_command(preCommand);
+    job.postCommand = normalize_command(postCommand);
+    job.compareEtags = compareEtags.unwrap_or(false);
+    job.maxRemoteOps = maxRemoteOps.filter(|v| *v > 0);
+    if let Some(r) = &defaultConflictResolution {
+        job.defaultConflictResolution = parse_conflict_resolution(r)?;
+    }
+    job.readConcurrency = readConcurrency.filter(|v| *v > 0);
+    job.writeConcurrency = writeConcurrency.filter(|v| *v > 0);
+    job.checkBeforeRun = checkBeforeRun.unwrap_or(false);
+    if let Some(fold) = &caseFold {
+        job.caseFold = parse_case_fold(fold)?;
+    }
+    job.validate_storage_roles().map_err(|e| e.to_string())?;
+    job.save(&state.db()).await.map_err(|e| e.to_string())?;
+    state.scheduler.update_job(&job);
 
     Ok(job)
 }
@@ -56,9 +172,27 @@ pub async fn update_job(
     syncMode: Option<String>,
     schedule: Option<Option<String>>,
     enabled: Option<bool>,
+    runAfter: Option<Option<String>>,
+    runAfterAlways: Option<bool>,
+    maxRuntimeSecs: Option<Option<u64>>,
+    additionalSources: Option<serde_json::Value>,
+    chunkSizeMb: Option<Option<u64>>,
+    streamThresholdMb: Option<Option<u64>>,
+    excludePreset: Option<String>,
+    excludePatterns: Option<Vec<String>>,
+    appendOnly: Option<bool>,
+    preCommand: Option<Option<String>>,
+    postCommand: Option<Option<String>>,
+    compareEtags: Option<bool>,
+    maxRemoteOps: Option<Option<u32>>,
+    defaultConflictResolution: Option<Option<String>>,
+    readConcurrency: Option<Option<u32>>,
+    writeConcurrency: Option<Option<u32>>,
+    checkBeforeRun: Option<bool>,
+    caseFold: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<SyncJob, String> {
-    let mut job = SyncJob::load(&state.db, &id)
+    let mut job = SyncJob::load(&state.db(), &id)
         .await
         .map_err(|e| e.to_string())?
         .ok_or_else(|| format!("任务不存在: {}", id))?;
@@ -76,14 +210,101 @@ pub async fn update_job(
         job.syncMode = parse_sync_mode(&sm)?;
     }
     if let Some(s) = schedule {
+        validate_schedule_expr(&s)?;
         job.schedule = s;
     }
     if let Some(e) = enabled {
         job.enabled = e;
     }
+    if let Some(r) = runAfter {
+        if let Some(prev) = &r {
+            SyncJob::validate_run_after(&state.db(), &job.id, prev)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        job.runAfter = r;
+    }
+    if let Some(always) = runAfterAlways {
+        job.runAfterAlways = always;
+    }
+    if let Some(m) = maxRuntimeSecs {
+        job.maxRuntimeSecs = m.filter(|s| *s > 0);
+    }
+    if let Some(sources) = additionalSources {
+        job.additionalSources = parse_additional_sources(sources)?;
+    }
+    if let Some(c) = chunkSizeMb {
+        job.chunkSizeMb = c.filter(|v| *v > 0);
+    }
+    if let Some(t) = streamThresholdMb {
+        job.streamThresholdMb = t.filter(|v| *v > 0);
+    }
+    let old_excludes = (job.excludePreset, job.excludePatterns.clone());
+    if let Some(preset) = &excludePreset {
+        job.excludePreset = parse_exclude_preset(preset)?;
+    }
+    if let Some(patterns) = excludePatterns {
+        job.excludePatterns = patterns;
+    }
+    if let Some(a) = appendOnly {
+        job.appendOnly = a;
+    }
+    if let Some(c) = preCommand {
+        job.preCommand = normalize_command(c);
+    }
+    if let Some(c) = postCommand {
+        job.postCommand = normalize_command(c);
+    }
+    if let Some(e) = compareEtags {
+        job.compareEtags = e;
+    }
+    if let Some(m) = maxRemoteOps {
+        job.maxRemoteOps = m.filter(|v| *v > 0);
+    }
+    if let Some(r) = defaultConflictResolution {
+        job.defaultConflictResolution = match r {
+            Some(name) => parse_conflict_resolution(&name)?,
+            None => None,
+        };
+    }
+    if let Some(c) = readConcurrency {
+        job.readConcurrency = c.filter(|v| *v > 0);
+    }
+    if let Some(c) = writeConcurrency {
+        job.writeConcurrency = c.filter(|v| *v > 0);
+    }
+    if let Some(c) = checkBeforeRun {
+        job.checkBeforeRun = c;
+    }
+    if let Some(fold) = &caseFold {
+        job.caseFold = parse_case_fold(fold)?;
+    }
+    job.validate_storage_roles().map_err(|e| e.to_string())?;
     job.updatedAt = chrono::Utc::now().timestamp();
 
-    job.save(&state.db).await.map_err(|e| e.to_string())?;
+    job.save(&state.db()).await.map_err(|e| e.to_string())?;
+    // 缓存的文件列表按旧的排除规则过滤过，规则变化后需要重新扫描
+    if old_excludes != (job.excludePreset, job.excludePatterns.clone()) {
+        crate::core::FileListCache::new(state.config_dir().join("cache")).clear(&job.id, None);
+    }
+    // 启用状态或定时表达式变化时重新调度
+    state.scheduler.update_job(&job);
+
+    Ok(job)
+}
+
+/// 切换任务的启用状态（禁用后同时暂停定时调度）
+#[tauri::command]
+pub async fn toggle_job(id: String, state: State<'_, AppState>) -> Result<SyncJob, String> {
+    let mut job = SyncJob::load(&state.db(), &id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("任务不存在: {}", id))?;
+
+    job.enabled = !job.enabled;
+    job.updatedAt = chrono::Utc::now().timestamp();
+    job.save(&state.db()).await.map_err(|e| e.to_string())?;
+    state.scheduler.update_job(&job);
 
     Ok(job)
 }
@@ -91,18 +312,56 @@ pub async fn update_job(
 /// 删除同步任务
 #[tauri::command]
 pub async fn delete_job(id: String, state: State<'_, AppState>) -> Result<(), String> {
-    SyncJob::delete(&state.db, &id)
+    SyncJob::delete(&state.db(), &id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    state.scheduler.remove_job(&id);
+
+    // 清理任务的扫描缓存和同步状态（失败只记录日志，可稍后通过 gc_orphans 清理）
+    crate::core::FileListCache::new(state.config_dir().join("cache")).clear(&id, None);
+    if let Err(e) = FileStateManager::new(state.db()).delete_job_states(&id).await {
+        tracing::warn!("清理任务 {} 的文件状态失败: {}", id, e);
+    }
+    match TransferManager::new(state.db()).cleanup_all(&id).await {
+        Ok(transfer_ids) => {
+            crate::config::TransferConfig::load(&state.config_dir())
+                .temp_file_naming()
+                .remove_temp_files(&transfer_ids);
+        }
+        Err(e) => tracing::warn!("清理任务 {} 的传输记录失败: {}", id, e),
+    }
+    Ok(())
 }
 
 /// 获取数据存储路径
 #[tauri::command]
 pub async fn get_data_path(state: State<'_, AppState>) -> Result<String, String> {
-    Ok(state.config_dir.to_string_lossy().to_string())
+    Ok(state.config_dir().to_string_lossy().to_string())
+}
+
+/// 打开数据库并执行完整性检查
+async fn open_verified_db(data_dir: &std::path::Path) -> anyhow::Result<sqlx::SqlitePool> {
+    let pool = AppState::open_db(data_dir).await?;
+    let result: String = match sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_one(&pool)
+        .await
+    {
+        Ok(r) => r,
+        Err(e) => {
+            pool.close().await;
+            return Err(e.into());
+        }
+    };
+    if result != "ok" {
+        pool.close().await;
+        return Err(anyhow::anyhow!("完整性检查未通过: {}", result));
+    }
+    Ok(pool)
 }
 
 /// 设置数据存储路径并迁移数据
+///
+/// 迁移期间不允许开始同步、分析等操作；复制并校验成功后才切换到新的数据库，之后关闭旧连接池
 #[tauri::command]
 pub async fn set_data_path(path: String, state: State<'_, AppState>) -> Result<String, String> {
     use std::path::PathBuf;
@@ -116,16 +375,33 @@ pub async fn set_data_path(path: String, state: State<'_, AppState>) -> Result<S
         return Err("指定的路径不是目录".to_string());
     }
     
-    let old_path = &state.config_dir;
+    let old_path = state.config_dir();
     
     // 如果路径相同，不需要迁移
-    if old_path == &new_path {
+    if old_path == new_path {
         return Ok("路径未改变".to_string());
     }
     
+    // 有同步、分析、校验等任务进行时不迁移；迁移期间新任务（包括定时任务）无法开始
+    let _migration = state.begin_migration()?;
+    
+    // 将 WAL 内容写回主库，确保复制的是一致的快照（旧连接池保持可用，切换后再关闭）
+    let old_db = state.db();
+    if let Err(e) = sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(&*old_db)
+        .await
+    {
+        return Err(format!("数据库检查点失败，已取消迁移: {}", e));
+    }
+    
     // 迁移数据文件
     let mut migrated_files = Vec::new();
     let files_to_migrate = ["synctools.db", "synctools.db-shm", "synctools.db-wal"];
+    let rollback = |migrated: &[String]| {
+        for file_name in migrated {
+            let _ = std::fs::remove_file(new_path.join(file_name));
+        }
+    };
     
     for file_name in &files_to_migrate {
         let old_file = old_path.join(file_name);
@@ -134,32 +410,50 @@ pub async fn set_data_path(path: String, state: State<'_, AppState>) -> Result<S
         if old_file.exists() {
             // 复制文件到新位置
             if let Err(e) = std::fs::copy(&old_file, &new_file) {
-                // 回滚已复制的文件
-                for migrated in &migrated_files {
-                    let _ = std::fs::remove_file(new_path.join(migrated));
-                }
+                rollback(&migrated_files);
                 return Err(format!("迁移文件 {} 失败: {}", file_name, e));
             }
             migrated_files.push(file_name.to_string());
         }
     }
     
-    // 获取配置文件路径（始终存在默认位置）
-    let config_file = crate::dirs::config_dir()
-        .map(|p| p.join("synctools").join("config.json"))
-        .ok_or_else(|| "无法获取配置目录".to_string())?;
+    // 打开迁移后的数据库并校验完整性
+    let pool = match open_verified_db(&new_path).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            rollback(&migrated_files);
+            return Err(format!("迁移后的数据库校验失败，已保留原数据: {}", e));
+        }
+    };
     
-    // 确保父目录存在
-    if let Some(parent) = config_file.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
+    // 获取配置文件路径（始终存在默认位置）
+    let config_file = match crate::dirs::config_dir() {
+        Some(dir) => dir.join("synctools").join("config.json"),
+        None => {
+            pool.close().await;
+            rollback(&migrated_files);
+            return Err("无法获取配置目录".to_string());
+        }
+    };
     
-    // 写入配置
+    // 写入配置（确保父目录存在），失败时保持使用原数据目录
     let config = serde_json::json!({
         "data_path": path
     });
-    std::fs::write(&config_file, serde_json::to_string_pretty(&config).unwrap())
-        .map_err(|e| format!("保存配置失败: {}", e))?;
+    let saved = config_file
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&config_file, serde_json::to_string_pretty(&config).unwrap()));
+    if let Err(e) = saved {
+        pool.close().await;
+        rollback(&migrated_files);
+        return Err(format!("保存配置失败: {}", e));
+    }
+    
+    // 数据目录和连接池一起切换，立即生效；旧连接池在切换后关闭
+    let old_db = state.replace_data_dir(new_path.clone(), pool);
+    old_db.close().await;
+    tracing::info!("数据目录已切换到: {}", new_path.display());
     
     // 删除旧文件
     for file_name in &migrated_files {
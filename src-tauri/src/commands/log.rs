@@ -1,13 +1,16 @@
 //! 日志相关命令
 
-use crate::logging::LogConfig;
+use crate::logging::{get_log_file, read_tail_lines, LogConfig};
 use crate::AppState;
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
+
+/// 最多读取的日志行数
+const MAX_TAIL_LINES: usize = 5000;
 
 /// 获取日志配置
 #[tauri::command]
 pub async fn get_log_config(state: State<'_, AppState>) -> Result<LogConfig, String> {
-    Ok(LogConfig::load(&state.config_dir))
+    Ok(LogConfig::load(&state.config_dir()))
 }
 
 /// 设置日志配置
@@ -18,7 +21,7 @@ pub async fn set_log_config(
     level: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<LogConfig, String> {
-    let mut config = LogConfig::load(&state.config_dir);
+    let mut config = LogConfig::load(&state.config_dir());
     
     if let Some(e) = enabled {
         config.enabled = e;
@@ -37,7 +40,65 @@ pub async fn set_log_config(
         }
     }
     
-    config.save(&state.config_dir).map_err(|e| e.to_string())?;
+    config.save(&state.config_dir()).map_err(|e| e.to_string())?;
     
     Ok(config)
 }
+
+/// 获取当前日志文件路径
+#[tauri::command]
+pub async fn get_log_path() -> Result<String, String> {
+    Ok(get_log_file().to_string_lossy().to_string())
+}
+
+/// 在文件管理器中显示日志文件
+#[tauri::command]
+pub async fn reveal_log() -> Result<(), String> {
+    let path = get_log_file();
+    if !path.exists() {
+        return Err("日志文件不存在，请先在设置中启用日志".to_string());
+    }
+    crate::commands::shell::show_in_folder(path.to_string_lossy().to_string()).await
+}
+
+/// 读取日志文件的最后若干行
+#[tauri::command]
+pub async fn read_log_tail(lines: usize) -> Result<Vec<String>, String> {
+    let path = get_log_file();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    read_tail_lines(&path, lines.min(MAX_TAIL_LINES)).map_err(|e| format!("读取日志失败: {}", e))
+}
+
+/// 实时日志是否开启
+#[tauri::command]
+pub async fn get_live_log() -> Result<bool, String> {
+    Ok(crate::logging::is_live_log_enabled())
+}
+
+/// 开启或关闭实时日志（开启后每条日志作为 `log-line` 事件发送到前端）
+#[tauri::command]
+pub async fn set_live_log(enabled: bool) -> Result<bool, String> {
+    crate::logging::set_live_log_enabled(enabled);
+    tracing::info!("实时日志已{}", if enabled { "开启" } else { "关闭" });
+    Ok(enabled)
+}
+
+/// 启动实时日志转发：从日志通道读取日志行，以 `log-line` 事件发送到前端
+pub fn start_live_log(app: &AppHandle) {
+    let Some((writer, mut receiver)) = crate::logging::take_live_log_receiver() else {
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(line) = receiver.recv().await {
+            // 通道满时丢弃的日志行数告知前端
+            let dropped = writer.take_dropped();
+            if dropped > 0 {
+                let _ = app.emit("log-line", format!("... 已丢弃 {} 条日志", dropped));
+            }
+            let _ = app.emit("log-line", line);
+        }
+    });
+}
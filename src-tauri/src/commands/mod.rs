@@ -1,7 +1,13 @@
+pub mod benchmark;
 pub mod cache;
+pub mod hook;
 pub mod job;
 pub mod log;
+pub mod rclone;
+pub mod scanner;
+pub mod scheduler;
 pub mod shell;
+pub mod storage;
 pub mod sync;
 pub mod test;
 pub mod transfer;
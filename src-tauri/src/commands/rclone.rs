@@ -0,0 +1,292 @@
+//! rclone 配置导入命令
+
+use crate::db::StorageConfig;
+use aes::cipher::{KeyIvInit, StreamCipher};
+use base64::Engine;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// rclone obscure 使用的固定密钥（仅防止明文泄露，不是加密）
+const RCLONE_CRYPT_KEY: [u8; 32] = [
+    0x9c, 0x93, 0x5b, 0x48, 0x73, 0x0a, 0x55, 0x4d, 0x6b, 0xfd, 0x7c, 0x63, 0xc8, 0x86, 0xa9, 0x2b,
+    0xd3, 0x90, 0x19, 0x8e, 0xb8, 0x12, 0x8a, 0xfb, 0xf4, 0xde, 0x16, 0x2b, 0x8b, 0x95, 0xf6, 0x38,
+];
+/// 加密的 rclone 配置文件头
+const RCLONE_ENCRYPTED_HEADER: &str = "RCLONE_ENCRYPT_V0:";
+
+type Aes256Ctr = ctr::Ctr128BE<aes::Aes256>;
+
+/// 可导入的 rclone 远程存储
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RcloneRemote {
+    pub name: String,
+    /// rclone 中的类型（s3 / webdav 等）
+    pub remote_type: String,
+    pub config: StorageConfig,
+    /// rclone 配置中没有、创建任务前需要补充的字段
+    pub missing: Vec<String>,
+}
+
+/// 跳过的 rclone 远程存储
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedRemote {
+    pub name: String,
+    pub remote_type: String,
+    pub reason: String,
+}
+
+/// rclone 配置导入结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RcloneImport {
+    /// 实际读取的配置文件路径
+    pub path: String,
+    pub remotes: Vec<RcloneRemote>,
+    pub skipped: Vec<SkippedRemote>,
+}
+
+/// 解析 rclone 配置文件，转换为存储配置供快速创建任务
+///
+/// 未指定路径时按 rclone 的查找顺序使用默认配置文件
+#[tauri::command]
+pub async fn import_rclone_remotes(path: Option<String>) -> Result<RcloneImport, String> {
+    let path = match path.filter(|p| !p.trim().is_empty()) {
+        Some(p) => PathBuf::from(p),
+        None => default_config_path().ok_or_else(|| "未找到 rclone 配置文件".to_string())?,
+    };
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("读取 rclone 配置失败 {}: {}", path.display(), e))?;
+    if content.trim_start().starts_with(RCLONE_ENCRYPTED_HEADER) {
+        return Err("rclone 配置已加密，请先用 rclone config 解除加密".to_string());
+    }
+
+    let mut remotes = Vec::new();
+    let mut skipped = Vec::new();
+    for (name, options) in parse_config(&content) {
+        let remote_type = options.get("type").cloned().unwrap_or_default();
+        match convert_remote(&options) {
+            Ok((config, missing)) => {
+                let config: StorageConfig = serde_json::from_value(config)
+                    .map_err(|e| format!("转换远程存储 {} 失败: {}", name, e))?;
+                remotes.push(RcloneRemote {
+                    name,
+                    remote_type,
+                    config,
+                    missing,
+                });
+            }
+            Err(reason) => {
+                tracing::info!("跳过 rclone 远程存储 {} ({}): {}", name, remote_type, reason);
+                skipped.push(SkippedRemote {
+                    name,
+                    remote_type,
+                    reason,
+                });
+            }
+        }
+    }
+
+    Ok(RcloneImport {
+        path: path.display().to_string(),
+        remotes,
+        skipped,
+    })
+}
+
+/// rclone 默认配置文件路径（RCLONE_CONFIG > 用户配置目录 > ~/.rclone.conf）
+fn default_config_path() -> Option<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Ok(path) = std::env::var("RCLONE_CONFIG") {
+        candidates.push(PathBuf::from(path));
+    }
+    if let Some(dir) = crate::dirs::config_dir() {
+        candidates.push(dir.join("rclone").join("rclone.conf"));
+    }
+    // macOS 上 rclone 同样使用 ~/.config
+    if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        let home = PathBuf::from(home);
+        candidates.push(home.join(".config").join("rclone").join("rclone.conf"));
+        candidates.push(home.join(".rclone.conf"));
+    }
+    candidates.into_iter().find(|p| p.is_file())
+}
+
+/// 解析 INI 格式的 rclone 配置，返回 远程名 -> 选项（保持文件中的顺序）
+fn parse_config(content: &str) -> Vec<(String, BTreeMap<String, String>)> {
+    let mut sections: Vec<(String, BTreeMap<String, String>)> = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.trim().to_string(), BTreeMap::new()));
+            continue;
+        }
+        if let (Some((key, value)), Some((_, options))) = (line.split_once('='), sections.last_mut())
+        {
+            options.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    sections
+}
+
+/// 还原 rclone obscure 处理过的密码（base64url 编码的 IV + AES-256-CTR 密文）
+fn reveal(obscured: &str) -> Option<String> {
+    let data = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(obscured.trim())
+        .ok()?;
+    if data.len() < 16 {
+        return None;
+    }
+    let (iv, ciphertext) = data.split_at(16);
+    let mut plain = ciphertext.to_vec();
+    Aes256Ctr::new(&RCLONE_CRYPT_KEY.into(), iv.into()).apply_keystream(&mut plain);
+    String::from_utf8(plain).ok()
+}
+
+/// 从 rclone 保存的 OAuth 令牌 JSON 中取出刷新令牌
+fn refresh_token(token_json: &str) -> Option<String> {
+    let token: Value = serde_json::from_str(token_json).ok()?;
+    token["refresh_token"].as_str().map(str::to_string)
+}
+
+/// 把一个 rclone 远程存储转换为存储配置 JSON 和缺失字段列表，不支持的类型返回原因
+fn convert_remote(options: &BTreeMap<String, String>) -> Result<(Value, Vec<String>), String> {
+    let get = |key: &str| options.get(key).filter(|v| !v.is_empty()).cloned();
+    let mut config = Map::new();
+    let mut missing = Vec::new();
+    let mut set = |field: &str, value: Option<String>| {
+        if let Some(value) = value {
+            config.insert(field.to_string(), json!(value));
+        }
+    };
+
+    let typ = match options.get("type").map(String::as_str) {
+        Some("s3") => {
+            set("accessKey", get("access_key_id"));
+            set("secretKey", get("secret_access_key"));
+            set("endpoint", get("endpoint"));
+            // rclone 的 bucket 属于远程路径，不在配置中
+            missing.push("bucket".to_string());
+            match get("region") {
+                Some(region) => set("region", Some(region)),
+                None => missing.push("region".to_string()),
+            }
+            "s3"
+        }
+        Some("webdav") => {
+            set("webdavEndpoint", get("url"));
+            set("username", get("user"));
+            if let Some(pass) = get("pass") {
+                match reveal(&pass) {
+                    Some(pass) => set("password", Some(pass)),
+                    None => missing.push("password".to_string()),
+                }
+            }
+            "webdav"
+        }
+        Some("b2") => {
+            set("keyId", get("account"));
+            set("applicationKey", get("key"));
+            missing.push("bucket".to_string());
+            missing.push("bucketId".to_string());
+            "b2"
+        }
+        Some("azureblob") => {
+            set("accountName", get("account"));
+            set("accountKey", get("key"));
+            // SAS URL 形如 https://<account>.blob.core.windows.net/<container>?<sas>
+            if let Some(sas_url) = get("sas_url") {
+                if let Some((_, query)) = sas_url.split_once('?') {
+                    set("sasToken", Some(query.to_string()));
+                }
+            }
+            set("endpoint", get("endpoint"));
+            missing.push("bucket".to_string());
+            "azblob"
+        }
+        Some("google cloud storage") | Some("gcs") => {
+            // 凭据文件用 ${file:...} 引用，不复制内容
+            match (get("service_account_file"), get("service_account_credentials")) {
+                (Some(file), _) => set("serviceAccountJson", Some(format!("${{file:{}}}", file))),
+                (None, Some(json)) => set("serviceAccountJson", Some(json)),
+                (None, None) => {
+                    set("accessKey", get("access_key_id"));
+                    set("secretKey", get("secret_access_key"));
+                    if get("access_key_id").is_none() {
+                        missing.push("serviceAccountJson".to_string());
+                    }
+                }
+            }
+            set("endpoint", get("endpoint"));
+            missing.push("bucket".to_string());
+            "gcs"
+        }
+        Some(typ @ ("dropbox" | "onedrive")) => {
+            // 未配置 client_id 时 rclone 使用内置应用，其令牌不能用于其他应用
+            match get("client_id") {
+                Some(id) => set("clientId", Some(id)),
+                None => missing.push("clientId".to_string()),
+            }
+            set("clientSecret", get("client_secret"));
+            match get("token").as_deref().and_then(refresh_token) {
+                Some(token) => set("refreshToken", Some(token)),
+                None => missing.push("refreshToken".to_string()),
+            }
+            if typ == "onedrive" {
+                set("driveId", get("drive_id"));
+            }
+            typ
+        }
+        Some("sftp") => return Err("暂不支持 SFTP 存储".to_string()),
+        Some("drive") => return Err("暂不支持 Google Drive 存储".to_string()),
+        Some(other) => return Err(format!("不支持的远程类型: {}", other)),
+        None => return Err("缺少 type 字段".to_string()),
+    };
+
+    config.insert("type".to_string(), json!(typ));
+    Ok((Value::Object(config), missing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn obscure(plain: &str, iv: [u8; 16]) -> String {
+        let mut data = plain.as_bytes().to_vec();
+        Aes256Ctr::new(&RCLONE_CRYPT_KEY.into(), &iv.into()).apply_keystream(&mut data);
+        let mut out = iv.to_vec();
+        out.extend(data);
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(out)
+    }
+
+    #[test]
+    fn test_parse_and_convert() {
+        let content = format!(
+            "# comment\n[nas]\ntype = webdav\nurl = https://nas/dav\nuser = me\npass = {}\n\n\
+             [box]\ntype = dropbox\nclient_id = app\ntoken = {{\"access_token\":\"a\",\"refresh_token\":\"r\"}}\n\n\
+             [home]\ntype = sftp\nhost = example.com\n",
+            obscure("secret", [7; 16])
+        );
+        let sections = parse_config(&content);
+        let names: Vec<_> = sections.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["nas", "box", "home"]);
+
+        let (config, missing) = convert_remote(&sections[0].1).unwrap();
+        assert_eq!(config["type"], "webdav");
+        assert_eq!(config["webdavEndpoint"], "https://nas/dav");
+        assert_eq!(config["password"], "secret");
+        assert!(missing.is_empty());
+
+        let (config, missing) = convert_remote(&sections[1].1).unwrap();
+        assert_eq!(config["refreshToken"], "r");
+        assert!(missing.is_empty());
+
+        assert!(convert_remote(&sections[2].1).is_err());
+    }
+}
@@ -0,0 +1,61 @@
+//! 扫描配置相关命令
+
+use crate::config::ScannerConfig;
+use crate::AppState;
+use tauri::State;
+
+/// 最小扫描并发数
+const MIN_SCAN_CONCURRENCY: usize = 1;
+/// 最大扫描并发数
+const MAX_SCAN_CONCURRENCY: usize = 64;
+
+/// 获取扫描配置
+#[tauri::command]
+pub async fn get_scanner_config(state: State<'_, AppState>) -> Result<ScannerConfig, String> {
+    Ok(ScannerConfig::load(&state.config_dir()))
+}
+
+/// 设置扫描配置
+#[tauri::command]
+pub async fn set_scanner_config(
+    concurrency: Option<usize>,
+    list_page_size: Option<usize>,
+    local_checksums: Option<bool>,
+    checksum_max_size_mb: Option<u64>,
+    skip_hidden: Option<bool>,
+    etag_checksums: Option<bool>,
+    max_tree_files: Option<usize>,
+    scan_timeout_secs: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<ScannerConfig, String> {
+    let mut config = ScannerConfig::load(&state.config_dir());
+    
+    if let Some(c) = concurrency {
+        config.concurrency = c.clamp(MIN_SCAN_CONCURRENCY, MAX_SCAN_CONCURRENCY);
+    }
+    if let Some(size) = list_page_size {
+        config.list_page_size = size;
+    }
+    if let Some(enabled) = local_checksums {
+        config.local_checksums = enabled;
+    }
+    if let Some(mb) = checksum_max_size_mb {
+        config.checksum_max_size_mb = mb;
+    }
+    if let Some(skip) = skip_hidden {
+        config.skip_hidden = skip;
+    }
+    if let Some(enabled) = etag_checksums {
+        config.etag_checksums = enabled;
+    }
+    if let Some(limit) = max_tree_files {
+        config.max_tree_files = limit;
+    }
+    if let Some(secs) = scan_timeout_secs {
+        config.scan_timeout_secs = secs;
+    }
+    
+    config.save(&state.config_dir()).map_err(|e| e.to_string())?;
+    
+    Ok(config)
+}
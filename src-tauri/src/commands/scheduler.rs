@@ -0,0 +1,72 @@
+//! 调度配置相关命令
+
+use crate::config::SchedulerConfig;
+use crate::db::SyncJob;
+use crate::AppState;
+use tauri::{AppHandle, State};
+
+/// 同时运行任务数的上限
+const MAX_CONCURRENT_JOBS: usize = 32;
+/// 定时触发前连接检查重试次数的上限
+const MAX_CHECK_RETRIES: u32 = 20;
+/// 定时触发前连接检查重试间隔的下限（秒）
+const MIN_CHECK_RETRY_SECS: u64 = 5;
+
+/// 获取调度配置
+#[tauri::command]
+pub async fn get_scheduler_config(state: State<'_, AppState>) -> Result<SchedulerConfig, String> {
+    Ok(SchedulerConfig::load(&state.config_dir()))
+}
+
+/// 设置调度配置（立即生效，已在运行的任务不受影响）
+#[tauri::command]
+pub async fn set_scheduler_config(
+    max_concurrent_jobs: Option<usize>,
+    check_retries: Option<u32>,
+    check_retry_secs: Option<u64>,
+    state: State<'_, AppState>,
+) -> Result<SchedulerConfig, String> {
+    let mut config = SchedulerConfig::load(&state.config_dir());
+
+    if let Some(max) = max_concurrent_jobs {
+        config.max_concurrent_jobs = max.min(MAX_CONCURRENT_JOBS);
+    }
+    if let Some(retries) = check_retries {
+        config.check_retries = retries.min(MAX_CHECK_RETRIES);
+    }
+    if let Some(secs) = check_retry_secs {
+        config.check_retry_secs = secs.max(MIN_CHECK_RETRY_SECS);
+    }
+
+    config.save(&state.config_dir()).map_err(|e| e.to_string())?;
+    state.job_limiter.set_limit(config.max_concurrent_jobs);
+
+    Ok(config)
+}
+
+/// 跳过定时任务的下一次触发，返回之后的下次触发时间（Unix 时间戳，秒）
+#[tauri::command]
+pub async fn skip_next_run(job_id: String, state: State<'_, AppState>) -> Result<Option<i64>, String> {
+    let (skipped_at, next) = state
+        .scheduler
+        .skip_next(&job_id)
+        .ok_or_else(|| "任务没有待触发的定时计划".to_string())?;
+    // 保存后重启应用仍然跳过
+    SyncJob::save_skipped_run(&state.db(), &job_id, skipped_at)
+        .await
+        .map_err(|e| format!("保存跳过记录失败: {}", e))?;
+    Ok(next)
+}
+
+/// 立即运行任务（不影响定时计划），与其他任务一样受同时运行任务数限制
+///
+/// 正在运行或排队中的任务由 start_sync 拒绝重复启动
+#[tauri::command]
+pub async fn run_now(
+    job_id: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<String, String> {
+    tracing::info!("手动立即运行任务: {}", job_id);
+    crate::commands::sync::start_sync(job_id, None, None, None, None, None, None, None, None, None, state, app).await
+}
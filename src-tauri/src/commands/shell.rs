@@ -1,11 +1,24 @@
+use std::io;
 use std::path::Path;
 use std::process::Command;
 
+/// 转换为当前平台的路径格式（仅 Windows 需要把正斜杠转换为反斜杠）
+fn to_platform_path(path: &str) -> String {
+    #[cfg(target_os = "windows")]
+    {
+        path.replace('/', "\\")
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        path.to_string()
+    }
+}
+
 /// 在文件管理器中显示文件/目录
 #[tauri::command]
 pub async fn show_in_folder(path: String) -> Result<(), String> {
-    // 将正斜杠转换为反斜杠（Windows 兼容）
-    let normalized_path = path.replace('/', "\\");
+    let normalized_path = to_platform_path(&path);
     let path = Path::new(&normalized_path);
     
     // 如果是文件，获取父目录
@@ -79,23 +92,109 @@ pub async fn rename_file(old_path: String, new_name: String) -> Result<(), Strin
         return Err(format!("目标文件已存在: {}", new_path.display()));
     }
     
-    std::fs::rename(&old_path, &new_path)
-        .map_err(|e| format!("重命名失败: {}", e))?;
+    match std::fs::rename(old_path, &new_path) {
+        Ok(()) => {}
+        // 跨文件系统（如挂载点、符号链接目录）无法直接重命名，退化为复制后删除
+        Err(e) if is_cross_device(&e) => {
+            tracing::debug!("跨文件系统重命名，改为复制后删除: {}", old_path.display());
+            move_by_copy(old_path, &new_path).map_err(|e| rename_error(&e))?;
+        }
+        Err(e) => return Err(rename_error(&e)),
+    }
     
     tracing::info!("文件重命名: {} -> {}", old_path.display(), new_path.display());
     
     Ok(())
 }
 
-/// 删除文件
+/// 是否为跨文件系统错误（Unix EXDEV、Windows ERROR_NOT_SAME_DEVICE）
+fn is_cross_device(e: &io::Error) -> bool {
+    #[cfg(unix)]
+    if e.raw_os_error() == Some(libc::EXDEV) {
+        return true;
+    }
+    e.kind() == io::ErrorKind::CrossesDevices
+}
+
+/// 重命名失败的提示信息
+fn rename_error(e: &io::Error) -> String {
+    match e.kind() {
+        io::ErrorKind::PermissionDenied => {
+            format!("重命名失败: 没有权限（文件可能被其他程序占用或为只读）- {}", e)
+        }
+        io::ErrorKind::AlreadyExists => format!("重命名失败: 目标已存在 - {}", e),
+        _ => format!("重命名失败: {}", e),
+    }
+}
+
+/// 复制后删除原文件/目录（复制失败时清理已复制的部分）
+fn move_by_copy(from: &Path, to: &Path) -> io::Result<()> {
+    let result = if from.is_dir() {
+        copy_dir(from, to)
+    } else {
+        std::fs::copy(from, to).map(|_| ())
+    };
+    if let Err(e) = result {
+        let _ = if to.is_dir() {
+            std::fs::remove_dir_all(to)
+        } else {
+            std::fs::remove_file(to)
+        };
+        return Err(e);
+    }
+
+    if from.is_dir() {
+        std::fs::remove_dir_all(from)
+    } else {
+        std::fs::remove_file(from)
+    }
+}
+
+/// 递归复制目录
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    for entry in walkdir::WalkDir::new(from) {
+        let entry = entry.map_err(io::Error::other)?;
+        let relative = entry
+            .path()
+            .strip_prefix(from)
+            .map_err(io::Error::other)?;
+        let target = to.join(relative);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// 移动到系统回收站
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+fn move_to_trash(path: &Path) -> Result<(), String> {
+    trash::delete(path).map_err(|e| format!("移动到回收站失败: {}（可选择永久删除）", e))
+}
+
+/// 移动到系统回收站（当前平台不支持）
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn move_to_trash(_path: &Path) -> Result<(), String> {
+    Err("当前平台不支持回收站，请选择永久删除".to_string())
+}
+
+/// 删除文件（默认移动到回收站，`permanent` 为 true 时永久删除）
 #[tauri::command]
-pub async fn delete_file(path: String) -> Result<(), String> {
+pub async fn delete_file(path: String, permanent: Option<bool>) -> Result<(), String> {
     let path = Path::new(&path);
     
     if !path.exists() {
         return Err(format!("文件不存在: {}", path.display()));
     }
     
+    if !permanent.unwrap_or(false) {
+        move_to_trash(path)?;
+        tracing::info!("文件已移动到回收站: {}", path.display());
+        return Ok(());
+    }
+    
     if path.is_dir() {
         std::fs::remove_dir_all(&path)
             .map_err(|e| format!("删除目录失败: {}", e))?;
@@ -104,7 +203,65 @@ pub async fn delete_file(path: String) -> Result<(), String> {
             .map_err(|e| format!("删除文件失败: {}", e))?;
     }
     
-    tracing::info!("文件已删除: {}", path.display());
+    tracing::info!("文件已永久删除: {}", path.display());
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_platform_path_unmodified() {
+        assert_eq!(to_platform_path("/home/user/a b/file.txt"), "/home/user/a b/file.txt");
+        assert_eq!(to_platform_path("relative/dir/"), "relative/dir/");
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_platform_path_backslashes() {
+        assert_eq!(to_platform_path("C:/Users/a/file.txt"), "C:\\Users\\a\\file.txt");
+    }
+
+    #[test]
+    fn test_is_cross_device() {
+        assert!(is_cross_device(&io::Error::from(io::ErrorKind::CrossesDevices)));
+        #[cfg(unix)]
+        assert!(is_cross_device(&io::Error::from_raw_os_error(libc::EXDEV)));
+        #[cfg(unix)]
+        assert!(!is_cross_device(&io::Error::from_raw_os_error(libc::EACCES)));
+        assert!(!is_cross_device(&io::Error::from(io::ErrorKind::PermissionDenied)));
+    }
+
+    #[test]
+    fn test_move_by_copy() {
+        let dir = std::env::temp_dir().join(format!("synctools-move-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src/sub")).unwrap();
+        std::fs::write(dir.join("src/sub/a.txt"), b"a").unwrap();
+        std::fs::write(dir.join("file.txt"), b"f").unwrap();
+
+        move_by_copy(&dir.join("src"), &dir.join("dst")).unwrap();
+        assert!(!dir.join("src").exists());
+        assert_eq!(std::fs::read(dir.join("dst/sub/a.txt")).unwrap(), b"a");
+
+        move_by_copy(&dir.join("file.txt"), &dir.join("moved.txt")).unwrap();
+        assert!(!dir.join("file.txt").exists());
+        assert_eq!(std::fs::read(dir.join("moved.txt")).unwrap(), b"f");
+
+        // 复制失败时保留原文件
+        assert!(move_by_copy(&dir.join("moved.txt"), &dir.join("missing/x.txt")).is_err());
+        assert!(dir.join("moved.txt").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_error_messages() {
+        let denied = rename_error(&io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(denied.contains("没有权限"), "{}", denied);
+        let exists = rename_error(&io::Error::from(io::ErrorKind::AlreadyExists));
+        assert!(exists.contains("目标已存在"), "{}", exists);
+    }
+}
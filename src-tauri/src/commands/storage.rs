@@ -0,0 +1,36 @@
+//! 存储类型相关命令
+
+use crate::db::StorageType;
+use serde::Serialize;
+
+/// 存储类型及其支持的功能
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageTypeInfo {
+    #[serde(rename = "type")]
+    pub typ: StorageType,
+    pub display_name: &'static str,
+    /// 必填的配置字段
+    pub required_fields: &'static [&'static str],
+    pub supports_streaming: bool,
+    pub supports_rename: bool,
+    pub supports_checksums: bool,
+    pub read_only: bool,
+}
+
+/// 列出所有支持的存储类型及其功能
+#[tauri::command]
+pub async fn list_storage_types() -> Result<Vec<StorageTypeInfo>, String> {
+    Ok(StorageType::ALL
+        .iter()
+        .map(|typ| StorageTypeInfo {
+            typ: typ.clone(),
+            display_name: typ.display_name(),
+            required_fields: typ.required_fields(),
+            supports_streaming: typ.supports_streaming(),
+            supports_rename: typ.supports_rename(),
+            supports_checksums: typ.has_checksums(),
+            read_only: typ.is_read_only(),
+        })
+        .collect())
+}
@@ -1,12 +1,12 @@
-use crate::core::comparator::FileComparator;
+use crate::core::comparator::{ConflictType, DirSummary, FileComparator, FileRelation, SyncAction};
 use crate::core::scanner::FileScanner;
-use crate::core::SyncEngine;
-use crate::db::SyncJob;
+use crate::core::{SyncEngine, SyncReport};
+use crate::db::{SyncJob, SyncStatus};
 use crate::AppState;
 use serde::Serialize;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, Emitter, Manager, State};
 
 // ============================================================================
 // 常量定义
@@ -32,6 +32,8 @@ pub struct DiffAction {
     pub reverse: bool,
     pub source_exists: bool,
     pub dest_exists: bool,
+    /// 归为该动作的原因（如 "源文件较新（新 3小时）"）
+    pub reason: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -47,12 +49,27 @@ pub struct DiffResult {
     pub skip_count: usize,
     pub conflict_count: usize,
     pub total_bytes: u64,
+    /// 执行前需要确认的删除路径：删除的文件，以及类型不一致时先被删除再重建的目标条目
+    /// （可直接作为 start_sync 的 confirmed_deletes）
+    pub delete_paths: Vec<String>,
     /// 源缓存时间（Unix时间戳，0表示未使用缓存）
     pub source_cached_at: u64,
     /// 目标缓存时间（Unix时间戳，0表示未使用缓存）
     pub dest_cached_at: u64,
 }
 
+/// 上传量估算结果（目标视为空）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadEstimate {
+    pub source_name: String,
+    pub file_count: usize,
+    pub dir_count: usize,
+    pub total_bytes: u64,
+    /// 源缓存时间（Unix时间戳，0表示未使用缓存）
+    pub source_cached_at: u64,
+}
+
 /// 分析同步任务（不执行同步，只返回差异）
 #[tauri::command]
 pub async fn analyze_job(
@@ -60,6 +77,119 @@ pub async fn analyze_job(
     force_refresh: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<DiffResult, String> {
+    analyze(job_id, force_refresh, &state)
+        .await
+        .map(|(result, _)| result)
+}
+
+/// 扫描一侧存储（未强制刷新时优先使用缓存），扫描完成后立即写入缓存
+///
+/// 返回文件树和缓存时间（0 表示未使用缓存）
+async fn scan_side(
+    scanner: &FileScanner,
+    storage: &dyn crate::storage::Storage,
+    cache: &crate::core::FileListCache,
+    job_id: &str,
+    side: &str,
+    config_json: &str,
+    force_refresh: bool,
+) -> anyhow::Result<(std::collections::HashMap<String, crate::storage::FileInfo>, u64)> {
+    if !force_refresh {
+        if let Some(cached) = cache.load(job_id, side, config_json) {
+            return Ok((cached.files, cached.cached_at));
+        }
+    }
+    let tree = scanner.scan_storage_with_timeout(storage).await?;
+    let _ = cache.save(job_id, side, config_json, &tree);
+    Ok((tree, 0))
+}
+
+/// 按目录汇总差异（执行一次分析），`depth` 为展开的目录层数
+#[tauri::command]
+pub async fn diff_summary_by_dir(
+    job_id: String,
+    depth: usize,
+    force_refresh: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<DirSummary, String> {
+    let (_, actions) = analyze(job_id, force_refresh, &state).await?;
+    Ok(FileComparator::summarize_by_dir(&actions, depth))
+}
+
+/// 直接比较两个存储配置（无需保存任务，不读写数据库和缓存）
+///
+/// `request_id` 代替任务 ID，可通过 `cancel_analyze(request_id, "analyze")` 取消（默认为 "adhoc"）
+#[tauri::command]
+pub async fn analyze_adhoc(
+    source_config: crate::db::StorageConfig,
+    dest_config: crate::db::StorageConfig,
+    sync_mode: String,
+    request_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DiffResult, String> {
+    let mode = super::job::parse_sync_mode(&sync_mode)?;
+
+    let key = cancel_key("analyze", request_id.as_deref().unwrap_or("adhoc"));
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    state
+        .analyze_cancels
+        .lock()
+        .await
+        .insert(key.clone(), cancel_flag.clone());
+    let cleanup_state = state.analyze_cancels.clone();
+    scopeguard::defer! {
+        tokio::spawn(async move {
+            cleanup_state.lock().await.remove(&key);
+        });
+    }
+
+    let source_storage = crate::storage::create_storage(&source_config)
+        .await
+        .map_err(|e| format!("源存储连接失败: {}", e))?;
+    let dest_storage = crate::storage::create_storage(&dest_config)
+        .await
+        .map_err(|e| format!("目标存储连接失败: {}", e))?;
+
+    let scan_config = crate::config::ScannerConfig::load(&state.config_dir()).to_scan_config();
+    let scanner = FileScanner::with_config(scan_config.concurrency, scan_config)
+        .with_cancel_flag(cancel_flag.clone());
+    let (source_result, dest_result) = tokio::join!(
+        scanner.scan_storage_with_timeout(source_storage.as_ref()),
+        scanner.scan_storage_with_timeout(dest_storage.as_ref()),
+    );
+    if cancel_flag.load(Ordering::Relaxed) {
+        return Err("操作已取消".to_string());
+    }
+    let source_tree = source_result.map_err(|e| format!("扫描源存储失败: {}", e))?;
+    let dest_tree = dest_result.map_err(|e| format!("扫描目标存储失败: {}", e))?;
+
+    let transfer_config = crate::config::TransferConfig::load(&state.config_dir());
+    let comparator = FileComparator::with_config(crate::core::CompareConfig {
+        overwrite_policy: transfer_config.overwrite_policy,
+        zero_byte: transfer_config.zero_byte_compare,
+        ..Default::default()
+    });
+    let (actions, relations) =
+        comparator.compare_trees_with_relations(&source_tree, &dest_tree, &mode);
+    Ok(build_diff_result(
+        source_storage.name(),
+        dest_storage.name(),
+        &source_tree,
+        &dest_tree,
+        &actions,
+        &relations,
+        0,
+        0,
+    ))
+}
+
+/// 扫描并比较两端，返回差异结果和比较得到的同步动作
+async fn analyze(
+    job_id: String,
+    force_refresh: Option<bool>,
+    state: &AppState,
+) -> Result<(DiffResult, Vec<SyncAction>), String> {
+    let _task = state.begin_task()?;
     let force_refresh = force_refresh.unwrap_or(false);
     // 创建取消标志
     let cancel_flag = Arc::new(AtomicBool::new(false));
@@ -67,18 +197,18 @@ pub async fn analyze_job(
         .analyze_cancels
         .lock()
         .await
-        .insert(job_id.clone(), cancel_flag.clone());
+        .insert(cancel_key("analyze", &job_id), cancel_flag.clone());
 
     // 在函数结束时清理取消标志
     let cleanup_state = state.analyze_cancels.clone();
-    let cleanup_job_id = job_id.clone();
+    let cleanup_key = cancel_key("analyze", &job_id);
     scopeguard::defer! {
         tokio::spawn(async move {
-            cleanup_state.lock().await.remove(&cleanup_job_id);
+            cleanup_state.lock().await.remove(&cleanup_key);
         });
     }
 
-    let job = SyncJob::load(&state.db, &job_id)
+    let job = SyncJob::load(&state.db(), &job_id)
         .await
         .map_err(|e| format!("加载任务失败: {}", e))?
         .ok_or_else(|| "任务不存在".to_string())?;
@@ -89,7 +219,7 @@ pub async fn analyze_job(
     }
 
     // 创建存储
-    let source_storage = crate::storage::create_storage(&job.sourceConfig)
+    let source_storage = crate::storage::create_job_source_storage(&job)
         .await
         .map_err(|e| format!("源存储连接失败: {}", e))?;
     let dest_storage = crate::storage::create_storage(&job.destConfig)
@@ -102,113 +232,127 @@ pub async fn analyze_job(
     }
 
     // 初始化缓存（只对远程存储使用缓存），缓存目录跟随数据存储目录
-    let cache_dir = state.config_dir.join("cache");
+    let cache_dir = state.config_dir().join("cache");
     
     // 从配置读取缓存 TTL，本地存储不使用缓存
-    let cache_config = crate::config::CacheConfig::load(&state.config_dir);
-    let source_is_local = matches!(job.sourceConfig.typ, crate::db::StorageType::Local);
+    let cache_config = crate::config::CacheConfig::load(&state.config_dir());
+    let source_is_local = job.source_is_local();
     let dest_is_local = matches!(job.destConfig.typ, crate::db::StorageType::Local);
     let source_ttl = if source_is_local { 0 } else { cache_config.remote_ttl };
     let dest_ttl = if dest_is_local { 0 } else { cache_config.remote_ttl };
     
-    let source_cache = crate::core::FileListCache::new(cache_dir.clone()).with_ttl(source_ttl);
-    let dest_cache = crate::core::FileListCache::new(cache_dir).with_ttl(dest_ttl);
+    let source_cache = crate::core::FileListCache::new(cache_dir.clone())
+        .with_ttl(source_ttl)
+        .with_format(cache_config.format);
+    let dest_cache = crate::core::FileListCache::new(cache_dir)
+        .with_ttl(dest_ttl)
+        .with_format(cache_config.format);
     
-    let source_config_json = serde_json::to_string(&job.sourceConfig).unwrap_or_default();
+    let source_config_json = job.source_config_json();
     let dest_config_json = serde_json::to_string(&job.destConfig).unwrap_or_default();
 
     // 如果强制刷新，先清除所有缓存
     if force_refresh {
-        source_cache.clear(&job_id);
-        dest_cache.clear(&job_id);
+        source_cache.clear(&job_id, Some("source"));
+        dest_cache.clear(&job_id, Some("dest"));
     }
 
-    // 扫描源存储（支持缓存）
-    let scanner = FileScanner::with_cancel(cancel_flag.clone());
-    let mut source_cached_at: u64 = 0;
-    let source_tree = if !force_refresh {
-        if let Some(cached) = source_cache.load(&job_id, "source", &source_config_json) {
-            source_cached_at = cached.cached_at;
-            cached.files
-        } else {
-            let tree = scanner
-                .scan_storage(source_storage.as_ref(), None)
-                .await
-                .map_err(|e| {
-                    if cancel_flag.load(Ordering::Relaxed) {
-                        "操作已取消".to_string()
-                    } else {
-                        format!("扫描源存储失败: {}", e)
-                    }
-                })?;
-            let _ = source_cache.save(&job_id, "source", &source_config_json, &tree);
-            tree
-        }
-    } else {
-        let tree = scanner
-            .scan_storage(source_storage.as_ref(), None)
-            .await
-            .map_err(|e| {
-                if cancel_flag.load(Ordering::Relaxed) {
-                    "操作已取消".to_string()
-                } else {
-                    format!("扫描源存储失败: {}", e)
-                }
-            })?;
-        let _ = source_cache.save(&job_id, "source", &source_config_json, &tree);
-        tree
-    };
+    // 扫描两侧存储（支持缓存）
+    let scan_config = crate::config::ScannerConfig::load(&state.config_dir())
+        .to_scan_config()
+        .with_excludes(job.excludePreset, &job.excludePatterns);
+    let scanner = FileScanner::with_config(scan_config.concurrency, scan_config)
+        .with_cancel_flag(cancel_flag.clone());
+
+    // 两侧并行扫描，各自完成后立即写入缓存：另一侧被取消或失败时，
+    // 已完成一侧的结果保留在缓存中，重新分析只需扫描未完成的一侧
+    let (source_result, dest_result) = tokio::join!(
+        scan_side(
+            &scanner,
+            source_storage.as_ref(),
+            &source_cache,
+            &job_id,
+            "source",
+            &source_config_json,
+            force_refresh,
+        ),
+        scan_side(
+            &scanner,
+            dest_storage.as_ref(),
+            &dest_cache,
+            &job_id,
+            "dest",
+            &dest_config_json,
+            force_refresh,
+        ),
+    );
 
     // 检查是否已取消
     if cancel_flag.load(Ordering::Relaxed) {
         return Err("操作已取消".to_string());
     }
-
-    // 扫描目标存储（支持缓存）
-    let mut dest_cached_at: u64 = 0;
-    let dest_tree = if !force_refresh {
-        if let Some(cached) = dest_cache.load(&job_id, "dest", &dest_config_json) {
-            dest_cached_at = cached.cached_at;
-            cached.files
-        } else {
-            let tree = scanner
-                .scan_storage(dest_storage.as_ref(), None)
-                .await
-                .map_err(|e| {
-                    if cancel_flag.load(Ordering::Relaxed) {
-                        "操作已取消".to_string()
-                    } else {
-                        format!("扫描目标存储失败: {}", e)
-                    }
-                })?;
-            let _ = dest_cache.save(&job_id, "dest", &dest_config_json, &tree);
-            tree
-        }
-    } else {
-        let tree = scanner
-            .scan_storage(dest_storage.as_ref(), None)
-            .await
-            .map_err(|e| {
-                if cancel_flag.load(Ordering::Relaxed) {
-                    "操作已取消".to_string()
-                } else {
-                    format!("扫描目标存储失败: {}", e)
-                }
-            })?;
-        let _ = dest_cache.save(&job_id, "dest", &dest_config_json, &tree);
-        tree
-    };
+    let (source_tree, source_cached_at) =
+        source_result.map_err(|e| format!("扫描源存储失败: {}", e))?;
+    let (dest_tree, dest_cached_at) =
+        dest_result.map_err(|e| format!("扫描目标存储失败: {}", e))?;
 
     // 比较文件
-    let comparator = FileComparator::default();
-    let actions = comparator.compare_trees(&source_tree, &dest_tree, &job.syncMode);
-    let summary = FileComparator::summarize_actions(&actions);
+    // 与同步时使用相同的覆盖策略和空文件比较方式，预览结果保持一致
+    let transfer_config = crate::config::TransferConfig::load(&state.config_dir());
+    let comparator = FileComparator::with_config(crate::core::CompareConfig {
+        compare_etags: job.compares_etags(),
+        case_fold: job.effective_case_fold(),
+        overwrite_policy: transfer_config.overwrite_policy,
+        zero_byte: transfer_config.zero_byte_compare,
+        ..Default::default()
+    });
+    let (actions, relations) =
+        comparator.compare_trees_with_relations(&source_tree, &dest_tree, &job.syncMode);
+    // 同步时按任务的默认解决方式处理的冲突，预览中显示处理后的动作
+    let (actions, _) = crate::core::ConflictResolution::apply_all(
+        actions,
+        &std::collections::HashMap::new(),
+        job.defaultConflictResolution,
+        chrono::Utc::now().timestamp(),
+    );
+    let result = build_diff_result(
+        source_storage.name(),
+        dest_storage.name(),
+        &source_tree,
+        &dest_tree,
+        &actions,
+        &relations,
+        source_cached_at,
+        dest_cached_at,
+    );
+    Ok((result, actions))
+}
+
+/// 将比较得到的同步动作转换为前端展示的差异结果
+#[allow(clippy::too_many_arguments)]
+fn build_diff_result(
+    source_name: &str,
+    dest_name: &str,
+    source_tree: &std::collections::HashMap<String, crate::storage::FileInfo>,
+    dest_tree: &std::collections::HashMap<String, crate::storage::FileInfo>,
+    actions: &[SyncAction],
+    relations: &std::collections::HashMap<String, FileRelation>,
+    source_cached_at: u64,
+    dest_cached_at: u64,
+) -> DiffResult {
+    let summary = FileComparator::summarize_actions(actions);
+
+    // 两边都存在的文件的比较说明
+    let relation_reason = |path: &String| -> Option<String> {
+        let relation = relations.get(path)?;
+        Some(relation.describe(source_tree.get(path)?, dest_tree.get(path)?))
+    };
 
     // 转换为前端需要的格式
     let diff_actions: Vec<DiffAction> = actions
         .iter()
         .map(|action| match action {
-            crate::core::comparator::SyncAction::Copy {
+            SyncAction::Copy {
                 source_path,
                 size,
                 reverse,
@@ -220,37 +364,89 @@ pub async fn analyze_job(
                 reverse: *reverse,
                 source_exists: !*reverse || source_tree.contains_key(source_path),
                 dest_exists: *reverse || dest_tree.contains_key(source_path),
+                reason: relation_reason(source_path).unwrap_or_else(|| {
+                    let missing = if *reverse { "源中不存在" } else { "目标中不存在" };
+                    missing.to_string()
+                }),
             },
-            crate::core::comparator::SyncAction::Delete { path, from_dest } => DiffAction {
+            SyncAction::Delete { path, from_dest } => DiffAction {
                 action_type: "delete".to_string(),
                 path: path.clone(),
                 size: dest_tree.get(path).map(|f| f.size).unwrap_or(0),
                 reverse: false,
                 source_exists: !*from_dest,
                 dest_exists: *from_dest,
+                reason: "源中已不存在".to_string(),
             },
-            crate::core::comparator::SyncAction::Skip { path } => DiffAction {
+            // 分析阶段不检测重命名，只有冲突「保留两者」时重命名目标中的版本
+            SyncAction::Rename { from, to, size } => DiffAction {
+                action_type: "copy".to_string(),
+                path: to.clone(),
+                size: *size,
+                reverse: false,
+                source_exists: source_tree.contains_key(to),
+                dest_exists: false,
+                reason: format!("保留两者：目标中的 {} 重命名保存", from),
+            },
+            SyncAction::Replace { path, size, .. } => DiffAction {
+                action_type: "copy".to_string(),
+                path: path.clone(),
+                size: *size,
+                reverse: false,
+                source_exists: true,
+                dest_exists: true,
+                reason: "一边是文件一边是目录".to_string(),
+            },
+            SyncAction::Skip { path } => DiffAction {
                 action_type: "skip".to_string(),
                 path: path.clone(),
                 size: source_tree.get(path).map(|f| f.size).unwrap_or(0),
                 reverse: false,
                 source_exists: true,
                 dest_exists: true,
+                reason: match relations.get(path) {
+                    Some(FileRelation::Equal | FileRelation::ProbablyEqual) => {
+                        relation_reason(path).unwrap_or_default()
+                    }
+                    // 文件不同但被跳过，只可能是覆盖策略不允许
+                    Some(_) => format!(
+                        "{}，覆盖策略不允许覆盖",
+                        relation_reason(path).unwrap_or_default()
+                    ),
+                    None => "仅目标中存在，备份模式保留".to_string(),
+                },
             },
-            crate::core::comparator::SyncAction::Conflict { path, .. } => DiffAction {
+            SyncAction::Conflict {
+                path,
+                conflict_type,
+                ..
+            } => DiffAction {
                 action_type: "conflict".to_string(),
                 path: path.clone(),
                 size: source_tree.get(path).map(|f| f.size).unwrap_or(0),
                 reverse: false,
                 source_exists: source_tree.contains_key(path),
                 dest_exists: dest_tree.contains_key(path),
+                reason: match conflict_type {
+                    ConflictType::TypeMismatch => "一边是文件一边是目录".to_string(),
+                    _ => relation_reason(path)
+                        .map(|r| format!("{}，两边都已修改", r))
+                        .unwrap_or_else(|| "两边都已修改".to_string()),
+                },
             },
         })
         .collect();
+    let delete_paths = actions
+        .iter()
+        .filter_map(|action| match action {
+            SyncAction::Delete { path, .. } | SyncAction::Replace { path, .. } => Some(path.clone()),
+            _ => None,
+        })
+        .collect();
 
-    Ok(DiffResult {
-        source_name: source_storage.name().to_string(),
-        dest_name: dest_storage.name().to_string(),
+    DiffResult {
+        source_name: source_name.to_string(),
+        dest_name: dest_name.to_string(),
         source_files: source_tree.len(),
         dest_files: dest_tree.len(),
         actions: diff_actions,
@@ -259,26 +455,38 @@ pub async fn analyze_job(
         skip_count: summary.skip_count,
         conflict_count: summary.conflict_count,
         total_bytes: summary.total_transfer_bytes(),
+        delete_paths,
         source_cached_at,
         dest_cached_at,
-    })
+    }
 }
 
 /// 开始同步任务
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn start_sync(
     job_id: String,
     auto_create_dir: Option<bool>,
     max_concurrent: Option<usize>,
     conflict_resolutions: Option<std::collections::HashMap<String, String>>,
+    allow_empty_source: Option<bool>,
+    confirmed_deletes: Option<Vec<String>>,
+    fail_fast: Option<bool>,
+    protect_patterns: Option<Vec<String>>,
+    auto_resolve_identical_conflicts: Option<bool>,
+    assume_empty_dest: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<String, String> {
+    // 同步期间持有，迁移数据目录时需要等待同步结束
+    let task = state.begin_task()?;
     let auto_create = auto_create_dir.unwrap_or(true);
+    let allow_empty_source = allow_empty_source.unwrap_or(false);
+    let confirmed_deletes = confirmed_deletes.map(|paths| paths.into_iter().collect());
     let concurrent = max_concurrent.unwrap_or(DEFAULT_MAX_CONCURRENT).clamp(MIN_CONCURRENT, MAX_CONCURRENT);
     let resolutions = conflict_resolutions.unwrap_or_default();
     // 从数据库加载任务
-    let job = SyncJob::load(&state.db, &job_id)
+    let job = SyncJob::load(&state.db(), &job_id)
         .await
         .map_err(|e| format!("加载任务失败: {}", e))?
         .ok_or_else(|| "任务不存在".to_string())?;
@@ -294,12 +502,14 @@ pub async fn start_sync(
     // 创建取消信号通道
     let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
 
-    // 保存取消信号
-    state
-        .cancel_signals
-        .lock()
-        .await
-        .insert(job_id.clone(), cancel_tx);
+    // 保存取消信号：检查和登记在同一次加锁中完成，同一任务不会同时启动两次
+    {
+        let mut signals = state.cancel_signals.lock().await;
+        if signals.contains_key(&job_id) {
+            return Err("任务正在运行或排队中".to_string());
+        }
+        signals.insert(job_id.clone(), cancel_tx);
+    }
 
     // 启动进度监听任务
     let app_clone = app.clone();
@@ -310,26 +520,57 @@ pub async fn start_sync(
     });
 
     // 在后台执行同步
-    let db_clone = state.db.clone();
+    let db_clone = state.db();
     let job_id_for_emit = job_id.clone();
     let job_for_sync = job.clone();
     let app_for_emit = app.clone();
     let cancel_signals = state.cancel_signals.clone();
-    let cache_dir = state.config_dir.join("cache");
-    let cache_config = crate::config::CacheConfig::load(&state.config_dir);
-    let transfer_config = crate::config::TransferConfig::load(&state.config_dir);
+    let base_config = global_sync_config(&state);
+    let job_limiter = state.job_limiter.clone();
 
     let resolutions_for_sync = resolutions.clone();
     tokio::spawn(async move {
+        let _task = task;
+        let mut cancel_rx = cancel_rx;
+
+        // 全局任务并发限制：没有空闲槽位时排队等待（排队期间可以取消）
+        let job_permit = match job_limiter.try_acquire() {
+            Some(permit) => permit,
+            None => {
+                tracing::info!("同时运行的任务数已达上限，任务排队: {}", job_id_for_emit);
+                let _ = progress_tx.send(queued_progress(&job_id_for_emit)).await;
+                let queued_at = chrono::Utc::now().timestamp();
+                tokio::select! {
+                    permit = job_limiter.acquire() => permit,
+                    _ = &mut cancel_rx => {
+                        tracing::info!("排队中的任务已取消: {}", job_id_for_emit);
+                        cancel_signals.lock().await.remove(&job_id_for_emit);
+                        let report = SyncReport::cancelled(&job_id_for_emit, queued_at);
+                        let _ = app_for_emit.emit(
+                            "sync-complete",
+                            serde_json::json!({
+                                "job_id": job_id_for_emit,
+                                "log_id": null,
+                                "result": Ok::<_, String>(serde_json::to_value(&report).ok()),
+                            }),
+                        );
+                        return;
+                    }
+                }
+            }
+        };
+
         let config = crate::core::SyncConfig {
             auto_create_dir: auto_create,
             max_concurrent_transfers: concurrent,
             conflict_resolutions: resolutions_for_sync,
-            cache_dir: Some(cache_dir),
-            remote_cache_ttl: cache_config.remote_ttl,
-            chunk_size: transfer_config.chunk_size_mb * 1024 * 1024,
-            large_file_threshold: transfer_config.stream_threshold_mb * 1024 * 1024,
-            ..Default::default()
+            allow_empty_source,
+            confirmed_deletes,
+            fail_fast: fail_fast.unwrap_or(false),
+            protect_patterns: protect_patterns.unwrap_or_default(),
+            auto_resolve_identical_conflicts: auto_resolve_identical_conflicts.unwrap_or(false),
+            assume_empty_dest: assume_empty_dest.unwrap_or(false),
+            ..base_config
         };
         
         tracing::debug!("同步配置: 并行数={}, 自动创建目录={}, 冲突解决方案数={}", 
@@ -345,6 +586,7 @@ pub async fn start_sync(
         });
 
         let result = engine.run_sync(&job_for_sync, Some(progress_tx)).await;
+        drop(job_permit);
 
         // 取消取消监听
         cancel_handle.abort();
@@ -352,11 +594,16 @@ pub async fn start_sync(
         // 从取消信号中移除
         cancel_signals.lock().await.remove(&job_id_for_emit);
 
+        // 触发串联的后续任务
+        let succeeded = matches!(&result, Ok(r) if r.status == SyncStatus::Completed);
+        trigger_chained_jobs(&app_for_emit, &job_id_for_emit, succeeded);
+
         // 发送完成事件
         let _ = app_for_emit.emit(
             "sync-complete",
             serde_json::json!({
                 "job_id": job_id_for_emit,
+                "log_id": result.as_ref().ok().and_then(|r| r.logId),
                 "result": result.as_ref()
                     .map(|r| serde_json::to_value(r).ok())
                     .map_err(|e| e.to_string()),
@@ -367,6 +614,266 @@ pub async fn start_sync(
     Ok(job_id)
 }
 
+/// 按全局配置（缓存、传输、扫描）构建同步配置，调用参数由调用方覆盖
+fn global_sync_config(state: &AppState) -> crate::core::SyncConfig {
+    let cache_config = crate::config::CacheConfig::load(&state.config_dir());
+    let transfer_config = crate::config::TransferConfig::load(&state.config_dir());
+    let scanner_config = crate::config::ScannerConfig::load(&state.config_dir());
+    let hook_config = crate::config::HookConfig::load(&state.config_dir());
+    crate::core::SyncConfig {
+        cache_dir: Some(state.config_dir().join("cache")),
+        remote_cache_ttl: cache_config.remote_ttl,
+        cache_format: cache_config.format,
+        chunk_size: transfer_config.chunk_size_mb * 1024 * 1024,
+        large_file_threshold: transfer_config.stream_threshold_mb * 1024 * 1024,
+        delete_after_copy: transfer_config.delete_after_copy,
+        detect_renames: transfer_config.detect_renames,
+        overwrite_policy: transfer_config.overwrite_policy,
+        zero_byte_compare: transfer_config.zero_byte_compare,
+        skip_locked_files: transfer_config.skip_locked_files,
+        speed_smoothing: transfer_config.speed_smoothing,
+        instant_speed: transfer_config.instant_speed,
+        preserve_xattrs: transfer_config.preserve_xattrs,
+        retry_budget: (transfer_config.retry_budget > 0).then_some(transfer_config.retry_budget),
+        prune_empty_dirs: transfer_config.prune_empty_dirs,
+        prune_existing_empty_dirs: transfer_config.prune_existing_empty_dirs,
+        temp_file_naming: transfer_config.temp_file_naming(),
+        allow_hooks: hook_config.allow_hooks,
+        hook_timeout_secs: hook_config.timeout_secs,
+        bandwidth_limiter: Some(state.bandwidth_limiter.clone()),
+        scan_config: scanner_config.to_scan_config(),
+        ..Default::default()
+    }
+}
+
+/// 任务实际使用的同步配置（全局配置 + 任务级设置，调用参数取 start_sync 的默认值）
+#[tauri::command]
+pub async fn get_effective_config(
+    job_id: String,
+    state: State<'_, AppState>,
+) -> Result<crate::core::SyncConfig, String> {
+    let job = SyncJob::load(&state.db(), &job_id)
+        .await
+        .map_err(|e| format!("加载任务失败: {}", e))?
+        .ok_or_else(|| "任务不存在".to_string())?;
+
+    let config = crate::core::SyncConfig {
+        max_concurrent_transfers: DEFAULT_MAX_CONCURRENT,
+        ..global_sync_config(&state)
+    };
+    Ok(config.for_job(&job))
+}
+
+/// 排队等待任务槽位时的进度
+fn queued_progress(job_id: &str) -> crate::db::SyncProgress {
+    crate::db::SyncProgress {
+        jobId: job_id.to_string(),
+        status: SyncStatus::Queued,
+        phase: "等待其他任务完成...".to_string(),
+        currentFile: String::new(),
+        filesScanned: 0,
+        filesToSync: 0,
+        filesCompleted: 0,
+        filesSkipped: 0,
+        filesFailed: 0,
+        bytesTransferred: 0,
+        bytesTotal: 0,
+        speed: 0,
+        startTime: chrono::Utc::now().timestamp(),
+        endTime: 0,
+        scanEtaSecs: None,
+        filesCopiedToSource: 0,
+        filesCopiedToDest: 0,
+    }
+}
+
+/// 通过同步引擎传输单个文件（用于诊断传输失败）
+#[tauri::command]
+pub async fn sync_single_file(
+    job_id: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<crate::core::SingleFileResult, String> {
+    let _task = state.begin_task()?;
+    let job = SyncJob::load(&state.db(), &job_id)
+        .await
+        .map_err(|e| format!("加载任务失败: {}", e))?
+        .ok_or_else(|| "任务不存在".to_string())?;
+
+    let transfer_config = crate::config::TransferConfig::load(&state.config_dir());
+    let config = crate::core::SyncConfig {
+        chunk_size: transfer_config.chunk_size_mb * 1024 * 1024,
+        large_file_threshold: transfer_config.stream_threshold_mb * 1024 * 1024,
+        temp_file_naming: transfer_config.temp_file_naming(),
+        bandwidth_limiter: Some(state.bandwidth_limiter.clone()),
+        ..Default::default()
+    };
+
+    let engine = SyncEngine::with_config(state.db(), config);
+    engine
+        .sync_single_file(&job, &path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 启动以指定任务为前置任务的后续任务（前置任务失败时仅启动 runAfterAlways 的任务）
+fn trigger_chained_jobs(app: &AppHandle, job_id: &str, succeeded: bool) {
+    let app = app.clone();
+    let job_id = job_id.to_string();
+    tokio::spawn(async move {
+        let state = app.state::<AppState>();
+        let chained = match SyncJob::load_chained(&state.db(), &job_id).await {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                tracing::warn!("加载串联任务失败: {}", e);
+                return;
+            }
+        };
+
+        for next in chained {
+            if !next.enabled || !(succeeded || next.runAfterAlways) {
+                tracing::debug!("跳过串联任务: {} (前置任务成功={})", next.name, succeeded);
+                continue;
+            }
+            tracing::info!("前置任务 {} 已结束，启动串联任务: {}", job_id, next.name);
+            if let Err(e) = start_sync(next.id.clone(), None, None, None, None, None, None, None, None, None, app.state::<AppState>(), app.clone()).await {
+                tracing::warn!("启动串联任务失败: {} - {}", next.name, e);
+            }
+        }
+    });
+}
+
+/// 检查任务的源和目标能否连接（目标根目录不存在不算失败，同步时可自动创建）
+async fn check_job_storages(job: &SyncJob) -> Result<(), String> {
+    let source = crate::storage::create_job_source_storage(job)
+        .await
+        .map_err(|e| format!("源存储连接失败: {}", e))?;
+    let dest = crate::storage::create_storage(&job.destConfig)
+        .await
+        .map_err(|e| format!("目标存储连接失败: {}", e))?;
+    check_storages(source.as_ref(), dest.as_ref()).await
+}
+
+/// 向两端发出真实请求：源根目录必须存在，目标根目录不存在时视为可用
+async fn check_storages(
+    source: &dyn crate::storage::Storage,
+    dest: &dyn crate::storage::Storage,
+) -> Result<(), String> {
+    source
+        .probe_root()
+        .await
+        .map_err(|e| format!("源存储无法访问: {}", e))?;
+    match dest.probe_root().await {
+        Err(e) if !crate::storage::SyncError::classify(&e).is_dir_missing() => {
+            Err(format!("目标存储无法访问: {}", e))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 检查任务的存储连接
+#[tauri::command]
+pub async fn check_job(job_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let job = SyncJob::load(&state.db(), &job_id)
+        .await
+        .map_err(|e| format!("加载任务失败: {}", e))?
+        .ok_or_else(|| "任务不存在".to_string())?;
+    check_job_storages(&job).await
+}
+
+/// 定时触发前检查存储连接（任务开启了 checkBeforeRun 时），不可用时等待后重试
+///
+/// 重试用尽后仍照常启动同步，由同步记录失败以提醒用户
+async fn wait_until_reachable(app: &AppHandle, job_id: &str) {
+    let config = crate::config::SchedulerConfig::load(&app.state::<AppState>().config_dir());
+    let interval = std::time::Duration::from_secs(config.check_retry_secs);
+    let retries = config.check_retries;
+    let result = retry_check(retries, interval, |attempt| async move {
+        let state = app.state::<AppState>();
+        let job = match SyncJob::load(&state.db(), job_id).await {
+            Ok(Some(job)) if job.checkBeforeRun => job,
+            _ => return Ok(()),
+        };
+        let result = check_job_storages(&job).await;
+        if let Err(e) = &result {
+            tracing::warn!(
+                "任务 {} 的存储暂不可用 ({}/{}): {}",
+                job.name,
+                attempt,
+                retries + 1,
+                e
+            );
+        }
+        result
+    })
+    .await;
+    if let Err(e) = result {
+        tracing::warn!("任务 {} 的存储连接检查多次失败，照常启动同步: {}", job_id, e);
+    }
+}
+
+/// 执行检查直到成功，失败时等待 `interval` 后重试，最多重试 `retries` 次；返回最后一次检查的结果
+///
+/// `check` 的参数为第几次检查（从 1 开始）
+async fn retry_check<F, Fut>(
+    retries: u32,
+    interval: std::time::Duration,
+    mut check: F,
+) -> Result<(), String>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: std::future::Future<Output = Result<(), String>>,
+{
+    let mut attempt = 1;
+    loop {
+        match check(attempt).await {
+            Err(_) if attempt <= retries => {
+                tokio::time::sleep(interval).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// 启动定时调度：注册所有任务，到点时启动同步
+pub fn start_scheduler(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        match SyncJob::load_all(&state.db()).await {
+            Ok(jobs) => {
+                for job in &jobs {
+                    state.scheduler.update_job(job);
+                }
+            }
+            Err(e) => tracing::warn!("加载定时任务失败: {}", e),
+        }
+        match SyncJob::load_skipped_runs(&state.db()).await {
+            Ok(skipped) => {
+                for (job_id, skipped_at) in skipped {
+                    state.scheduler.restore_skip(&job_id, skipped_at);
+                }
+            }
+            Err(e) => tracing::warn!("加载跳过的定时触发失败: {}", e),
+        }
+
+        let scheduler = state.scheduler.clone();
+        scheduler
+            .run(|job_id| {
+                let app = app.clone();
+                tokio::spawn(async move {
+                    tracing::info!("定时触发同步任务: {}", job_id);
+                    wait_until_reachable(&app, &job_id).await;
+                    if let Err(e) = start_sync(job_id.clone(), None, None, None, None, None, None, None, None, None, app.state::<AppState>(), app.clone()).await {
+                        tracing::warn!("定时任务启动失败: {} - {}", job_id, e);
+                    }
+                });
+            })
+            .await;
+    });
+}
+
 /// 取消同步任务
 #[tauri::command]
 pub async fn cancel_sync(job_id: String, state: State<'_, AppState>) -> Result<(), String> {
@@ -379,17 +886,32 @@ pub async fn cancel_sync(job_id: String, state: State<'_, AppState>) -> Result<(
     }
 }
 
-/// 取消分析任务
+/// 取消标志的键：按操作区分，同一任务上同时运行的分析、校验等操作互不覆盖
+fn cancel_key(operation: &str, job_id: &str) -> String {
+    format!("{}:{}", operation, job_id)
+}
+
+/// 取消分析任务（以及共用取消机制的校验、查重等操作）
+///
+/// 指定 `operation` 时只取消该操作，否则取消任务上所有正在运行的操作
 #[tauri::command]
-pub async fn cancel_analyze(job_id: String, state: State<'_, AppState>) -> Result<(), String> {
+pub async fn cancel_analyze(
+    job_id: String,
+    operation: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
     let cancels = state.analyze_cancels.lock().await;
-    if let Some(flag) = cancels.get(&job_id) {
-        flag.store(true, Ordering::Relaxed);
-        Ok(())
-    } else {
-        // 没有正在运行的分析任务也返回成功，前端可能已经取消
-        Ok(())
+    for (key, flag) in cancels.iter() {
+        let matched = match &operation {
+            Some(op) => *key == cancel_key(op, &job_id),
+            None => key.split_once(':').is_some_and(|(_, id)| id == job_id),
+        };
+        if matched {
+            flag.store(true, Ordering::Relaxed);
+        }
     }
+    // 没有正在运行的操作也返回成功，前端可能已经取消
+    Ok(())
 }
 
 /// 获取未完成的传输状态
@@ -400,7 +922,7 @@ pub async fn get_pending_transfers(
 ) -> Result<Vec<PendingTransfer>, String> {
     use crate::core::TransferManager;
 
-    let manager = TransferManager::new(state.db.clone());
+    let manager = TransferManager::new(state.db());
     let transfers = manager
         .get_pending_transfers(&job_id)
         .await
@@ -440,7 +962,7 @@ pub async fn resume_sync(
 ) -> Result<String, String> {
     // 检查是否有未完成的传输
     use crate::core::TransferManager;
-    let manager = TransferManager::new(state.db.clone());
+    let manager = TransferManager::new(state.db());
     let pending = manager
         .get_pending_transfers(&job_id)
         .await
@@ -448,7 +970,7 @@ pub async fn resume_sync(
 
     if pending.is_empty() {
         // 没有未完成的传输，执行正常同步
-        return start_sync(job_id, auto_create_dir, max_concurrent, None, state, app).await;
+        return start_sync(job_id, auto_create_dir, max_concurrent, None, None, None, None, None, None, None, state, app).await;
     }
 
     tracing::debug!(
@@ -458,7 +980,7 @@ pub async fn resume_sync(
     );
 
     // 重新开始同步（会自动跳过已完成的文件）
-    start_sync(job_id, auto_create_dir, max_concurrent, None, state, app).await
+    start_sync(job_id, auto_create_dir, max_concurrent, None, None, None, None, None, None, None, state, app).await
 }
 
 /// 同步历史记录条目
@@ -471,6 +993,10 @@ pub struct SyncHistoryEntry {
     pub status: String,
     pub files_scanned: i64,
     pub files_copied: i64,
+    /// 从目标复制到源的文件数（旧记录为 None）
+    pub files_copied_to_source: Option<i64>,
+    /// 从源复制到目标的文件数（旧记录为 None）
+    pub files_copied_to_dest: Option<i64>,
     pub files_deleted: Option<i64>,
     pub files_skipped: Option<i64>,
     pub files_failed: Option<i64>,
@@ -488,6 +1014,8 @@ struct SyncLogRow {
     pub status: String,
     pub files_scanned: i64,
     pub files_copied: i64,
+    pub files_copied_to_source: Option<i64>,
+    pub files_copied_to_dest: Option<i64>,
     pub files_deleted: Option<i64>,
     pub bytes_transferred: i64,
     pub error_message: Option<String>,
@@ -501,7 +1029,7 @@ pub async fn get_sync_history(
     state: State<'_, AppState>,
 ) -> Result<Vec<SyncHistoryEntry>, String> {
     let logs = sqlx::query_as::<_, SyncLogRow>(
-        "SELECT id, job_id, start_time, end_time, status, files_scanned, files_copied, files_deleted, bytes_transferred, error_message
+        "SELECT id, job_id, start_time, end_time, status, files_scanned, files_copied, files_copied_to_source, files_copied_to_dest, files_deleted, bytes_transferred, error_message
          FROM sync_logs
          WHERE job_id = ?
          ORDER BY start_time DESC
@@ -509,7 +1037,7 @@ pub async fn get_sync_history(
     )
     .bind(&job_id)
     .bind(limit)
-    .fetch_all(&*state.db)
+    .fetch_all(&*state.db())
     .await
     .map_err(|e| e.to_string())?;
 
@@ -523,6 +1051,8 @@ pub async fn get_sync_history(
             status: log.status,
             files_scanned: log.files_scanned,
             files_copied: log.files_copied,
+            files_copied_to_source: log.files_copied_to_source,
+            files_copied_to_dest: log.files_copied_to_dest,
             files_deleted: log.files_deleted,
             files_skipped: None, // 未在数据库中存储
             files_failed: None,  // 未在数据库中存储
@@ -532,19 +1062,487 @@ pub async fn get_sync_history(
         .collect())
 }
 
-/// 清除任务的扫描缓存
+/// 只重试某次同步中失败的文件（不重新扫描整个存储）
+#[tauri::command]
+pub async fn retry_failed(
+    log_id: i64,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<crate::core::SyncReport, String> {
+    let _task = state.begin_task()?;
+    let job_id: String = sqlx::query_scalar("SELECT job_id FROM sync_logs WHERE id = ?")
+        .bind(log_id)
+        .fetch_optional(&*state.db())
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "同步记录不存在".to_string())?;
+
+    let paths: Vec<String> =
+        sqlx::query_scalar("SELECT DISTINCT file_path FROM sync_log_files WHERE log_id = ?")
+            .bind(log_id)
+            .fetch_all(&*state.db())
+            .await
+            .map_err(|e| e.to_string())?;
+    if paths.is_empty() {
+        return Err("该次同步没有失败文件记录".to_string());
+    }
+
+    let job = SyncJob::load(&state.db(), &job_id)
+        .await
+        .map_err(|e| format!("加载任务失败: {}", e))?
+        .ok_or_else(|| "任务不存在".to_string())?;
+
+    let transfer_config = crate::config::TransferConfig::load(&state.config_dir());
+    let config = crate::core::SyncConfig {
+        chunk_size: transfer_config.chunk_size_mb * 1024 * 1024,
+        large_file_threshold: transfer_config.stream_threshold_mb * 1024 * 1024,
+        delete_after_copy: transfer_config.delete_after_copy,
+        skip_locked_files: transfer_config.skip_locked_files,
+        speed_smoothing: transfer_config.speed_smoothing,
+        instant_speed: transfer_config.instant_speed,
+        preserve_xattrs: transfer_config.preserve_xattrs,
+        retry_budget: (transfer_config.retry_budget > 0).then_some(transfer_config.retry_budget),
+        temp_file_naming: transfer_config.temp_file_naming(),
+        bandwidth_limiter: Some(state.bandwidth_limiter.clone()),
+        ..Default::default()
+    };
+    let engine = Arc::new(SyncEngine::with_config(state.db(), config));
+
+    // 转发进度事件
+    let (progress_tx, mut progress_rx) =
+        tokio::sync::mpsc::channel::<crate::db::SyncProgress>(PROGRESS_CHANNEL_BUFFER);
+    let app_clone = app.clone();
+    tokio::spawn(async move {
+        while let Some(progress) = progress_rx.recv().await {
+            let _ = app_clone.emit("sync-progress", &progress);
+        }
+    });
+
+    // 支持通过 cancel_sync 取消
+    let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
+    state.cancel_signals.lock().await.insert(job_id.clone(), cancel_tx);
+    let engine_for_cancel = engine.clone();
+    let cancel_handle = tokio::spawn(async move {
+        if cancel_rx.await.is_ok() {
+            engine_for_cancel.cancel();
+        }
+    });
+
+    let result = engine.sync_paths(&job, &paths, Some(progress_tx)).await;
+
+    cancel_handle.abort();
+    state.cancel_signals.lock().await.remove(&job_id);
+
+    result.map_err(|e| e.to_string())
+}
+
+/// 估算首次上传量（只扫描源存储，目标视为空）
+#[tauri::command]
+pub async fn estimate_upload(
+    job_id: String,
+    force_refresh: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<UploadEstimate, String> {
+    let _task = state.begin_task()?;
+    let force_refresh = force_refresh.unwrap_or(false);
+    // 与分析共用取消标志，可通过 cancel_analyze 取消
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    state
+        .analyze_cancels
+        .lock()
+        .await
+        .insert(cancel_key("estimate", &job_id), cancel_flag.clone());
+
+    let cleanup_state = state.analyze_cancels.clone();
+    let cleanup_key = cancel_key("estimate", &job_id);
+    scopeguard::defer! {
+        tokio::spawn(async move {
+            cleanup_state.lock().await.remove(&cleanup_key);
+        });
+    }
+
+    let job = SyncJob::load(&state.db(), &job_id)
+        .await
+        .map_err(|e| format!("加载任务失败: {}", e))?
+        .ok_or_else(|| "任务不存在".to_string())?;
+
+    let source_storage = crate::storage::create_job_source_storage(&job)
+        .await
+        .map_err(|e| format!("源存储连接失败: {}", e))?;
+
+    // 与 analyze_job 共用源文件列表缓存
+    let cache_config = crate::config::CacheConfig::load(&state.config_dir());
+    let source_ttl = if job.source_is_local() {
+        0
+    } else {
+        cache_config.remote_ttl
+    };
+    let source_cache =
+        crate::core::FileListCache::new(state.config_dir().join("cache"))
+            .with_ttl(source_ttl)
+            .with_format(cache_config.format);
+    let source_config_json = job.source_config_json();
+
+    let cached = if force_refresh {
+        source_cache.clear(&job_id, Some("source"));
+        None
+    } else {
+        source_cache.load(&job_id, "source", &source_config_json)
+    };
+
+    let mut source_cached_at: u64 = 0;
+    let source_tree = match cached {
+        Some(cached) => {
+            source_cached_at = cached.cached_at;
+            cached.files
+        }
+        None => {
+            let scan_config = crate::config::ScannerConfig::load(&state.config_dir())
+                .to_scan_config()
+                .with_excludes(job.excludePreset, &job.excludePatterns);
+            let scanner = FileScanner::with_config(scan_config.concurrency, scan_config)
+                .with_cancel_flag(cancel_flag.clone());
+            let tree = scanner
+                .scan_storage_with_timeout(source_storage.as_ref())
+                .await
+                .map_err(|e| {
+                    if cancel_flag.load(Ordering::Relaxed) {
+                        "操作已取消".to_string()
+                    } else {
+                        format!("扫描源存储失败: {}", e)
+                    }
+                })?;
+            let _ = source_cache.save(&job_id, "source", &source_config_json, &tree);
+            tree
+        }
+    };
+
+    let (dirs, files): (Vec<_>, Vec<_>) = source_tree.values().partition(|f| f.is_dir);
+
+    Ok(UploadEstimate {
+        source_name: source_storage.name().to_string(),
+        file_count: files.len(),
+        dir_count: dirs.len(),
+        total_bytes: files.iter().map(|f| f.size).sum(),
+        source_cached_at,
+    })
+}
+
+/// 扫描任务的一端并返回原始文件列表（用于调试和外部工具）
+///
+/// `side` 为 "source" 或 "dest"；`use_cache` 为 true 时优先使用未过期的扫描缓存。
+#[tauri::command]
+pub async fn scan_endpoint(
+    job_id: String,
+    side: String,
+    use_cache: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::storage::FileInfo>, String> {
+    let _task = state.begin_task()?;
+    let is_source = match side.as_str() {
+        "source" => true,
+        "dest" => false,
+        _ => return Err(format!("无效的端: {}（应为 source 或 dest）", side)),
+    };
+
+    // 与分析共用取消标志，可通过 cancel_analyze 取消
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    state
+        .analyze_cancels
+        .lock()
+        .await
+        .insert(cancel_key("scan", &job_id), cancel_flag.clone());
+
+    let cleanup_state = state.analyze_cancels.clone();
+    let cleanup_key = cancel_key("scan", &job_id);
+    scopeguard::defer! {
+        tokio::spawn(async move {
+            cleanup_state.lock().await.remove(&cleanup_key);
+        });
+    }
+
+    let job = SyncJob::load(&state.db(), &job_id)
+        .await
+        .map_err(|e| format!("加载任务失败: {}", e))?
+        .ok_or_else(|| "任务不存在".to_string())?;
+
+    let (storage, is_local, config_json) = if is_source {
+        let storage = crate::storage::create_job_source_storage(&job)
+            .await
+            .map_err(|e| format!("源存储连接失败: {}", e))?;
+        (storage, job.source_is_local(), job.source_config_json())
+    } else {
+        let storage = crate::storage::create_storage(&job.destConfig)
+            .await
+            .map_err(|e| format!("目标存储连接失败: {}", e))?;
+        let is_local = matches!(job.destConfig.typ, crate::db::StorageType::Local);
+        (storage, is_local, serde_json::to_string(&job.destConfig).unwrap_or_default())
+    };
+
+    // 与 analyze_job 共用文件列表缓存
+    let cache_config = crate::config::CacheConfig::load(&state.config_dir());
+    let ttl = if is_local { 0 } else { cache_config.remote_ttl };
+    let cache = crate::core::FileListCache::new(state.config_dir().join("cache"))
+        .with_ttl(ttl)
+        .with_format(cache_config.format);
+
+    let cached = if use_cache.unwrap_or(false) {
+        cache.load(&job_id, &side, &config_json)
+    } else {
+        None
+    };
+    let tree = match cached {
+        Some(cached) => cached.files,
+        None => {
+            let scan_config = crate::config::ScannerConfig::load(&state.config_dir())
+                .to_scan_config()
+                .with_excludes(job.excludePreset, &job.excludePatterns);
+            let scanner = FileScanner::with_config(scan_config.concurrency, scan_config)
+                .with_cancel_flag(cancel_flag.clone());
+            let tree = scanner
+                .scan_storage_with_timeout(storage.as_ref())
+                .await
+                .map_err(|e| {
+                    if cancel_flag.load(Ordering::Relaxed) {
+                        "操作已取消".to_string()
+                    } else {
+                        format!("扫描 {} 失败: {}", storage.name(), e)
+                    }
+                })?;
+            let _ = cache.save(&job_id, &side, &config_json, &tree);
+            tree
+        }
+    };
+
+    let mut files: Vec<_> = tree.into_values().collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// 查找源存储中内容重复的文件
+#[tauri::command]
+pub async fn find_duplicates(
+    job_id: String,
+    state: State<'_, AppState>,
+) -> Result<crate::core::DuplicateReport, String> {
+    let _task = state.begin_task()?;
+    // 与分析共用取消标志，可通过 cancel_analyze 取消
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    state
+        .analyze_cancels
+        .lock()
+        .await
+        .insert(cancel_key("dedup", &job_id), cancel_flag.clone());
+
+    let cleanup_state = state.analyze_cancels.clone();
+    let cleanup_key = cancel_key("dedup", &job_id);
+    scopeguard::defer! {
+        tokio::spawn(async move {
+            cleanup_state.lock().await.remove(&cleanup_key);
+        });
+    }
+
+    let job = SyncJob::load(&state.db(), &job_id)
+        .await
+        .map_err(|e| format!("加载任务失败: {}", e))?
+        .ok_or_else(|| "任务不存在".to_string())?;
+
+    let source_storage = crate::storage::create_job_source_storage(&job)
+        .await
+        .map_err(|e| format!("源存储连接失败: {}", e))?;
+
+    let scan_config = crate::config::ScannerConfig::load(&state.config_dir())
+        .to_scan_config()
+        .with_excludes(job.excludePreset, &job.excludePatterns);
+    let scanner = FileScanner::with_config(scan_config.concurrency, scan_config)
+        .with_cancel_flag(cancel_flag.clone());
+    let tree = scanner
+        .scan_storage_with_timeout(source_storage.as_ref())
+        .await
+        .map_err(|e| format!("扫描源存储失败: {}", e))?;
+
+    crate::core::dedup::find_duplicates(source_storage.as_ref(), &tree, Some(&cancel_flag))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 校验目标：按已保存的文件状态检查目标是否与上次同步一致（不复制任何文件）
+#[tauri::command]
+pub async fn verify_destination(
+    job_id: String,
+    state: State<'_, AppState>,
+) -> Result<crate::core::VerifyReport, String> {
+    let _task = state.begin_task()?;
+    // 与分析共用取消标志，可通过 cancel_analyze 取消
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    state
+        .analyze_cancels
+        .lock()
+        .await
+        .insert(cancel_key("verify", &job_id), cancel_flag.clone());
+
+    let cleanup_state = state.analyze_cancels.clone();
+    let cleanup_key = cancel_key("verify", &job_id);
+    scopeguard::defer! {
+        tokio::spawn(async move {
+            cleanup_state.lock().await.remove(&cleanup_key);
+        });
+    }
+
+    let job = SyncJob::load(&state.db(), &job_id)
+        .await
+        .map_err(|e| format!("加载任务失败: {}", e))?
+        .ok_or_else(|| "任务不存在".to_string())?;
+
+    let states = crate::core::FileStateManager::new(state.db())
+        .get_job_states(&job_id)
+        .await
+        .map_err(|e| format!("加载文件状态失败: {}", e))?;
+    if states.is_empty() {
+        return Err("没有已同步的文件记录，请先完成一次同步".to_string());
+    }
+
+    let dest_storage = crate::storage::create_storage(&job.destConfig)
+        .await
+        .map_err(|e| format!("目标存储连接失败: {}", e))?;
+
+    let verify = crate::core::verify::verify_destination(
+        dest_storage.as_ref(),
+        &states,
+        job.effective_case_fold(),
+        Some(&cancel_flag),
+    );
+    // 逐个获取目标文件信息，与扫描使用相同的时间上限
+    let scan_timeout = crate::config::ScannerConfig::load(&state.config_dir())
+        .to_scan_config()
+        .scan_timeout_secs;
+    let result = match scan_timeout {
+        Some(secs) => tokio::time::timeout(std::time::Duration::from_secs(secs), verify)
+            .await
+            .map_err(|_| {
+                format!("校验超时：超过 {} 秒仍未完成，请在扫描设置中调高扫描超时", secs)
+            })?,
+        None => verify.await,
+    };
+    result.map_err(|e| e.to_string())
+}
+
+/// 重建文件状态：重新扫描源并计算 hash，按源和目标的当前内容修正已保存的同步状态
+#[tauri::command]
+pub async fn rehash_job_state(
+    job_id: String,
+    state: State<'_, AppState>,
+) -> Result<crate::core::RehashReport, String> {
+    let _task = state.begin_task()?;
+    // 同步时会写入文件状态，运行中不重建
+    if state.cancel_signals.lock().await.contains_key(&job_id) {
+        return Err("任务正在同步，请等待完成后再重建文件状态".to_string());
+    }
+
+    // 与分析共用取消标志，可通过 cancel_analyze 取消
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    state
+        .analyze_cancels
+        .lock()
+        .await
+        .insert(cancel_key("rehash", &job_id), cancel_flag.clone());
+
+    let cleanup_state = state.analyze_cancels.clone();
+    let cleanup_key = cancel_key("rehash", &job_id);
+    scopeguard::defer! {
+        tokio::spawn(async move {
+            cleanup_state.lock().await.remove(&cleanup_key);
+        });
+    }
+
+    let job = SyncJob::load(&state.db(), &job_id)
+        .await
+        .map_err(|e| format!("加载任务失败: {}", e))?
+        .ok_or_else(|| "任务不存在".to_string())?;
+
+    let state_manager = crate::core::FileStateManager::new(state.db());
+    let states = state_manager
+        .get_job_states(&job_id)
+        .await
+        .map_err(|e| format!("加载文件状态失败: {}", e))?;
+    if states.is_empty() {
+        return Ok(crate::core::RehashReport::default());
+    }
+
+    let source_storage = crate::storage::create_job_source_storage(&job)
+        .await
+        .map_err(|e| format!("源存储连接失败: {}", e))?;
+    let dest_storage = crate::storage::create_storage(&job.destConfig)
+        .await
+        .map_err(|e| format!("目标存储连接失败: {}", e))?;
+
+    let scan_config = crate::config::ScannerConfig::load(&state.config_dir())
+        .to_scan_config()
+        .with_excludes(job.excludePreset, &job.excludePatterns);
+    let scanner = FileScanner::with_config(scan_config.concurrency, scan_config)
+        .with_cancel_flag(cancel_flag.clone());
+    let tree = scanner
+        .scan_storage_with_timeout(source_storage.as_ref())
+        .await
+        .map_err(|e| format!("扫描源存储失败: {}", e))?;
+
+    let (mut report, mut changes) = crate::core::rehash::rehash_states(
+        source_storage.as_ref(),
+        dest_storage.as_ref(),
+        &tree,
+        &states,
+        job.effective_case_fold(),
+        Some(&cancel_flag),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // 写回前再次检查：持有锁期间同步无法开始；重建期间被同步改动过的记录保持不变
+    let signals = state.cancel_signals.lock().await;
+    if signals.contains_key(&job_id) {
+        return Err("任务在重建期间开始同步，已放弃本次重建结果".to_string());
+    }
+    let current = state_manager
+        .get_job_states(&job_id)
+        .await
+        .map_err(|e| format!("加载文件状态失败: {}", e))?;
+    changes.retain_unchanged(&states, &current);
+    report.updated = changes.updated.len();
+    report.removed = changes.removed.len();
+
+    state_manager
+        .batch_upsert(&changes.updated)
+        .await
+        .map_err(|e| format!("保存文件状态失败: {}", e))?;
+    state_manager
+        .delete_file_states(&job_id, &changes.removed)
+        .await
+        .map_err(|e| format!("删除文件状态失败: {}", e))?;
+    drop(signals);
+
+    Ok(report)
+}
+
+/// 清除任务的扫描缓存（可只清除源或目标一侧，保留另一侧的缓存）
 #[tauri::command]
 pub async fn clear_scan_cache(
     job_id: Option<String>,
+    side: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    let cache_dir = state.config_dir.join("cache");
+    let cache_dir = state.config_dir().join("cache");
     let cache = crate::core::FileListCache::new(cache_dir);
+
+    if let Some(s) = &side {
+        if s != "source" && s != "dest" {
+            return Err(format!("无效的缓存类型: {}（应为 source 或 dest）", s));
+        }
+    }
     
     match job_id {
         Some(id) => {
-            cache.clear(&id);
-            tracing::info!("已清除任务 {} 的扫描缓存", id);
+            cache.clear(&id, side.as_deref());
+            tracing::info!("已清除任务 {} 的扫描缓存 ({})", id, side.as_deref().unwrap_or("全部"));
         }
         None => {
             cache.clear_all();
@@ -554,3 +1552,112 @@ pub async fn clear_scan_cache(
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+
+    #[test]
+    fn test_build_diff_result() {
+        let file = |path: &str, size: u64| crate::storage::FileInfo {
+            path: path.to_string(),
+            size,
+            modified_time: 100,
+            is_dir: false,
+            checksum: None,
+            hidden: false,
+        };
+        let tree = |files: &[(&str, u64)]| {
+            files
+                .iter()
+                .map(|(p, size)| (p.to_string(), file(p, *size)))
+                .collect::<std::collections::HashMap<_, _>>()
+        };
+        let source_tree = tree(&[("new.txt", 10), ("same.txt", 5)]);
+        let dest_tree = tree(&[("same.txt", 5), ("old.txt", 7)]);
+        let actions = vec![
+            SyncAction::Copy {
+                source_path: "new.txt".to_string(),
+                dest_path: "new.txt".to_string(),
+                size: 10,
+                reverse: false,
+            },
+            SyncAction::Skip {
+                path: "same.txt".to_string(),
+            },
+            SyncAction::Delete {
+                path: "old.txt".to_string(),
+                from_dest: true,
+            },
+        ];
+        let relations =
+            std::collections::HashMap::from([("same.txt".to_string(), FileRelation::Equal)]);
+
+        let result = build_diff_result(
+            "src",
+            "dst",
+            &source_tree,
+            &dest_tree,
+            &actions,
+            &relations,
+            0,
+            42,
+        );
+        assert_eq!((result.source_files, result.dest_files), (2, 2));
+        assert_eq!(
+            (result.copy_count, result.delete_count, result.skip_count, result.conflict_count),
+            (1, 1, 1, 0)
+        );
+        assert_eq!(result.total_bytes, 10);
+        assert_eq!(result.dest_cached_at, 42);
+
+        let copy = &result.actions[0];
+        assert_eq!((copy.action_type.as_str(), copy.size), ("copy", 10));
+        assert!(copy.source_exists && !copy.dest_exists);
+        assert_eq!(copy.reason, "目标中不存在");
+        let delete = &result.actions[2];
+        assert_eq!((delete.action_type.as_str(), delete.size), ("delete", 7));
+        assert!(!delete.source_exists && delete.dest_exists);
+    }
+
+    #[tokio::test]
+    async fn test_check_storages() {
+        let (source, dest) = (MemoryStorage::new(), MemoryStorage::new());
+        assert!(check_storages(&source, &dest).await.is_ok());
+
+        // 目标根目录不存在时同步可以自动创建
+        dest.set_root_missing(true);
+        assert!(check_storages(&source, &dest).await.is_ok());
+
+        // 源根目录不存在（如磁盘未挂载、VPN 断开）视为不可用
+        source.set_root_missing(true);
+        let err = check_storages(&source, &dest).await.unwrap_err();
+        assert!(err.starts_with("源存储无法访问"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_retry_check() {
+        let interval = std::time::Duration::ZERO;
+
+        // 第三次检查成功
+        let mut calls = 0;
+        let result = retry_check(3, interval, |attempt| {
+            calls += 1;
+            async move { if attempt < 3 { Err(format!("失败 {}", attempt)) } else { Ok(()) } }
+        })
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(calls, 3);
+
+        // 重试用尽后返回最后一次的错误
+        let mut calls = 0;
+        let result = retry_check(2, interval, |attempt| {
+            calls += 1;
+            async move { Err::<(), _>(format!("失败 {}", attempt)) }
+        })
+        .await;
+        assert_eq!(result.unwrap_err(), "失败 3");
+        assert_eq!(calls, 3);
+    }
+}
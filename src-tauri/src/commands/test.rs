@@ -1,5 +1,6 @@
 #![allow(clippy::too_many_arguments)]
 
+use crate::storage::secret::resolve_optional;
 use serde::Serialize;
 
 #[derive(Debug, Serialize)]
@@ -22,11 +23,100 @@ pub async fn test_connection(
     root: Option<String>,
     username: Option<String>,
     password: Option<String>,
+    key_id: Option<String>,
+    application_key: Option<String>,
+    bucket_id: Option<String>,
+    http_url: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    refresh_token: Option<String>,
+    tenant: Option<String>,
+    drive_id: Option<String>,
+    account_name: Option<String>,
+    account_key: Option<String>,
+    sas_token: Option<String>,
+    service_account_json: Option<String>,
+    force_path_style: Option<bool>,
 ) -> Result<TestConnectionResult, String> {
+    // 解析凭据引用（${env:NAME} / ${file:/path}）
+    let secrets = resolve_optional(&access_key).and_then(|ak| {
+        Ok((
+            ak,
+            resolve_optional(&secret_key)?,
+            resolve_optional(&password)?,
+            resolve_optional(&application_key)?,
+            resolve_optional(&client_secret)?,
+            resolve_optional(&refresh_token)?,
+            resolve_optional(&account_key)?,
+            resolve_optional(&sas_token)?,
+            resolve_optional(&service_account_json)?,
+        ))
+    });
+    let (
+        access_key,
+        secret_key,
+        password,
+        application_key,
+        client_secret,
+        refresh_token,
+        account_key,
+        sas_token,
+        service_account_json,
+    ) = match secrets {
+        Ok(s) => s,
+        Err(e) => {
+            return Ok(TestConnectionResult {
+                success: false,
+                message: "读取凭据失败".to_string(),
+                details: Some(e.to_string()),
+            })
+        }
+    };
+
     match typ.as_str() {
         "local" => test_local_connection(&path).await,
-        "s3" => test_s3_connection(&bucket, &region, &access_key, &secret_key, &endpoint).await,
+        "s3" => {
+            test_s3_connection(
+                &bucket,
+                &region,
+                &access_key,
+                &secret_key,
+                &endpoint,
+                force_path_style,
+            )
+            .await
+        }
         "webdav" => test_webdav_connection(&webdav_endpoint, &root, &username, &password).await,
+        "b2" => test_b2_connection(&key_id, &application_key, &bucket, &bucket_id).await,
+        "http" => test_http_connection(&http_url, &username, &password).await,
+        "onedrive" => {
+            test_onedrive_connection(
+                &client_id,
+                client_secret,
+                &refresh_token,
+                &tenant,
+                &drive_id,
+                &root,
+            )
+            .await
+        }
+        "azblob" => {
+            test_azblob_connection(&account_name, &account_key, &sas_token, &bucket, &endpoint)
+                .await
+        }
+        "gcs" => {
+            test_gcs_connection(
+                &bucket,
+                service_account_json,
+                &access_key,
+                &secret_key,
+                &endpoint,
+            )
+            .await
+        }
+        "dropbox" => {
+            test_dropbox_connection(&client_id, client_secret, &refresh_token, &root).await
+        }
         _ => Ok(TestConnectionResult {
             success: false,
             message: "不支持的存储类型".to_string(),
@@ -82,6 +172,7 @@ async fn test_s3_connection(
     access_key: &Option<String>,
     secret_key: &Option<String>,
     endpoint: &Option<String>,
+    force_path_style: Option<bool>,
 ) -> Result<TestConnectionResult, String> {
     use opendal::services::S3;
     use opendal::Operator;
@@ -114,6 +205,11 @@ async fn test_s3_connection(
         }
     }
 
+    // 与同步时使用相同的地址风格
+    if !crate::storage::s3::use_path_style(force_path_style, endpoint.as_deref(), bucket) {
+        builder = builder.enable_virtual_host_style();
+    }
+
     let operator = Operator::new(builder)
         .map_err(|e| format!("S3 配置错误: {}", e))?
         .finish();
@@ -190,3 +286,256 @@ async fn test_webdav_connection(
         }),
     }
 }
+
+async fn test_b2_connection(
+    key_id: &Option<String>,
+    application_key: &Option<String>,
+    bucket: &Option<String>,
+    bucket_id: &Option<String>,
+) -> Result<TestConnectionResult, String> {
+    use opendal::services::B2;
+    use opendal::Operator;
+
+    let key_id = key_id
+        .as_ref()
+        .ok_or_else(|| "B2 Key ID 不能为空".to_string())?;
+
+    let application_key = application_key
+        .as_ref()
+        .ok_or_else(|| "B2 Application Key 不能为空".to_string())?;
+
+    let bucket = bucket
+        .as_ref()
+        .ok_or_else(|| "B2 bucket 不能为空".to_string())?;
+
+    let bucket_id = bucket_id
+        .as_ref()
+        .ok_or_else(|| "B2 bucket ID 不能为空".to_string())?;
+
+    let builder = B2::default()
+        .application_key_id(key_id)
+        .application_key(application_key)
+        .bucket(bucket)
+        .bucket_id(bucket_id);
+
+    let operator = Operator::new(builder)
+        .map_err(|e| format!("B2 配置错误: {}", e))?
+        .finish();
+
+    match operator.list("").await {
+        Ok(_) => Ok(TestConnectionResult {
+            success: true,
+            message: "B2 连接成功".to_string(),
+            details: Some(format!("Bucket: {}", bucket)),
+        }),
+        Err(e) => Ok(TestConnectionResult {
+            success: false,
+            message: "B2 连接失败".to_string(),
+            details: Some(format!("检查密钥和 bucket 信息: {}", e)),
+        }),
+    }
+}
+
+async fn test_azblob_connection(
+    account_name: &Option<String>,
+    account_key: &Option<String>,
+    sas_token: &Option<String>,
+    container: &Option<String>,
+    endpoint: &Option<String>,
+) -> Result<TestConnectionResult, String> {
+    use crate::storage::{AzureBlobStorage, Storage};
+
+    let account_name = account_name
+        .as_ref()
+        .ok_or_else(|| "Azure 存储账户名不能为空".to_string())?;
+
+    let container = container
+        .as_ref()
+        .ok_or_else(|| "Azure 容器名不能为空".to_string())?;
+
+    let storage = AzureBlobStorage::new(
+        account_name,
+        account_key.as_deref(),
+        sas_token.as_deref(),
+        container,
+        endpoint.clone(),
+        None,
+        None,
+    )
+    .await
+    .map_err(|e| format!("Azure Blob 配置错误: {}", e))?;
+
+    match storage.verify_container().await {
+        Ok(_) => Ok(TestConnectionResult {
+            success: true,
+            message: "Azure Blob 连接成功".to_string(),
+            details: Some(storage.name().to_string()),
+        }),
+        Err(e) => Ok(TestConnectionResult {
+            success: false,
+            message: "Azure Blob 连接失败".to_string(),
+            details: Some(format!("检查账户名、密钥/SAS 令牌和容器: {}", e)),
+        }),
+    }
+}
+
+async fn test_gcs_connection(
+    bucket: &Option<String>,
+    service_account_json: Option<String>,
+    access_key: &Option<String>,
+    secret_key: &Option<String>,
+    endpoint: &Option<String>,
+) -> Result<TestConnectionResult, String> {
+    use crate::storage::GcsStorage;
+
+    let bucket = bucket
+        .as_ref()
+        .ok_or_else(|| "GCS bucket 不能为空".to_string())?;
+
+    let storage = match (service_account_json, access_key, secret_key) {
+        (Some(credential), _, _) => {
+            GcsStorage::new(bucket, &credential, endpoint.clone(), None, None).await
+        }
+        (None, Some(ak), Some(sk)) => {
+            GcsStorage::with_hmac(bucket, ak, sk, endpoint.clone(), None, None).await
+        }
+        _ => return Err("GCS 需要服务账号 JSON 或 HMAC 密钥".to_string()),
+    }
+    .map_err(|e| format!("GCS 配置错误: {}", e))?;
+
+    match storage.verify_bucket().await {
+        Ok(_) => Ok(TestConnectionResult {
+            success: true,
+            message: "GCS 连接成功".to_string(),
+            details: Some(format!("Bucket: {}", bucket)),
+        }),
+        Err(e) => Ok(TestConnectionResult {
+            success: false,
+            message: "GCS 连接失败".to_string(),
+            details: Some(format!("检查凭据和 bucket 权限: {}", e)),
+        }),
+    }
+}
+
+async fn test_http_connection(
+    http_url: &Option<String>,
+    username: &Option<String>,
+    password: &Option<String>,
+) -> Result<TestConnectionResult, String> {
+    let url = http_url
+        .as_ref()
+        .ok_or_else(|| "HTTP 地址不能为空".to_string())?;
+
+    let client = reqwest::Client::new();
+    let mut request = client.get(url);
+    if let Some(user) = username {
+        request = request.basic_auth(user, password.as_ref());
+    }
+
+    match request.send().await {
+        Ok(response) if response.status().is_success() => Ok(TestConnectionResult {
+            success: true,
+            message: "HTTP 连接成功".to_string(),
+            details: Some(format!("{} (只读)", url)),
+        }),
+        Ok(response) => Ok(TestConnectionResult {
+            success: false,
+            message: "HTTP 连接失败".to_string(),
+            details: Some(format!("服务器返回 {}", response.status())),
+        }),
+        Err(e) => Ok(TestConnectionResult {
+            success: false,
+            message: "HTTP 连接失败".to_string(),
+            details: Some(format!("检查地址和网络: {}", e)),
+        }),
+    }
+}
+
+async fn test_onedrive_connection(
+    client_id: &Option<String>,
+    client_secret: Option<String>,
+    refresh_token: &Option<String>,
+    tenant: &Option<String>,
+    drive_id: &Option<String>,
+    root: &Option<String>,
+) -> Result<TestConnectionResult, String> {
+    use crate::storage::{OneDriveStorage, Storage};
+
+    let client_id = client_id
+        .as_ref()
+        .ok_or_else(|| "OneDrive 应用 ID 不能为空".to_string())?;
+
+    let refresh_token = refresh_token
+        .as_ref()
+        .ok_or_else(|| "OneDrive 刷新令牌不能为空".to_string())?;
+
+    let storage = OneDriveStorage::new(
+        client_id,
+        client_secret,
+        refresh_token,
+        tenant.as_deref(),
+        drive_id.as_deref(),
+        root.as_deref(),
+        None,
+    )
+    .map_err(|e| format!("OneDrive 配置错误: {}", e))?;
+
+    match storage.stat("").await {
+        Ok(Some(_)) => Ok(TestConnectionResult {
+            success: true,
+            message: "OneDrive 连接成功".to_string(),
+            details: Some(storage.name().to_string()),
+        }),
+        Ok(None) => Ok(TestConnectionResult {
+            success: false,
+            message: "OneDrive 根目录不存在".to_string(),
+            details: Some(format!("根目录: {}", root.as_deref().unwrap_or("/"))),
+        }),
+        Err(e) => Ok(TestConnectionResult {
+            success: false,
+            message: "OneDrive 连接失败".to_string(),
+            details: Some(format!("检查应用 ID、刷新令牌和租户: {}", e)),
+        }),
+    }
+}
+
+async fn test_dropbox_connection(
+    app_key: &Option<String>,
+    app_secret: Option<String>,
+    refresh_token: &Option<String>,
+    root: &Option<String>,
+) -> Result<TestConnectionResult, String> {
+    use crate::storage::{DropboxStorage, Storage};
+
+    let app_key = app_key
+        .as_ref()
+        .ok_or_else(|| "Dropbox App Key 不能为空".to_string())?;
+
+    let refresh_token = refresh_token
+        .as_ref()
+        .ok_or_else(|| "Dropbox 刷新令牌不能为空".to_string())?;
+
+    let storage = DropboxStorage::new(app_key, app_secret, refresh_token, root.as_deref(), None)
+        .map_err(|e| format!("Dropbox 配置错误: {}", e))?;
+
+    // Dropbox 根目录的 stat 不发请求，先单独验证令牌
+    match storage.verify_account().await {
+        Ok(_) => match storage.stat("").await {
+            Ok(Some(_)) => Ok(TestConnectionResult {
+                success: true,
+                message: "Dropbox 连接成功".to_string(),
+                details: Some(storage.name().to_string()),
+            }),
+            _ => Ok(TestConnectionResult {
+                success: false,
+                message: "Dropbox 根目录不存在".to_string(),
+                details: Some(format!("根目录: {}", root.as_deref().unwrap_or("/"))),
+            }),
+        },
+        Err(e) => Ok(TestConnectionResult {
+            success: false,
+            message: "Dropbox 连接失败".to_string(),
+            details: Some(format!("检查 App Key、刷新令牌和根目录: {}", e)),
+        }),
+    }
+}
@@ -1,13 +1,21 @@
 //! 传输配置相关命令
 
 use crate::config::TransferConfig;
+use crate::core::transfer::TEMP_SWEEP_MIN_AGE_SECS;
+use crate::core::TransferManager;
 use crate::AppState;
-use tauri::State;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+/// 最小速度平滑系数
+const MIN_SPEED_SMOOTHING: f64 = 0.01;
+/// 最大速度平滑系数（1 即不平滑）
+const MAX_SPEED_SMOOTHING: f64 = 1.0;
 
 /// 获取传输配置
 #[tauri::command]
 pub async fn get_transfer_config(state: State<'_, AppState>) -> Result<TransferConfig, String> {
-    Ok(TransferConfig::load(&state.config_dir))
+    Ok(TransferConfig::load(&state.config_dir()))
 }
 
 /// 设置传输配置
@@ -15,9 +23,23 @@ pub async fn get_transfer_config(state: State<'_, AppState>) -> Result<TransferC
 pub async fn set_transfer_config(
     chunk_size_mb: Option<u64>,
     stream_threshold_mb: Option<u64>,
+    delete_after_copy: Option<bool>,
+    detect_renames: Option<bool>,
+    overwrite_policy: Option<crate::core::OverwritePolicy>,
+    zero_byte_compare: Option<crate::core::ZeroByteCompare>,
+    skip_locked_files: Option<bool>,
+    speed_smoothing: Option<f64>,
+    instant_speed: Option<bool>,
+    bandwidth_schedule: Option<Vec<crate::core::BandwidthWindow>>,
+    preserve_xattrs: Option<bool>,
+    retry_budget: Option<u32>,
+    prune_empty_dirs: Option<bool>,
+    prune_existing_empty_dirs: Option<bool>,
+    temp_file_prefix: Option<String>,
+    temp_file_keep_extension: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<TransferConfig, String> {
-    let mut config = TransferConfig::load(&state.config_dir);
+    let mut config = TransferConfig::load(&state.config_dir());
     
     if let Some(size) = chunk_size_mb {
         config.chunk_size_mb = size;
@@ -25,8 +47,88 @@ pub async fn set_transfer_config(
     if let Some(threshold) = stream_threshold_mb {
         config.stream_threshold_mb = threshold;
     }
+    if let Some(d) = delete_after_copy {
+        config.delete_after_copy = d;
+    }
+    if let Some(d) = detect_renames {
+        config.detect_renames = d;
+    }
+    if let Some(policy) = overwrite_policy {
+        config.overwrite_policy = policy;
+    }
+    if let Some(mode) = zero_byte_compare {
+        config.zero_byte_compare = mode;
+    }
+    if let Some(skip) = skip_locked_files {
+        config.skip_locked_files = skip;
+    }
+    if let Some(factor) = speed_smoothing {
+        if !factor.is_finite() {
+            return Err("速度平滑系数无效".to_string());
+        }
+        config.speed_smoothing = factor.clamp(MIN_SPEED_SMOOTHING, MAX_SPEED_SMOOTHING);
+    }
+    if let Some(instant) = instant_speed {
+        config.instant_speed = instant;
+    }
+    if let Some(windows) = bandwidth_schedule {
+        for window in &windows {
+            window.validate().map_err(|e| e.to_string())?;
+        }
+        config.bandwidth_schedule = windows;
+    }
+    if let Some(preserve) = preserve_xattrs {
+        config.preserve_xattrs = preserve;
+    }
+    if let Some(budget) = retry_budget {
+        config.retry_budget = budget;
+    }
+    if let Some(prune) = prune_empty_dirs {
+        config.prune_empty_dirs = prune;
+    }
+    if let Some(prune) = prune_existing_empty_dirs {
+        config.prune_existing_empty_dirs = prune;
+    }
+    if let Some(prefix) = temp_file_prefix {
+        let prefix = prefix.trim();
+        if prefix.is_empty() {
+            return Err("临时文件前缀不能为空".to_string());
+        }
+        if prefix.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|']) {
+            return Err(format!("临时文件前缀包含无效字符: {}", prefix));
+        }
+        config.temp_file_prefix = prefix.to_string();
+    }
+    if let Some(keep) = temp_file_keep_extension {
+        config.temp_file_keep_extension = keep;
+    }
     
-    config.save(&state.config_dir).map_err(|e| e.to_string())?;
+    config.save(&state.config_dir()).map_err(|e| e.to_string())?;
+    state.bandwidth_limiter.set_schedule(config.bandwidth_schedule.clone());
     
     Ok(config)
 }
+
+/// 清理遗留的传输临时文件（程序上次中途退出时留下），断点续传仍在使用的除外，返回删除的数量
+pub async fn sweep_temp_files(state: &AppState) -> usize {
+    let keep = match TransferManager::new(state.db()).transfer_ids().await {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::warn!("加载传输记录失败，跳过临时文件清理: {}", e);
+            return 0;
+        }
+    };
+    let naming = TransferConfig::load(&state.config_dir()).temp_file_naming();
+    let min_age = Duration::from_secs(TEMP_SWEEP_MIN_AGE_SECS);
+    tauri::async_runtime::spawn_blocking(move || naming.sweep(&keep, min_age))
+        .await
+        .unwrap_or(0)
+}
+
+/// 启动时在后台清理遗留的传输临时文件
+pub fn start_temp_sweep(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        sweep_temp_files(&app.state::<AppState>()).await;
+    });
+}
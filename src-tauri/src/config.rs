@@ -17,6 +17,12 @@ const DEFAULT_REMOTE_TTL: u64 = 1800;
 const DEFAULT_CHUNK_SIZE_MB: u64 = 8;
 /// 默认流式传输阈值（MB）
 const DEFAULT_STREAM_THRESHOLD_MB: u64 = 128;
+/// 默认计算校验和的最大文件大小（MB）
+const DEFAULT_CHECKSUM_MAX_SIZE_MB: u64 = 256;
+/// 定时触发前连接检查失败后的默认重试次数
+const DEFAULT_CHECK_RETRIES: u32 = 3;
+/// 定时触发前连接检查的默认重试间隔（秒）
+const DEFAULT_CHECK_RETRY_SECS: u64 = 120;
 
 // ============================================================================
 // 通用配置加载/保存工具
@@ -91,6 +97,9 @@ pub struct CacheConfig {
     /// 远程存储缓存 TTL（秒），0 表示永不过期
     #[serde(default = "default_remote_ttl")]
     pub remote_ttl: u64,
+    /// 文件列表缓存格式（json / binary）
+    #[serde(default)]
+    pub format: crate::core::CacheFormat,
 }
 
 fn default_remote_ttl() -> u64 {
@@ -101,6 +110,7 @@ impl Default for CacheConfig {
     fn default() -> Self {
         Self {
             remote_ttl: DEFAULT_REMOTE_TTL,
+            format: crate::core::CacheFormat::default(),
         }
     }
 }
@@ -131,21 +141,85 @@ pub struct TransferConfig {
     /// 启用流式传输的阈值（MB），默认 128
     #[serde(default = "default_stream_threshold")]
     pub stream_threshold_mb: u64,
+    /// 所有复制完成后再执行删除（降低并行度，但中断时不会丢失移动中的文件）
+    #[serde(default)]
+    pub delete_after_copy: bool,
+    /// 识别源端移动过的文件，在目标内重命名而不是重新上传
+    #[serde(default)]
+    pub detect_renames: bool,
+    /// 备份模式下目标已存在且不同时的覆盖策略（always / newer-only / larger-only）
+    #[serde(default)]
+    pub overwrite_policy: crate::core::OverwritePolicy,
+    /// 两边都是空文件时的比较方式（normal / always-equal）
+    #[serde(default)]
+    pub zero_byte_compare: crate::core::ZeroByteCompare,
+    /// 文件被其他程序占用时跳过并在报告中列出，而不是计为失败
+    #[serde(default)]
+    pub skip_locked_files: bool,
+    /// 速度平滑系数（0~1，新速度的权重），越大越灵敏，越小越平稳，默认 0.3
+    #[serde(default = "default_speed_smoothing")]
+    pub speed_smoothing: f64,
+    /// 显示瞬时速度而不是平滑后的速度
+    #[serde(default)]
+    pub instant_speed: bool,
+    /// 按时间段限速（本地时间），不在任何时间段内时不限速
+    #[serde(default)]
+    pub bandwidth_schedule: Vec<crate::core::BandwidthWindow>,
+    /// 本地到本地同步时复制扩展属性（xattr，如 macOS Finder 标签）
+    #[serde(default)]
+    pub preserve_xattrs: bool,
+    /// 一次同步中所有文件共享的重试总次数（0 表示不限制），用尽后失败的文件不再重试
+    #[serde(default)]
+    pub retry_budget: u32,
+    /// 镜像同步后删除因删除文件而变空的目标目录
+    #[serde(default)]
+    pub prune_empty_dirs: bool,
+    /// 清理空目录时同时删除同步前就已为空的目录
+    #[serde(default)]
+    pub prune_existing_empty_dirs: bool,
+    /// 流式传输临时文件的名称前缀（便于安全软件按名称放行）
+    #[serde(default = "default_temp_file_prefix")]
+    pub temp_file_prefix: String,
+    /// 临时文件名保留原文件的扩展名（`<前缀><id>.<扩展名>.tmp`）
+    #[serde(default)]
+    pub temp_file_keep_extension: bool,
 }
 
 fn default_chunk_size() -> u64 {
     DEFAULT_CHUNK_SIZE_MB
 }
 
+fn default_speed_smoothing() -> f64 {
+    crate::core::engine::DEFAULT_SPEED_SMOOTHING
+}
+
 fn default_stream_threshold() -> u64 {
     DEFAULT_STREAM_THRESHOLD_MB
 }
 
+fn default_temp_file_prefix() -> String {
+    crate::core::transfer::DEFAULT_TEMP_FILE_PREFIX.to_string()
+}
+
 impl Default for TransferConfig {
     fn default() -> Self {
         Self {
             chunk_size_mb: DEFAULT_CHUNK_SIZE_MB,
             stream_threshold_mb: DEFAULT_STREAM_THRESHOLD_MB,
+            delete_after_copy: false,
+            detect_renames: false,
+            overwrite_policy: crate::core::OverwritePolicy::default(),
+            zero_byte_compare: crate::core::ZeroByteCompare::default(),
+            skip_locked_files: false,
+            speed_smoothing: crate::core::engine::DEFAULT_SPEED_SMOOTHING,
+            instant_speed: false,
+            bandwidth_schedule: Vec::new(),
+            preserve_xattrs: false,
+            retry_budget: 0,
+            prune_empty_dirs: false,
+            prune_existing_empty_dirs: false,
+            temp_file_prefix: default_temp_file_prefix(),
+            temp_file_keep_extension: false,
         }
     }
 }
@@ -160,4 +234,186 @@ impl TransferConfig {
     pub fn save(&self, config_dir: &Path) -> io::Result<()> {
         save_config_section(config_dir, "transfer", self)
     }
+
+    /// 流式传输临时文件的命名方式
+    pub fn temp_file_naming(&self) -> crate::core::TempFileNaming {
+        crate::core::TempFileNaming {
+            prefix: self.temp_file_prefix.clone(),
+            keep_extension: self.temp_file_keep_extension,
+        }
+    }
+}
+
+// ============================================================================
+// 扫描配置
+// ============================================================================
+
+/// 扫描配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScannerConfig {
+    /// 扫描并发数，默认 8
+    #[serde(default = "default_scan_concurrency")]
+    pub concurrency: usize,
+    /// 列表分页大小（0 表示使用后端默认值）
+    #[serde(default)]
+    pub list_page_size: usize,
+    /// 是否为本地存储计算内容校验和（用于校验和比较）
+    #[serde(default)]
+    pub local_checksums: bool,
+    /// 计算校验和的最大文件大小（MB，0 表示不限制），默认 256MB
+    #[serde(default = "default_checksum_max_size_mb")]
+    pub checksum_max_size_mb: u64,
+    /// 目标为 S3/GCS 时用本地文件 MD5 与 ETag/md5Hash 比较（分段上传的对象回退到大小/时间比较）
+    #[serde(default)]
+    pub etag_checksums: bool,
+    /// 是否跳过隐藏文件（"." 开头及 Windows 隐藏/系统文件）
+    #[serde(default)]
+    pub skip_hidden: bool,
+    /// 单侧列出的最大条目数（包括目录和被排除的文件，0 表示不限制），列出过程中超出时中止，避免超大目录树耗尽内存
+    #[serde(default)]
+    pub max_tree_files: usize,
+    /// 单侧扫描的最长时间（秒，0 表示不限制），超出时以扫描超时失败
+    #[serde(default)]
+    pub scan_timeout_secs: u64,
+}
+
+fn default_checksum_max_size_mb() -> u64 {
+    DEFAULT_CHECKSUM_MAX_SIZE_MB
+}
+
+fn default_scan_concurrency() -> usize {
+    crate::core::scanner::DEFAULT_SCAN_CONCURRENCY
+}
+
+impl Default for ScannerConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: crate::core::scanner::DEFAULT_SCAN_CONCURRENCY,
+            list_page_size: 0,
+            local_checksums: false,
+            checksum_max_size_mb: DEFAULT_CHECKSUM_MAX_SIZE_MB,
+            etag_checksums: false,
+            skip_hidden: false,
+            max_tree_files: 0,
+            scan_timeout_secs: 0,
+        }
+    }
+}
+
+impl ScannerConfig {
+    /// 从配置文件加载扫描配置
+    pub fn load(config_dir: &Path) -> Self {
+        load_config_section(config_dir, "scanner")
+    }
+
+    /// 保存扫描配置
+    pub fn save(&self, config_dir: &Path) -> io::Result<()> {
+        save_config_section(config_dir, "scanner", self)
+    }
+
+    /// 生成扫描器使用的配置
+    pub fn to_scan_config(&self) -> crate::core::ScanConfig {
+        crate::core::ScanConfig {
+            concurrency: self.concurrency.max(1),
+            list_page_size: (self.list_page_size > 0).then_some(self.list_page_size),
+            local_checksums: self.local_checksums,
+            checksum_max_size: self.checksum_max_size_mb * 1024 * 1024,
+            etag_checksums: self.etag_checksums,
+            skip_hidden: self.skip_hidden,
+            max_tree_files: (self.max_tree_files > 0).then_some(self.max_tree_files),
+            scan_timeout_secs: (self.scan_timeout_secs > 0).then_some(self.scan_timeout_secs),
+            ..Default::default()
+        }
+    }
+}
+
+// ============================================================================
+// 调度配置
+// ============================================================================
+
+/// 调度配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulerConfig {
+    /// 同时运行的同步任务上限（0 表示不限制），超出的任务排队等待
+    #[serde(default)]
+    pub max_concurrent_jobs: usize,
+    /// 定时触发前连接检查失败后的重试次数，用尽后照常启动同步并记录失败
+    #[serde(default = "default_check_retries")]
+    pub check_retries: u32,
+    /// 定时触发前连接检查的重试间隔（秒）
+    #[serde(default = "default_check_retry_secs")]
+    pub check_retry_secs: u64,
+}
+
+fn default_check_retries() -> u32 {
+    DEFAULT_CHECK_RETRIES
+}
+
+fn default_check_retry_secs() -> u64 {
+    DEFAULT_CHECK_RETRY_SECS
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_jobs: 0,
+            check_retries: DEFAULT_CHECK_RETRIES,
+            check_retry_secs: DEFAULT_CHECK_RETRY_SECS,
+        }
+    }
+}
+
+impl SchedulerConfig {
+    /// 从配置文件加载调度配置
+    pub fn load(config_dir: &Path) -> Self {
+        load_config_section(config_dir, "scheduler")
+    }
+
+    /// 保存调度配置
+    pub fn save(&self, config_dir: &Path) -> io::Result<()> {
+        save_config_section(config_dir, "scheduler", self)
+    }
+}
+
+// ============================================================================
+// 任务钩子配置
+// ============================================================================
+
+/// 任务钩子配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookConfig {
+    /// 是否允许执行任务的前置/后置命令（默认关闭，命令以当前用户权限运行）
+    #[serde(default)]
+    pub allow_hooks: bool,
+    /// 单个钩子命令的超时时间（秒），超时后结束进程
+    #[serde(default = "default_hook_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_hook_timeout() -> u64 {
+    crate::core::hooks::DEFAULT_HOOK_TIMEOUT_SECS
+}
+
+impl Default for HookConfig {
+    fn default() -> Self {
+        Self {
+            allow_hooks: false,
+            timeout_secs: crate::core::hooks::DEFAULT_HOOK_TIMEOUT_SECS,
+        }
+    }
+}
+
+impl HookConfig {
+    /// 从配置文件加载钩子配置
+    pub fn load(config_dir: &Path) -> Self {
+        load_config_section(config_dir, "hooks")
+    }
+
+    /// 保存钩子配置
+    pub fn save(&self, config_dir: &Path) -> io::Result<()> {
+        save_config_section(config_dir, "hooks", self)
+    }
 }
@@ -0,0 +1,255 @@
+//! 带宽限制 - 按时间段设置传输速度上限，所有同步任务共享同一个令牌桶
+
+use anyhow::{anyhow, Result};
+use chrono::{Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// 重新计算当前时间段限速的间隔
+const RATE_RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// 等待令牌时检查取消标志的间隔
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 限速时间段（本地时间，"HH:MM" 格式，结束早于开始时跨越午夜）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandwidthWindow {
+    pub start: String,
+    pub end: String,
+    /// 速度上限（字节/秒），0 表示不限制
+    pub max_bytes_per_sec: u64,
+}
+
+/// 解析 "HH:MM" 为当天的分钟数
+fn parse_minute(time: &str) -> Result<u32> {
+    let (h, m) = time
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| anyhow!("无效的时间 '{}'，应为 HH:MM", time))?;
+    let h: u32 = h.parse().map_err(|_| anyhow!("无效的时间 '{}'", time))?;
+    let m: u32 = m.parse().map_err(|_| anyhow!("无效的时间 '{}'", time))?;
+    if h > 23 || m > 59 {
+        return Err(anyhow!("无效的时间 '{}'", time));
+    }
+    Ok(h * 60 + m)
+}
+
+impl BandwidthWindow {
+    /// 检查时间格式
+    pub fn validate(&self) -> Result<()> {
+        parse_minute(&self.start)?;
+        parse_minute(&self.end)?;
+        Ok(())
+    }
+
+    /// 时间段是否包含给定的分钟（当天 0~1439）
+    fn contains(&self, minute: u32) -> bool {
+        let (Ok(start), Ok(end)) = (parse_minute(&self.start), parse_minute(&self.end)) else {
+            return false;
+        };
+        if start <= end {
+            (start..end).contains(&minute)
+        } else {
+            minute >= start || minute < end
+        }
+    }
+}
+
+/// 给定时刻的速度上限：取第一个包含该时刻的时间段，都不包含时不限制
+pub fn limit_at(windows: &[BandwidthWindow], minute: u32) -> Option<u64> {
+    windows
+        .iter()
+        .find(|w| w.contains(minute))
+        .map(|w| w.max_bytes_per_sec)
+        .filter(|&rate| rate > 0)
+}
+
+/// 令牌桶状态
+#[derive(Debug)]
+struct Bucket {
+    /// 可用字节数（为负表示欠账，需要等待补充）
+    tokens: f64,
+    last_refill: Instant,
+    /// 当前速度上限，None 表示不限制
+    rate: Option<u64>,
+    rate_checked_at: Option<Instant>,
+}
+
+/// 全局带宽限制器
+#[derive(Debug)]
+pub struct BandwidthLimiter {
+    windows: RwLock<Vec<BandwidthWindow>>,
+    bucket: Mutex<Bucket>,
+}
+
+impl BandwidthLimiter {
+    pub fn new(windows: Vec<BandwidthWindow>) -> Self {
+        Self {
+            windows: RwLock::new(windows),
+            bucket: Mutex::new(Bucket {
+                tokens: 0.0,
+                last_refill: Instant::now(),
+                rate: None,
+                rate_checked_at: None,
+            }),
+        }
+    }
+
+    /// 替换限速时间段（立即生效）
+    pub fn set_schedule(&self, windows: Vec<BandwidthWindow>) {
+        info!("带宽限速时间段: {} 个", windows.len());
+        *self.windows.write().unwrap_or_else(|e| e.into_inner()) = windows;
+        self.bucket.lock().unwrap_or_else(|e| e.into_inner()).rate_checked_at = None;
+    }
+
+    /// 当前速度上限（按需重新计算），同时补充令牌
+    fn refresh(&self, bucket: &mut Bucket) -> Option<u64> {
+        let now = Instant::now();
+        let stale = bucket
+            .rate_checked_at
+            .is_none_or(|t| now.duration_since(t) >= RATE_RECHECK_INTERVAL);
+        if stale {
+            let time = Local::now();
+            let minute = time.hour() * 60 + time.minute();
+            let rate = limit_at(&self.windows.read().unwrap_or_else(|e| e.into_inner()), minute);
+            if rate != bucket.rate {
+                info!("当前带宽上限: {:?} 字节/秒", rate);
+                bucket.rate = rate;
+            }
+            bucket.rate_checked_at = Some(now);
+        }
+
+        match bucket.rate {
+            Some(rate) => {
+                // 最多积攒 1 秒的令牌
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * rate as f64).min(rate as f64);
+            }
+            None => bucket.tokens = 0.0,
+        }
+        bucket.last_refill = now;
+        bucket.rate
+    }
+
+    /// 申请传输 `bytes` 字节，超出当前速度上限时等待；等待期间限速解除则立即返回，
+    /// `cancelled` 被设置时返回错误
+    pub async fn acquire(&self, bytes: u64, cancelled: &AtomicBool) -> Result<()> {
+        {
+            let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+            if self.refresh(&mut bucket).is_none() {
+                return Ok(());
+            }
+            bucket.tokens -= bytes as f64;
+        }
+
+        loop {
+            if cancelled.load(Ordering::SeqCst) {
+                // 未使用的令牌归还给其他任务
+                let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+                bucket.tokens += bytes as f64;
+                return Err(anyhow!("操作已取消"));
+            }
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+                let Some(rate) = self.refresh(&mut bucket) else {
+                    return Ok(());
+                };
+                if bucket.tokens >= 0.0 {
+                    return Ok(());
+                }
+                Duration::from_secs_f64(-bucket.tokens / rate as f64).min(CANCEL_POLL_INTERVAL)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn window(start: &str, end: &str, rate: u64) -> BandwidthWindow {
+        BandwidthWindow {
+            start: start.to_string(),
+            end: end.to_string(),
+            max_bytes_per_sec: rate,
+        }
+    }
+
+    #[test]
+    fn test_limit_at() {
+        let windows = vec![window("09:00", "18:00", 1024), window("22:00", "02:00", 2048)];
+
+        assert_eq!(limit_at(&windows, 9 * 60), Some(1024));
+        assert_eq!(limit_at(&windows, 18 * 60), None);
+        // 跨越午夜的时间段
+        assert_eq!(limit_at(&windows, 23 * 60), Some(2048));
+        assert_eq!(limit_at(&windows, 60), Some(2048));
+        assert_eq!(limit_at(&windows, 3 * 60), None);
+        assert_eq!(limit_at(&[window("00:00", "23:59", 0)], 60), None);
+
+        assert!(window("24:00", "01:00", 1).validate().is_err());
+        assert!(window("9:30", "1:05", 1).validate().is_ok());
+    }
+
+    /// 全天限速为 `rate` 字节/秒
+    fn all_day(rate: u64) -> BandwidthLimiter {
+        BandwidthLimiter::new(vec![window("00:00", "12:00", rate), window("12:00", "00:00", rate)])
+    }
+
+    #[test]
+    fn test_refill_is_capped_at_one_second() {
+        let limiter = all_day(1000);
+        let mut bucket = limiter.bucket.lock().unwrap();
+        bucket.last_refill = Instant::now() - Duration::from_secs(10);
+        assert_eq!(limiter.refresh(&mut bucket), Some(1000));
+        assert_eq!(bucket.tokens, 1000.0);
+
+        // 不限速时不积攒令牌
+        drop(bucket);
+        limiter.set_schedule(Vec::new());
+        let mut bucket = limiter.bucket.lock().unwrap();
+        assert_eq!(limiter.refresh(&mut bucket), None);
+        assert_eq!(bucket.tokens, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_for_refill() {
+        let cancelled = AtomicBool::new(false);
+
+        let unlimited = BandwidthLimiter::new(Vec::new());
+        let start = Instant::now();
+        unlimited.acquire(1 << 30, &cancelled).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        // 初始没有令牌，400 字节在 1000 字节/秒下需要约 0.4 秒
+        let limiter = all_day(1000);
+        let start = Instant::now();
+        limiter.acquire(400, &cancelled).await.unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(350), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_stops_when_cancelled() {
+        let limiter = Arc::new(all_day(100));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let setter = cancelled.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            setter.store(true, Ordering::SeqCst);
+        });
+
+        // 不取消时需要等待约 100 秒
+        let start = Instant::now();
+        assert!(limiter.acquire(10_000, &cancelled).await.is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // 取消的申请不占用令牌
+        assert!(limiter.bucket.lock().unwrap().tokens > -100.0);
+    }
+}
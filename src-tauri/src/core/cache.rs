@@ -5,10 +5,51 @@
 use crate::storage::FileInfo;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tracing::info;
+use tracing::{info, warn};
+
+/// 压缩缓存文件头（后接 zstd 压缩的 JSON），无文件头的按未压缩 JSON 读取
+const ZSTD_MAGIC: &[u8; 4] = b"STZ\x01";
+/// 二进制缓存文件头（后接 zstd 压缩的 bincode）
+const BINARY_MAGIC: &[u8; 4] = b"STB\x01";
+/// zstd 压缩级别（级别 3 在速度和压缩率之间较均衡）
+const ZSTD_LEVEL: i32 = 3;
+/// 检测缓存目录是否可写时创建的临时文件的前缀（后接进程 ID 和随机 ID，多个实例同时检测互不影响）
+const WRITE_PROBE_PREFIX: &str = ".write_probe";
+
+/// 已记录过不可写警告的缓存目录（每个目录只警告一次，恢复可写后移除，再次不可写时重新警告）
+static UNWRITABLE_WARNED: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+
+fn unwritable_warned() -> MutexGuard<'static, HashSet<PathBuf>> {
+    UNWRITABLE_WARNED
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 缓存目录状态
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStatus {
+    pub cache_dir: String,
+    /// 目录不可写时缓存被禁用，每次同步都会完整扫描
+    pub writable: bool,
+    pub error: Option<String>,
+}
+
+/// 缓存文件格式（读取时按文件头自动识别，新旧格式可以共存）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheFormat {
+    /// JSON（便于排查问题）
+    #[default]
+    Json,
+    /// bincode 二进制（大文件列表加载更快）
+    Binary,
+}
 
 /// 缓存条目
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,29 +74,111 @@ pub struct FileListCache {
     cache_dir: PathBuf,
     /// 缓存有效期（秒），0 表示永不过期
     ttl_seconds: u64,
+    /// 写入格式
+    format: CacheFormat,
+    /// 缓存目录是否可写（不可写时不再尝试写入）
+    writable: bool,
 }
 
 impl FileListCache {
     pub fn new(cache_dir: PathBuf) -> Self {
-        // 确保缓存目录存在
-        let _ = std::fs::create_dir_all(&cache_dir);
+        // 确保缓存目录存在且可写
+        let writable = match Self::probe_writable(&cache_dir) {
+            Ok(()) => {
+                unwritable_warned().remove(&cache_dir);
+                true
+            }
+            Err(e) => {
+                if unwritable_warned().insert(cache_dir.clone()) {
+                    warn!(
+                        "缓存目录不可写，已禁用文件列表缓存（每次同步都会完整扫描）: {:?} - {}",
+                        cache_dir, e
+                    );
+                }
+                false
+            }
+        };
         Self {
             cache_dir,
             ttl_seconds: 0, // 默认永不过期，直到手动刷新
+            format: CacheFormat::default(),
+            writable,
+        }
+    }
+
+    /// 创建缓存目录并写入、删除一个临时文件
+    fn probe_writable(cache_dir: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(cache_dir)?;
+        let probe = cache_dir.join(format!(
+            "{}_{}_{}",
+            WRITE_PROBE_PREFIX,
+            std::process::id(),
+            uuid::Uuid::new_v4().simple()
+        ));
+        std::fs::write(&probe, b"")?;
+        std::fs::remove_file(&probe)
+    }
+
+    /// 检测缓存目录状态
+    pub fn status(cache_dir: &Path) -> CacheStatus {
+        let error = Self::probe_writable(cache_dir).err().map(|e| e.to_string());
+        CacheStatus {
+            cache_dir: cache_dir.display().to_string(),
+            writable: error.is_none(),
+            error,
         }
     }
 
+    /// 缓存目录是否可写
+    pub fn is_writable(&self) -> bool {
+        self.writable
+    }
+
     /// 设置缓存有效期（0 表示永不过期）
     pub fn with_ttl(mut self, seconds: u64) -> Self {
         self.ttl_seconds = seconds;
         self
     }
 
+    /// 设置写入格式
+    pub fn with_format(mut self, format: CacheFormat) -> Self {
+        self.format = format;
+        self
+    }
+
     /// 获取缓存文件路径
     fn cache_path(&self, job_id: &str, storage_type: &str) -> PathBuf {
         self.cache_dir.join(format!("{}_{}.cache", job_id, storage_type))
     }
 
+    /// 读取缓存条目（按文件头识别格式，兼容旧的未压缩缓存）
+    fn read_entry(path: &Path) -> Option<CacheEntry> {
+        let data = std::fs::read(path).ok()?;
+        if let Some(compressed) = data.strip_prefix(BINARY_MAGIC) {
+            let raw = zstd::decode_all(compressed).ok()?;
+            return bincode::deserialize(&raw).ok();
+        }
+        match data.strip_prefix(ZSTD_MAGIC) {
+            Some(compressed) => {
+                let json = zstd::decode_all(compressed).ok()?;
+                serde_json::from_slice(&json).ok()
+            }
+            None => serde_json::from_slice(&data).ok(),
+        }
+    }
+
+    /// 按配置的格式写入缓存条目（zstd 压缩）
+    fn write_entry(&self, path: &Path, entry: &CacheEntry) -> Result<()> {
+        let (magic, raw) = match self.format {
+            CacheFormat::Json => (ZSTD_MAGIC, serde_json::to_vec(entry)?),
+            CacheFormat::Binary => (BINARY_MAGIC, bincode::serialize(entry)?),
+        };
+        let mut data = magic.to_vec();
+        data.extend(zstd::encode_all(raw.as_slice(), ZSTD_LEVEL)?);
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
     /// 计算配置哈希
     fn hash_config(config: &str) -> String {
         let hash = blake3::hash(config.as_bytes());
@@ -83,14 +206,9 @@ impl FileListCache {
             return None;
         }
 
-        let data = match std::fs::read(&path) {
-            Ok(d) => d,
-            Err(_) => return None,
-        };
-
-        let entry: CacheEntry = match serde_json::from_slice(&data) {
-            Ok(e) => e,
-            Err(_) => {
+        let entry = match Self::read_entry(&path) {
+            Some(e) => e,
+            None => {
                 // 缓存损坏，删除
                 let _ = std::fs::remove_file(&path);
                 return None;
@@ -153,6 +271,9 @@ impl FileListCache {
         config_json: &str,
         files: &HashMap<String, FileInfo>,
     ) -> Result<()> {
+        if !self.writable {
+            return Ok(());
+        }
         let path = self.cache_path(job_id, storage_type);
         
         let entry = CacheEntry {
@@ -161,22 +282,85 @@ impl FileListCache {
             config_hash: Self::hash_config(config_json),
         };
 
-        let data = serde_json::to_vec(&entry)?;
-        std::fs::write(&path, data)?;
+        self.write_entry(&path, &entry)?;
 
         info!("已缓存 {} 个文件到 {:?}", files.len(), path);
 
         Ok(())
     }
 
-    /// 清除指定任务的缓存
-    pub fn clear(&self, job_id: &str) {
+    /// 增量更新已有缓存的文件列表（保留原缓存时间，不延长有效期）
+    ///
+    /// 缓存不存在或配置已变化时删除缓存并返回 false，下次同步将重新扫描
+    pub fn update(
+        &self,
+        job_id: &str,
+        storage_type: &str,
+        config_json: &str,
+        files: &HashMap<String, FileInfo>,
+    ) -> Result<bool> {
+        if !self.writable {
+            return Ok(false);
+        }
+        let path = self.cache_path(job_id, storage_type);
+
+        let existing = Self::read_entry(&path);
+        let config_hash = Self::hash_config(config_json);
+        let cached_at = match existing {
+            Some(entry) if entry.config_hash == config_hash => entry.cached_at,
+            _ => {
+                let _ = std::fs::remove_file(&path);
+                return Ok(false);
+            }
+        };
+
+        let entry = CacheEntry {
+            files: files.clone(),
+            cached_at,
+            config_hash,
+        };
+        self.write_entry(&path, &entry)?;
+
+        info!("已增量更新缓存 {} 个文件到 {:?}", files.len(), path);
+
+        Ok(true)
+    }
+
+    /// 清除指定任务某一侧的缓存
+    pub fn remove(&self, job_id: &str, storage_type: &str) {
+        let _ = std::fs::remove_file(self.cache_path(job_id, storage_type));
+    }
+
+    /// 清除指定任务的缓存（`side` 为 "source" / "dest" 时只清除该侧，None 时清除两侧）
+    pub fn clear(&self, job_id: &str, side: Option<&str>) {
         for storage_type in ["source", "dest"] {
+            if side.is_some_and(|s| s != storage_type) {
+                continue;
+            }
             let path = self.cache_path(job_id, storage_type);
             let _ = std::fs::remove_file(&path);
         }
     }
 
+    /// 缓存目录中出现的所有任务 ID（按 `<job_id>_<side>.cache` 命名解析）
+    pub fn cached_job_ids(&self) -> HashSet<String> {
+        let mut ids = HashSet::new();
+        if let Ok(entries) = std::fs::read_dir(&self.cache_dir) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let Some(stem) = name.strip_suffix(".cache") else {
+                    continue;
+                };
+                if let Some((job_id, side)) = stem.rsplit_once('_') {
+                    if side == "source" || side == "dest" {
+                        ids.insert(job_id.to_string());
+                    }
+                }
+            }
+        }
+        ids
+    }
+
     /// 清除所有缓存
     pub fn clear_all(&self) {
         if let Ok(entries) = std::fs::read_dir(&self.cache_dir) {
@@ -202,4 +386,156 @@ mod tests {
         assert_eq!(hash1, hash2);
         assert_ne!(hash1, hash3);
     }
+
+    #[test]
+    fn test_unwritable_cache_dir() {
+        // 缓存目录的父路径是普通文件，无法创建目录
+        let file = std::env::temp_dir().join(format!("synctools-cache-file-{}", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+        let dir = file.join("cache");
+
+        let cache = FileListCache::new(dir.clone());
+        assert!(!cache.is_writable());
+        assert!(!FileListCache::status(&dir).writable);
+        // 写入直接跳过，不报错
+        cache.save("job", "dest", "cfg", &HashMap::new()).unwrap();
+        assert!(cache.load("job", "dest", "cfg").is_none());
+
+        let _ = std::fs::remove_file(&file);
+    }
+
+    #[test]
+    fn test_unwritable_warning_resets_when_writable() {
+        let file = std::env::temp_dir().join(format!("synctools-cache-warn-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&file, b"").unwrap();
+        let dir = file.join("cache");
+
+        assert!(!FileListCache::new(dir.clone()).is_writable());
+        assert!(unwritable_warned().contains(&dir));
+
+        // 目录恢复可写后清除记录，之后再次不可写时会重新警告
+        std::fs::remove_file(&file).unwrap();
+        assert!(FileListCache::new(dir.clone()).is_writable());
+        assert!(!unwritable_warned().contains(&dir));
+
+        let _ = std::fs::remove_dir_all(&file);
+    }
+
+    #[test]
+    fn test_write_probe_leaves_no_files() {
+        let dir = std::env::temp_dir().join(format!("synctools-cache-probe-{}", uuid::Uuid::new_v4()));
+        FileListCache::probe_writable(&dir).unwrap();
+        FileListCache::probe_writable(&dir).unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_update_keeps_cached_at_and_checks_config() {
+        let dir = std::env::temp_dir().join(format!("synctools-cache-test-{}", std::process::id()));
+        let cache = FileListCache::new(dir.clone());
+        let mut files = HashMap::new();
+
+        // 没有已有缓存时不创建
+        assert!(!cache.update("job", "dest", "cfg", &files).unwrap());
+        assert!(cache.load("job", "dest", "cfg").is_none());
+
+        cache.save("job", "dest", "cfg", &files).unwrap();
+        let cached_at = cache.load("job", "dest", "cfg").unwrap().cached_at;
+
+        files.insert(
+            "a.txt".to_string(),
+            FileInfo {
+                path: "a.txt".to_string(),
+                size: 1,
+                modified_time: 0,
+                is_dir: false,
+                checksum: None,
+                hidden: false,
+            },
+        );
+        assert!(cache.update("job", "dest", "cfg", &files).unwrap());
+        let loaded = cache.load("job", "dest", "cfg").unwrap();
+        assert_eq!(loaded.cached_at, cached_at);
+        assert!(loaded.files.contains_key("a.txt"));
+
+        // 配置变化时删除缓存
+        assert!(!cache.update("job", "dest", "other", &files).unwrap());
+        assert!(cache.load("job", "dest", "cfg").is_none());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_compressed_and_legacy_formats() {
+        let dir = std::env::temp_dir().join(format!("synctools-cache-fmt-{}", std::process::id()));
+        let cache = FileListCache::new(dir.clone());
+        let files: HashMap<String, FileInfo> = (0..100)
+            .map(|i| {
+                let path = format!("dir/file_{}.txt", i);
+                let info = FileInfo {
+                    path: path.clone(),
+                    size: i,
+                    modified_time: 0,
+                    is_dir: false,
+                    checksum: None,
+                    hidden: false,
+                };
+                (path, info)
+            })
+            .collect();
+
+        // 新缓存带压缩文件头
+        cache.save("job", "source", "cfg", &files).unwrap();
+        let raw = std::fs::read(cache.cache_path("job", "source")).unwrap();
+        assert!(raw.starts_with(ZSTD_MAGIC));
+        assert_eq!(cache.load("job", "source", "cfg").unwrap().files.len(), 100);
+
+        // 旧的未压缩 JSON 缓存仍可读取
+        let legacy = CacheEntry {
+            files,
+            cached_at: FileListCache::now(),
+            config_hash: FileListCache::hash_config("cfg"),
+        };
+        std::fs::write(
+            cache.cache_path("job", "dest"),
+            serde_json::to_vec(&legacy).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(cache.load("job", "dest", "cfg").unwrap().files.len(), 100);
+
+        // 二进制格式与 JSON 格式可以互相读取
+        let binary = FileListCache::new(dir.clone()).with_format(CacheFormat::Binary);
+        binary.save("job", "dest", "cfg", &legacy.files).unwrap();
+        let raw = std::fs::read(cache.cache_path("job", "dest")).unwrap();
+        assert!(raw.starts_with(BINARY_MAGIC));
+        assert_eq!(cache.load("job", "dest", "cfg").unwrap().files.len(), 100);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_clear_single_side() {
+        let dir = std::env::temp_dir().join(format!("synctools-cache-clear-{}", std::process::id()));
+        let cache = FileListCache::new(dir.clone());
+        let files = HashMap::new();
+        cache.save("job", "source", "cfg", &files).unwrap();
+        cache.save("job", "dest", "cfg", &files).unwrap();
+
+        cache.clear("job", Some("dest"));
+        assert!(cache.load("job", "source", "cfg").is_some());
+        assert!(cache.load("job", "dest", "cfg").is_none());
+
+        // 只刷新源（如 estimate_upload 强制刷新）时保留目标缓存
+        cache.save("job", "dest", "cfg", &files).unwrap();
+        cache.clear("job", Some("source"));
+        assert!(cache.load("job", "source", "cfg").is_none());
+        assert!(cache.load("job", "dest", "cfg").is_some());
+        cache.save("job", "source", "cfg", &files).unwrap();
+
+        cache.clear("job", None);
+        assert!(cache.load("job", "source", "cfg").is_none());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
 }
@@ -1,5 +1,7 @@
+use crate::core::file_state::MD5_CHECKSUM_PREFIX;
 use crate::db::SyncMode;
 use crate::storage::FileInfo;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
 /// 同步动作
@@ -19,6 +21,15 @@ pub enum SyncAction {
         /// 删除目标还是源
         from_dest: bool,
     },
+    /// 替换类型不一致的条目：先删除目标中的错误类型条目，再按源端类型重建
+    Replace {
+        path: String,
+        size: u64,
+        /// 源端是否为目录（目录只需创建，文件需要复制内容）
+        source_is_dir: bool,
+    },
+    /// 在目标内重命名（源端移动过的文件，目标已有相同内容）
+    Rename { from: String, to: String, size: u64 },
     /// 跳过（文件相同）
     Skip { path: String },
     /// 冲突（需要用户决定）
@@ -30,6 +41,20 @@ pub enum SyncAction {
     },
 }
 
+impl SyncAction {
+    /// 动作对应的文件路径（重命名取新路径）
+    pub fn path(&self) -> &str {
+        match self {
+            SyncAction::Copy { source_path, .. } => source_path,
+            SyncAction::Delete { path, .. } => path,
+            SyncAction::Rename { to, .. } => to,
+            SyncAction::Replace { path, .. } => path,
+            SyncAction::Skip { path } => path,
+            SyncAction::Conflict { path, .. } => path,
+        }
+    }
+}
+
 /// 冲突类型
 #[derive(Debug, Clone)]
 pub enum ConflictType {
@@ -39,6 +64,8 @@ pub enum ConflictType {
     SameSizeDifferentTime,
     /// 一边修改一边删除
     ModifiedVsDeleted,
+    /// 一边是文件一边是目录
+    TypeMismatch,
 }
 
 /// 文件比较结果
@@ -56,6 +83,106 @@ pub enum FileRelation {
     ProbablyEqual,
 }
 
+impl FileRelation {
+    /// 比较结果的可读说明，如 "源文件较新（新 3小时）"、"大小不同（1.2 MB → 3.4 MB）"
+    pub fn describe(&self, source: &FileInfo, dest: &FileInfo) -> String {
+        match self {
+            FileRelation::Equal => "内容相同".to_string(),
+            FileRelation::ProbablyEqual => "大小相同（未比较修改时间）".to_string(),
+            FileRelation::SourceNewer => format!(
+                "源文件较新（新 {}）",
+                format_duration(source.modified_time - dest.modified_time)
+            ),
+            FileRelation::DestNewer => format!(
+                "目标文件较新（新 {}）",
+                format_duration(dest.modified_time - source.modified_time)
+            ),
+            FileRelation::Different if source.size != dest.size => format!(
+                "大小不同（{} → {}）",
+                format_size(dest.size),
+                format_size(source.size)
+            ),
+            FileRelation::Different => "校验和不同".to_string(),
+        }
+    }
+}
+
+/// 备份模式下目标已存在且内容不同时的覆盖策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverwritePolicy {
+    /// 总是用源覆盖
+    #[default]
+    Always,
+    /// 仅源文件更新时覆盖
+    NewerOnly,
+    /// 仅源文件更大时覆盖（适用于只追加的日志）
+    LargerOnly,
+}
+
+impl OverwritePolicy {
+    /// 是否用源覆盖目标
+    fn should_overwrite(self, src: &FileInfo, dst: &FileInfo) -> bool {
+        match self {
+            Self::Always => true,
+            Self::NewerOnly => src.modified_time > dst.modified_time,
+            Self::LargerOnly => src.size > dst.size,
+        }
+    }
+}
+
+/// 两边都是空文件时的比较方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ZeroByteCompare {
+    /// 与其他文件相同（严格模式下比较修改时间）
+    #[default]
+    Normal,
+    /// 空文件内容必然相同，总是视为相同
+    AlwaysEqual,
+}
+
+/// 目标路径的大小写转换（如 Windows 源同步到区分大小写的 Linux 目标时统一为小写）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseFold {
+    /// 保持原样
+    #[default]
+    Preserve,
+    Lower,
+    Upper,
+}
+
+impl CaseFold {
+    /// 名称（与序列化名称一致）
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Preserve => "preserve",
+            Self::Lower => "lower",
+            Self::Upper => "upper",
+        }
+    }
+
+    /// 按名称解析
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "preserve" => Some(Self::Preserve),
+            "lower" => Some(Self::Lower),
+            "upper" => Some(Self::Upper),
+            _ => None,
+        }
+    }
+
+    /// 源路径对应的目标路径
+    pub fn apply(self, path: &str) -> String {
+        match self {
+            Self::Preserve => path.to_string(),
+            Self::Lower => path.to_lowercase(),
+            Self::Upper => path.to_uppercase(),
+        }
+    }
+}
+
 /// 比较配置
 #[derive(Debug, Clone)]
 pub struct CompareConfig {
@@ -63,10 +190,20 @@ pub struct CompareConfig {
     pub time_tolerance_seconds: i64,
     /// 是否使用 checksum 比较
     pub use_checksum: bool,
+    /// 是否用本地 MD5 与 S3 ETag 比较（仅单段上传的 ETag 是内容 MD5）
+    pub etag_md5: bool,
+    /// 两边都是 S3 兼容存储时直接比较 ETag，相同即跳过（不比较大小和时间）
+    pub compare_etags: bool,
     /// 是否忽略文件时间（仅比较大小和 checksum）
     pub ignore_mtime: bool,
     /// 大小相同时是否认为文件相同（适用于 WebDAV 等不保留 mtime 的场景）
     pub size_only_for_same_size: bool,
+    /// 备份模式的覆盖策略（镜像模式总是覆盖）
+    pub overwrite_policy: OverwritePolicy,
+    /// 两边都是空文件时的比较方式
+    pub zero_byte: ZeroByteCompare,
+    /// 目标路径的大小写转换（仅用于单向同步）
+    pub case_fold: CaseFold,
 }
 
 impl Default for CompareConfig {
@@ -74,8 +211,13 @@ impl Default for CompareConfig {
         Self {
             time_tolerance_seconds: 2,
             use_checksum: false,
+            etag_md5: false,
+            compare_etags: false,
             ignore_mtime: false,
             size_only_for_same_size: true, // 默认开启，避免 WebDAV 重复同步
+            overwrite_policy: OverwritePolicy::Always,
+            zero_byte: ZeroByteCompare::Normal,
+            case_fold: CaseFold::Preserve,
         }
     }
 }
@@ -104,10 +246,33 @@ impl FileComparator {
         // 首先检查 checksum（如果有）
         if self.config.use_checksum {
             if let (Some(src_sum), Some(dst_sum)) = (&source.checksum, &dest.checksum) {
-                if src_sum == dst_sum {
+                // 不同算法（如本地 blake3 与 S3 ETag）的校验和不可比较，回退到大小/时间比较
+                if checksum_scheme(src_sum) == checksum_scheme(dst_sum) {
+                    return if src_sum == dst_sum {
+                        FileRelation::Equal
+                    } else {
+                        FileRelation::Different
+                    };
+                }
+            }
+        }
+
+        // 远程到远程：ETag 相同即认为相同，无需读取文件内容
+        if self.config.compare_etags {
+            if let (Some(src_sum), Some(dst_sum)) = (&source.checksum, &dest.checksum) {
+                match etags_match(src_sum, dst_sum) {
+                    Some(true) if source.size == dest.size => return FileRelation::Equal,
+                    Some(false) => return FileRelation::Different,
+                    _ => {}
+                }
+            }
+        }
+
+        // 本地 MD5 与 S3 ETag 相同即认为相同；不同或无法比较时回退到大小/时间比较
+        if self.config.etag_md5 {
+            if let (Some(src_sum), Some(dst_sum)) = (&source.checksum, &dest.checksum) {
+                if md5_matches_etag(src_sum, dst_sum) == Some(true) {
                     return FileRelation::Equal;
-                } else {
-                    return FileRelation::Different;
                 }
             }
         }
@@ -123,6 +288,10 @@ impl FileComparator {
             return FileRelation::Different;
         }
 
+        if source.size == 0 && self.config.zero_byte == ZeroByteCompare::AlwaysEqual {
+            return FileRelation::Equal;
+        }
+
         // 大小相同时，如果开启了 size_only_for_same_size，直接认为相同
         // 这适用于 WebDAV 等不保留原始修改时间的存储
         if self.config.size_only_for_same_size {
@@ -162,12 +331,48 @@ impl FileComparator {
         source: &HashMap<String, FileInfo>,
         dest: &HashMap<String, FileInfo>,
         mode: &SyncMode,
+    ) -> Vec<SyncAction> {
+        self.compare_trees_inner(source, dest, mode, None)
+    }
+
+    /// 比较两个文件树，同时返回两边都存在的文件的比较结果（路径 -> 关系），用于解释每个动作的原因
+    pub fn compare_trees_with_relations(
+        &self,
+        source: &HashMap<String, FileInfo>,
+        dest: &HashMap<String, FileInfo>,
+        mode: &SyncMode,
+    ) -> (Vec<SyncAction>, HashMap<String, FileRelation>) {
+        let mut relations = HashMap::new();
+        let actions = self.compare_trees_inner(source, dest, mode, Some(&mut relations));
+        (actions, relations)
+    }
+
+    fn compare_trees_inner(
+        &self,
+        source: &HashMap<String, FileInfo>,
+        dest: &HashMap<String, FileInfo>,
+        mode: &SyncMode,
+        mut relations: Option<&mut HashMap<String, FileRelation>>,
     ) -> Vec<SyncAction> {
         let mut actions = Vec::new();
 
+        // 大小写转换：把源文件树映射到目标路径后比较，生成动作后再还原源路径
+        let folded;
+        let mut source_paths = HashMap::new();
+        let source = if self.config.case_fold == CaseFold::Preserve {
+            source
+        } else {
+            folded = Self::fold_tree(source, self.config.case_fold, &mut source_paths);
+            &folded
+        };
+
         // 收集所有路径
         let all_paths: HashSet<_> = source.keys().chain(dest.keys()).collect();
 
+        // 收集两边的目录（包括扫描时未包含目录条目、仅由文件路径推断出的父目录）
+        let source_dirs = Self::collect_dirs(source);
+        let dest_dirs = Self::collect_dirs(dest);
+
         for path in all_paths {
             let src_file = source.get(path);
             let dst_file = dest.get(path);
@@ -180,18 +385,34 @@ impl FileComparator {
                         continue;
                     }
 
-                    match self.compare_files(src, dst) {
+                    // 一边是文件一边是目录
+                    if src.is_dir != dst.is_dir {
+                        actions.push(Self::type_mismatch_action(path, Some(src), Some(dst), mode));
+                        continue;
+                    }
+
+                    let relation = self.compare_files(src, dst);
+                    if let Some(relations) = relations.as_deref_mut() {
+                        relations.insert(path.clone(), relation.clone());
+                    }
+
+                    match relation {
                         FileRelation::Equal | FileRelation::ProbablyEqual => {
                             SyncAction::Skip { path: path.clone() }
                         }
-                        FileRelation::SourceNewer => SyncAction::Copy {
-                            source_path: path.clone(),
-                            dest_path: path.clone(),
-                            size: src.size,
-                            reverse: false,
+                        FileRelation::SourceNewer => match mode {
+                            // 补齐模式：目标已有的文件一律保留
+                            SyncMode::Seed => SyncAction::Skip { path: path.clone() },
+                            _ => SyncAction::Copy {
+                                source_path: path.clone(),
+                                dest_path: path.clone(),
+                                size: src.size,
+                                reverse: false,
+                            },
                         },
                         FileRelation::DestNewer => {
                             match mode {
+                                SyncMode::Seed => SyncAction::Skip { path: path.clone() },
                                 SyncMode::Bidirectional => {
                                     // 双向同步：目标更新时，从目标同步到源
                                     SyncAction::Copy {
@@ -201,8 +422,13 @@ impl FileComparator {
                                         reverse: true,
                                     }
                                 }
+                                SyncMode::Backup
+                                    if !self.config.overwrite_policy.should_overwrite(src, dst) =>
+                                {
+                                    SyncAction::Skip { path: path.clone() }
+                                }
                                 SyncMode::Mirror | SyncMode::Backup => {
-                                    // 镜像/备份：总是用源覆盖目标
+                                    // 镜像/备份：用源覆盖目标
                                     SyncAction::Copy {
                                         source_path: path.clone(),
                                         dest_path: path.clone(),
@@ -215,6 +441,7 @@ impl FileComparator {
                         FileRelation::Different => {
                             // 大小不同，根据模式处理
                             match mode {
+                                SyncMode::Seed => SyncAction::Skip { path: path.clone() },
                                 SyncMode::Bidirectional => {
                                     // 双向同步时，大小不同是冲突
                                     SyncAction::Conflict {
@@ -224,6 +451,12 @@ impl FileComparator {
                                         conflict_type: ConflictType::BothModified,
                                     }
                                 }
+                                SyncMode::Backup
+                                    if !self.config.overwrite_policy.should_overwrite(src, dst) =>
+                                {
+                                    tracing::debug!("覆盖策略不允许覆盖，跳过: {}", path);
+                                    SyncAction::Skip { path: path.clone() }
+                                }
                                 SyncMode::Mirror | SyncMode::Backup => {
                                     // 镜像/备份：用源覆盖
                                     SyncAction::Copy {
@@ -243,6 +476,11 @@ impl FileComparator {
                     if src.is_dir {
                         continue; // 目录会在复制文件时自动创建
                     }
+                    // 源是文件，但目标同名路径是目录
+                    if dest_dirs.contains(path.as_str()) {
+                        actions.push(Self::type_mismatch_action(path, Some(src), None, mode));
+                        continue;
+                    }
                     SyncAction::Copy {
                         source_path: path.clone(),
                         dest_path: path.clone(),
@@ -256,6 +494,11 @@ impl FileComparator {
                     if dst.is_dir {
                         continue;
                     }
+                    // 目标是文件，但源同名路径是目录
+                    if source_dirs.contains(path.as_str()) {
+                        actions.push(Self::type_mismatch_action(path, None, Some(dst), mode));
+                        continue;
+                    }
                     match mode {
                         SyncMode::Mirror => {
                             // 镜像模式：删除目标中多余的文件
@@ -273,8 +516,8 @@ impl FileComparator {
                                 reverse: true,
                             }
                         }
-                        SyncMode::Backup => {
-                            // 备份模式：保留目标中的额外文件
+                        SyncMode::Backup | SyncMode::Seed => {
+                            // 备份/补齐模式：保留目标中的额外文件
                             SyncAction::Skip { path: path.clone() }
                         }
                     }
@@ -286,31 +529,41 @@ impl FileComparator {
             actions.push(action);
         }
 
+        if !source_paths.is_empty() {
+            actions = Self::restore_source_paths(actions, &source_paths);
+        }
+
         // 按操作类型和路径排序，确保一致性
         actions.sort_by(|a, b| {
             let order_a = match a {
-                SyncAction::Copy { .. } => 0,
-                SyncAction::Delete { .. } => 2,
-                SyncAction::Skip { .. } => 3,
-                SyncAction::Conflict { .. } => 1,
+                SyncAction::Replace { .. } => 0,
+                SyncAction::Copy { .. } | SyncAction::Rename { .. } => 1,
+                SyncAction::Delete { .. } => 3,
+                SyncAction::Skip { .. } => 4,
+                SyncAction::Conflict { .. } => 2,
             };
             let order_b = match b {
-                SyncAction::Copy { .. } => 0,
-                SyncAction::Delete { .. } => 2,
-                SyncAction::Skip { .. } => 3,
-                SyncAction::Conflict { .. } => 1,
+                SyncAction::Replace { .. } => 0,
+                SyncAction::Copy { .. } | SyncAction::Rename { .. } => 1,
+                SyncAction::Delete { .. } => 3,
+                SyncAction::Skip { .. } => 4,
+                SyncAction::Conflict { .. } => 2,
             };
 
             order_a.cmp(&order_b).then_with(|| {
                 let path_a = match a {
                     SyncAction::Copy { source_path, .. } => source_path,
                     SyncAction::Delete { path, .. } => path,
+                    SyncAction::Rename { to, .. } => to,
+                    SyncAction::Replace { path, .. } => path,
                     SyncAction::Skip { path } => path,
                     SyncAction::Conflict { path, .. } => path,
                 };
                 let path_b = match b {
                     SyncAction::Copy { source_path, .. } => source_path,
                     SyncAction::Delete { path, .. } => path,
+                    SyncAction::Rename { to, .. } => to,
+                    SyncAction::Replace { path, .. } => path,
                     SyncAction::Skip { path } => path,
                     SyncAction::Conflict { path, .. } => path,
                 };
@@ -321,6 +574,132 @@ impl FileComparator {
         actions
     }
 
+    /// 收集文件树中的所有目录（显式目录条目 + 文件路径推断出的父目录）
+    fn collect_dirs(tree: &HashMap<String, FileInfo>) -> HashSet<&str> {
+        let mut dirs = HashSet::new();
+        for (path, info) in tree {
+            if info.is_dir {
+                dirs.insert(path.as_str());
+            }
+            let mut current = path.as_str();
+            while let Some(pos) = current.rfind('/') {
+                current = &current[..pos];
+                if current.is_empty() || !dirs.insert(current) {
+                    break;
+                }
+            }
+        }
+        dirs
+    }
+
+    /// 把源文件树的路径转换为目标路径；`source_paths` 记录转换后路径 -> 原路径（仅记录有变化的）
+    ///
+    /// 多个源路径转换后相同时只保留排序最前的一个
+    fn fold_tree(
+        source: &HashMap<String, FileInfo>,
+        case_fold: CaseFold,
+        source_paths: &mut HashMap<String, String>,
+    ) -> HashMap<String, FileInfo> {
+        let mut paths: Vec<&String> = source.keys().collect();
+        paths.sort();
+
+        let mut folded = HashMap::with_capacity(source.len());
+        for path in paths {
+            let key = case_fold.apply(path);
+            if folded.contains_key(&key) {
+                tracing::warn!("大小写转换后路径重复，跳过: {} -> {}", path, key);
+                continue;
+            }
+            if &key != path {
+                source_paths.insert(key.clone(), path.clone());
+            }
+            let info = FileInfo {
+                path: key.clone(),
+                ..source[path].clone()
+            };
+            folded.insert(key, info);
+        }
+        folded
+    }
+
+    /// 把复制动作的源路径还原为转换前的路径
+    ///
+    /// 替换和冲突动作只有一个路径，源路径被转换过时无法执行，改为跳过
+    fn restore_source_paths(
+        actions: Vec<SyncAction>,
+        source_paths: &HashMap<String, String>,
+    ) -> Vec<SyncAction> {
+        actions
+            .into_iter()
+            .map(|action| match action {
+                SyncAction::Copy {
+                    source_path,
+                    dest_path,
+                    size,
+                    reverse: false,
+                } => SyncAction::Copy {
+                    source_path: source_paths.get(&source_path).cloned().unwrap_or(source_path),
+                    dest_path,
+                    size,
+                    reverse: false,
+                },
+                SyncAction::Replace {
+                    path,
+                    source_is_dir: false,
+                    ..
+                }
+                | SyncAction::Conflict { path, .. }
+                    if source_paths.contains_key(&path) =>
+                {
+                    tracing::warn!("大小写转换后类型不一致，已跳过: {}", path);
+                    SyncAction::Skip { path }
+                }
+                other => other,
+            })
+            .collect()
+    }
+
+    /// 处理文件/目录类型不一致的路径
+    ///
+    /// 镜像模式以源为准：删除目标中的错误类型条目后重建；
+    /// 备份和双向模式不主动删除目标数据，标记为冲突交由用户处理。
+    fn type_mismatch_action(
+        path: &str,
+        src: Option<&FileInfo>,
+        dst: Option<&FileInfo>,
+        mode: &SyncMode,
+    ) -> SyncAction {
+        // 未出现在文件树中的一侧一定是（推断出的）目录
+        let source_is_dir = match src {
+            Some(f) => f.is_dir,
+            None => true,
+        };
+        tracing::debug!(
+            "文件类型不一致: {} (源为{}, 目标为{})",
+            path,
+            if source_is_dir { "目录" } else { "文件" },
+            if source_is_dir { "文件" } else { "目录" }
+        );
+
+        match mode {
+            SyncMode::Mirror => SyncAction::Replace {
+                path: path.to_string(),
+                size: src.filter(|f| !f.is_dir).map_or(0, |f| f.size),
+                source_is_dir,
+            },
+            SyncMode::Bidirectional | SyncMode::Backup => SyncAction::Conflict {
+                path: path.to_string(),
+                source_info: src.cloned(),
+                dest_info: dst.cloned(),
+                conflict_type: ConflictType::TypeMismatch,
+            },
+            // 补齐模式：保留目标中已有的路径
+            SyncMode::Seed => SyncAction::Skip {
+                path: path.to_string(),
+            },
+        }
+    }
+
     /// 统计同步动作
     pub fn summarize_actions(actions: &[SyncAction]) -> ActionSummary {
         let mut summary = ActionSummary::default();
@@ -328,6 +707,9 @@ impl FileComparator {
         for action in actions {
             match action {
                 SyncAction::Copy { size, reverse, .. } => {
+                    if *size == 0 {
+                        summary.zero_size_copy_count += 1;
+                    }
                     if *reverse {
                         summary.reverse_copy_count += 1;
                         summary.reverse_copy_bytes += size;
@@ -336,6 +718,14 @@ impl FileComparator {
                         summary.copy_bytes += size;
                     }
                 }
+                SyncAction::Replace { size, source_is_dir, .. } => {
+                    if *size == 0 && !source_is_dir {
+                        summary.zero_size_copy_count += 1;
+                    }
+                    summary.copy_count += 1;
+                    summary.copy_bytes += size;
+                }
+                SyncAction::Rename { .. } => summary.rename_count += 1,
                 SyncAction::Delete { .. } => summary.delete_count += 1,
                 SyncAction::Skip { .. } => summary.skip_count += 1,
                 SyncAction::Conflict { .. } => summary.conflict_count += 1,
@@ -344,6 +734,88 @@ impl FileComparator {
 
         summary
     }
+
+    /// 按目录汇总同步动作，展开 `depth` 层目录（0 表示只有根节点）
+    ///
+    /// 每个目录的统计包含其下所有子目录中的文件
+    pub fn summarize_by_dir(actions: &[SyncAction], depth: usize) -> DirSummary {
+        let mut root = DirNode::default();
+        for action in actions {
+            let path = action.path().trim_start_matches('/');
+            let parents: Vec<&str> = match path.rsplit_once('/') {
+                Some((parent, _)) => parent.split('/').take(depth).collect(),
+                None => Vec::new(),
+            };
+
+            let mut node = &mut root;
+            node.summary.tally(action);
+            for name in parents {
+                node = node.children.entry(name.to_string()).or_default();
+                node.summary.tally(action);
+            }
+        }
+        root.into_summary(String::new())
+    }
+}
+
+/// 目录汇总（按目录名排序的子目录）
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirSummary {
+    /// 目录路径（根目录为空字符串）
+    pub path: String,
+    pub copy_count: usize,
+    pub delete_count: usize,
+    pub skip_count: usize,
+    pub conflict_count: usize,
+    /// 需要传输的字节数
+    pub total_bytes: u64,
+    pub children: Vec<DirSummary>,
+}
+
+impl DirSummary {
+    fn tally(&mut self, action: &SyncAction) {
+        match action {
+            SyncAction::Copy { size, .. } | SyncAction::Replace { size, .. } => {
+                self.copy_count += 1;
+                self.total_bytes += size;
+            }
+            // 重命名无需传输数据，按复制计数（与差异预览一致）
+            SyncAction::Rename { .. } => self.copy_count += 1,
+            SyncAction::Delete { .. } => self.delete_count += 1,
+            SyncAction::Skip { .. } => self.skip_count += 1,
+            SyncAction::Conflict { .. } => self.conflict_count += 1,
+        }
+    }
+}
+
+/// 汇总时使用的目录节点
+#[derive(Default)]
+struct DirNode {
+    summary: DirSummary,
+    children: std::collections::BTreeMap<String, DirNode>,
+}
+
+impl DirNode {
+    fn into_summary(self, path: String) -> DirSummary {
+        let children = self
+            .children
+            .into_iter()
+            .map(|(name, node)| {
+                let child_path = if path.is_empty() {
+                    name
+                } else {
+                    format!("{}/{}", path, name)
+                };
+                node.into_summary(child_path)
+            })
+            .collect();
+        DirSummary {
+            path,
+            children,
+            ..self.summary
+        }
+    }
 }
 
 impl Default for FileComparator {
@@ -360,8 +832,12 @@ pub struct ActionSummary {
     pub reverse_copy_count: usize,
     pub reverse_copy_bytes: u64,
     pub delete_count: usize,
+    /// 重命名数（无需传输数据）
+    pub rename_count: usize,
     pub skip_count: usize,
     pub conflict_count: usize,
+    /// 大小为 0 的复制数（WebDAV 未返回 content_length 时大小为 0）
+    pub zero_size_copy_count: usize,
 }
 
 impl ActionSummary {
@@ -369,11 +845,463 @@ impl ActionSummary {
         self.copy_count
             + self.reverse_copy_count
             + self.delete_count
+            + self.rename_count
             + self.skip_count
             + self.conflict_count
     }
 
+    /// 需要执行的文件数（复制、重命名、删除）
+    pub fn files_to_sync(&self) -> usize {
+        self.copy_count + self.reverse_copy_count + self.rename_count + self.delete_count
+    }
+
     pub fn total_transfer_bytes(&self) -> u64 {
         self.copy_bytes + self.reverse_copy_bytes
     }
+
+    /// 文件大小是否不可信（半数以上待复制文件大小为 0，通常是服务端未返回大小）
+    pub fn sizes_unknown(&self) -> bool {
+        let copies = self.copy_count + self.reverse_copy_count;
+        copies > 0 && self.zero_size_copy_count * 2 >= copies
+    }
+
+    /// 进度使用的总字节数，大小不可信时返回 0（改为按文件数计算进度）
+    pub fn progress_bytes_total(&self) -> u64 {
+        if self.sizes_unknown() {
+            0
+        } else {
+            self.total_transfer_bytes()
+        }
+    }
+}
+
+/// 格式化文件大小
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// 格式化时间差（秒）
+fn format_duration(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    if seconds < 60 {
+        format!("{}秒", seconds)
+    } else if seconds < 3600 {
+        format!("{}分钟", seconds / 60)
+    } else if seconds < 86400 {
+        format!("{}小时", seconds / 3600)
+    } else {
+        format!("{}天", seconds / 86400)
+    }
+}
+
+/// 校验和算法标识（"blake3:" 等前缀），无前缀的视为存储后端的 ETag
+fn checksum_scheme(checksum: &str) -> &str {
+    match checksum.split_once(':') {
+        Some((scheme, _)) => scheme,
+        None => "etag",
+    }
+}
+
+/// 比较本地 MD5（"md5:" 前缀）与 S3 ETag：相同时返回 Some(true)，不同或无法比较时返回 None
+///
+/// 分段上传对象的 ETag 形如 `<hex>-<分段数>`，是各分段 MD5 的 MD5，不是内容 MD5；
+/// SSE-KMS/SSE-C 加密对象的 ETag 虽是 32 位十六进制也不是内容 MD5，因此不同不能说明内容不同
+fn md5_matches_etag(a: &str, b: &str) -> Option<bool> {
+    let (md5, etag) = match (
+        a.strip_prefix(MD5_CHECKSUM_PREFIX),
+        b.strip_prefix(MD5_CHECKSUM_PREFIX),
+    ) {
+        (Some(md5), None) => (md5, b),
+        (None, Some(md5)) => (md5, a),
+        _ => return None,
+    };
+    if checksum_scheme(etag) != "etag"
+        || etag.len() != 32
+        || !etag.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return None;
+    }
+    md5.eq_ignore_ascii_case(etag).then_some(true)
+}
+
+/// 比较两个远程对象的 ETag（或 "md5:" 校验和），无法判断时返回 None
+///
+/// ETag 相同说明内容相同；不同则不能说明内容不同：分段上传的 ETag 取决于分段大小，
+/// SSE-KMS/SSE-C 加密对象的 ETag 同样是 32 位十六进制但不是内容 MD5。
+/// 只有两边都是已知的 MD5（"md5:" 校验和）时才判定为不同
+fn etags_match(a: &str, b: &str) -> Option<bool> {
+    if checksum_scheme(a) != checksum_scheme(b) {
+        return md5_matches_etag(a, b);
+    }
+    if a.eq_ignore_ascii_case(b) {
+        return Some(true);
+    }
+    match checksum_scheme(a) {
+        "etag" => None,
+        _ => Some(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, size: u64) -> FileInfo {
+        FileInfo {
+            path: path.to_string(),
+            size,
+            modified_time: 0,
+            is_dir: false,
+            checksum: None,
+            hidden: false,
+        }
+    }
+
+    fn dir(path: &str) -> FileInfo {
+        FileInfo {
+            path: path.to_string(),
+            size: 0,
+            modified_time: 0,
+            is_dir: true,
+            checksum: None,
+            hidden: false,
+        }
+    }
+
+    fn tree(entries: Vec<FileInfo>) -> HashMap<String, FileInfo> {
+        entries.into_iter().map(|f| (f.path.clone(), f)).collect()
+    }
+
+    #[test]
+    fn test_file_replaced_by_dir_in_mirror() {
+        // 源中 a 是文件，目标中 a 是目录（仅通过子文件推断）
+        let source = tree(vec![file("a", 10)]);
+        let dest = tree(vec![file("a/x.txt", 5)]);
+
+        let actions = FileComparator::default().compare_trees(&source, &dest, &SyncMode::Mirror);
+
+        assert!(matches!(
+            &actions[0],
+            SyncAction::Replace { path, size: 10, source_is_dir: false } if path == "a"
+        ));
+        assert!(!actions
+            .iter()
+            .any(|a| matches!(a, SyncAction::Copy { source_path, .. } if source_path == "a")));
+    }
+
+    #[test]
+    fn test_dir_replaced_by_file_in_mirror() {
+        // 源中 a 是目录，目标中 a 是文件
+        let source = tree(vec![dir("a"), file("a/x.txt", 5)]);
+        let dest = tree(vec![file("a", 10)]);
+
+        let actions = FileComparator::default().compare_trees(&source, &dest, &SyncMode::Mirror);
+
+        assert!(matches!(
+            &actions[0],
+            SyncAction::Replace { path, source_is_dir: true, .. } if path == "a"
+        ));
+        assert!(actions
+            .iter()
+            .any(|a| matches!(a, SyncAction::Copy { source_path, .. } if source_path == "a/x.txt")));
+        assert!(!actions
+            .iter()
+            .any(|a| matches!(a, SyncAction::Delete { path, .. } if path == "a")));
+    }
+
+    #[test]
+    fn test_explicit_dir_entries_mismatch() {
+        let source = tree(vec![dir("a")]);
+        let dest = tree(vec![file("a", 10)]);
+
+        let actions = FileComparator::default().compare_trees(&source, &dest, &SyncMode::Mirror);
+
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(
+            &actions[0],
+            SyncAction::Replace { source_is_dir: true, .. }
+        ));
+    }
+
+    #[test]
+    fn test_case_fold_maps_dest_paths() {
+        let source = tree(vec![dir("Docs"), file("Docs/Report.PDF", 10), file("Docs/new.TXT", 3)]);
+        let dest = tree(vec![dir("docs"), file("docs/report.pdf", 10), file("docs/Old.txt", 1)]);
+        let comparator = FileComparator::with_config(CompareConfig {
+            case_fold: CaseFold::Lower,
+            ..Default::default()
+        });
+
+        let actions = comparator.compare_trees(&source, &dest, &SyncMode::Mirror);
+
+        assert_eq!(actions.len(), 3);
+        assert!(matches!(
+            &actions[0],
+            SyncAction::Copy { source_path, dest_path, .. }
+                if source_path == "Docs/new.TXT" && dest_path == "docs/new.txt"
+        ));
+        assert!(matches!(&actions[1], SyncAction::Delete { path, .. } if path == "docs/Old.txt"));
+        assert!(matches!(&actions[2], SyncAction::Skip { path } if path == "docs/report.pdf"));
+    }
+
+    #[test]
+    fn test_seed_only_copies_missing() {
+        let mut newer = file("changed.txt", 20);
+        newer.modified_time = 100;
+        let source = tree(vec![file("new.txt", 1), newer, file("a", 10), file("same.txt", 3)]);
+        let dest = tree(vec![
+            file("changed.txt", 5),
+            file("a/x.txt", 5),
+            file("same.txt", 3),
+            file("extra.txt", 7),
+        ]);
+
+        let actions = FileComparator::default().compare_trees(&source, &dest, &SyncMode::Seed);
+
+        let copied: Vec<_> = actions
+            .iter()
+            .filter_map(|a| match a {
+                SyncAction::Copy { source_path, reverse: false, .. } => Some(source_path.as_str()),
+                SyncAction::Skip { .. } => None,
+                other => panic!("unexpected action: {:?}", other),
+            })
+            .collect();
+        assert_eq!(copied, vec!["new.txt"]);
+    }
+
+    #[test]
+    fn test_type_mismatch_is_conflict_outside_mirror() {
+        let source = tree(vec![file("a", 10)]);
+        let dest = tree(vec![file("a/x.txt", 5)]);
+
+        for mode in [SyncMode::Bidirectional, SyncMode::Backup] {
+            let actions = FileComparator::default().compare_trees(&source, &dest, &mode);
+            assert!(actions.iter().any(|a| matches!(
+                a,
+                SyncAction::Conflict { path, conflict_type: ConflictType::TypeMismatch, .. } if path == "a"
+            )));
+            assert!(!actions
+                .iter()
+                .any(|a| matches!(a, SyncAction::Replace { .. })));
+        }
+    }
+
+    #[test]
+    fn test_nested_paths_without_mismatch() {
+        let source = tree(vec![file("a/b/c.txt", 1)]);
+        let dest = tree(vec![file("a/b/d.txt", 1)]);
+
+        let actions = FileComparator::default().compare_trees(&source, &dest, &SyncMode::Mirror);
+
+        assert!(!actions
+            .iter()
+            .any(|a| matches!(a, SyncAction::Replace { .. } | SyncAction::Conflict { .. })));
+    }
+
+    #[test]
+    fn test_backup_overwrite_policy() {
+        // 只追加的日志：目标更大、更新
+        let source = tree(vec![FileInfo { modified_time: 100, ..file("log.txt", 10) }]);
+        let dest = tree(vec![FileInfo { modified_time: 200, ..file("log.txt", 20) }]);
+        let with_policy = |policy| {
+            FileComparator::with_config(CompareConfig {
+                overwrite_policy: policy,
+                ..Default::default()
+            })
+        };
+        let copies = |actions: &[SyncAction]| {
+            actions.iter().any(|a| matches!(a, SyncAction::Copy { reverse: false, .. }))
+        };
+
+        for policy in [OverwritePolicy::NewerOnly, OverwritePolicy::LargerOnly] {
+            let actions = with_policy(policy).compare_trees(&source, &dest, &SyncMode::Backup);
+            assert!(!copies(&actions));
+            // 镜像模式不受覆盖策略影响
+            let actions = with_policy(policy).compare_trees(&source, &dest, &SyncMode::Mirror);
+            assert!(copies(&actions));
+        }
+        let actions =
+            with_policy(OverwritePolicy::Always).compare_trees(&source, &dest, &SyncMode::Backup);
+        assert!(copies(&actions));
+
+        // 源更大时 larger-only 覆盖
+        let actions =
+            with_policy(OverwritePolicy::LargerOnly).compare_trees(&dest, &source, &SyncMode::Backup);
+        assert!(copies(&actions));
+    }
+
+    #[test]
+    fn test_zero_byte_compare() {
+        let src = FileInfo { modified_time: 100, ..file("empty", 0) };
+        let dst = FileInfo { modified_time: 5000, ..file("empty", 0) };
+        let comparator = |zero_byte| {
+            FileComparator::with_config(CompareConfig {
+                size_only_for_same_size: false,
+                zero_byte,
+                ..Default::default()
+            })
+        };
+
+        assert_eq!(
+            comparator(ZeroByteCompare::Normal).compare_files(&src, &dst),
+            FileRelation::DestNewer
+        );
+        assert_eq!(
+            comparator(ZeroByteCompare::AlwaysEqual).compare_files(&src, &dst),
+            FileRelation::Equal
+        );
+    }
+
+    #[test]
+    fn test_md5_matches_etag() {
+        let md5 = "md5:9e107d9d372bb6826bd81d3542a419d6";
+        assert_eq!(md5_matches_etag(md5, "9E107D9D372BB6826BD81D3542A419D6"), Some(true));
+        // 不同的 ETag 可能是 SSE-KMS/SSE-C 加密对象的，不能说明内容不同
+        assert_eq!(md5_matches_etag("e4d909c290d0fb1ca068ffaddf22cbd0", md5), None);
+        // 分段上传的 ETag 和其他算法的校验和无法比较
+        assert_eq!(md5_matches_etag(md5, "9e107d9d372bb6826bd81d3542a419d6-3"), None);
+        assert_eq!(md5_matches_etag(md5, "blake3:9e107d9d372bb6826bd81d3542a419d6"), None);
+
+        let comparator = FileComparator::with_config(CompareConfig {
+            etag_md5: true,
+            ..Default::default()
+        });
+        let local = FileInfo { checksum: Some(md5.to_string()), ..file("a", 10) };
+        // ETag 相同时忽略修改时间
+        let same = FileInfo {
+            checksum: Some("9e107d9d372bb6826bd81d3542a419d6".to_string()),
+            modified_time: 100,
+            ..file("a", 10)
+        };
+        assert_eq!(comparator.compare_files(&local, &same), FileRelation::Equal);
+        // ETag 不同时回退到大小/时间比较，不会因加密对象的 ETag 每次都重新上传
+        let encrypted = FileInfo {
+            checksum: Some("e4d909c290d0fb1ca068ffaddf22cbd0".to_string()),
+            ..file("a", 10)
+        };
+        assert_eq!(comparator.compare_files(&local, &encrypted), FileRelation::Equal);
+        let changed = FileInfo { size: 11, ..encrypted };
+        assert_eq!(comparator.compare_files(&local, &changed), FileRelation::Different);
+    }
+
+    #[test]
+    fn test_compare_etags() {
+        let single = "9e107d9d372bb6826bd81d3542a419d6";
+        assert_eq!(etags_match(single, &single.to_uppercase()), Some(true));
+        // 32 位十六进制的 ETag 也可能不是 MD5（SSE-KMS/SSE-C），不同时无法判断
+        assert_eq!(etags_match(single, "e4d909c290d0fb1ca068ffaddf22cbd0"), None);
+        assert_eq!(
+            etags_match(&format!("md5:{}", single), "e4d909c290d0fb1ca068ffaddf22cbd0"),
+            None
+        );
+        assert_eq!(
+            etags_match(&format!("md5:{}", single), "md5:e4d909c290d0fb1ca068ffaddf22cbd0"),
+            Some(false)
+        );
+        assert_eq!(etags_match("abc-2", "abc-2"), Some(true));
+        // 分段大小不同时 ETag 不同，不能说明内容不同
+        assert_eq!(etags_match("abc-2", "def-3"), None);
+        assert_eq!(etags_match(&format!("md5:{}", single), single), Some(true));
+
+        let comparator = FileComparator::with_config(CompareConfig {
+            compare_etags: true,
+            size_only_for_same_size: false,
+            ..Default::default()
+        });
+        let etag = |sum: &str, mtime| FileInfo {
+            checksum: Some(sum.to_string()),
+            modified_time: mtime,
+            ..file("a", 10)
+        };
+        // 时间不同但 ETag 相同：跳过传输
+        assert_eq!(
+            comparator.compare_files(&etag(single, 100), &etag(single, 9000)),
+            FileRelation::Equal
+        );
+        // ETag 不同时不能说明内容不同，按时间比较
+        let other = etag("e4d909c290d0fb1ca068ffaddf22cbd0", 100);
+        assert_eq!(
+            comparator.compare_files(&etag(single, 100), &other),
+            FileRelation::Equal
+        );
+        assert_eq!(
+            comparator.compare_files(&etag(single, 9000), &other),
+            FileRelation::SourceNewer
+        );
+        // 无法比较时回退到时间比较
+        assert_eq!(
+            comparator.compare_files(&etag("abc-2", 9000), &etag("def-3", 100)),
+            FileRelation::SourceNewer
+        );
+    }
+
+    #[test]
+    fn test_summarize_by_dir() {
+        let actions = vec![
+            SyncAction::Copy {
+                source_path: "a/b/1.txt".to_string(),
+                dest_path: "a/b/1.txt".to_string(),
+                size: 10,
+                reverse: false,
+            },
+            SyncAction::Delete { path: "a/2.txt".to_string(), from_dest: true },
+            SyncAction::Skip { path: "c/3.txt".to_string() },
+            SyncAction::Skip { path: "root.txt".to_string() },
+        ];
+
+        let root = FileComparator::summarize_by_dir(&actions, 1);
+        assert_eq!((root.copy_count, root.delete_count, root.skip_count), (1, 1, 2));
+        assert_eq!(root.total_bytes, 10);
+        let paths: Vec<_> = root.children.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, ["a", "c"]);
+        assert_eq!(root.children[0].total_bytes, 10);
+        assert!(root.children[0].children.is_empty());
+
+        let root = FileComparator::summarize_by_dir(&actions, 2);
+        assert_eq!(root.children[0].children[0].path, "a/b");
+        assert_eq!(root.children[0].children[0].delete_count, 0);
+    }
+
+    #[test]
+    fn test_relations_and_reasons() {
+        let source = tree(vec![
+            FileInfo { modified_time: 4 * 3600, ..file("a.txt", 10) },
+            file("b.bin", 3 * 1024 * 1024),
+            file("new.txt", 1),
+        ]);
+        let dest = tree(vec![
+            FileInfo { modified_time: 3600, ..file("a.txt", 10) },
+            file("b.bin", 1024),
+        ]);
+        let comparator = FileComparator::with_config(CompareConfig {
+            size_only_for_same_size: false,
+            ..Default::default()
+        });
+
+        let (actions, relations) =
+            comparator.compare_trees_with_relations(&source, &dest, &SyncMode::Mirror);
+        assert_eq!(actions.len(), 3);
+        // 只记录两边都存在的文件
+        assert_eq!(relations.len(), 2);
+        assert_eq!(relations["a.txt"], FileRelation::SourceNewer);
+        assert_eq!(
+            relations["a.txt"].describe(&source["a.txt"], &dest["a.txt"]),
+            "源文件较新（新 3小时）"
+        );
+        assert_eq!(
+            relations["b.bin"].describe(&source["b.bin"], &dest["b.bin"]),
+            "大小不同（1.0 KB → 3.0 MB）"
+        );
+    }
 }
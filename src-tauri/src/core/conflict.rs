@@ -1,6 +1,9 @@
+use crate::core::comparator::{ConflictType, SyncAction};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 /// 冲突解决策略
@@ -11,6 +14,8 @@ pub enum ConflictResolution {
     KeepDest,
     KeepBoth,
     Skip,
+    /// 两边都修改时保留修改时间较新的一边（时间相同比较大小，仍相同则需要手动处理）
+    NewerWins,
 }
 
 impl std::fmt::Display for ConflictResolution {
@@ -20,20 +25,146 @@ impl std::fmt::Display for ConflictResolution {
             ConflictResolution::KeepDest => write!(f, "keep_dest"),
             ConflictResolution::KeepBoth => write!(f, "keep_both"),
             ConflictResolution::Skip => write!(f, "skip"),
+            ConflictResolution::NewerWins => write!(f, "newer_wins"),
         }
     }
 }
 
 impl From<&str> for ConflictResolution {
     fn from(s: &str) -> Self {
-        match s {
-            "keep_source" => ConflictResolution::KeepSource,
-            "keep_dest" => ConflictResolution::KeepDest,
-            "keep_both" => ConflictResolution::KeepBoth,
-            "skip" => ConflictResolution::Skip,
-            _ => ConflictResolution::Skip,
+        Self::parse(s).unwrap_or(ConflictResolution::Skip)
+    }
+}
+
+impl ConflictResolution {
+    /// 按名称解析（与序列化名称一致）
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "keep_source" => Some(ConflictResolution::KeepSource),
+            "keep_dest" => Some(ConflictResolution::KeepDest),
+            "keep_both" => Some(ConflictResolution::KeepBoth),
+            "skip" => Some(ConflictResolution::Skip),
+            "newer_wins" => Some(ConflictResolution::NewerWins),
+            _ => None,
+        }
+    }
+
+    /// 按本策略把冲突转换为要执行的动作，无法自动处理时返回 None（保留冲突）
+    ///
+    /// 保留两者：目标中的版本先重命名为冲突文件名并复制回源，源的版本覆盖原路径；
+    /// 类型冲突只支持跳过和保留源；较新者优先只处理两边都存在的文件
+    pub fn apply(self, action: &SyncAction, now: i64) -> Option<Vec<SyncAction>> {
+        let SyncAction::Conflict {
+            path,
+            source_info,
+            dest_info,
+            conflict_type,
+        } = action
+        else {
+            return None;
+        };
+        let copy = |size: u64, reverse: bool| SyncAction::Copy {
+            source_path: path.clone(),
+            dest_path: path.clone(),
+            size,
+            reverse,
+        };
+
+        match (self, conflict_type) {
+            (ConflictResolution::Skip, _) => Some(vec![SyncAction::Skip { path: path.clone() }]),
+            (
+                ConflictResolution::NewerWins,
+                ConflictType::BothModified | ConflictType::SameSizeDifferentTime,
+            ) => {
+                let (s, d) = (source_info.as_ref()?, dest_info.as_ref()?);
+                match s.modified_time.cmp(&d.modified_time).then(s.size.cmp(&d.size)) {
+                    Ordering::Greater => Some(vec![copy(s.size, false)]),
+                    Ordering::Less => Some(vec![copy(d.size, true)]),
+                    Ordering::Equal => None,
+                }
+            }
+            (ConflictResolution::NewerWins, _) => None,
+            (ConflictResolution::KeepSource, ConflictType::TypeMismatch) => {
+                source_info.as_ref().map(|s| {
+                    vec![SyncAction::Replace {
+                        path: path.clone(),
+                        size: s.size,
+                        source_is_dir: s.is_dir,
+                    }]
+                })
+            }
+            (_, ConflictType::TypeMismatch) => None,
+            // 一边已删除时，保留源/目标即让另一边与之一致（包括删除）
+            (ConflictResolution::KeepSource, _) => Some(vec![match source_info {
+                Some(s) => copy(s.size, false),
+                None => SyncAction::Delete {
+                    path: path.clone(),
+                    from_dest: true,
+                },
+            }]),
+            (ConflictResolution::KeepDest, _) => Some(vec![match dest_info {
+                Some(d) => copy(d.size, true),
+                None => SyncAction::Delete {
+                    path: path.clone(),
+                    from_dest: false,
+                },
+            }]),
+            (ConflictResolution::KeepBoth, _) => match (source_info, dest_info) {
+                (Some(s), Some(d)) => {
+                    let renamed = ConflictResolver::generate_conflict_name(path, "dest", now);
+                    Some(vec![
+                        SyncAction::Rename {
+                            from: path.clone(),
+                            to: renamed.clone(),
+                            size: d.size,
+                        },
+                        copy(s.size, false),
+                        SyncAction::Copy {
+                            source_path: renamed.clone(),
+                            dest_path: renamed,
+                            size: d.size,
+                            reverse: true,
+                        },
+                    ])
+                }
+                // 一边已删除时保留仍存在的文件
+                (Some(s), None) => Some(vec![copy(s.size, false)]),
+                (None, Some(d)) => Some(vec![copy(d.size, true)]),
+                (None, None) => None,
+            },
         }
     }
+
+    /// 按路径指定的解决方式（`resolutions`）处理冲突，未指定的使用 `default`
+    ///
+    /// 返回处理后的动作和已处理的冲突数；同步和分析预览使用同一规则
+    pub fn apply_all(
+        actions: Vec<SyncAction>,
+        resolutions: &HashMap<String, String>,
+        default: Option<Self>,
+        now: i64,
+    ) -> (Vec<SyncAction>, usize) {
+        if default.is_none() && resolutions.is_empty() {
+            return (actions, 0);
+        }
+        let mut resolved = 0;
+        let mut result = Vec::with_capacity(actions.len());
+        for action in actions {
+            if let SyncAction::Conflict { path, .. } = &action {
+                let resolution = resolutions
+                    .get(path)
+                    .map(|r| Self::from(r.as_str()))
+                    .or(default);
+                if let Some(replacement) = resolution.and_then(|r| r.apply(&action, now)) {
+                    resolved += 1;
+                    result.extend(replacement);
+                    continue;
+                }
+            }
+            result.push(action);
+        }
+        (result, resolved)
+    }
 }
 
 /// 冲突记录
@@ -203,3 +334,151 @@ impl Default for ConflictResolver {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FileInfo;
+
+    fn info((modified_time, size): (i64, u64)) -> FileInfo {
+        FileInfo {
+            path: "a.txt".to_string(),
+            size,
+            modified_time,
+            is_dir: false,
+            checksum: None,
+            hidden: false,
+        }
+    }
+
+    fn conflict(source: (i64, u64), dest: (i64, u64)) -> SyncAction {
+        SyncAction::Conflict {
+            path: "a.txt".to_string(),
+            source_info: Some(info(source)),
+            dest_info: Some(info(dest)),
+            conflict_type: ConflictType::BothModified,
+        }
+    }
+
+    /// 动作的简要描述，便于比较
+    fn describe(actions: &[SyncAction]) -> Vec<String> {
+        actions
+            .iter()
+            .map(|action| match action {
+                SyncAction::Copy {
+                    source_path,
+                    size,
+                    reverse,
+                    ..
+                } => {
+                    let direction = if *reverse { "<-" } else { "->" };
+                    format!("copy {} {} {}", source_path, size, direction)
+                }
+                SyncAction::Delete { path, from_dest } => {
+                    format!("delete {} {}", path, if *from_dest { "dest" } else { "source" })
+                }
+                SyncAction::Rename { from, to, size } => format!("rename {} {} {}", from, to, size),
+                other => format!("{:?}", other),
+            })
+            .collect()
+    }
+
+    /// 较新者优先的结果：Some(true) 复制源到目标，Some(false) 复制目标到源
+    fn newer_wins(source: (i64, u64), dest: (i64, u64)) -> Option<bool> {
+        let actions = ConflictResolution::NewerWins.apply(&conflict(source, dest), 0)?;
+        match actions.as_slice() {
+            [SyncAction::Copy { reverse, .. }] => Some(!reverse),
+            other => panic!("unexpected actions: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_newer_wins() {
+        assert_eq!(newer_wins((200, 1), (100, 5)), Some(true));
+        assert_eq!(newer_wins((100, 5), (200, 1)), Some(false));
+        // 时间相同时保留较大的一边
+        assert_eq!(newer_wins((100, 5), (100, 1)), Some(true));
+        assert_eq!(newer_wins((100, 1), (100, 5)), Some(false));
+        // 时间和大小都相同时需要手动处理
+        assert_eq!(newer_wins((100, 5), (100, 5)), None);
+    }
+
+    #[test]
+    fn test_keep_source_and_dest() {
+        let action = conflict((100, 5), (200, 3));
+        let keep_source = ConflictResolution::KeepSource.apply(&action, 0).unwrap();
+        assert_eq!(describe(&keep_source), vec!["copy a.txt 5 ->"]);
+        let keep_dest = ConflictResolution::KeepDest.apply(&action, 0).unwrap();
+        assert_eq!(describe(&keep_dest), vec!["copy a.txt 3 <-"]);
+
+        // 一边已删除：让另一边与之一致
+        let deleted_in_source = SyncAction::Conflict {
+            path: "a.txt".to_string(),
+            source_info: None,
+            dest_info: Some(info((200, 3))),
+            conflict_type: ConflictType::ModifiedVsDeleted,
+        };
+        let actions = ConflictResolution::KeepSource.apply(&deleted_in_source, 0).unwrap();
+        assert_eq!(describe(&actions), vec!["delete a.txt dest"]);
+        let actions = ConflictResolution::KeepDest.apply(&deleted_in_source, 0).unwrap();
+        assert_eq!(describe(&actions), vec!["copy a.txt 3 <-"]);
+    }
+
+    #[test]
+    fn test_keep_both() {
+        let now = 1_700_000_000;
+        let renamed = ConflictResolver::generate_conflict_name("a.txt", "dest", now);
+        let action = conflict((100, 5), (200, 3));
+        let actions = ConflictResolution::KeepBoth.apply(&action, now).unwrap();
+        // 目标版本先改名并复制回源，源版本覆盖原路径
+        assert_eq!(
+            describe(&actions),
+            vec![
+                format!("rename a.txt {} 3", renamed),
+                "copy a.txt 5 ->".to_string(),
+                format!("copy {} 3 <-", renamed),
+            ]
+        );
+
+        // 一边已删除时保留仍存在的文件，不删除
+        let deleted_in_dest = SyncAction::Conflict {
+            path: "a.txt".to_string(),
+            source_info: Some(info((100, 5))),
+            dest_info: None,
+            conflict_type: ConflictType::ModifiedVsDeleted,
+        };
+        let actions = ConflictResolution::KeepBoth.apply(&deleted_in_dest, now).unwrap();
+        assert_eq!(describe(&actions), vec!["copy a.txt 5 ->"]);
+    }
+
+    #[test]
+    fn test_apply_all_prefers_path_resolution() {
+        let actions = vec![
+            conflict((100, 5), (200, 3)),
+            SyncAction::Skip {
+                path: "b.txt".to_string(),
+            },
+        ];
+        let (result, resolved) =
+            ConflictResolution::apply_all(actions.clone(), &HashMap::new(), None, 0);
+        assert_eq!((result.len(), resolved), (2, 0));
+
+        let (result, resolved) = ConflictResolution::apply_all(
+            actions.clone(),
+            &HashMap::new(),
+            Some(ConflictResolution::KeepDest),
+            0,
+        );
+        assert_eq!(resolved, 1);
+        assert_eq!(describe(&result[..1]), vec!["copy a.txt 3 <-"]);
+
+        let per_path = HashMap::from([("a.txt".to_string(), "keep_source".to_string())]);
+        let (result, _) = ConflictResolution::apply_all(
+            actions,
+            &per_path,
+            Some(ConflictResolution::KeepDest),
+            0,
+        );
+        assert_eq!(describe(&result[..1]), vec!["copy a.txt 5 ->"]);
+    }
+}
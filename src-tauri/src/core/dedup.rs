@@ -0,0 +1,168 @@
+//! 重复文件检测 - 在源存储中查找内容相同的文件
+
+use crate::core::file_state::{
+    calculate_hash_ranged, calculate_quick_hash_ranged, QUICK_HASH_FULL_LIMIT,
+};
+use crate::storage::{FileInfo, Storage};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{debug, info};
+
+/// 一组内容相同的文件
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: u64,
+    pub paths: Vec<String>,
+    /// 去重后可节省的字节数（保留一份）
+    pub wasted_bytes: u64,
+}
+
+/// 重复文件检测结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateReport {
+    pub groups: Vec<DuplicateGroup>,
+    pub files_checked: usize,
+    pub total_wasted_bytes: u64,
+}
+
+/// 查找重复文件
+///
+/// 先按大小分组，只对大小相同的文件计算快速 hash（按范围读取采样块）；
+/// 快速 hash 相同时再分块读取计算完整 hash 确认，小文件的快速 hash 即完整 hash。
+pub async fn find_duplicates(
+    storage: &dyn Storage,
+    tree: &HashMap<String, FileInfo>,
+    cancel_flag: Option<&AtomicBool>,
+) -> Result<DuplicateReport> {
+    let is_cancelled = || cancel_flag.is_some_and(|f| f.load(Ordering::Relaxed));
+
+    // 按大小分组（空文件不参与）
+    let mut by_size: HashMap<u64, Vec<&str>> = HashMap::new();
+    for file in tree.values().filter(|f| !f.is_dir && f.size > 0) {
+        by_size.entry(file.size).or_default().push(&file.path);
+    }
+
+    let mut groups = Vec::new();
+    let mut files_checked = 0;
+
+    for (size, paths) in by_size.into_iter().filter(|(_, p)| p.len() > 1) {
+        // 快速 hash 分组
+        let mut by_quick: HashMap<String, Vec<&str>> = HashMap::new();
+        for path in paths {
+            if is_cancelled() {
+                return Err(anyhow::anyhow!("操作已取消"));
+            }
+            match calculate_quick_hash_ranged(storage, path, size).await {
+                Ok(hash) => {
+                    files_checked += 1;
+                    by_quick.entry(hash).or_default().push(path);
+                }
+                Err(e) => debug!("读取文件失败，跳过: {} - {}", path, e),
+            }
+        }
+
+        for (quick_hash, candidates) in by_quick.into_iter().filter(|(_, p)| p.len() > 1) {
+            let mut by_full: HashMap<String, Vec<String>> = HashMap::new();
+            if size <= QUICK_HASH_FULL_LIMIT as u64 {
+                by_full.insert(quick_hash, candidates.iter().map(|p| p.to_string()).collect());
+            } else {
+                // 完整 hash 确认（快速 hash 只采样了部分内容）
+                for path in candidates {
+                    if is_cancelled() {
+                        return Err(anyhow::anyhow!("操作已取消"));
+                    }
+                    match calculate_hash_ranged(storage, path, size).await {
+                        Ok(hash) => by_full.entry(hash).or_default().push(path.to_string()),
+                        Err(e) => debug!("读取文件失败，跳过: {} - {}", path, e),
+                    }
+                }
+            }
+
+            for (hash, mut paths) in by_full.into_iter().filter(|(_, p)| p.len() > 1) {
+                paths.sort();
+                groups.push(DuplicateGroup {
+                    hash,
+                    size,
+                    wasted_bytes: size * (paths.len() as u64 - 1),
+                    paths,
+                });
+            }
+        }
+    }
+
+    // 可节省空间多的排在前面
+    groups.sort_by(|a, b| b.wasted_bytes.cmp(&a.wasted_bytes));
+    let total_wasted_bytes = groups.iter().map(|g| g.wasted_bytes).sum();
+
+    info!(
+        "重复文件检测完成: {} 组, 可节省 {} 字节",
+        groups.len(),
+        total_wasted_bytes
+    );
+
+    Ok(DuplicateReport {
+        groups,
+        files_checked,
+        total_wasted_bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+
+    fn tree_of(storage: &MemoryStorage, paths: &[&str]) -> HashMap<String, FileInfo> {
+        paths
+            .iter()
+            .map(|path| {
+                let size = storage.get(path).unwrap().len() as u64;
+                let info = FileInfo {
+                    path: path.to_string(),
+                    size,
+                    modified_time: 0,
+                    is_dir: false,
+                    checksum: None,
+                    hidden: false,
+                };
+                (path.to_string(), info)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicates_reads_ranges_once() {
+        let big: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        // 只在采样块之外不同：快速 hash 相同，完整 hash 不同
+        let mut big_other = big.clone();
+        big_other[40_000] ^= 1;
+        let storage = MemoryStorage::new();
+        storage.put("a/big.bin", &big, 0);
+        storage.put("b/big.bin", &big, 0);
+        storage.put("c/big.bin", &big_other, 0);
+        storage.put("a/small.txt", b"same", 0);
+        storage.put("b/small.txt", b"same", 0);
+        storage.put("unique.txt", b"other", 0);
+        let tree = tree_of(
+            &storage,
+            &["a/big.bin", "b/big.bin", "c/big.bin", "a/small.txt", "b/small.txt", "unique.txt"],
+        );
+
+        let report = find_duplicates(&storage, &tree, None).await.unwrap();
+        assert_eq!(report.groups.len(), 2);
+        assert_eq!(report.groups[0].paths, vec!["a/big.bin", "b/big.bin"]);
+        assert_eq!(report.groups[1].paths, vec!["a/small.txt", "b/small.txt"]);
+        assert_eq!(report.total_wasted_bytes, big.len() as u64 + 4);
+
+        // 不整体读取文件；只有快速 hash 冲突的大文件读取完整内容，且只读一次
+        assert_eq!(storage.full_reads.load(Ordering::SeqCst), 0);
+        let sampled = 3 * 3 * 16384 + 2 * 4;
+        let full = 3 * big.len() as u64;
+        assert_eq!(storage.range_bytes.load(Ordering::SeqCst), sampled + full);
+    }
+}
@@ -1,17 +1,29 @@
 #![allow(non_snake_case)]
 
+use crate::core::bandwidth::BandwidthLimiter;
 use crate::core::cache::FileListCache;
-use crate::core::comparator::{ActionSummary, FileComparator, SyncAction};
-use crate::core::file_state::{calculate_quick_hash, FileState, FileStateManager};
-use crate::core::scanner::{FileScanner, ScanConfig};
-use crate::db::{SyncJob, SyncProgress, SyncStatus};
-use crate::storage::Storage;
+use crate::core::conflict::ConflictResolution;
+use crate::core::comparator::{
+    ActionSummary, CaseFold, CompareConfig, ConflictType, FileComparator, SyncAction,
+};
+use crate::core::file_state::{
+    calculate_quick_hash, calculate_quick_hash_ranged, ChecksumAlgorithm, FileState,
+    FileStateManager,
+};
+use crate::core::hooks;
+use crate::core::scanner::{matches_glob, FileScanner, ScanConfig};
+use crate::core::transfer::{TempFileNaming, TransferManager, TransferState, TransferStatus};
+use crate::db::{SyncJob, SyncMode, SyncProgress, SyncStatus};
+use crate::storage::{
+    FileInfo, ListCounter, Storage, StorageCapabilities, SyncError, DELETE_BATCH_SIZE,
+};
 use anyhow::Result;
 use serde::Serialize;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::{mpsc, RwLock, Semaphore};
+use tokio::sync::{mpsc, Notify, RwLock, Semaphore, SemaphorePermit};
 use tracing::{debug, error, info, warn};
 
 // ============================================================================
@@ -30,33 +42,189 @@ const DEFAULT_MAX_RETRIES: u32 = 5;
 const DEFAULT_RETRY_BASE_DELAY_MS: u64 = 2000;
 /// 默认远程缓存 TTL（秒，30分钟）
 const DEFAULT_REMOTE_CACHE_TTL: u64 = 1800;
-/// 文件扫描并发数
-const SCANNER_CONCURRENCY: usize = 8;
 /// 进度更新间隔（毫秒）
 const PROGRESS_UPDATE_INTERVAL_MS: u64 = 500;
+/// 默认速度平滑系数（新速度的权重）
+pub const DEFAULT_SPEED_SMOOTHING: f64 = 0.3;
 /// 重试指数退避基数
 const RETRY_BACKOFF_BASE: u64 = 2;
+/// 超时取消后等待同步收尾（保存已完成文件状态）的时间（秒）
+const TIMEOUT_GRACE_SECS: u64 = 30;
+/// 仅追加模式下比对的目标文件末尾长度（64KB）
+const APPEND_CHECK_BYTES: u64 = 64 * 1024;
+/// 比较冲突文件内容时每次读取的长度（4MB）
+const CONFLICT_COMPARE_CHUNK: u64 = 4 * 1024 * 1024;
+/// 同步过程中保存文件状态的间隔（秒）
+const STATE_FLUSH_INTERVAL_SECS: u64 = 5;
+/// 未保存的文件状态达到该数量时立即保存
+const STATE_FLUSH_BATCH: usize = 500;
+/// 检测重命名时并发计算快速哈希的文件数
+const RENAME_HASH_CONCURRENCY: usize = 8;
 
 // ============================================================================
 // 参数封装结构体
 // ============================================================================
 
 /// 重试配置
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 struct RetryConfig {
     max_retries: u32,
     base_delay_ms: u64,
+    /// 文件被占用时跳过（否则视为失败）
+    skip_locked: bool,
+    /// 本次同步剩余的重试次数（所有文件共享，None 表示不限制）
+    budget: Option<Arc<AtomicU32>>,
+}
+
+impl RetryConfig {
+    /// 占用一次重试，预算已用尽时返回 false
+    fn take_retry(&self) -> bool {
+        match &self.budget {
+            None => true,
+            Some(remaining) => remaining
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok(),
+        }
+    }
 }
 
 /// 传输参数
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 struct TransferParams {
     chunk_size: u64,
     stream_threshold: u64,
+    /// 断点续传状态管理（未启用断点续传时为 None）
+    transfers: Option<Arc<TransferManager>>,
+    /// 带宽限制（写入目标前申请）
+    limiter: Option<Arc<BandwidthLimiter>>,
+    /// 取消标志，流式传输的每个分块都会检查
+    cancelled: Arc<AtomicBool>,
+    /// 仅追加模式：目标是源的前缀时只上传新增部分
+    append_only: bool,
+    /// 本地到本地复制时保留扩展属性
+    preserve_xattrs: bool,
+    /// 流式传输临时文件的命名方式（断点续传的临时文件也按此命名）
+    temp_naming: crate::core::TempFileNaming,
+    /// 与扫描共享的远程操作并发上限（None 表示不限制）
+    remote_ops: Option<Arc<Semaphore>>,
+    /// 读取阶段的并发上限（None 表示只受传输并发数限制）
+    read_slots: Option<Arc<Semaphore>>,
+    /// 写入阶段的并发上限（None 表示只受传输并发数限制）
+    write_slots: Option<Arc<Semaphore>>,
+    /// 读写分别限制时同时进行的传输数（读写上限之和，保证两个阶段都能用满）
+    max_in_flight: Option<usize>,
+}
+
+impl TransferParams {
+    /// 申请读取许可（未限制时返回 None）
+    async fn read_permit(&self) -> Result<Option<SemaphorePermit<'_>>> {
+        Self::permit(&self.read_slots).await
+    }
+
+    /// 申请写入许可（未限制时返回 None）
+    async fn write_permit(&self) -> Result<Option<SemaphorePermit<'_>>> {
+        Self::permit(&self.write_slots).await
+    }
+
+    async fn permit(slots: &Option<Arc<Semaphore>>) -> Result<Option<SemaphorePermit<'_>>> {
+        match slots {
+            Some(slots) => Ok(Some(slots.acquire().await?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// 扫描进度上报参数
+struct ScanProgressReporter {
+    progress_tx: Option<mpsc::Sender<SyncProgress>>,
+    job_id: String,
+    start_time: i64,
+    /// 扫描器实时累加的已列出条目数
+    listed: Arc<ListCounter>,
+    /// 本次同步已完成扫描的各侧列出的条目数之和（过滤前的原始条目数）
+    listed_total: AtomicU64,
+    /// 是否有一侧没有扫描（从缓存加载或视为空），此时 `listed_total` 不完整
+    partial: AtomicBool,
+    /// 上次同步列出的原始条目总数（用于估计剩余时间，与 `listed_total` 同一口径）
+    expected_total: Option<u64>,
+}
+
+impl ScanProgressReporter {
+    /// 扫描存储，扫描期间定时发送进度；`base` 为之前已扫描的条目数
+    async fn scan(
+        &self,
+        scanner: &FileScanner,
+        storage: &dyn Storage,
+        phase: String,
+        base: u64,
+    ) -> Result<HashMap<String, FileInfo>> {
+        self.listed.reset();
+        let listed_before = self.listed_total.load(Ordering::SeqCst);
+        let ticker = self.progress_tx.clone().map(|tx| {
+            let listed = self.listed.clone();
+            let job_id = self.job_id.clone();
+            let start_time = self.start_time;
+            let expected_total = self.expected_total;
+            tokio::spawn(async move {
+                let started = Instant::now();
+                loop {
+                    tokio::time::sleep(Duration::from_millis(PROGRESS_UPDATE_INTERVAL_MS)).await;
+                    let count = listed.get();
+                    let scanned = base + count;
+                    let eta = estimate_scan_eta(
+                        listed_before + count,
+                        count,
+                        started.elapsed(),
+                        expected_total,
+                    );
+                    let _ = tx
+                        .send(SyncProgress {
+                            jobId: job_id.clone(),
+                            status: SyncStatus::Scanning,
+                            phase: phase.clone(),
+                            currentFile: format!("已列出 {} 个条目", count),
+                            filesScanned: scanned as u32,
+                            filesToSync: 0,
+                            filesCompleted: 0,
+                            filesSkipped: 0,
+                            filesFailed: 0,
+                            bytesTransferred: 0,
+                            bytesTotal: 0,
+                            speed: 0,
+                            startTime: start_time,
+                            endTime: 0,
+                            scanEtaSecs: eta,
+                            filesCopiedToSource: 0,
+                            filesCopiedToDest: 0,
+                        })
+                        .await;
+                }
+            })
+        });
+
+        let result = scanner.scan_storage_with_timeout(storage).await;
+        if let Some(handle) = ticker {
+            handle.abort();
+        }
+        self.listed_total.fetch_add(self.listed.get(), Ordering::SeqCst);
+        result
+    }
+
+    /// 记录没有扫描的一侧，`files` 为其文件数（从缓存加载时用作已列出条目数的近似值）
+    fn skip(&self, files: usize) {
+        self.listed_total.fetch_add(files as u64, Ordering::SeqCst);
+        self.partial.store(true, Ordering::SeqCst);
+    }
+
+    /// 两侧都实际扫描时列出的原始条目总数
+    fn entries_listed(&self) -> Option<u64> {
+        (!self.partial.load(Ordering::SeqCst)).then(|| self.listed_total.load(Ordering::SeqCst))
+    }
 }
 
 /// 同步配置
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SyncConfig {
     /// 最大并发传输数
     pub max_concurrent_transfers: usize,
@@ -82,6 +250,69 @@ pub struct SyncConfig {
     pub cache_dir: Option<std::path::PathBuf>,
     /// 远程存储缓存 TTL（秒），本地存储不使用缓存
     pub remote_cache_ttl: u64,
+    /// 文件列表缓存写入格式
+    pub cache_format: crate::core::CacheFormat,
+    /// 镜像模式下是否允许源为空（源为空时会删除目标中的全部文件）
+    pub allow_empty_source: bool,
+    /// 视目标为空：跳过目标扫描，所有源文件直接复制（首次同步到新目标，双向同步时忽略）
+    ///
+    /// 目标顶层列表为空时自动启用；能确认目标不为空时忽略此选项
+    pub assume_empty_dest: bool,
+    /// 最长运行时间（秒），None 时使用任务自身的设置
+    pub max_runtime_secs: Option<u64>,
+    /// 已确认的删除路径（None 表示不需要确认，未确认的删除会被跳过）
+    pub confirmed_deletes: Option<std::collections::HashSet<String>>,
+    /// 快速失败：出现第一个失败后停止启动新的传输
+    pub fail_fast: bool,
+    /// 受保护路径（glob），匹配的目标文件永不删除
+    pub protect_patterns: Vec<String>,
+    /// 冲突文件两边内容完全相同时自动跳过（需要读取两边文件）
+    pub auto_resolve_identical_conflicts: bool,
+    /// 所有复制完成后再执行删除（移动被识别为删除+复制时，中断不会丢失数据）
+    pub delete_after_copy: bool,
+    /// 源端只新增、目标端只删除且内容相同的文件改为在目标内重命名
+    pub detect_renames: bool,
+    /// 备份模式下目标已存在且不同时的覆盖策略
+    pub overwrite_policy: crate::core::OverwritePolicy,
+    /// 两边都是空文件时的比较方式
+    pub zero_byte_compare: crate::core::ZeroByteCompare,
+    /// 文件被其他程序占用时跳过并警告，而不是计为失败
+    pub skip_locked_files: bool,
+    /// 速度平滑系数（0~1，新速度的权重）
+    pub speed_smoothing: f64,
+    /// 进度中报告瞬时速度而不是平滑后的速度
+    pub instant_speed: bool,
+    /// 本地到本地同步时复制扩展属性（xattr）
+    pub preserve_xattrs: bool,
+    /// 是否执行任务的前置/后置命令
+    pub allow_hooks: bool,
+    /// 钩子命令超时（秒）
+    pub hook_timeout_secs: u64,
+    /// 一次同步中所有文件共享的重试总次数（None 表示不限制）
+    pub retry_budget: Option<u32>,
+    /// 镜像模式下删除因同步而变空的目标目录
+    pub prune_empty_dirs: bool,
+    /// 清理空目录时同时删除原本就为空的目标目录
+    pub prune_existing_empty_dirs: bool,
+    /// 流式传输临时文件的命名方式
+    pub temp_file_naming: crate::core::TempFileNaming,
+    /// 只追加：目标文件是源文件的前缀时只传输新增的部分（任务级设置）
+    pub append_only: bool,
+    /// 扫描和传输共享的远程操作并发上限（任务级设置，None 表示不限制）
+    pub max_remote_ops: Option<usize>,
+    /// 同时读取源文件的传输数（任务级设置，None 时使用传输并发数）
+    pub read_concurrency: Option<usize>,
+    /// 同时写入目标的传输数（任务级设置，None 时使用传输并发数）
+    pub write_concurrency: Option<usize>,
+    /// 直接比较 ETag（任务级设置，仅两边都是 S3 兼容存储时生效）
+    pub compare_etags: bool,
+    /// 实际生效的目标路径大小写转换（任务级设置，双向同步时不生效）
+    pub case_fold: crate::core::CaseFold,
+    /// 没有按路径指定解决方式的冲突的默认解决方式（任务级设置）
+    pub default_conflict_resolution: Option<ConflictResolution>,
+    /// 全局带宽限制（None 表示不限速）
+    #[serde(skip)]
+    pub bandwidth_limiter: Option<Arc<BandwidthLimiter>>,
 }
 
 impl Default for SyncConfig {
@@ -99,6 +330,62 @@ impl Default for SyncConfig {
             force_refresh: false,
             cache_dir: None,
             remote_cache_ttl: DEFAULT_REMOTE_CACHE_TTL,
+            cache_format: crate::core::CacheFormat::default(),
+            allow_empty_source: false,
+            assume_empty_dest: false,
+            max_runtime_secs: None,
+            confirmed_deletes: None,
+            protect_patterns: Vec::new(),
+            auto_resolve_identical_conflicts: false,
+            delete_after_copy: false,
+            detect_renames: false,
+            overwrite_policy: crate::core::OverwritePolicy::default(),
+            zero_byte_compare: crate::core::ZeroByteCompare::default(),
+            skip_locked_files: false,
+            speed_smoothing: DEFAULT_SPEED_SMOOTHING,
+            instant_speed: false,
+            preserve_xattrs: false,
+            allow_hooks: false,
+            hook_timeout_secs: hooks::DEFAULT_HOOK_TIMEOUT_SECS,
+            retry_budget: None,
+            prune_empty_dirs: false,
+            prune_existing_empty_dirs: false,
+            temp_file_naming: crate::core::TempFileNaming::default(),
+            append_only: false,
+            max_remote_ops: None,
+            read_concurrency: None,
+            write_concurrency: None,
+            compare_etags: false,
+            case_fold: crate::core::CaseFold::default(),
+            default_conflict_resolution: None,
+            bandwidth_limiter: None,
+            fail_fast: false,
+        }
+    }
+}
+
+impl SyncConfig {
+    /// 应用任务级设置后的配置（分块大小、流式传输阈值、排除规则、最长运行时间、
+    /// 只追加、并发上限、比较方式和冲突的默认解决方式）
+    pub fn for_job(&self, job: &SyncJob) -> Self {
+        let mb = |v: Option<u64>| v.filter(|v| *v > 0).map(|v| v * 1024 * 1024);
+        let limit = |v: Option<u32>| v.filter(|n| *n > 0).map(|n| n as usize);
+        Self {
+            chunk_size: mb(job.chunkSizeMb).unwrap_or(self.chunk_size),
+            large_file_threshold: mb(job.streamThresholdMb).unwrap_or(self.large_file_threshold),
+            scan_config: self
+                .scan_config
+                .clone()
+                .with_excludes(job.excludePreset, &job.excludePatterns),
+            max_runtime_secs: self.max_runtime_secs.or(job.maxRuntimeSecs).filter(|s| *s > 0),
+            append_only: job.appendOnly,
+            max_remote_ops: limit(job.maxRemoteOps),
+            read_concurrency: limit(job.readConcurrency),
+            write_concurrency: limit(job.writeConcurrency),
+            compare_etags: job.compares_etags(),
+            case_fold: job.effective_case_fold(),
+            default_conflict_resolution: job.defaultConflictResolution,
+            ..self.clone()
         }
     }
 }
@@ -113,12 +400,60 @@ pub struct SyncReport {
     pub status: SyncStatus,
     pub filesScanned: u32,
     pub filesCopied: u32,
+    /// 从目标复制到源的文件数（双向同步的反向复制）
+    pub filesCopiedToSource: u32,
+    /// 从源复制到目标的文件数（含移动和替换）
+    pub filesCopiedToDest: u32,
     pub filesDeleted: u32,
     pub filesSkipped: u32,
     pub filesFailed: u32,
     pub bytesTransferred: u64,
     pub duration: u64,
     pub errors: Vec<String>,
+    /// 因被其他程序占用而跳过的文件
+    pub skippedLocked: Vec<String>,
+    /// 对应的同步历史记录 ID（未写入历史时为 None）
+    pub logId: Option<i64>,
+}
+
+impl SyncReport {
+    /// 已取消（未执行任何操作）的报告
+    pub fn cancelled(job_id: &str, start_time: i64) -> Self {
+        let end_time = chrono::Utc::now().timestamp();
+        Self {
+            jobId: job_id.to_string(),
+            startTime: start_time,
+            endTime: end_time,
+            status: SyncStatus::Cancelled,
+            filesScanned: 0,
+            filesCopied: 0,
+            filesCopiedToSource: 0,
+            filesCopiedToDest: 0,
+            filesDeleted: 0,
+            filesSkipped: 0,
+            filesFailed: 0,
+            bytesTransferred: 0,
+            duration: (end_time - start_time) as u64,
+            errors: vec!["同步已取消".to_string()],
+            skippedLocked: Vec::new(),
+            logId: None,
+        }
+    }
+}
+
+/// 单文件传输结果（用于诊断传输问题）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SingleFileResult {
+    pub path: String,
+    pub success: bool,
+    pub size: u64,
+    pub bytesTransferred: u64,
+    /// 传输内容的 hash（成功时）
+    pub hash: Option<String>,
+    pub retries: u32,
+    pub durationMs: u64,
+    pub error: Option<String>,
 }
 
 /// 传输统计
@@ -126,9 +461,35 @@ pub struct SyncReport {
 struct TransferStats {
     files_completed: AtomicU64,
     files_failed: AtomicU64,
+    /// 因被占用而跳过的文件数
+    files_locked: AtomicU64,
+    /// 成功从目标复制到源的文件数
+    files_copied_to_source: AtomicU64,
+    /// 成功从源复制到目标的文件数
+    files_copied_to_dest: AtomicU64,
     bytes_transferred: AtomicU64,
 }
 
+impl TransferStats {
+    /// 失败或因被占用而跳过的文件数（这些文件都没有完成复制）
+    fn files_not_done(&self) -> u64 {
+        self.files_failed.load(Ordering::Relaxed) + self.files_locked.load(Ordering::Relaxed)
+    }
+
+    /// 把目前为止的计数填入报告（同步未能正常结束时报告已完成的部分）
+    fn fill_report(&self, report: &mut SyncReport) {
+        let to_source = self.files_copied_to_source.load(Ordering::Relaxed) as u32;
+        let to_dest = self.files_copied_to_dest.load(Ordering::Relaxed) as u32;
+        let completed = self.files_completed.load(Ordering::Relaxed) as u32;
+        report.filesCopied = to_source + to_dest;
+        report.filesCopiedToSource = to_source;
+        report.filesCopiedToDest = to_dest;
+        report.filesDeleted = completed.saturating_sub(to_source + to_dest);
+        report.filesFailed = self.files_failed.load(Ordering::Relaxed) as u32;
+        report.bytesTransferred = self.bytes_transferred.load(Ordering::Relaxed);
+    }
+}
+
 /// 执行结果，包含文件状态信息
 struct ActionResult {
     file_path: Option<String>,
@@ -136,9 +497,37 @@ struct ActionResult {
     file_size: Option<i64>,
 }
 
+/// 并行执行结果
+struct ParallelOutcome {
+    files_copied: u32,
+    files_copied_to_source: u32,
+    files_copied_to_dest: u32,
+    files_deleted: u32,
+    files_failed: u32,
+    bytes_transferred: u64,
+    errors: Vec<String>,
+    /// 失败的文件（路径，错误信息）
+    failed_files: Vec<(String, String)>,
+    /// 因被占用而跳过的文件
+    skipped_locked: Vec<String>,
+    /// 成功执行的动作（用于增量更新目标缓存）
+    completed_actions: Vec<SyncAction>,
+}
+
 /// 带重试的动作执行结果
 struct RetryResult {
     file_state: Option<FileState>,
+    /// 成功前的重试次数
+    retries: u32,
+    /// 文件被占用而跳过（未执行）
+    locked: bool,
+}
+
+/// 带重试的动作最终失败
+struct RetryError {
+    message: String,
+    /// 实际进行的重试次数（不可重试的错误、取消或重试预算用尽时少于配置的次数）
+    retries: u32,
 }
 
 /// 同步引擎
@@ -146,6 +535,10 @@ pub struct SyncEngine {
     db: Arc<sqlx::SqlitePool>,
     config: SyncConfig,
     cancelled: Arc<AtomicBool>,
+    /// 因超过最长运行时间而取消
+    timed_out: AtomicBool,
+    /// 正在执行的同步的扫描文件数和传输统计，超时强制结束时用于报告已完成的部分
+    live_stats: std::sync::Mutex<Option<(u32, Arc<TransferStats>)>>,
 }
 
 impl SyncEngine {
@@ -154,6 +547,8 @@ impl SyncEngine {
             db,
             config: SyncConfig::default(),
             cancelled: Arc::new(AtomicBool::new(false)),
+            timed_out: AtomicBool::new(false),
+            live_stats: std::sync::Mutex::new(None),
         }
     }
 
@@ -162,6 +557,8 @@ impl SyncEngine {
             db,
             config,
             cancelled: Arc::new(AtomicBool::new(false)),
+            timed_out: AtomicBool::new(false),
+            live_stats: std::sync::Mutex::new(None),
         }
     }
 
@@ -175,11 +572,213 @@ impl SyncEngine {
         self.cancelled.load(Ordering::SeqCst)
     }
 
-    /// 运行同步任务
+    /// 超时取消时的错误信息
+    fn timeout_message(&self) -> Option<String> {
+        self.timed_out
+            .load(Ordering::SeqCst)
+            .then(|| "同步超过最长运行时间，已自动取消".to_string())
+    }
+
+    /// 删除目标中因删除或移走文件而变空的目录（由深到浅）
+    ///
+    /// 重新列出目标（包括排除规则忽略的文件和隐藏文件）找出空目录，再逐个非递归删除：
+    /// 列表遗漏的文件或列出后新写入的文件会让删除失败，而不会被一并删除
+    async fn prune_empty_dirs(&self, dest: &dyn Storage, completed: &[SyncAction]) {
+        // 被删除或移走的路径的所有上级目录
+        let mut vacated: HashSet<String> = HashSet::new();
+        for action in completed {
+            let path = match action {
+                SyncAction::Delete { path, from_dest: true, .. } => path,
+                SyncAction::Rename { from, .. } => from,
+                _ => continue,
+            };
+            let mut path = path.trim_end_matches('/');
+            while let Some((parent, _)) = path.rsplit_once('/') {
+                if !vacated.insert(parent.to_string()) {
+                    break;
+                }
+                path = parent;
+            }
+        }
+        let only = (!self.config.prune_existing_empty_dirs).then_some(&vacated);
+        if only.is_some_and(|v| v.is_empty()) {
+            return;
+        }
+
+        let files = match dest.list_files(None).await {
+            Ok(files) => files,
+            Err(e) => {
+                warn!("列出目标目录失败，跳过清理空目录: {}", e);
+                return;
+            }
+        };
+
+        let mut pruned = 0;
+        for dir in crate::storage::empty_dirs(&files, only) {
+            match dest.remove_empty_dir(&dir).await {
+                Ok(()) => {
+                    debug!("已删除空目录: {}", dir);
+                    pruned += 1;
+                }
+                // 子目录删除失败时上级目录不为空，删除同样会失败，继续即可
+                Err(e) => warn!("删除空目录失败: {} - {}", dir, e),
+            }
+        }
+        if pruned > 0 {
+            info!("已清理 {} 个空目录", pruned);
+        }
+    }
+
+    /// 运行同步任务：依次执行前置命令、同步、后置命令
+    ///
+    /// 前置命令失败时不执行同步；后置命令通过 SYNCTOOLS_STATUS 环境变量获得同步结果，失败只记录警告
     pub async fn run_sync(
         &self,
         job: &SyncJob,
         progress_tx: Option<mpsc::Sender<SyncProgress>>,
+    ) -> Result<SyncReport> {
+        let hooks = [job.preCommand.as_deref(), job.postCommand.as_deref()]
+            .map(|c| c.map(str::trim).filter(|c| !c.is_empty()));
+        if hooks.iter().any(Option::is_some) && !self.config.allow_hooks {
+            warn!("任务 {} 配置了前置/后置命令，但未在设置中允许执行钩子，已忽略", job.name);
+            return self.run_sync_timed(job, progress_tx).await;
+        }
+        let [pre_command, post_command] = hooks;
+        let timeout = Duration::from_secs(self.config.hook_timeout_secs.max(1));
+        let mut envs = vec![
+            ("SYNCTOOLS_JOB_ID", job.id.clone()),
+            ("SYNCTOOLS_JOB_NAME", job.name.clone()),
+        ];
+
+        if let Some(command) = pre_command {
+            let start_time = chrono::Utc::now().timestamp();
+            self.cancelled.store(false, Ordering::SeqCst);
+            let result =
+                hooks::run_hook("前置命令", command, &envs, timeout, Some(&*self.cancelled)).await;
+            if self.is_cancelled() {
+                info!("任务 {} 在执行前置命令时被取消", job.name);
+                return Ok(SyncReport::cancelled(&job.id, start_time));
+            }
+            if let Err(e) = result {
+                warn!("任务 {} 的前置命令失败，取消同步: {}", job.name, e);
+                let mut report = self.create_failed_report(&job.id, start_time, vec![e.to_string()]);
+                report.logId = self
+                    .log_sync_result(
+                        &job.id,
+                        start_time,
+                        report.endTime,
+                        &report.status,
+                        0,
+                        None,
+                        0,
+                        0,
+                        0,
+                        0,
+                        0,
+                        report.errors.first().cloned(),
+                        &[],
+                    )
+                    .await;
+                return Ok(report);
+            }
+        }
+
+        let result = self.run_sync_timed(job, progress_tx).await;
+
+        if let Some(command) = post_command {
+            // 与界面、日志中的状态名称一致
+            let status = match &result {
+                Ok(report) => serde_json::to_value(&report.status)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_string))
+                    .unwrap_or_default(),
+                Err(_) => "failed".to_string(),
+            };
+            envs.push(("SYNCTOOLS_STATUS", status));
+            if let Ok(report) = &result {
+                envs.push(("SYNCTOOLS_FILES_COPIED", report.filesCopied.to_string()));
+                envs.push(("SYNCTOOLS_FILES_FAILED", report.filesFailed.to_string()));
+            }
+            if let Err(e) = hooks::run_hook("后置命令", command, &envs, timeout, None).await {
+                warn!("任务 {} 的后置命令失败: {}", job.name, e);
+            }
+        }
+
+        result
+    }
+
+    /// 运行同步（设置了最长运行时间时，超时后自动取消）
+    async fn run_sync_timed(
+        &self,
+        job: &SyncJob,
+        progress_tx: Option<mpsc::Sender<SyncProgress>>,
+    ) -> Result<SyncReport> {
+        self.timed_out.store(false, Ordering::SeqCst);
+        *self.live_stats.lock().unwrap_or_else(|e| e.into_inner()) = None;
+
+        let Some(max_runtime) = self.config.for_job(job).max_runtime_secs else {
+            return self.run_sync_inner(job, progress_tx).await;
+        };
+
+        let start_time = chrono::Utc::now().timestamp();
+        let sync = self.run_sync_inner(job, progress_tx);
+        tokio::pin!(sync);
+
+        if let Ok(result) = tokio::time::timeout(Duration::from_secs(max_runtime), &mut sync).await {
+            return result;
+        }
+
+        warn!("同步任务超过最长运行时间 {} 秒，正在取消: {}", max_runtime, job.id);
+        self.timed_out.store(true, Ordering::SeqCst);
+        self.cancel();
+
+        // 等待同步流程响应取消，以保存已完成文件的状态并停止进度任务
+        match tokio::time::timeout(Duration::from_secs(TIMEOUT_GRACE_SECS), &mut sync).await {
+            Ok(Ok(mut report)) => {
+                if let Some(msg) = self.timeout_message() {
+                    if !report.errors.contains(&msg) {
+                        report.errors.retain(|e| e != "同步已取消");
+                        report.errors.insert(0, msg);
+                    }
+                }
+                Ok(report)
+            }
+            Ok(Err(e)) => Err(e),
+            Err(_) => {
+                warn!("同步任务未能在 {} 秒内响应取消，强制结束: {}", TIMEOUT_GRACE_SECS, job.id);
+                let mut report = self.create_cancelled_report(&job.id, start_time);
+                report.errors = self.timeout_message().into_iter().collect();
+                // 已开始传输时报告到目前为止完成的部分
+                let live = self.live_stats.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                if let Some((files_scanned, stats)) = live {
+                    report.filesScanned = files_scanned;
+                    stats.fill_report(&mut report);
+                }
+                report.logId = self.log_sync_result(
+                    &job.id,
+                    start_time,
+                    report.endTime,
+                    &report.status,
+                    report.filesScanned,
+                    None,
+                    report.filesCopied,
+                    report.filesCopiedToSource,
+                    report.filesCopiedToDest,
+                    report.filesDeleted,
+                    report.bytesTransferred,
+                    report.errors.first().cloned(),
+                    &[],
+                )
+                .await;
+                Ok(report)
+            }
+        }
+    }
+
+    async fn run_sync_inner(
+        &self,
+        job: &SyncJob,
+        progress_tx: Option<mpsc::Sender<SyncProgress>>,
     ) -> Result<SyncReport> {
         let start_time = chrono::Utc::now().timestamp();
         let job_id = job.id.clone();
@@ -210,12 +809,21 @@ impl SyncEngine {
                 speed: 0,
                 startTime: start_time,
                 endTime: 0,
+                scanEtaSecs: None,
+                filesCopiedToSource: 0,
+                filesCopiedToDest: 0,
             },
         )
         .await;
 
+        // 只读存储只能作为单向同步的源
+        if let Err(e) = job.validate_storage_roles() {
+            error!("任务存储配置无效: {}", e);
+            return Ok(self.create_failed_report(&job_id, start_time, vec![e.to_string()]));
+        }
+
         // 创建存储连接
-        let source_storage = match crate::storage::create_storage(&job.sourceConfig).await {
+        let source_storage = match crate::storage::create_job_source_storage(job).await {
             Ok(s) => s,
             Err(e) => {
                 error!("创建源存储失败: {}", e);
@@ -239,22 +847,31 @@ impl SyncEngine {
             }
         };
 
-        // 快速检测目标目录是否可访问（使用 stat 而不是 list_files，避免递归扫描）
-        match dest_storage.stat("").await {
+        // 目标根目录不存在、由本次同步创建时，目标必然为空
+        let mut dest_created = false;
+
+        // 快速检测目标目录是否可访问（列出根目录的一页，不递归扫描；stat 根路径对 OpenDAL 后端不发请求）
+        match dest_storage.probe_root().await {
             Ok(_) => {
                 debug!("目标存储可访问");
             }
             Err(e) => {
-                let err_str = e.to_string();
-                // 检测是否是目录不存在的错误 (409 Conflict 或 404 Not Found)
-                if err_str.contains("409") || err_str.contains("Conflict") || err_str.contains("404") || err_str.contains("NotFound") {
+                // 检测是否是目录不存在的错误（404，或 WebDAV 写入返回的 409）
+                if SyncError::classify(&e).is_dir_missing() {
                     if self.config.auto_create_dir {
                         debug!("目标目录不存在，尝试自动创建...");
-                        // 尝试创建根目录
-                        if let Err(create_err) = dest_storage.create_dir("/").await {
-                            debug!("创建根目录失败: {}", create_err);
-                            // 再次检查是否可用
-                            if dest_storage.stat("").await.is_err() {
+                        // 尝试创建根目录（目录被临时锁定等情况按传输的重试策略退避重试）
+                        let retry_config = RetryConfig {
+                            max_retries: self.config.max_retries,
+                            base_delay_ms: self.config.retry_base_delay_ms,
+                            skip_locked: false,
+                            budget: None,
+                        };
+                        let created =
+                            Self::create_dest_root(dest_storage.as_ref(), &retry_config, &self.cancelled).await;
+                        match created {
+                            Some(created) => dest_created = created,
+                            None => {
                                 warn!("目标目录不存在且无法创建");
                                 return Ok(self.create_failed_report(
                                     &job_id,
@@ -279,6 +896,16 @@ impl SyncEngine {
             }
         }
 
+        // 检查存储是否支持同步模式需要的操作（避免运行中途才因无写权限失败）
+        if let Some(msg) = check_capabilities(
+            &job.syncMode,
+            source_storage.capabilities(),
+            dest_storage.capabilities(),
+        ) {
+            warn!("{}", msg);
+            return Ok(self.create_failed_report(&job_id, start_time, vec![msg]));
+        }
+
         // 检查取消
         if self.is_cancelled() {
             return Ok(self.create_cancelled_report(&job_id, start_time));
@@ -302,30 +929,52 @@ impl SyncEngine {
                 speed: 0,
                 startTime: start_time,
                 endTime: 0,
+                scanEtaSecs: None,
+                filesCopiedToSource: 0,
+                filesCopiedToDest: 0,
             },
         )
         .await;
 
-        let scanner = FileScanner::with_config(SCANNER_CONCURRENCY, self.config.scan_config.clone());
+        let scan_config = self.config.for_job(job).scan_config;
+        // 扫描和传输从同一个远程操作并发上限中申请许可
+        let transfer_params = self.transfer_params(job);
+        let listed = Arc::new(ListCounter::new(scan_config.max_tree_files.map(|n| n as u64)));
+        let scanner = FileScanner::with_config(scan_config.concurrency, scan_config.clone())
+            .with_listed_counter(listed.clone())
+            .with_ops_limiter(transfer_params.remote_ops.clone());
+        let scan_reporter = ScanProgressReporter {
+            progress_tx: progress_tx.clone(),
+            job_id: job_id.clone(),
+            start_time,
+            listed,
+            listed_total: AtomicU64::new(0),
+            partial: AtomicBool::new(false),
+            expected_total: self.last_entries_listed(&job_id).await,
+        };
 
         // 初始化缓存管理器（只对远程存储使用缓存），缓存目录跟随数据存储目录
         let cache_dir = self.config.cache_dir.clone()
             .unwrap_or_else(|| std::path::PathBuf::from(".synctools/cache"));
         
         // 本地存储不使用缓存（TTL=0 表示直接扫描），远程存储使用配置的 TTL
-        let source_is_local = matches!(job.sourceConfig.typ, crate::db::StorageType::Local);
+        let source_is_local = job.source_is_local();
         let dest_is_local = matches!(job.destConfig.typ, crate::db::StorageType::Local);
         let source_ttl = if source_is_local { 0 } else { self.config.remote_cache_ttl };
         let dest_ttl = if dest_is_local { 0 } else { self.config.remote_cache_ttl };
         
-        let source_cache = FileListCache::new(cache_dir.clone()).with_ttl(source_ttl);
-        let dest_cache = FileListCache::new(cache_dir).with_ttl(dest_ttl);
-        let source_config_json = serde_json::to_string(&job.sourceConfig).unwrap_or_default();
+        let source_cache = FileListCache::new(cache_dir.clone())
+            .with_ttl(source_ttl)
+            .with_format(self.config.cache_format);
+        let dest_cache = FileListCache::new(cache_dir)
+            .with_ttl(dest_ttl)
+            .with_format(self.config.cache_format);
+        let source_config_json = job.source_config_json();
         let dest_config_json = serde_json::to_string(&job.destConfig).unwrap_or_default();
         let force_refresh = self.config.force_refresh;
 
         // 扫描源存储（支持缓存）
-        let source_tree = if !force_refresh {
+        let mut source_tree = if !force_refresh {
             if let Some(cached) = source_cache.load(&job_id, "source", &source_config_json) {
                 self.send_progress(
                     &progress_tx,
@@ -344,12 +993,19 @@ impl SyncEngine {
                         speed: 0,
                         startTime: start_time,
                         endTime: 0,
+                        scanEtaSecs: None,
+                        filesCopiedToSource: 0,
+                        filesCopiedToDest: 0,
                     },
                 )
                 .await;
+                scan_reporter.skip(cached.files.len());
                 cached.files
             } else {
-                match scanner.scan_storage(source_storage.as_ref(), None).await {
+                match scan_reporter
+                    .scan(&scanner, source_storage.as_ref(), "正在扫描源文件...".to_string(), 0)
+                    .await
+                {
                     Ok(t) => {
                         let _ = source_cache.save(&job_id, "source", &source_config_json, &t);
                         t
@@ -366,8 +1022,11 @@ impl SyncEngine {
             }
         } else {
             // 强制刷新，清除缓存并重新扫描
-            source_cache.clear(&job_id);
-            match scanner.scan_storage(source_storage.as_ref(), None).await {
+            source_cache.clear(&job_id, Some("source"));
+            match scan_reporter
+                .scan(&scanner, source_storage.as_ref(), "正在扫描源文件...".to_string(), 0)
+                .await
+            {
                 Ok(t) => {
                     let _ = source_cache.save(&job_id, "source", &source_config_json, &t);
                     t
@@ -404,12 +1063,29 @@ impl SyncEngine {
                 speed: 0,
                 startTime: start_time,
                 endTime: 0,
+                scanEtaSecs: None,
+                filesCopiedToSource: 0,
+                filesCopiedToDest: 0,
             },
         )
         .await;
 
+        // 目标为空时（首次同步到新目标）不扫描目标，所有源文件直接复制
+        let assume_empty_dest = if job.syncMode == SyncMode::Bidirectional {
+            if self.config.assume_empty_dest {
+                warn!("双向同步需要比较两边文件，忽略「视目标为空」选项");
+            }
+            false
+        } else {
+            Self::dest_is_empty(dest_storage.as_ref(), dest_created, self.config.assume_empty_dest).await
+        };
+
         // 扫描目标存储（支持缓存）
-        let dest_tree = if !force_refresh {
+        let mut dest_tree = if assume_empty_dest {
+            info!("目标为空，跳过目标扫描，全部 {} 个源文件将直接复制", source_tree.len());
+            scan_reporter.skip(0);
+            HashMap::new()
+        } else if !force_refresh {
             if let Some(cached) = dest_cache.load(&job_id, "dest", &dest_config_json) {
                 self.send_progress(
                     &progress_tx,
@@ -428,9 +1104,13 @@ impl SyncEngine {
                         speed: 0,
                         startTime: start_time,
                         endTime: 0,
+                        scanEtaSecs: None,
+                        filesCopiedToSource: 0,
+                        filesCopiedToDest: 0,
                     },
                 )
                 .await;
+                scan_reporter.skip(cached.files.len());
                 cached.files
             } else {
                 self.send_progress(
@@ -450,11 +1130,22 @@ impl SyncEngine {
                         speed: 0,
                         startTime: start_time,
                         endTime: 0,
+                        scanEtaSecs: None,
+                        filesCopiedToSource: 0,
+                        filesCopiedToDest: 0,
                     },
                 )
                 .await;
 
-                match scanner.scan_storage(dest_storage.as_ref(), None).await {
+                match scan_reporter
+                    .scan(
+                        &scanner,
+                        dest_storage.as_ref(),
+                        format!("正在扫描目标文件 (源 {} 个)...", source_tree.len()),
+                        source_tree.len() as u64,
+                    )
+                    .await
+                {
                     Ok(t) => {
                         let _ = dest_cache.save(&job_id, "dest", &dest_config_json, &t);
                         t
@@ -470,7 +1161,15 @@ impl SyncEngine {
                 }
             }
         } else {
-            match scanner.scan_storage(dest_storage.as_ref(), None).await {
+            match scan_reporter
+                .scan(
+                    &scanner,
+                    dest_storage.as_ref(),
+                    format!("正在扫描目标文件 (源 {} 个)...", source_tree.len()),
+                    source_tree.len() as u64,
+                )
+                .await
+            {
                 Ok(t) => {
                     let _ = dest_cache.save(&job_id, "dest", &dest_config_json, &t);
                     t
@@ -486,6 +1185,48 @@ impl SyncEngine {
             }
         };
 
+        // 目标为 S3/GCS 时可用本地 MD5 与 ETag/md5Hash 比较，相同的文件无需上传
+        let etag_md5 = scan_config.etag_checksums
+            && source_is_local
+            && matches!(
+                job.destConfig.typ,
+                crate::db::StorageType::S3 | crate::db::StorageType::Gcs
+            );
+
+        // 为本地存储补充内容校验和（未变化的文件复用缓存）
+        if scan_config.local_checksums || etag_md5 {
+            if source_is_local {
+                let algorithm = if etag_md5 {
+                    ChecksumAlgorithm::Md5
+                } else if matches!(job.destConfig.typ, crate::db::StorageType::Dropbox) {
+                    // 按 Dropbox content_hash 规则计算，可直接与目标校验和比较
+                    ChecksumAlgorithm::DropboxContentHash
+                } else {
+                    ChecksumAlgorithm::Blake3
+                };
+                self.fill_local_checksums(
+                    &job_id,
+                    "source",
+                    source_storage.as_ref(),
+                    &mut source_tree,
+                    algorithm,
+                    &scan_config,
+                )
+                .await;
+            }
+            if dest_is_local && scan_config.local_checksums {
+                self.fill_local_checksums(
+                    &job_id,
+                    "dest",
+                    dest_storage.as_ref(),
+                    &mut dest_tree,
+                    ChecksumAlgorithm::Blake3,
+                    &scan_config,
+                )
+                .await;
+            }
+        }
+
         let files_scanned = (source_tree.len() + dest_tree.len()) as u32;
         debug!(
             "扫描完成: 源 {} 文件, 目标 {} 文件",
@@ -497,6 +1238,23 @@ impl SyncEngine {
             return Ok(self.create_cancelled_report(&job_id, start_time));
         }
 
+        // 镜像模式下源为空而目标非空，很可能是源目录未挂载，中止以免清空目标
+        if job.syncMode == SyncMode::Mirror
+            && source_tree.is_empty()
+            && !dest_tree.is_empty()
+            && !self.config.allow_empty_source
+        {
+            warn!("源存储为空，目标有 {} 个文件，已中止镜像同步", dest_tree.len());
+            return Ok(self.create_failed_report(
+                &job_id,
+                start_time,
+                vec![format!(
+                    "源存储为空，继续镜像同步将删除目标中的 {} 个文件。请确认源目录已挂载，或在同步时允许空源",
+                    dest_tree.len()
+                )],
+            ));
+        }
+
         // 比较文件
         self.send_progress(
             &progress_tx,
@@ -515,16 +1273,35 @@ impl SyncEngine {
                 speed: 0,
                 startTime: start_time,
                 endTime: 0,
+                scanEtaSecs: None,
+                filesCopiedToSource: 0,
+                filesCopiedToDest: 0,
             },
         )
         .await;
 
-        let comparator = FileComparator::default();
+        if job.effective_case_fold() != job.caseFold {
+            warn!("双向同步需要两边路径一一对应，忽略大小写转换设置");
+        }
+        let comparator = FileComparator::with_config(CompareConfig {
+            use_checksum: scan_config.local_checksums,
+            etag_md5,
+            compare_etags: job.compares_etags(),
+            case_fold: job.effective_case_fold(),
+            overwrite_policy: self.config.overwrite_policy,
+            zero_byte: self.config.zero_byte_compare,
+            ..Default::default()
+        });
         let mut actions = comparator.compare_trees(&source_tree, &dest_tree, &job.syncMode);
 
         // 加载已保存的文件状态，用于增量同步
         let state_manager = FileStateManager::new(self.db.clone());
-        let saved_states = state_manager.get_job_states(&job_id).await.unwrap_or_default();
+        // 目标为空时已保存的同步状态均已失效
+        let saved_states = if assume_empty_dest {
+            HashMap::new()
+        } else {
+            state_manager.get_job_states(&job_id).await.unwrap_or_default()
+        };
         
         // 用 hash 过滤不需要同步的文件
         let mut skipped_by_hash = 0usize;
@@ -547,8 +1324,39 @@ impl SyncEngine {
         // 计算需要检查的文件的 hash
         if !files_to_hash.is_empty() {
             debug!("检查 {} 个文件的 hash 是否变化...", files_to_hash.len());
-            
-            for (path, _) in &files_to_hash {
+
+            let hash_total = files_to_hash.len();
+            let progress_interval = Duration::from_millis(PROGRESS_UPDATE_INTERVAL_MS);
+            let mut last_progress = Instant::now();
+            for (index, (path, _)) in files_to_hash.iter().enumerate() {
+                // 定期报告检查进度，避免比较阶段长时间没有进度
+                if last_progress.elapsed() >= progress_interval {
+                    last_progress = Instant::now();
+                    self.send_progress(
+                        &progress_tx,
+                        SyncProgress {
+                            jobId: job_id.clone(),
+                            status: SyncStatus::Comparing,
+                            phase: format!("正在检查文件哈希 {}/{}", index, hash_total),
+                            currentFile: path.clone(),
+                            filesScanned: files_scanned,
+                            filesToSync: 0,
+                            filesCompleted: 0,
+                            filesSkipped: skipped_by_hash as u32,
+                            filesFailed: 0,
+                            bytesTransferred: 0,
+                            bytesTotal: 0,
+                            speed: 0,
+                            startTime: start_time,
+                            endTime: 0,
+                            scanEtaSecs: None,
+                            filesCopiedToSource: 0,
+                            filesCopiedToDest: 0,
+                        },
+                    )
+                    .await;
+                }
+
                 if let Some(saved) = saved_states.get(path) {
                     if let Some(saved_hash) = &saved.checksum {
                         // 读取文件计算 hash
@@ -578,7 +1386,63 @@ impl SyncEngine {
                 }
             }
         }
-        
+
+        // 内容相同的冲突自动跳过
+        if self.config.auto_resolve_identical_conflicts {
+            let resolved = Self::resolve_identical_conflicts(
+                &mut actions,
+                source_storage.as_ref(),
+                dest_storage.as_ref(),
+            )
+            .await;
+            if resolved > 0 {
+                info!("{} 个冲突文件两边内容相同，已自动跳过", resolved);
+            }
+        }
+
+        // 按路径指定的解决方式处理冲突，未指定的使用任务的默认解决方式
+        let (resolved_actions, resolved) = ConflictResolution::apply_all(
+            actions,
+            &self.config.conflict_resolutions,
+            job.defaultConflictResolution,
+            chrono::Utc::now().timestamp(),
+        );
+        actions = resolved_actions;
+        if resolved > 0 {
+            info!("已按解决方式处理 {} 个冲突", resolved);
+        }
+
+        // 受保护的路径不删除
+        if !self.config.protect_patterns.is_empty() {
+            let protected =
+                Self::apply_protect_patterns(&mut actions, &dest_tree, &self.config.protect_patterns);
+            if protected > 0 {
+                info!("{} 个删除操作命中保护规则，已跳过", protected);
+            }
+        }
+
+        // 需要确认删除时，仅执行已确认的删除，其余转为跳过
+        if let Some(confirmed) = &self.config.confirmed_deletes {
+            let unconfirmed = Self::apply_confirmed_deletes(&mut actions, confirmed);
+            if unconfirmed > 0 {
+                info!("{} 个删除操作未确认，已跳过", unconfirmed);
+            }
+        }
+
+        // 移动过的文件在目标内重命名（在保护规则和删除确认之后，只使用最终会执行的删除）
+        if self.config.detect_renames {
+            let renamed = Self::detect_renames(
+                &mut actions,
+                &dest_tree,
+                source_storage.as_ref(),
+                dest_storage.as_ref(),
+            )
+            .await;
+            if renamed > 0 {
+                info!("{} 个文件识别为移动，改为在目标内重命名", renamed);
+            }
+        }
+
         let summary = FileComparator::summarize_actions(&actions);
 
         debug!(
@@ -591,9 +1455,14 @@ impl SyncEngine {
             summary.conflict_count
         );
 
-        let files_to_sync =
-            (summary.copy_count + summary.reverse_copy_count + summary.delete_count) as u32;
-        let bytes_total = summary.total_transfer_bytes();
+        let files_to_sync = summary.files_to_sync() as u32;
+        let bytes_total = summary.progress_bytes_total();
+        if summary.sizes_unknown() {
+            debug!(
+                "{} 个待复制文件大小未知，进度按文件数计算",
+                summary.zero_size_copy_count
+            );
+        }
 
         if self.is_cancelled() {
             return Ok(self.create_cancelled_report(&job_id, start_time));
@@ -617,6 +1486,9 @@ impl SyncEngine {
                 speed: 0,
                 startTime: start_time,
                 endTime: 0,
+                scanEtaSecs: None,
+                filesCopiedToSource: 0,
+                filesCopiedToDest: 0,
             },
         )
         .await;
@@ -629,13 +1501,40 @@ impl SyncEngine {
                 dest_storage.clone(),
                 actions,
                 &summary,
+                transfer_params,
                 progress_tx.clone(),
                 start_time,
                 files_scanned,
             )
             .await;
 
-        let (files_copied, files_deleted, files_failed, bytes_transferred, errors) = result;
+        let ParallelOutcome {
+            files_copied,
+            files_copied_to_source,
+            files_copied_to_dest,
+            files_deleted,
+            files_failed,
+            bytes_transferred,
+            mut errors,
+            failed_files,
+            skipped_locked,
+            completed_actions,
+        } = result;
+        if let Some(msg) = self.timeout_message() {
+            errors.insert(0, msg);
+        }
+
+        if self.config.prune_empty_dirs
+            && job.syncMode == SyncMode::Mirror
+            && job.destConfig.typ.has_real_dirs()
+            && !self.is_cancelled()
+        {
+            self.prune_empty_dirs(dest_storage.as_ref(), &completed_actions).await;
+        }
+        if let Err(e) = crate::storage::onedrive::persist_rotated_tokens(&self.db).await {
+            error!("{}", e);
+            errors.push(e.to_string());
+        }
 
         let end_time = chrono::Utc::now().timestamp();
         let status = if files_failed > 0 {
@@ -647,13 +1546,16 @@ impl SyncEngine {
         };
 
         // 记录到数据库
-        self.log_sync_result(
+        let log_id = self.log_sync_result(
             &job_id,
             start_time,
             end_time,
             &status,
             files_scanned,
+            scan_reporter.entries_listed(),
             files_copied,
+            files_copied_to_source,
+            files_copied_to_dest,
             files_deleted,
             bytes_transferred,
             if errors.is_empty() {
@@ -661,6 +1563,7 @@ impl SyncEngine {
             } else {
                 Some(errors.join("; "))
             },
+            &failed_files,
         )
         .await;
 
@@ -682,6 +1585,9 @@ impl SyncEngine {
                 speed: 0,
                 startTime: start_time,
                 endTime: chrono::Utc::now().timestamp(),  // 记录完成时间
+                scanEtaSecs: None,
+                filesCopiedToSource: files_copied_to_source,
+                filesCopiedToDest: files_copied_to_dest,
             },
         )
         .await;
@@ -691,11 +1597,18 @@ impl SyncEngine {
             job_id, files_copied, files_deleted, files_failed
         );
 
-        // 同步完成后清除所有缓存（文件列表已变化）
+        // 文件列表已变化：源缓存清除，目标缓存按成功的动作增量更新（部分失败后重试无需全量扫描）
         if files_copied > 0 || files_deleted > 0 {
-            source_cache.clear(&job_id);
-            dest_cache.clear(&job_id);
-            debug!("已清除源和目标扫描缓存");
+            source_cache.remove(&job_id, "source");
+            apply_completed_actions(&mut dest_tree, &source_tree, &completed_actions);
+            match dest_cache.update(&job_id, "dest", &dest_config_json, &dest_tree) {
+                Ok(true) => debug!("已清除源扫描缓存，增量更新目标缓存"),
+                Ok(false) => debug!("已清除源和目标扫描缓存"),
+                Err(e) => {
+                    warn!("更新目标缓存失败: {}", e);
+                    dest_cache.remove(&job_id, "dest");
+                }
+            }
         }
 
         Ok(SyncReport {
@@ -705,76 +1618,382 @@ impl SyncEngine {
             status,
             filesScanned: files_scanned,
             filesCopied: files_copied,
+            filesCopiedToSource: files_copied_to_source,
+            filesCopiedToDest: files_copied_to_dest,
             filesDeleted: files_deleted,
             filesSkipped: summary.skip_count as u32,
             filesFailed: files_failed,
             bytesTransferred: bytes_transferred,
             duration: (end_time - start_time) as u64,
             errors,
+            skippedLocked: skipped_locked,
+            logId: log_id,
         })
     }
 
-    /// 并行执行同步操作
-    #[allow(clippy::too_many_arguments)]
-    async fn execute_sync_parallel(
-        &self,
-        job_id: &str,
-        source_storage: Arc<dyn Storage>,
-        dest_storage: Arc<dyn Storage>,
-        actions: Vec<SyncAction>,
-        summary: &ActionSummary,
-        progress_tx: Option<mpsc::Sender<SyncProgress>>,
-        start_time: i64,
-        files_scanned: u32,
-    ) -> (u32, u32, u32, u64, Vec<String>) {
-        let semaphore = Arc::new(Semaphore::new(self.config.max_concurrent_transfers));
-        let stats = Arc::new(TransferStats::default());
-        let errors = Arc::new(RwLock::new(Vec::<String>::new()));
-        let synced_states = Arc::new(RwLock::new(Vec::<FileState>::new()));
-        let cancelled = self.cancelled.clone();
+    /// 通过同步引擎传输单个文件（不扫描、不更新文件状态，用于复现传输问题）
+    pub async fn sync_single_file(&self, job: &SyncJob, path: &str) -> Result<SingleFileResult> {
+        let path = path.trim_start_matches('/').to_string();
+        self.cancelled.store(false, Ordering::SeqCst);
 
-        let files_to_sync =
-            (summary.copy_count + summary.reverse_copy_count + summary.delete_count) as u32;
-        let bytes_total = summary.total_transfer_bytes();
+        let source_storage = crate::storage::create_job_source_storage(job)
+            .await
+            .map_err(|e| anyhow::anyhow!("创建源存储失败: {}", e))?;
+        let dest_storage = crate::storage::create_storage(&job.destConfig)
+            .await
+            .map_err(|e| anyhow::anyhow!("创建目标存储失败: {}", e))?;
 
-        // 过滤出需要执行的动作
-        let executable_actions: Vec<_> = actions
-            .into_iter()
-            .filter(|a| !matches!(a, SyncAction::Skip { .. }))
-            .collect();
+        let meta = source_storage
+            .stat(&path)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("源文件不存在: {}", path))?;
+        if meta.is_dir {
+            return Err(anyhow::anyhow!("路径是目录，不是文件: {}", path));
+        }
 
-        let mut handles = Vec::new();
-        let _transfer_start = Instant::now();
+        let action = SyncAction::Copy {
+            source_path: path.clone(),
+            dest_path: job.effective_case_fold().apply(&path),
+            size: meta.size,
+            reverse: false,
+        };
+        let stats = Arc::new(TransferStats::default());
+        let start = Instant::now();
 
-        // 启动进度更新任务
-        let progress_tx_clone = progress_tx.clone();
-        let stats_clone = stats.clone();
-        let job_id_clone = job_id.to_string();
-        let cancelled_clone = cancelled.clone();
+        let result = Self::execute_action_with_retry(
+            &action,
+            source_storage.as_ref(),
+            dest_storage.as_ref(),
+            RetryConfig {
+                max_retries: self.config.max_retries,
+                base_delay_ms: self.config.retry_base_delay_ms,
+                skip_locked: false,
+                budget: None,
+            },
+            &self.cancelled,
+            &job.id,
+            Some(&stats),
+            self.transfer_params(job),
+        )
+        .await;
 
-        let progress_handle = tokio::spawn(async move {
-            let mut last_bytes = 0u64;
-            let mut last_time = Instant::now();
-            // 使用指数移动平均平滑速度（权重 0.3 给新值，0.7 给旧值）
-            let mut smoothed_speed: f64 = 0.0;
-            const SPEED_SMOOTHING_FACTOR: f64 = 0.3;
+        let bytes_transferred = stats.bytes_transferred.load(Ordering::Relaxed);
+        let duration_ms = start.elapsed().as_millis() as u64;
 
-            loop {
-                tokio::time::sleep(Duration::from_millis(PROGRESS_UPDATE_INTERVAL_MS)).await;
+        Ok(match result {
+            Ok(r) => SingleFileResult {
+                path,
+                success: true,
+                size: meta.size,
+                bytesTransferred: bytes_transferred,
+                hash: r.file_state.and_then(|s| s.checksum),
+                retries: r.retries,
+                durationMs: duration_ms,
+                error: None,
+            },
+            Err(e) => SingleFileResult {
+                path,
+                success: false,
+                size: meta.size,
+                bytesTransferred: bytes_transferred,
+                hash: None,
+                retries: e.retries,
+                durationMs: duration_ms,
+                error: Some(e.message),
+            },
+        })
+    }
 
-                if cancelled_clone.load(Ordering::SeqCst) {
+    /// 只同步指定路径（用于重试上次失败的文件，不扫描整个存储）
+    pub async fn sync_paths(
+        &self,
+        job: &SyncJob,
+        paths: &[String],
+        progress_tx: Option<mpsc::Sender<SyncProgress>>,
+    ) -> Result<SyncReport> {
+        let start_time = chrono::Utc::now().timestamp();
+        let job_id = job.id.clone();
+        self.cancelled.store(false, Ordering::SeqCst);
+
+        info!("重试同步任务 {} 的 {} 个文件", job.name, paths.len());
+
+        if let Err(e) = job.validate_storage_roles() {
+            return Ok(self.create_failed_report(&job_id, start_time, vec![e.to_string()]));
+        }
+
+        let source_storage = match crate::storage::create_job_source_storage(job).await {
+            Ok(s) => s,
+            Err(e) => {
+                return Ok(self.create_failed_report(
+                    &job_id,
+                    start_time,
+                    vec![format!("源存储连接失败: {}", e)],
+                ))
+            }
+        };
+        let dest_storage = match crate::storage::create_storage(&job.destConfig).await {
+            Ok(s) => s,
+            Err(e) => {
+                return Ok(self.create_failed_report(
+                    &job_id,
+                    start_time,
+                    vec![format!("目标存储连接失败: {}", e)],
+                ))
+            }
+        };
+
+        // 根据两端当前状态重新决定每个路径的动作
+        let actions = Self::plan_retry_actions(
+            source_storage.as_ref(),
+            dest_storage.as_ref(),
+            paths,
+            &job.syncMode,
+            job.effective_case_fold(),
+        )
+        .await;
+
+        let summary = FileComparator::summarize_actions(&actions);
+        let files_scanned = paths.len() as u32;
+
+        let mut outcome = self
+            .execute_sync_parallel(
+                &job_id,
+                source_storage,
+                dest_storage,
+                actions,
+                &summary,
+                self.transfer_params(job),
+                progress_tx.clone(),
+                start_time,
+                files_scanned,
+            )
+            .await;
+        if let Err(e) = crate::storage::onedrive::persist_rotated_tokens(&self.db).await {
+            error!("{}", e);
+            outcome.errors.push(e.to_string());
+        }
+
+        let end_time = chrono::Utc::now().timestamp();
+        let status = if outcome.files_failed > 0 {
+            SyncStatus::Failed
+        } else if self.is_cancelled() {
+            SyncStatus::Cancelled
+        } else {
+            SyncStatus::Completed
+        };
+
+        let log_id = self.log_sync_result(
+            &job_id,
+            start_time,
+            end_time,
+            &status,
+            files_scanned,
+            None,
+            outcome.files_copied,
+            outcome.files_copied_to_source,
+            outcome.files_copied_to_dest,
+            outcome.files_deleted,
+            outcome.bytes_transferred,
+            if outcome.errors.is_empty() {
+                None
+            } else {
+                Some(outcome.errors.join("; "))
+            },
+            &outcome.failed_files,
+        )
+        .await;
+
+        self.send_progress(
+            &progress_tx,
+            SyncProgress {
+                jobId: job_id.clone(),
+                status: status.clone(),
+                phase: "重试完成".to_string(),
+                currentFile: String::new(),
+                filesScanned: files_scanned,
+                filesToSync: summary.files_to_sync() as u32,
+                filesCompleted: outcome.files_copied + outcome.files_deleted,
+                filesSkipped: summary.skip_count as u32,
+                filesFailed: outcome.files_failed,
+                bytesTransferred: outcome.bytes_transferred,
+                bytesTotal: summary.progress_bytes_total(),
+                speed: 0,
+                startTime: start_time,
+                endTime: end_time,
+                scanEtaSecs: None,
+                filesCopiedToSource: outcome.files_copied_to_source,
+                filesCopiedToDest: outcome.files_copied_to_dest,
+            },
+        )
+        .await;
+
+        Ok(SyncReport {
+            jobId: job_id,
+            startTime: start_time,
+            endTime: end_time,
+            status,
+            filesScanned: files_scanned,
+            filesCopied: outcome.files_copied,
+            filesCopiedToSource: outcome.files_copied_to_source,
+            filesCopiedToDest: outcome.files_copied_to_dest,
+            filesDeleted: outcome.files_deleted,
+            filesSkipped: summary.skip_count as u32,
+            filesFailed: outcome.files_failed,
+            bytesTransferred: outcome.bytes_transferred,
+            duration: (end_time - start_time) as u64,
+            errors: outcome.errors,
+            skippedLocked: outcome.skipped_locked,
+            logId: log_id,
+        })
+    }
+
+    /// 按两端当前状态决定重试路径的动作
+    ///
+    /// 路径按源路径传入，目标路径按 `case_fold` 转换；转换后重复时与完整同步一致，只保留排序最前的路径。
+    /// 转换大小写时无法从重试路径区分源路径和目标路径，源端不存在的路径不删除，留给下次完整同步处理
+    async fn plan_retry_actions(
+        source: &dyn Storage,
+        dest: &dyn Storage,
+        paths: &[String],
+        mode: &SyncMode,
+        case_fold: CaseFold,
+    ) -> Vec<SyncAction> {
+        let mut sorted: Vec<&String> = paths.iter().collect();
+        sorted.sort();
+        let mut folded_paths = HashSet::new();
+        let kept: HashSet<&str> = sorted
+            .into_iter()
+            .filter(|p| folded_paths.insert(case_fold.apply(p)))
+            .map(|p| p.as_str())
+            .collect();
+
+        let mut actions = Vec::with_capacity(paths.len());
+        for path in paths {
+            if !kept.contains(path.as_str()) {
+                warn!("大小写转换后路径重复，跳过: {}", path);
+                actions.push(SyncAction::Skip { path: path.clone() });
+                continue;
+            }
+            let dest_path = case_fold.apply(path);
+            let src = source.stat(path).await.ok().flatten();
+            let dst = dest.stat(&dest_path).await.ok().flatten();
+            let action = match (src, dst) {
+                (Some(s), _) if !s.is_dir => SyncAction::Copy {
+                    source_path: path.clone(),
+                    dest_path,
+                    size: s.size,
+                    reverse: false,
+                },
+                (None, Some(d))
+                    if !d.is_dir && *mode == SyncMode::Mirror && case_fold == CaseFold::Preserve =>
+                {
+                    SyncAction::Delete {
+                        path: dest_path,
+                        from_dest: true,
+                    }
+                }
+                (None, Some(d)) if !d.is_dir && *mode == SyncMode::Bidirectional => {
+                    SyncAction::Copy {
+                        source_path: path.clone(),
+                        dest_path,
+                        size: d.size,
+                        reverse: true,
+                    }
+                }
+                _ => SyncAction::Skip { path: path.clone() },
+            };
+            actions.push(action);
+        }
+        actions
+    }
+
+    /// 并行执行同步操作
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_sync_parallel(
+        &self,
+        job_id: &str,
+        source_storage: Arc<dyn Storage>,
+        dest_storage: Arc<dyn Storage>,
+        actions: Vec<SyncAction>,
+        summary: &ActionSummary,
+        transfer_params: TransferParams,
+        progress_tx: Option<mpsc::Sender<SyncProgress>>,
+        start_time: i64,
+        files_scanned: u32,
+    ) -> ParallelOutcome {
+        // 分别限制读写并发时，传输数放宽到读写上限之和，由读写许可控制实际并发
+        let max_tasks = transfer_params
+            .max_in_flight
+            .unwrap_or(self.config.max_concurrent_transfers);
+        let semaphore = Arc::new(Semaphore::new(max_tasks));
+        let stats = Arc::new(TransferStats::default());
+        *self.live_stats.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some((files_scanned, stats.clone()));
+        let errors = Arc::new(RwLock::new(Vec::<String>::new()));
+        let failed_files = Arc::new(RwLock::new(Vec::<(String, String)>::new()));
+        let skipped_locked = Arc::new(RwLock::new(Vec::<String>::new()));
+        let synced_states = Arc::new(RwLock::new(Vec::<FileState>::new()));
+        let completed_actions = Arc::new(RwLock::new(Vec::<SyncAction>::new()));
+        let cancelled = self.cancelled.clone();
+        let retry_budget = self.config.retry_budget.map(|n| Arc::new(AtomicU32::new(n)));
+
+        let files_to_sync = summary.files_to_sync() as u32;
+        let bytes_total = summary.progress_bytes_total();
+
+        // 过滤出需要执行的动作，类型替换动作单独作为第一阶段执行
+        // （子路径的复制依赖替换后的目录结构，不能与其并发）
+        let (replace_actions, other_actions): (Vec<_>, Vec<_>) = actions
+            .into_iter()
+            .filter(|a| !matches!(a, SyncAction::Skip { .. }))
+            .partition(|a| matches!(a, SyncAction::Replace { .. }));
+        // 重命名在复制和删除之前执行（待删除的旧目录中可能有需要移走的文件）
+        let (rename_actions, other_actions): (Vec<_>, Vec<_>) = other_actions
+            .into_iter()
+            .partition(|a| matches!(a, SyncAction::Rename { .. }));
+        let phases = if self.config.delete_after_copy {
+            // 删除单独作为最后阶段，等所有复制完成后再执行
+            let (delete_actions, copy_actions): (Vec<_>, Vec<_>) = other_actions
+                .into_iter()
+                .partition(|a| matches!(a, SyncAction::Delete { .. }));
+            vec![replace_actions, rename_actions, copy_actions, delete_actions]
+        } else {
+            vec![replace_actions, rename_actions, other_actions]
+        };
+
+        let _transfer_start = Instant::now();
+
+        // 启动进度更新任务
+        let progress_tx_clone = progress_tx.clone();
+        let stats_clone = stats.clone();
+        let job_id_clone = job_id.to_string();
+        let cancelled_clone = cancelled.clone();
+        // 指数移动平均的新值权重，1 即不平滑
+        let smoothing = self.config.speed_smoothing.clamp(0.01, 1.0);
+        let instant_only = self.config.instant_speed;
+
+        let progress_handle = tokio::spawn(async move {
+            let mut last_bytes = 0u64;
+            let mut last_time = Instant::now();
+            let mut smoothed_speed: f64 = 0.0;
+
+            loop {
+                tokio::time::sleep(Duration::from_millis(PROGRESS_UPDATE_INTERVAL_MS)).await;
+
+                if cancelled_clone.load(Ordering::SeqCst) {
                     break;
                 }
 
                 let completed = stats_clone.files_completed.load(Ordering::Relaxed);
                 let failed = stats_clone.files_failed.load(Ordering::Relaxed);
+                // 已处理数（含因被占用而跳过的文件）
+                let processed = completed + failed + stats_clone.files_locked.load(Ordering::Relaxed);
                 let bytes = stats_clone.bytes_transferred.load(Ordering::Relaxed);
 
                 // 计算瞬时速度
                 let now = Instant::now();
                 let elapsed = now.duration_since(last_time).as_secs_f64();
                 let instant_speed = if elapsed > 0.0 {
-                    (bytes - last_bytes) as f64 / elapsed
+                    bytes.saturating_sub(last_bytes) as f64 / elapsed
                 } else {
                     0.0
                 };
@@ -785,17 +2004,23 @@ impl SyncEngine {
                 if smoothed_speed == 0.0 {
                     smoothed_speed = instant_speed;
                 } else if instant_speed > 0.0 {
-                    smoothed_speed = SPEED_SMOOTHING_FACTOR * instant_speed + (1.0 - SPEED_SMOOTHING_FACTOR) * smoothed_speed;
+                    smoothed_speed = smoothing * instant_speed + (1.0 - smoothing) * smoothed_speed;
                 }
 
-                let speed = smoothed_speed as u64;
+                let speed = if instant_only { instant_speed } else { smoothed_speed } as u64;
 
                 if let Some(tx) = &progress_tx_clone {
+                    // 总字节数未知时按文件数计算百分比
+                    let percent = if bytes_total > 0 {
+                        (bytes as f64 / bytes_total as f64 * 100.0).min(100.0)
+                    } else {
+                        processed as f64 / files_to_sync.max(1) as f64 * 100.0
+                    };
                     debug!(
                         "进度更新: {}/{} MB ({:.1}%), 速度: {:.2} MB/s",
                         bytes / 1024 / 1024,
                         bytes_total / 1024 / 1024,
-                        (bytes as f64 / bytes_total.max(1) as f64) * 100.0,
+                        percent,
                         speed as f64 / 1024.0 / 1024.0
                     );
                     
@@ -803,11 +2028,11 @@ impl SyncEngine {
                         .send(SyncProgress {
                             jobId: job_id_clone.clone(),
                             status: SyncStatus::Syncing,
-                            phase: format!("同步中 {}/{}", completed + failed, files_to_sync),
+                            phase: format!("同步中 {}/{}", processed, files_to_sync),
                             currentFile: String::new(),
                             filesScanned: files_scanned,
                             filesToSync: files_to_sync,
-                            filesCompleted: (completed + failed) as u32,
+                            filesCompleted: processed as u32,
                             filesSkipped: 0,
                             filesFailed: failed as u32,
                             bytesTransferred: bytes,
@@ -815,6 +2040,15 @@ impl SyncEngine {
                             speed,
                             startTime: start_time,
                             endTime: 0,
+                            scanEtaSecs: None,
+                            filesCopiedToSource: stats_clone
+                                .files_copied_to_source
+                                .load(Ordering::Relaxed)
+                                as u32,
+                            filesCopiedToDest: stats_clone
+                                .files_copied_to_dest
+                                .load(Ordering::Relaxed)
+                                as u32,
                         })
                         .await;
                 } else {
@@ -822,89 +2056,249 @@ impl SyncEngine {
                 }
 
                 // 检查是否完成
-                if completed + failed >= files_to_sync as u64 {
+                if processed >= files_to_sync as u64 {
                     break;
                 }
             }
         });
 
-        // 执行每个动作
-        for action in executable_actions {
+        // 定期保存已同步的文件状态，程序中途退出时下次同步仍可跳过这些文件
+        let flush_notify = Arc::new(Notify::new());
+        let flush_done = Arc::new(AtomicBool::new(false));
+        let flush_handle = {
+            let synced_states = synced_states.clone();
+            let notify = flush_notify.clone();
+            let done = flush_done.clone();
+            let state_manager = FileStateManager::new(self.db.clone());
+            let interval = Duration::from_secs(STATE_FLUSH_INTERVAL_SECS);
+            tokio::spawn(async move {
+                loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep(interval) => {}
+                        _ = notify.notified() => {}
+                    }
+                    let pending = std::mem::take(&mut *synced_states.write().await);
+                    if !pending.is_empty() {
+                        if let Err(e) = state_manager.batch_upsert(&pending).await {
+                            warn!("保存文件状态失败: {}", e);
+                            // 放回缓冲区，下次或结束时再保存
+                            synced_states.write().await.extend(pending);
+                        }
+                    }
+                    if done.load(Ordering::SeqCst) {
+                        break;
+                    }
+                }
+            })
+        };
+
+        // 重命名的新路径 -> 原路径：重命名失败时不再覆盖原路径（保留两者的冲突需要先移走目标中的版本）
+        let rename_sources: HashMap<String, String> = phases
+            .iter()
+            .flatten()
+            .filter_map(|a| match a {
+                SyncAction::Rename { from, to, .. } => Some((to.clone(), from.clone())),
+                _ => None,
+            })
+            .collect();
+
+        // 按阶段执行动作
+        let phase_count = phases.len();
+        for (phase_index, mut phase_actions) in phases.into_iter().enumerate() {
             if cancelled.load(Ordering::SeqCst) {
                 break;
             }
 
-            let permit = match semaphore.clone().acquire_owned().await {
-                Ok(p) => p,
-                Err(_) => {
-                    tracing::error!("Semaphore closed unexpectedly");
-                    break;
+            if !rename_sources.is_empty() {
+                let kept: HashSet<String> = failed_files
+                    .read()
+                    .await
+                    .iter()
+                    .filter_map(|(path, _)| rename_sources.get(path).cloned())
+                    .collect();
+                if !kept.is_empty() {
+                    let before = phase_actions.len();
+                    phase_actions.retain(|a| match a {
+                        SyncAction::Copy {
+                            dest_path,
+                            reverse: false,
+                            ..
+                        } => !kept.contains(dest_path),
+                        _ => true,
+                    });
+                    let skipped = before - phase_actions.len();
+                    if skipped > 0 {
+                        warn!("{} 个文件未能从原路径移走，跳过覆盖", skipped);
+                        errors
+                            .write()
+                            .await
+                            .push(format!("{} 个文件未能从原路径移走，已跳过覆盖", skipped));
+                    }
                 }
-            };
-            let source = source_storage.clone();
-            let dest = dest_storage.clone();
-            let stats = stats.clone();
-            let errors = errors.clone();
-            let synced_states = synced_states.clone();
-            let cancelled = cancelled.clone();
-            let retry_config = RetryConfig {
-                max_retries: self.config.max_retries,
-                base_delay_ms: self.config.retry_base_delay_ms,
-            };
-            let transfer_params = TransferParams {
-                chunk_size: self.config.chunk_size,
-                stream_threshold: self.config.large_file_threshold,
-            };
-            let job_id = job_id.to_string();
-
-            let stats_clone = stats.clone();
-            let handle = tokio::spawn(async move {
-                let result = Self::execute_action_with_retry(
-                    &action,
-                    source.as_ref(),
-                    dest.as_ref(),
-                    retry_config,
-                    &cancelled,
-                    &job_id,
-                    Some(&stats_clone),
-                    transfer_params,
+            }
+
+            // 复制有失败（含被占用跳过）时不执行延后的删除，避免删除尚未成功复制的文件
+            let failed_so_far = stats.files_not_done();
+            if self.config.delete_after_copy
+                && phase_index == phase_count - 1
+                && failed_so_far > 0
+                && !phase_actions.is_empty()
+            {
+                warn!(
+                    "{} 个文件未能复制，跳过 {} 个延后的删除操作",
+                    failed_so_far,
+                    phase_actions.len()
+                );
+                break;
+            }
+
+            // 支持批量删除的存储先合并删除，失败的再作为普通动作逐个执行（带重试）
+            let phase_actions = self
+                .delete_in_batches(
+                    phase_actions,
+                    source_storage.as_ref(),
+                    dest_storage.as_ref(),
+                    &stats,
+                    &completed_actions,
+                    &transfer_params,
                 )
                 .await;
 
-                match result {
-                    Ok(retry_result) => {
-                        stats.files_completed.fetch_add(1, Ordering::Relaxed);
-                        // 注意：字节数已在传输过程中实时更新，这里不再累加
+            let mut handles = Vec::new();
+
+            for action in phase_actions {
+                if cancelled.load(Ordering::SeqCst) {
+                    break;
+                }
+
+                let permit = match semaphore.clone().acquire_owned().await {
+                    Ok(p) => p,
+                    Err(_) => {
+                        tracing::error!("Semaphore closed unexpectedly");
+                        break;
+                    }
+                };
+                // 远程操作并发上限与扫描共享，在传输并发数之外再申请一个许可
+                let op_permit = match &transfer_params.remote_ops {
+                    Some(ops) => match ops.clone().acquire_owned().await {
+                        Ok(p) => Some(p),
+                        Err(_) => {
+                            tracing::error!("Semaphore closed unexpectedly");
+                            break;
+                        }
+                    },
+                    None => None,
+                };
+                let source = source_storage.clone();
+                let dest = dest_storage.clone();
+                let stats = stats.clone();
+                let errors = errors.clone();
+                let failed_files = failed_files.clone();
+                let skipped_locked = skipped_locked.clone();
+                let synced_states = synced_states.clone();
+                let flush_notify = flush_notify.clone();
+                let completed_actions = completed_actions.clone();
+                let cancelled = cancelled.clone();
+                let retry_config = RetryConfig {
+                    max_retries: self.config.max_retries,
+                    base_delay_ms: self.config.retry_base_delay_ms,
+                    skip_locked: self.config.skip_locked_files,
+                    budget: retry_budget.clone(),
+                };
+                let transfer_params = transfer_params.clone();
+                let job_id = job_id.to_string();
+                let fail_fast = self.config.fail_fast;
+
+                let stats_clone = stats.clone();
+                let handle = tokio::spawn(async move {
+                    let result = Self::execute_action_with_retry(
+                        &action,
+                        source.as_ref(),
+                        dest.as_ref(),
+                        retry_config,
+                        &cancelled,
+                        &job_id,
+                        Some(&stats_clone),
+                        transfer_params,
+                    )
+                    .await
+                    .map_err(|e| e.message);
+
+                    match result {
+                        Ok(retry_result) if retry_result.locked => {
+                            stats.files_locked.fetch_add(1, Ordering::Relaxed);
+                            skipped_locked.write().await.push(action.path().to_string());
+                        }
+                        Ok(retry_result) => {
+                            stats.files_completed.fetch_add(1, Ordering::Relaxed);
+                            match &action {
+                                SyncAction::Copy { reverse: true, .. } => {
+                                    stats.files_copied_to_source.fetch_add(1, Ordering::Relaxed);
+                                }
+                                SyncAction::Copy { .. }
+                                | SyncAction::Rename { .. }
+                                | SyncAction::Replace { .. } => {
+                                    stats.files_copied_to_dest.fetch_add(1, Ordering::Relaxed);
+                                }
+                                _ => {}
+                            }
+                            // 注意：字节数已在传输过程中实时更新，这里不再累加
                         
-                        // 收集成功同步的文件状态
-                        if let Some(state) = retry_result.file_state {
-                            let mut states = synced_states.write().await;
-                            states.push(state);
+                            // 收集成功同步的文件状态
+                            if let Some(state) = retry_result.file_state {
+                                let mut states = synced_states.write().await;
+                                states.push(state);
+                                if states.len() >= STATE_FLUSH_BATCH {
+                                    flush_notify.notify_one();
+                                }
+                            }
+                            completed_actions.write().await.push(action);
+                        }
+                        Err(e) => {
+                            stats.files_failed.fetch_add(1, Ordering::Relaxed);
+                            failed_files
+                                .write()
+                                .await
+                                .push((action.path().to_string(), e.clone()));
+                            let mut errs = errors.write().await;
+                            // 快速失败：第一个失败触发取消，其错误放在最前面
+                            if fail_fast && !cancelled.swap(true, Ordering::SeqCst) {
+                                warn!("快速失败已触发，停止后续传输: {}", e);
+                                errs.insert(0, format!("快速失败: {}", e));
+                            } else {
+                                errs.push(e);
+                            }
                         }
                     }
-                    Err(e) => {
-                        stats.files_failed.fetch_add(1, Ordering::Relaxed);
-                        let mut errs = errors.write().await;
-                        errs.push(e);
-                    }
-                }
 
-                drop(permit);
-            });
+                    drop(permit);
+                    drop(op_permit);
+                });
 
-            handles.push(handle);
-        }
+                handles.push(handle);
+            }
 
-        // 等待所有任务完成
-        for handle in handles {
-            let _ = handle.await;
+            // 等待本阶段所有任务完成
+            for handle in handles {
+                let _ = handle.await;
+            }
         }
 
         // 停止进度更新
         progress_handle.abort();
 
-        // 保存成功同步的文件状态
-        let states_to_save = synced_states.read().await.clone();
+        // 已完成的断点记录不再需要，未完成的保留到下次同步继续
+        if let Some(manager) = &transfer_params.transfers {
+            if let Err(e) = manager.cleanup_completed(job_id).await {
+                warn!("清理已完成的传输记录失败: {}", e);
+            }
+        }
+
+        // 等待定期保存结束，再保存剩余的文件状态
+        flush_done.store(true, Ordering::SeqCst);
+        flush_notify.notify_one();
+        let _ = flush_handle.await;
+        let states_to_save = std::mem::take(&mut *synced_states.write().await);
         if !states_to_save.is_empty() {
             let state_manager = FileStateManager::new(self.db.clone());
             if let Err(e) = state_manager.batch_upsert(&states_to_save).await {
@@ -919,19 +2313,154 @@ impl SyncEngine {
         let bytes_transferred = stats.bytes_transferred.load(Ordering::Relaxed);
 
         // 分离复制和删除的计数
-        let files_copied =
-            files_completed.min(summary.copy_count as u32 + summary.reverse_copy_count as u32);
+        let files_copied = files_completed
+            .min((summary.copy_count + summary.reverse_copy_count + summary.rename_count) as u32);
         let files_deleted = files_completed.saturating_sub(files_copied);
 
         let error_list = errors.read().await.clone();
+        let failed_list = failed_files.read().await.clone();
+        let locked_list = skipped_locked.read().await.clone();
+        if !locked_list.is_empty() {
+            warn!("{} 个文件被其他程序占用，已跳过", locked_list.len());
+        }
+        let completed_list = std::mem::take(&mut *completed_actions.write().await);
 
-        (
+        ParallelOutcome {
             files_copied,
+            files_copied_to_source: stats.files_copied_to_source.load(Ordering::Relaxed) as u32,
+            files_copied_to_dest: stats.files_copied_to_dest.load(Ordering::Relaxed) as u32,
             files_deleted,
             files_failed,
             bytes_transferred,
-            error_list,
-        )
+            errors: error_list,
+            failed_files: failed_list,
+            skipped_locked: locked_list,
+            completed_actions: completed_list,
+        }
+    }
+
+    /// 把删除动作合并为批量删除（仅存储支持时），返回仍需逐个执行的动作（含批量删除失败的）
+    async fn delete_in_batches(
+        &self,
+        actions: Vec<SyncAction>,
+        source: &dyn Storage,
+        dest: &dyn Storage,
+        stats: &TransferStats,
+        completed_actions: &RwLock<Vec<SyncAction>>,
+        transfer_params: &TransferParams,
+    ) -> Vec<SyncAction> {
+        let mut dest_deletes = Vec::new();
+        let mut source_deletes = Vec::new();
+        let mut remaining = Vec::with_capacity(actions.len());
+        for action in actions {
+            match action {
+                SyncAction::Delete { from_dest: true, .. } if dest.supports_delete_batch() => {
+                    dest_deletes.push(action)
+                }
+                SyncAction::Delete { from_dest: false, .. } if source.supports_delete_batch() => {
+                    source_deletes.push(action)
+                }
+                _ => remaining.push(action),
+            }
+        }
+
+        for (storage, deletes) in [(dest, dest_deletes), (source, source_deletes)] {
+            // 只有一个删除时按普通动作执行
+            if deletes.len() < 2 {
+                remaining.extend(deletes);
+                continue;
+            }
+            for chunk in deletes.chunks(DELETE_BATCH_SIZE) {
+                if self.cancelled.load(Ordering::SeqCst) {
+                    break;
+                }
+                let _op_permit = match &transfer_params.remote_ops {
+                    Some(ops) => ops.acquire().await.ok(),
+                    None => None,
+                };
+                let paths: Vec<String> = chunk.iter().map(|a| a.path().to_string()).collect();
+                debug!("批量删除 {} 个文件: {}", paths.len(), storage.name());
+                // 每删除一个文件立即计入进度
+                let on_deleted = |path: &str| {
+                    debug!("已删除: {}", path);
+                    stats.files_completed.fetch_add(1, Ordering::Relaxed);
+                };
+                let results = storage.delete_batch(&paths, &on_deleted).await;
+
+                let mut completed = completed_actions.write().await;
+                for (action, result) in chunk.iter().zip(results) {
+                    match result {
+                        Ok(()) => completed.push(action.clone()),
+                        Err(e) => {
+                            debug!("批量删除失败，单独重试: {} - {}", action.path(), e);
+                            remaining.push(action.clone());
+                        }
+                    }
+                }
+            }
+        }
+        remaining
+    }
+
+    /// 目标是否可视为空（跳过目标扫描）：根目录由本次同步创建，或顶层列表为空
+    ///
+    /// 能确认目标不为空时忽略手动的 `assume_empty_dest`，无法确认时按该选项处理
+    async fn dest_is_empty(dest: &dyn Storage, dest_created: bool, assume_empty_dest: bool) -> bool {
+        if dest_created {
+            return true;
+        }
+        match dest.root_is_empty().await {
+            Ok(Some(empty)) => {
+                if assume_empty_dest && !empty {
+                    warn!("目标不为空，忽略「视目标为空」选项，正常扫描目标");
+                }
+                empty
+            }
+            Ok(None) => {
+                if assume_empty_dest {
+                    warn!("{} 无法确认目标是否为空，按「视目标为空」选项跳过目标扫描", dest.name());
+                }
+                assume_empty_dest
+            }
+            Err(e) => {
+                warn!("检查目标是否为空失败，正常扫描目标: {}", e);
+                false
+            }
+        }
+    }
+
+    /// 创建目标根目录，失败且重新列出根目录仍不可访问时按指数退避重试（如 WebDAV 暂时返回 423 Locked）
+    ///
+    /// 返回 `Some(true)` 表示由本次创建，`Some(false)` 表示目录已存在，`None` 表示无法创建
+    async fn create_dest_root(
+        dest_storage: &dyn Storage,
+        retry_config: &RetryConfig,
+        cancelled: &AtomicBool,
+    ) -> Option<bool> {
+        for attempt in 0..=retry_config.max_retries {
+            match dest_storage.create_dir("/").await {
+                Ok(()) => return Some(true),
+                Err(e) => {
+                    debug!("创建根目录失败: {}", e);
+                    // 重新列出根目录确认是否已可用（可能已被其他进程创建）
+                    if dest_storage.probe_root().await.is_ok() {
+                        return Some(false);
+                    }
+                }
+            }
+            if attempt >= retry_config.max_retries || cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+            let delay = retry_config.base_delay_ms * RETRY_BACKOFF_BASE.pow(attempt);
+            warn!(
+                "创建目标目录失败，{}ms 后重试 ({}/{})",
+                delay,
+                attempt + 1,
+                retry_config.max_retries
+            );
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+        }
+        None
     }
 
     /// 带重试的动作执行
@@ -944,15 +2473,20 @@ impl SyncEngine {
         job_id: &str,
         stats: Option<&Arc<TransferStats>>,
         transfer_params: TransferParams,
-    ) -> Result<RetryResult, String> {
+    ) -> Result<RetryResult, RetryError> {
         let mut last_error = String::new();
+        let mut retries = 0;
 
         for attempt in 0..=retry_config.max_retries {
+            retries = attempt;
             if cancelled.load(Ordering::SeqCst) {
-                return Err("操作已取消".to_string());
+                return Err(RetryError {
+                    message: "操作已取消".to_string(),
+                    retries,
+                });
             }
 
-            match Self::execute_action(action, source, dest, stats, transfer_params).await {
+            match Self::execute_action(action, source, dest, stats, job_id, &transfer_params).await {
                 Ok(result) => {
                     // 如果有文件信息，创建 FileState
                     let file_state = if let (Some(path), Some(hash), Some(size)) = 
@@ -971,15 +2505,46 @@ impl SyncEngine {
                     
                     return Ok(RetryResult {
                         file_state,
+                        retries: attempt,
+                        locked: false,
                     });
                 }
                 Err(e) => {
+                    // 传输因取消而中止，不再重试
+                    if cancelled.load(Ordering::SeqCst) {
+                        return Err(RetryError {
+                            message: "操作已取消".to_string(),
+                            retries,
+                        });
+                    }
                     last_error = e.to_string();
+                    let kind = SyncError::classify(&e);
 
-                    if attempt < retry_config.max_retries {
-                        // 指数退避
-                        let delay = retry_config.base_delay_ms * RETRY_BACKOFF_BASE.pow(attempt);
-                        warn!(
+                    // 文件被占用时重试也不会成功，按配置跳过
+                    if matches!(kind, SyncError::Locked(_)) && retry_config.skip_locked {
+                        warn!("文件被其他程序占用，已跳过: {} ({})", action.path(), last_error);
+                        return Ok(RetryResult {
+                            file_state: None,
+                            retries: attempt,
+                            locked: true,
+                        });
+                    }
+
+                    // 文件不存在、认证失败等错误重试也不会成功
+                    if !kind.is_retryable() {
+                        warn!("操作失败且不可重试: {}", last_error);
+                        break;
+                    }
+
+                    if attempt < retry_config.max_retries && !retry_config.take_retry() {
+                        warn!("本次同步的重试次数已用尽，不再重试: {}", last_error);
+                        break;
+                    }
+
+                    if attempt < retry_config.max_retries {
+                        // 指数退避
+                        let delay = retry_config.base_delay_ms * RETRY_BACKOFF_BASE.pow(attempt);
+                        warn!(
                             "操作失败，{}ms 后重试 ({}/{}): {}",
                             delay,
                             attempt + 1,
@@ -988,20 +2553,16 @@ impl SyncEngine {
                         );
                         tokio::time::sleep(Duration::from_millis(delay)).await;
                     } else {
-                        error!("操作最终失败 (已重试{}次): {}", retry_config.max_retries, last_error);
+                        error!("操作最终失败 (已重试{}次): {}", attempt, last_error);
                     }
                 }
             }
         }
 
-        let path = match action {
-            SyncAction::Copy { source_path, .. } => source_path.clone(),
-            SyncAction::Delete { path, .. } => path.clone(),
-            SyncAction::Skip { path } => path.clone(),
-            SyncAction::Conflict { path, .. } => path.clone(),
-        };
-
-        Err(format!("{}: {}", path, last_error))
+        Err(RetryError {
+            message: format!("{}: {}", action.path(), last_error),
+            retries,
+        })
     }
 
     /// 执行单个动作
@@ -1010,7 +2571,8 @@ impl SyncEngine {
         source: &dyn Storage,
         dest: &dyn Storage,
         stats: Option<&Arc<TransferStats>>,
-        transfer_params: TransferParams,
+        job_id: &str,
+        transfer_params: &TransferParams,
     ) -> Result<ActionResult> {
         match action {
             SyncAction::Copy {
@@ -1019,122 +2581,18 @@ impl SyncEngine {
                 size,
                 reverse,
             } => {
-                let (from, to, from_path, to_path) = if *reverse {
-                    (dest, source, dest_path.as_str(), source_path.as_str())
-                } else {
-                    (source, dest, source_path.as_str(), dest_path.as_str())
-                };
-
-                debug!(
-                    "复制: {} -> {} ({}字节, reverse={})",
-                    from_path, to_path, size, reverse
-                );
-
-                // 启用流式传输的阈值（可配置，默认 128MB）
-                // 优点：内存可控，实时进度显示，减少系统调用
-                if *size > transfer_params.stream_threshold {
-                    // 大文件：临时文件 + 分块流式传输
-                    let chunk_size = transfer_params.chunk_size;
-                    debug!("  流式传输 ({}MB, 块大小: {}MB)", size / 1024 / 1024, chunk_size / 1024 / 1024);
-                    
-                    use tokio::io::AsyncWriteExt;
-                    use futures::stream::StreamExt;
-                    
-                    let total_size = *size;
-                    let temp_dir = std::env::temp_dir();
-                    let temp_filename = format!("synctools_{}.tmp", uuid::Uuid::new_v4());
-                    let temp_path = temp_dir.join(&temp_filename);
-                    
-                    // 阶段1：分块读取源文件，写入临时文件，计算 hash
-                    // 下载进度：在读取时更新 50% 进度（改善下载体验）
-                    debug!("  阶段1: 缓存到临时文件...");
-                    let mut temp_file = tokio::fs::File::create(&temp_path).await?;
-                    let mut hasher = blake3::Hasher::new();
-                    let mut offset = 0u64;
-                    
-                    while offset < total_size {
-                        let chunk_len = (total_size - offset).min(chunk_size);
-                        let chunk = from.read_range(from_path, offset, chunk_len).await?;
-                        let chunk_actual_len = chunk.len() as u64;
-                        
-                        hasher.update(&chunk);
-                        temp_file.write_all(&chunk).await?;
-                        offset += chunk_actual_len;
-                        
-                        // 阶段1（读取/下载）更新 50% 进度
-                        if let Some(ref s) = stats {
-                            s.bytes_transferred.fetch_add(chunk_actual_len / 2, Ordering::Relaxed);
-                        }
-                    }
-                    
-                    temp_file.flush().await?;
-                    drop(temp_file);
-                    
-                    let file_hash = hasher.finalize().to_hex().to_string();
-                    
-                    // 阶段2：分块流式上传（更新剩余 50% 进度）
-                    debug!("  阶段2: {}MB 块流式上传...", chunk_size / 1024 / 1024);
-                    let temp_file = tokio::fs::File::open(&temp_path).await?;
-                    
-                    // 使用配置的块大小缓冲区的 ReaderStream
-                    let reader_stream = tokio_util::io::ReaderStream::with_capacity(temp_file, chunk_size as usize);
-                    
-                    let stats_clone = stats.map(|s| s.clone());
-                    let byte_stream = reader_stream.map(move |result| {
-                        result
-                            .map(|bytes| {
-                                let len = bytes.len() as u64;
-                                
-                                // 阶段2（上传）更新剩余 50% 进度
-                                if let Some(ref s) = stats_clone {
-                                    s.bytes_transferred.fetch_add(len - len / 2, Ordering::Relaxed);
-                                }
-                                
-                                bytes.to_vec()
-                            })
-                            .map_err(|e| anyhow::Error::from(e))
-                    });
-                    
-                    to.write_stream(to_path, Box::pin(byte_stream), Some(total_size)).await?;
-                    
-                    // 清理临时文件
-                    let _ = tokio::fs::remove_file(&temp_path).await;
-                    debug!("  流式传输完成");
-                    
-                    return Ok(ActionResult {
-                        file_path: if !*reverse { Some(source_path.clone()) } else { None },
-                        file_hash: if !*reverse { Some(file_hash) } else { None },
-                        file_size: if !*reverse { Some(total_size as i64) } else { None },
-                    });
-                }
-                
-                // 常规文件传输
-                let data = from.read(from_path).await?;
-                let actual_size = data.len() as u64;
-                debug!("  读取完成: {} 实际{}字节", from_path, actual_size);
-                
-                // 读取完成后更新 50% 进度（改善下载体验）
-                if let Some(s) = &stats {
-                    s.bytes_transferred.fetch_add(actual_size / 2, Ordering::Relaxed);
-                }
-
-                // 计算文件 hash（用于增量同步）
-                let file_hash = calculate_quick_hash(&data);
-                let file_size = data.len() as i64;
-
-                to.write(to_path, data).await?;
-                debug!("  写入完成: {}", to_path);
-                
-                // 写入完成后更新剩余进度
-                if let Some(s) = stats {
-                    s.bytes_transferred.fetch_add(actual_size - actual_size / 2, Ordering::Relaxed);
-                }
-
-                Ok(ActionResult {
-                    file_path: if !*reverse { Some(source_path.clone()) } else { None },
-                    file_hash: if !*reverse { Some(file_hash) } else { None },
-                    file_size: if !*reverse { Some(file_size) } else { None },
-                })
+                Self::execute_copy(
+                    source_path,
+                    dest_path,
+                    *size,
+                    *reverse,
+                    source,
+                    dest,
+                    stats,
+                    job_id,
+                    transfer_params,
+                )
+                .await
             }
             SyncAction::Delete { path, from_dest } => {
                 let storage = if *from_dest { dest } else { source };
@@ -1145,6 +2603,36 @@ impl SyncEngine {
                     file_size: None,
                 })
             }
+            SyncAction::Rename { from, to, .. } => {
+                debug!("重命名目标文件: {} -> {}", from, to);
+                dest.rename(from, to).await?;
+                Ok(ActionResult {
+                    file_path: None,
+                    file_hash: None,
+                    file_size: None,
+                })
+            }
+            SyncAction::Replace {
+                path,
+                size,
+                source_is_dir,
+            } => {
+                // 先删除目标中类型错误的条目（重试时条目可能已被删除，删除不存在的路径不会报错）
+                debug!("替换类型不一致的条目: {} (源为目录: {})", path, source_is_dir);
+                dest.delete(path).await?;
+
+                if *source_is_dir {
+                    dest.create_dir(path).await?;
+                    return Ok(ActionResult {
+                        file_path: None,
+                        file_hash: None,
+                        file_size: None,
+                    });
+                }
+
+                Self::execute_copy(path, path, *size, false, source, dest, stats, job_id, transfer_params)
+                    .await
+            }
             SyncAction::Skip { .. } => Ok(ActionResult {
                 file_path: None,
                 file_hash: None,
@@ -1157,6 +2645,803 @@ impl SyncEngine {
         }
     }
 
+    /// 比较冲突文件两边的完整内容，相同的转为跳过，返回自动解决的数量
+    async fn resolve_identical_conflicts(
+        actions: &mut [SyncAction],
+        source: &dyn Storage,
+        dest: &dyn Storage,
+    ) -> usize {
+        let mut resolved = 0;
+        for action in actions.iter_mut() {
+            let SyncAction::Conflict {
+                path,
+                source_info: Some(src),
+                dest_info: Some(dst),
+                conflict_type: ConflictType::BothModified | ConflictType::SameSizeDifferentTime,
+            } = action
+            else {
+                continue;
+            };
+
+            // 两边大小都已知且不同，内容不可能相同
+            if src.size > 0 && dst.size > 0 && src.size != dst.size {
+                continue;
+            }
+
+            let size = src.size.max(dst.size);
+            match Self::same_content(source, dest, path, (size > 0).then_some(size)).await {
+                Ok(true) => {
+                    debug!("冲突文件内容相同，跳过: {}", path);
+                    *action = SyncAction::Skip { path: path.clone() };
+                    resolved += 1;
+                }
+                Ok(false) => {}
+                Err(e) => debug!("读取冲突文件失败，保留冲突: {} - {}", path, e),
+            }
+        }
+        resolved
+    }
+
+    /// 按块读取并比较两边同一路径的文件内容，遇到第一处不同即停止（不把整个文件读入内存）
+    ///
+    /// `size` 已知时只读取到该长度，避免超出文件末尾的范围请求；未知时读到不足一块为止
+    async fn same_content(
+        source: &dyn Storage,
+        dest: &dyn Storage,
+        path: &str,
+        size: Option<u64>,
+    ) -> Result<bool> {
+        let mut offset = 0;
+        loop {
+            let len = match size {
+                Some(size) if offset >= size => return Ok(true),
+                Some(size) => (size - offset).min(CONFLICT_COMPARE_CHUNK),
+                None => CONFLICT_COMPARE_CHUNK,
+            };
+            let (src_part, dst_part) = tokio::try_join!(
+                source.read_range(path, offset, len),
+                dest.read_range(path, offset, len),
+            )?;
+            if src_part != dst_part {
+                return Ok(false);
+            }
+            // 读到的长度不足即已到文件末尾
+            if (src_part.len() as u64) < len {
+                return Ok(true);
+            }
+            offset += len;
+        }
+    }
+
+    /// 会删除受保护路径的动作转为跳过，返回跳过的数量
+    ///
+    /// 替换类型不一致的条目时先删除目标中的条目（目录连同其中的内容），
+    /// 因此替换的路径本身或目标中其下的任一条目受保护时同样跳过
+    fn apply_protect_patterns(
+        actions: &mut [SyncAction],
+        dest_tree: &HashMap<String, FileInfo>,
+        patterns: &[String],
+    ) -> usize {
+        let is_protected = |path: &str| patterns.iter().any(|pattern| matches_glob(path, pattern));
+        let mut protected = 0;
+        for action in actions.iter_mut() {
+            let hit = match action {
+                SyncAction::Delete { path, .. } => is_protected(path.as_str()),
+                SyncAction::Replace { path, .. } => {
+                    let children = format!("{}/", path);
+                    is_protected(path.as_str())
+                        || dest_tree
+                            .keys()
+                            .any(|p| p.starts_with(&children) && is_protected(p.as_str()))
+                }
+                _ => false,
+            };
+            if hit {
+                *action = SyncAction::Skip {
+                    path: action.path().to_string(),
+                };
+                protected += 1;
+            }
+        }
+        protected
+    }
+
+    /// 未确认的删除转为跳过，返回跳过的数量
+    ///
+    /// 替换类型不一致的条目会先删除目标中的条目，同样需要确认其路径
+    fn apply_confirmed_deletes(
+        actions: &mut [SyncAction],
+        confirmed: &std::collections::HashSet<String>,
+    ) -> usize {
+        let mut unconfirmed = 0;
+        for action in actions.iter_mut() {
+            if let SyncAction::Delete { path, .. } | SyncAction::Replace { path, .. } = action {
+                if !confirmed.contains(path.as_str()) {
+                    *action = SyncAction::Skip { path: path.clone() };
+                    unconfirmed += 1;
+                }
+            }
+        }
+        unconfirmed
+    }
+
+    /// 把"源端新增 + 目标端删除"且内容相同的文件对合并为目标内重命名，返回合并的数量
+    ///
+    /// 先按大小配对，再按范围读取两边的采样块比较快速哈希；大小为 0 的文件不参与（无法区分）。
+    async fn detect_renames(
+        actions: &mut Vec<SyncAction>,
+        dest_tree: &HashMap<String, FileInfo>,
+        source: &dyn Storage,
+        dest: &dyn Storage,
+    ) -> usize {
+        use futures::stream::StreamExt;
+
+        // 目标端待删除的文件，按大小分组
+        let mut deletes_by_size: HashMap<u64, Vec<String>> = HashMap::new();
+        for action in actions.iter() {
+            if let SyncAction::Delete {
+                path,
+                from_dest: true,
+            } = action
+            {
+                if let Some(info) = dest_tree.get(path).filter(|i| !i.is_dir && i.size > 0) {
+                    deletes_by_size.entry(info.size).or_default().push(path.clone());
+                }
+            }
+        }
+        if deletes_by_size.is_empty() {
+            return 0;
+        }
+
+        // 大小能配对的源文件和目标文件，并发计算快速哈希
+        let mut to_hash: Vec<(bool, String, u64)> = Vec::new();
+        let mut paired_sizes: HashSet<u64> = HashSet::new();
+        for action in actions.iter() {
+            if let SyncAction::Copy {
+                source_path,
+                size,
+                reverse: false,
+                ..
+            } = action
+            {
+                if deletes_by_size.contains_key(size) {
+                    to_hash.push((true, source_path.clone(), *size));
+                    paired_sizes.insert(*size);
+                }
+            }
+        }
+        for size in &paired_sizes {
+            for candidate in &deletes_by_size[size] {
+                to_hash.push((false, candidate.clone(), *size));
+            }
+        }
+        let hashes: Vec<(bool, String, Option<String>)> = futures::stream::iter(to_hash)
+            .map(|(from_source, path, size)| async move {
+                let storage = if from_source { source } else { dest };
+                let hash = match calculate_quick_hash_ranged(storage, &path, size).await {
+                    Ok(hash) => Some(hash),
+                    Err(e) => {
+                        debug!("读取文件失败，不检测重命名: {} - {}", path, e);
+                        None
+                    }
+                };
+                (from_source, path, hash)
+            })
+            .buffer_unordered(RENAME_HASH_CONCURRENCY)
+            .collect()
+            .await;
+        let mut source_hashes: HashMap<String, String> = HashMap::new();
+        let mut dest_hashes: HashMap<String, String> = HashMap::new();
+        for (from_source, path, hash) in hashes {
+            if let Some(hash) = hash {
+                if from_source {
+                    source_hashes.insert(path, hash);
+                } else {
+                    dest_hashes.insert(path, hash);
+                }
+            }
+        }
+
+        let mut matched: HashSet<String> = HashSet::new();
+        for action in actions.iter_mut() {
+            let SyncAction::Copy {
+                source_path,
+                dest_path,
+                size,
+                reverse: false,
+            } = action
+            else {
+                continue;
+            };
+            let (Some(candidates), Some(source_hash)) =
+                (deletes_by_size.get(&*size), source_hashes.get(source_path.as_str()))
+            else {
+                continue;
+            };
+
+            let found = candidates.iter().find(|c| {
+                !matched.contains(*c) && dest_hashes.get(c.as_str()) == Some(source_hash)
+            });
+            if let Some(candidate) = found {
+                debug!("识别为移动: {} -> {}", candidate, dest_path);
+                matched.insert(candidate.clone());
+                *action = SyncAction::Rename {
+                    from: candidate.clone(),
+                    to: dest_path.clone(),
+                    size: *size,
+                };
+            }
+        }
+
+        // 已合并为重命名的删除不再单独执行
+        actions.retain(|a| {
+            !matches!(a, SyncAction::Delete { path, from_dest: true } if matched.contains(path))
+        });
+        matched.len()
+    }
+
+    /// 复制单个文件，按需复制扩展属性
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_copy(
+        source_path: &str,
+        dest_path: &str,
+        size: u64,
+        reverse: bool,
+        source: &dyn Storage,
+        dest: &dyn Storage,
+        stats: Option<&Arc<TransferStats>>,
+        job_id: &str,
+        transfer_params: &TransferParams,
+    ) -> Result<ActionResult> {
+        let result = Self::copy_content(
+            source_path,
+            dest_path,
+            size,
+            reverse,
+            source,
+            dest,
+            stats,
+            job_id,
+            transfer_params,
+        )
+        .await?;
+
+        if transfer_params.preserve_xattrs {
+            let (from, to, from_path, to_path) = if reverse {
+                (dest, source, dest_path, source_path)
+            } else {
+                (source, dest, source_path, dest_path)
+            };
+            // 仅两边都是本地存储时复制，设置失败只警告
+            if let (Some(from_file), Some(to_file)) =
+                (from.local_path(from_path), to.local_path(to_path))
+            {
+                let copied = tokio::task::spawn_blocking(move || {
+                    crate::storage::local::copy_xattrs(&from_file, &to_file)
+                })
+                .await;
+                match copied {
+                    Ok(Ok(n)) if n > 0 => debug!("  已复制 {} 个扩展属性: {}", n, to_path),
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => warn!("复制扩展属性失败 {}: {}", to_path, e),
+                    Err(e) => warn!("复制扩展属性失败 {}: {}", to_path, e),
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// 复制文件内容（小文件整体读写，大文件经临时文件流式传输）
+    #[allow(clippy::too_many_arguments)]
+    async fn copy_content(
+        source_path: &str,
+        dest_path: &str,
+        size: u64,
+        reverse: bool,
+        source: &dyn Storage,
+        dest: &dyn Storage,
+        stats: Option<&Arc<TransferStats>>,
+        job_id: &str,
+        transfer_params: &TransferParams,
+    ) -> Result<ActionResult> {
+        let (from, to, from_path, to_path) = if reverse {
+            (dest, source, dest_path, source_path)
+        } else {
+            (source, dest, source_path, dest_path)
+        };
+
+        debug!(
+            "复制: {} -> {} ({}字节, reverse={})",
+            from_path, to_path, size, reverse
+        );
+
+        if transfer_params.append_only && to.supports_append() {
+            if let Some(result) =
+                Self::try_append(from, to, from_path, to_path, size, stats, transfer_params).await?
+            {
+                return Ok(result);
+            }
+        }
+
+        // 启用流式传输的阈值（可配置，默认 128MB）
+        // 优点：内存可控，实时进度显示，减少系统调用
+        if size > transfer_params.stream_threshold {
+            // 大文件：临时文件 + 分块流式传输
+            let chunk_size = transfer_params.chunk_size;
+            debug!("  流式传输 ({}MB, 块大小: {}MB)", size / 1024 / 1024, chunk_size / 1024 / 1024);
+            
+            use tokio::io::AsyncWriteExt;
+            use futures::stream::StreamExt;
+            
+            let total_size = size;
+
+            // 断点续传：临时文件按传输 ID 命名并跨重试保留，中断后从已下载的位置继续
+            let resume = match &transfer_params.transfers {
+                Some(manager) => {
+                    Self::load_download_state(
+                        manager,
+                        &transfer_params.temp_naming,
+                        job_id,
+                        from,
+                        from_path,
+                        total_size,
+                    )
+                    .await
+                }
+                None => None,
+            };
+            let temp_path = match &resume {
+                Some(state) => transfer_params.temp_naming.temp_path(&state.id, from_path),
+                None => transfer_params
+                    .temp_naming
+                    .temp_path(&uuid::Uuid::new_v4().to_string(), from_path),
+            };
+            // 出错或取消时删除临时文件（断点续传的临时文件保留，下次从断点继续）
+            let _temp_guard = resume.is_none().then(|| {
+                scopeguard::guard(temp_path.clone(), |path| {
+                    let _ = std::fs::remove_file(path);
+                })
+            });
+            
+            // 阶段1：分块读取源文件，写入临时文件，计算 hash
+            // 下载进度：在读取时更新 50% 进度（改善下载体验）
+            debug!("  阶段1: 缓存到临时文件...");
+            let downloaded = resume.as_ref().map_or(0, |s| s.transferred_size);
+            let (mut temp_file, mut hasher, mut offset) =
+                match Self::reopen_partial_download(&temp_path, downloaded).await? {
+                    Some((file, hasher)) => {
+                        debug!("  从 {} 字节处继续下载", downloaded);
+                        (file, hasher, downloaded)
+                    }
+                    None => (
+                        tokio::fs::File::create(&temp_path).await?,
+                        blake3::Hasher::new(),
+                        0u64,
+                    ),
+                };
+            if let Some(ref s) = stats {
+                s.bytes_transferred.fetch_add(offset / 2, Ordering::Relaxed);
+            }
+            
+            let read_permit = transfer_params.read_permit().await?;
+            while offset < total_size {
+                if transfer_params.cancelled.load(Ordering::SeqCst) {
+                    return Err(anyhow::anyhow!("操作已取消"));
+                }
+                let chunk_len = (total_size - offset).min(chunk_size);
+                let chunk = from.read_range(from_path, offset, chunk_len).await?;
+                let chunk_actual_len = chunk.len() as u64;
+                if chunk_actual_len == 0 {
+                    return Err(anyhow::anyhow!("源文件在 {} 字节处提前结束: {}", offset, from_path));
+                }
+                
+                hasher.update(&chunk);
+                temp_file.write_all(&chunk).await?;
+                offset += chunk_actual_len;
+                
+                // 阶段1（读取/下载）更新 50% 进度
+                if let Some(ref s) = stats {
+                    s.bytes_transferred.fetch_add(chunk_actual_len / 2, Ordering::Relaxed);
+                }
+
+                // 先落盘再记录进度，保证记录的偏移不超过临时文件的实际内容
+                if let (Some(manager), Some(state)) = (&transfer_params.transfers, &resume) {
+                    temp_file.flush().await?;
+                    if let Err(e) = manager.update_progress(&state.id, offset).await {
+                        warn!("保存下载进度失败: {}", e);
+                    }
+                }
+            }
+            
+            temp_file.flush().await?;
+            drop(temp_file);
+            drop(read_permit);
+            
+            let file_hash = hasher.finalize().to_hex().to_string();
+            
+            // 阶段2：分块流式上传（更新剩余 50% 进度）
+            debug!("  阶段2: {}MB 块流式上传...", chunk_size / 1024 / 1024);
+            let temp_file = tokio::fs::File::open(&temp_path).await?;
+            let temp_len = temp_file.metadata().await?.len();
+            if temp_len != total_size {
+                let _ = tokio::fs::remove_file(&temp_path).await;
+                return Err(anyhow::anyhow!(
+                    "临时文件大小不一致（{} / {} 字节）: {}",
+                    temp_len,
+                    total_size,
+                    from_path
+                ));
+            }
+            
+            // 使用配置的块大小缓冲区的 ReaderStream
+            let reader_stream = tokio_util::io::ReaderStream::with_capacity(temp_file, chunk_size as usize);
+            
+            // 上传时重新计算临时文件的 hash，与阶段1 不一致时在最后一块之前让上传流出错
+            let expected_hash = file_hash.clone();
+            let mut verify_hasher = blake3::Hasher::new();
+            let mut verified_len = 0u64;
+            let corrupted = Arc::new(AtomicBool::new(false));
+            let corrupted_flag = corrupted.clone();
+
+            let stats_clone = stats.map(|s| s.clone());
+            let limiter = transfer_params.limiter.clone();
+            let cancelled = transfer_params.cancelled.clone();
+            let byte_stream = reader_stream
+                .then(move |result| {
+                    let limiter = limiter.clone();
+                    let cancelled = cancelled.clone();
+                    async move {
+                        if let (Ok(bytes), Some(limiter)) = (&result, &limiter) {
+                            // 取消时由下面的检查让上传流出错
+                            let _ = limiter.acquire(bytes.len() as u64, &cancelled).await;
+                        }
+                        // 取消后让上传流出错，使 write_stream 立即中止
+                        if cancelled.load(Ordering::SeqCst) {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::Interrupted,
+                                "操作已取消",
+                            ));
+                        }
+                        result
+                    }
+                })
+                .map(move |result| {
+                    let bytes = result.map_err(anyhow::Error::from)?;
+                    let len = bytes.len() as u64;
+
+                    verify_hasher.update(&bytes);
+                    verified_len += len;
+                    if verified_len == total_size
+                        && verify_hasher.finalize().to_hex().as_str() != expected_hash
+                    {
+                        corrupted_flag.store(true, Ordering::SeqCst);
+                        return Err(anyhow::anyhow!("临时文件校验失败"));
+                    }
+
+                    // 阶段2（上传）更新剩余 50% 进度
+                    if let Some(ref s) = stats_clone {
+                        s.bytes_transferred.fetch_add(len - len / 2, Ordering::Relaxed);
+                    }
+
+                    Ok(bytes.to_vec())
+                });
+            
+            let _write_permit = transfer_params.write_permit().await?;
+            if let Err(e) = to.write_stream(to_path, Box::pin(byte_stream), Some(total_size)).await {
+                // 临时文件已损坏：删除后重试时重新下载（断点续传的临时文件也不再复用）
+                if corrupted.load(Ordering::SeqCst) {
+                    let _ = tokio::fs::remove_file(&temp_path).await;
+                    return Err(anyhow::anyhow!(
+                        "临时文件在缓存后被改动，hash 与读取时不一致: {}",
+                        from_path
+                    ));
+                }
+                return Err(e);
+            }
+            
+            // 清理临时文件
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            if let (Some(manager), Some(state)) = (&transfer_params.transfers, &resume) {
+                if let Err(e) = manager.mark_completed(&state.id).await {
+                    warn!("更新传输状态失败: {}", e);
+                }
+            }
+            debug!("  流式传输完成");
+            
+            return Ok(ActionResult {
+                file_path: if !reverse { Some(source_path.to_string()) } else { None },
+                file_hash: if !reverse { Some(file_hash) } else { None },
+                file_size: if !reverse { Some(total_size as i64) } else { None },
+            });
+        }
+        
+        // 常规文件传输
+        let read_permit = transfer_params.read_permit().await?;
+        let data = from.read(from_path).await?;
+        drop(read_permit);
+        let actual_size = data.len() as u64;
+        debug!("  读取完成: {} 实际{}字节", from_path, actual_size);
+        
+        // 读取完成后更新 50% 进度（改善下载体验）
+        if let Some(s) = &stats {
+            s.bytes_transferred.fetch_add(actual_size / 2, Ordering::Relaxed);
+        }
+
+        // 计算文件 hash（用于增量同步）
+        let file_hash = calculate_quick_hash(&data);
+        let file_size = data.len() as i64;
+
+        let write_permit = transfer_params.write_permit().await?;
+        if let Some(limiter) = &transfer_params.limiter {
+            limiter.acquire(actual_size, &transfer_params.cancelled).await?;
+        }
+        to.write(to_path, data).await?;
+        drop(write_permit);
+        debug!("  写入完成: {}", to_path);
+        
+        // 写入完成后更新剩余进度
+        if let Some(s) = stats {
+            s.bytes_transferred.fetch_add(actual_size - actual_size / 2, Ordering::Relaxed);
+        }
+
+        Ok(ActionResult {
+            file_path: if !reverse { Some(source_path.to_string()) } else { None },
+            file_hash: if !reverse { Some(file_hash) } else { None },
+            file_size: if !reverse { Some(file_size) } else { None },
+        })
+    }
+
+    /// 追加复制：目标文件是源文件的前缀时，从目标大小处读取源文件的新增部分并追加到目标；
+    /// 不满足条件时返回 None，按普通复制处理
+    async fn try_append(
+        from: &dyn Storage,
+        to: &dyn Storage,
+        from_path: &str,
+        to_path: &str,
+        size: u64,
+        stats: Option<&Arc<TransferStats>>,
+        transfer_params: &TransferParams,
+    ) -> Result<Option<ActionResult>> {
+        let old_size = match to.stat(to_path).await? {
+            Some(meta) if !meta.is_dir && meta.size > 0 && meta.size < size => meta.size,
+            _ => return Ok(None),
+        };
+
+        // 比对目标的开头、中间和末尾各一段与源文件相同位置的内容，确认目标未被改写：
+        // 只比对末尾时，改写过但结尾相同的文件会在旧内容后追加，目标被静默损坏
+        if !Self::dest_is_prefix(from, to, from_path, to_path, old_size).await? {
+            debug!("  目标不是源文件的前缀，完整复制: {}", to_path);
+            return Ok(None);
+        }
+
+        debug!("  追加写入: {} 从 {} 字节处追加 {} 字节", to_path, old_size, size - old_size);
+        // 已有部分无需传输，直接计入进度
+        if let Some(s) = stats {
+            s.bytes_transferred.fetch_add(old_size, Ordering::Relaxed);
+        }
+
+        let mut offset = old_size;
+        while offset < size {
+            if transfer_params.cancelled.load(Ordering::SeqCst) {
+                return Err(anyhow::anyhow!("操作已取消"));
+            }
+            let chunk_len = (size - offset).min(transfer_params.chunk_size);
+            let chunk = {
+                let _read_permit = transfer_params.read_permit().await?;
+                from.read_range(from_path, offset, chunk_len).await?
+            };
+            let chunk_actual_len = chunk.len() as u64;
+            if chunk_actual_len == 0 {
+                return Err(anyhow::anyhow!("源文件在 {} 字节处提前结束: {}", offset, from_path));
+            }
+
+            let _write_permit = transfer_params.write_permit().await?;
+            if let Some(limiter) = &transfer_params.limiter {
+                limiter.acquire(chunk_actual_len, &transfer_params.cancelled).await?;
+            }
+            to.append(to_path, offset, chunk).await?;
+            offset += chunk_actual_len;
+
+            if let Some(s) = stats {
+                s.bytes_transferred.fetch_add(chunk_actual_len, Ordering::Relaxed);
+            }
+        }
+
+        // 只传输了新增部分，没有完整内容的 hash，不记录文件状态
+        Ok(Some(ActionResult {
+            file_path: None,
+            file_hash: None,
+            file_size: None,
+        }))
+    }
+
+    /// 目标已有的 `old_size` 字节是否与源文件开头相同（按开头、中间、末尾三段抽样比对）
+    async fn dest_is_prefix(
+        from: &dyn Storage,
+        to: &dyn Storage,
+        from_path: &str,
+        to_path: &str,
+        old_size: u64,
+    ) -> Result<bool> {
+        let check_len = old_size.min(APPEND_CHECK_BYTES);
+        let last = old_size - check_len;
+        let mut offsets = vec![0, last / 2, last];
+        offsets.dedup();
+        for offset in offsets {
+            let (source_part, dest_part) = tokio::try_join!(
+                from.read_range(from_path, offset, check_len),
+                to.read_range(to_path, offset, check_len),
+            )?;
+            if source_part != dest_part {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// 任务的传输参数（任务设置了分块大小或流式传输阈值时覆盖全局配置）
+    fn transfer_params(&self, job: &SyncJob) -> TransferParams {
+        let config = self.config.for_job(job);
+        let (read, write) = (config.read_concurrency, config.write_concurrency);
+        let max_in_flight = (read.is_some() || write.is_some()).then(|| {
+            read.unwrap_or(config.max_concurrent_transfers)
+                + write.unwrap_or(config.max_concurrent_transfers)
+        });
+        TransferParams {
+            chunk_size: config.chunk_size,
+            stream_threshold: config.large_file_threshold,
+            // 断点续传管理器（未启用断点续传时为 None）
+            transfers: self
+                .config
+                .enable_resume
+                .then(|| Arc::new(TransferManager::new(self.db.clone()))),
+            limiter: self.config.bandwidth_limiter.clone(),
+            cancelled: self.cancelled.clone(),
+            append_only: config.append_only,
+            preserve_xattrs: self.config.preserve_xattrs,
+            temp_naming: self.config.temp_file_naming.clone(),
+            remote_ops: config.max_remote_ops.map(|n| Arc::new(Semaphore::new(n))),
+            read_slots: read.map(|n| Arc::new(Semaphore::new(n))),
+            write_slots: write.map(|n| Arc::new(Semaphore::new(n))),
+            max_in_flight,
+        }
+    }
+
+    /// 读取（或新建）大文件下载的断点记录；源文件无法获取元数据时不使用断点
+    ///
+    /// 同一文件旧版本（源文件已变化）的断点记录和临时文件一并删除
+    async fn load_download_state(
+        manager: &TransferManager,
+        naming: &TempFileNaming,
+        job_id: &str,
+        from: &dyn Storage,
+        from_path: &str,
+        total_size: u64,
+    ) -> Option<TransferState> {
+        let meta = match from.stat(from_path).await {
+            Ok(Some(meta)) => meta,
+            Ok(None) => return None,
+            Err(e) => {
+                debug!("获取源文件信息失败，不使用断点续传: {}", e);
+                return None;
+            }
+        };
+        // ID 包含大小和修改时间，源文件变化后不会续接旧的临时文件
+        let id = TransferManager::download_transfer_id(
+            job_id,
+            from_path,
+            total_size,
+            meta.modified_time,
+        );
+
+        match manager.remove_stale_downloads(job_id, from_path, &id).await {
+            Ok(stale) if !stale.is_empty() => {
+                let naming = naming.clone();
+                let _ = tokio::task::spawn_blocking(move || naming.remove_temp_files(&stale)).await;
+            }
+            Ok(_) => {}
+            Err(e) => warn!("清理旧的传输记录失败: {}", e),
+        }
+
+        match manager.get_transfer(&id).await {
+            Ok(Some(state))
+                if state.total_size == total_size
+                    && !matches!(state.status, TransferStatus::Completed) =>
+            {
+                return Some(state)
+            }
+            Ok(_) => {}
+            Err(e) => warn!("读取传输状态失败: {}", e),
+        }
+
+        let mut state = TransferManager::create_transfer_state(job_id, from_path, total_size);
+        state.id = id;
+        state.status = TransferStatus::InProgress;
+        if let Err(e) = manager.save_transfer(&state).await {
+            warn!("保存传输状态失败，不使用断点续传: {}", e);
+            return None;
+        }
+        Some(state)
+    }
+
+    /// 打开已下载部分的临时文件：截断到记录的进度并重新计算这部分的 hash
+    ///
+    /// 临时文件不存在或比记录的进度短时返回 None，需要从头下载
+    async fn reopen_partial_download(
+        temp_path: &std::path::Path,
+        downloaded: u64,
+    ) -> Result<Option<(tokio::fs::File, blake3::Hasher)>> {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        if downloaded == 0 {
+            return Ok(None);
+        }
+        let mut file = match tokio::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(temp_path)
+            .await
+        {
+            Ok(f) => f,
+            Err(_) => return Ok(None),
+        };
+        if file.metadata().await?.len() < downloaded {
+            return Ok(None);
+        }
+        file.set_len(downloaded).await?;
+
+        // blake3 的中间状态无法持久化，续传前重新读取已下载部分
+        let mut hasher = blake3::Hasher::new();
+        let mut buf = vec![0u8; 1024 * 1024];
+        let mut remaining = downloaded;
+        while remaining > 0 {
+            let want = remaining.min(buf.len() as u64) as usize;
+            let n = file.read(&mut buf[..want]).await?;
+            if n == 0 {
+                return Ok(None);
+            }
+            hasher.update(&buf[..n]);
+            remaining -= n as u64;
+        }
+        file.seek(std::io::SeekFrom::End(0)).await?;
+
+        Ok(Some((file, hasher)))
+    }
+
+    /// 为本地文件树计算校验和并写回缓存（失败仅记录日志，不影响同步）
+    async fn fill_local_checksums(
+        &self,
+        job_id: &str,
+        side: &str,
+        storage: &dyn Storage,
+        tree: &mut HashMap<String, FileInfo>,
+        algorithm: ChecksumAlgorithm,
+        scan_config: &ScanConfig,
+    ) {
+        let state_manager = FileStateManager::new(self.db.clone());
+        let known = state_manager.get_checksums(job_id, side).await.unwrap_or_default();
+
+        match storage
+            .fill_checksums(
+                tree,
+                &known,
+                scan_config.checksum_max_size,
+                scan_config.concurrency,
+                algorithm,
+            )
+            .await
+        {
+            Ok(hashed) => {
+                debug!("计算校验和 ({}): {} 个文件", side, hashed);
+                if let Err(e) = state_manager.save_checksums(job_id, side, tree).await {
+                    warn!("保存校验和缓存失败: {}", e);
+                }
+            }
+            Err(e) => warn!("计算校验和失败 ({}): {}", side, e),
+        }
+    }
+
     /// 发送进度更新
     async fn send_progress(&self, tx: &Option<mpsc::Sender<SyncProgress>>, progress: SyncProgress) {
         if let Some(tx) = tx {
@@ -1179,32 +3464,22 @@ impl SyncEngine {
             status: SyncStatus::Failed,
             filesScanned: 0,
             filesCopied: 0,
+            filesCopiedToSource: 0,
+            filesCopiedToDest: 0,
             filesDeleted: 0,
             filesSkipped: 0,
             filesFailed: 0,
             bytesTransferred: 0,
             duration: (end_time - start_time) as u64,
             errors,
+            skippedLocked: Vec::new(),
+            logId: None,
         }
     }
 
     /// 创建取消报告
     fn create_cancelled_report(&self, job_id: &str, start_time: i64) -> SyncReport {
-        let end_time = chrono::Utc::now().timestamp();
-        SyncReport {
-            jobId: job_id.to_string(),
-            startTime: start_time,
-            endTime: end_time,
-            status: SyncStatus::Cancelled,
-            filesScanned: 0,
-            filesCopied: 0,
-            filesDeleted: 0,
-            filesSkipped: 0,
-            filesFailed: 0,
-            bytesTransferred: 0,
-            duration: (end_time - start_time) as u64,
-            errors: vec!["同步已取消".to_string()],
-        }
+        SyncReport::cancelled(job_id, start_time)
     }
 
     /// 记录同步结果到数据库
@@ -1216,11 +3491,15 @@ impl SyncEngine {
         end_time: i64,
         status: &SyncStatus,
         files_scanned: u32,
+        entries_listed: Option<u64>,
         files_copied: u32,
+        files_copied_to_source: u32,
+        files_copied_to_dest: u32,
         files_deleted: u32,
         bytes_transferred: u64,
         error_message: Option<String>,
-    ) {
+        failed_files: &[(String, String)],
+    ) -> Option<i64> {
         let status_str = match status {
             SyncStatus::Completed => "completed",
             SyncStatus::Failed => "failed",
@@ -1230,24 +3509,65 @@ impl SyncEngine {
 
         let result = sqlx::query(
             r#"INSERT INTO sync_logs 
-               (job_id, start_time, end_time, status, files_scanned, files_copied, files_deleted, bytes_transferred, error_message)
-               VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"#
+               (job_id, start_time, end_time, status, files_scanned, entries_listed, files_copied, files_copied_to_source, files_copied_to_dest, files_deleted, bytes_transferred, error_message)
+               VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#
         )
         .bind(job_id)
         .bind(start_time)
         .bind(end_time)
         .bind(status_str)
         .bind(files_scanned as i64)
+        .bind(entries_listed.map(|n| n as i64))
         .bind(files_copied as i64)
+        .bind(files_copied_to_source as i64)
+        .bind(files_copied_to_dest as i64)
         .bind(files_deleted as i64)
         .bind(bytes_transferred as i64)
         .bind(error_message)
         .execute(&*self.db)
         .await;
 
-        if let Err(e) = result {
-            warn!("记录同步日志失败: {}", e);
+        let log_id = match result {
+            Ok(r) => r.last_insert_rowid(),
+            Err(e) => {
+                warn!("记录同步日志失败: {}", e);
+                return None;
+            }
+        };
+
+        // 记录失败的文件，用于之后只重试这些文件
+        for (path, error) in failed_files {
+            let result = sqlx::query(
+                "INSERT INTO sync_log_files (log_id, file_path, error_message) VALUES (?, ?, ?)",
+            )
+            .bind(log_id)
+            .bind(path)
+            .bind(error)
+            .execute(&*self.db)
+            .await;
+            if let Err(e) = result {
+                warn!("记录失败文件失败: {}", e);
+                break;
+            }
         }
+
+        Some(log_id)
+    }
+
+    /// 上次成功同步列出的原始条目总数（源 + 目标），没有记录时为 None
+    async fn last_entries_listed(&self, job_id: &str) -> Option<u64> {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT entries_listed FROM sync_logs
+             WHERE job_id = ? AND status = 'completed' AND entries_listed IS NOT NULL
+             ORDER BY start_time DESC LIMIT 1",
+        )
+        .bind(job_id)
+        .fetch_optional(&*self.db)
+        .await
+        .ok()
+        .flatten()
+        .filter(|n| *n > 0)
+        .map(|n| n as u64)
     }
 
     /// 获取数据库引用
@@ -1255,3 +3575,747 @@ impl SyncEngine {
         &self.db
     }
 }
+
+/// 按本次扫描速度（条目/秒）估计剩余扫描时间；没有预期总数、尚未列出条目或已超过预期总数时无法估计
+fn estimate_scan_eta(
+    scanned: u64,
+    listed: u64,
+    elapsed: Duration,
+    expected_total: Option<u64>,
+) -> Option<u64> {
+    let total = expected_total?;
+    let secs = elapsed.as_secs_f64();
+    if listed == 0 || secs <= 0.0 || scanned >= total {
+        return None;
+    }
+    let rate = listed as f64 / secs;
+    Some(((total - scanned) as f64 / rate).ceil() as u64)
+}
+
+/// 检查存储能力是否满足同步模式，不满足时返回错误信息
+fn check_capabilities(
+    mode: &SyncMode,
+    source: StorageCapabilities,
+    dest: StorageCapabilities,
+) -> Option<String> {
+    if !source.read {
+        return Some("源存储不可读".to_string());
+    }
+    if !dest.write {
+        return Some("目标存储不可写（只读存储或无写入权限）".to_string());
+    }
+    match mode {
+        SyncMode::Mirror if !dest.delete => {
+            Some("镜像模式需要删除目标中的文件，但目标存储不支持删除".to_string())
+        }
+        SyncMode::Bidirectional if !source.write || !dest.read => {
+            Some("双向同步需要源和目标都可读写".to_string())
+        }
+        _ => None,
+    }
+}
+
+/// 把成功执行的动作应用到目标文件树，使其与同步后的目标一致
+///
+/// 复制的文件使用源端的大小和修改时间（下次比较视为相同），不保留校验和
+fn apply_completed_actions(
+    dest_tree: &mut HashMap<String, FileInfo>,
+    source_tree: &HashMap<String, FileInfo>,
+    actions: &[SyncAction],
+) {
+    let put_from_source = |tree: &mut HashMap<String, FileInfo>, source_path: &str, dest_path: &str| {
+        if let Some(src) = source_tree.get(source_path) {
+            tree.insert(
+                dest_path.to_string(),
+                FileInfo {
+                    path: dest_path.to_string(),
+                    checksum: None,
+                    ..src.clone()
+                },
+            );
+        }
+    };
+
+    // 补充新建的父目录
+    let put_parents = |tree: &mut HashMap<String, FileInfo>, dest_path: &str| {
+        let mut parent = dest_path;
+        while let Some((dir, _)) = parent.rsplit_once('/') {
+            if !tree.contains_key(dir) {
+                put_from_source(tree, dir, dir);
+            }
+            parent = dir;
+        }
+    };
+
+    for action in actions {
+        match action {
+            SyncAction::Copy {
+                source_path,
+                dest_path,
+                reverse: false,
+                ..
+            } => {
+                put_from_source(dest_tree, source_path, dest_path);
+                put_parents(dest_tree, dest_path);
+            }
+            SyncAction::Rename { from, to, .. } => {
+                dest_tree.remove(from);
+                put_from_source(dest_tree, to, to);
+                put_parents(dest_tree, to);
+            }
+            SyncAction::Replace { path, .. } => put_from_source(dest_tree, path, path),
+            SyncAction::Delete {
+                path,
+                from_dest: true,
+            } => {
+                dest_tree.remove(path);
+                let dir_prefix = format!("{}/", path);
+                dest_tree.retain(|p, _| !p.starts_with(&dir_prefix));
+            }
+            // 反向复制和删除源文件只影响源端
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+
+    fn test_engine(config: SyncConfig) -> SyncEngine {
+        let db = sqlx::SqlitePool::connect_lazy("sqlite::memory:").unwrap();
+        SyncEngine::with_config(Arc::new(db), config)
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_dir_with_unlisted_file() {
+        let engine = test_engine(SyncConfig::default());
+        let dest = MemoryStorage::new();
+        dest.put("docs/keep.txt", b"data", 0);
+        dest.put_dir("old/sub");
+        // 列表遗漏了该文件（如元数据不可读），目录看起来是空的
+        dest.hide_from_listing("docs/keep.txt");
+
+        let completed = vec![
+            SyncAction::Delete {
+                path: "docs/removed.txt".to_string(),
+                from_dest: true,
+            },
+            SyncAction::Delete {
+                path: "old/sub/removed.txt".to_string(),
+                from_dest: true,
+            },
+        ];
+        engine.prune_empty_dirs(&dest, &completed).await;
+
+        assert_eq!(dest.get("docs/keep.txt").as_deref(), Some(&b"data"[..]));
+        assert!(dest.contains("docs"));
+        assert!(!dest.contains("old/sub") && !dest.contains("old"));
+    }
+
+    fn test_retry_config(max_retries: u32) -> RetryConfig {
+        RetryConfig {
+            max_retries,
+            base_delay_ms: 1,
+            skip_locked: false,
+            budget: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_dest_root_retries() {
+        let cancelled = AtomicBool::new(false);
+
+        // 前两次创建失败（目录被锁定），第三次成功
+        let dest = MemoryStorage::new();
+        dest.set_root_missing(true);
+        dest.fail_create_dir(2);
+        let created = SyncEngine::create_dest_root(&dest, &test_retry_config(3), &cancelled).await;
+        assert_eq!(created, Some(true));
+        assert_eq!(dest.create_dir_calls.load(Ordering::SeqCst), 3);
+
+        // 重试用尽仍失败
+        let dest = MemoryStorage::new();
+        dest.set_root_missing(true);
+        dest.fail_create_dir(10);
+        let created = SyncEngine::create_dest_root(&dest, &test_retry_config(3), &cancelled).await;
+        assert_eq!(created, None);
+        assert_eq!(dest.create_dir_calls.load(Ordering::SeqCst), 4);
+
+        // 创建失败但根目录实际已存在
+        let dest = MemoryStorage::new();
+        dest.fail_create_dir(10);
+        let created = SyncEngine::create_dest_root(&dest, &test_retry_config(3), &cancelled).await;
+        assert_eq!(created, Some(false));
+        assert_eq!(dest.create_dir_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dest_is_empty() {
+        // 顶层列表为空时自动视为空
+        let dest = MemoryStorage::new();
+        assert!(SyncEngine::dest_is_empty(&dest, false, false).await);
+
+        // 目标不为空：忽略手动选项，正常扫描
+        dest.put("docs/a.txt", b"data", 0);
+        assert!(!SyncEngine::dest_is_empty(&dest, false, false).await);
+        assert!(!SyncEngine::dest_is_empty(&dest, false, true).await);
+
+        // 根目录由本次同步创建
+        assert!(SyncEngine::dest_is_empty(&dest, true, false).await);
+    }
+
+    #[tokio::test]
+    async fn test_scan_reporter_counts_raw_entries() {
+        let storage = MemoryStorage::new();
+        for i in 0..10 {
+            storage.put(&format!("dir/{}.txt", i), b"x", 0);
+        }
+        storage.put("skip.tmp", b"x", 0);
+        let listed = Arc::new(ListCounter::default());
+        let config = ScanConfig {
+            exclude_patterns: vec!["*.tmp".to_string()],
+            ..ScanConfig::default()
+        };
+        let scanner = FileScanner::with_config(1, config).with_listed_counter(listed.clone());
+        let reporter = ScanProgressReporter {
+            progress_tx: None,
+            job_id: "job".to_string(),
+            start_time: 0,
+            listed,
+            listed_total: AtomicU64::new(0),
+            partial: AtomicBool::new(false),
+            expected_total: None,
+        };
+
+        // 过滤后 10 个文件，列出的原始条目为 12 个（含目录和被排除的文件）
+        let tree = reporter.scan(&scanner, &storage, String::new(), 0).await.unwrap();
+        assert_eq!(tree.len(), 10);
+        assert_eq!(reporter.entries_listed(), Some(12));
+        let tree = reporter.scan(&scanner, &storage, String::new(), 10).await.unwrap();
+        assert_eq!(tree.len(), 10);
+        assert_eq!(reporter.entries_listed(), Some(24));
+
+        // 有一侧没有扫描时不记录
+        reporter.skip(10);
+        assert_eq!(reporter.entries_listed(), None);
+
+        // 同一口径：预期共 24 个，已列出 12 个，按列出速度估计剩余 12 个的用时
+        assert_eq!(estimate_scan_eta(12, 12, Duration::from_secs(1), Some(24)), Some(1));
+        assert_eq!(estimate_scan_eta(12, 12, Duration::from_secs(12), Some(24)), Some(12));
+        assert_eq!(estimate_scan_eta(24, 12, Duration::from_secs(1), Some(24)), None);
+        assert_eq!(estimate_scan_eta(0, 0, Duration::from_secs(1), Some(24)), None);
+    }
+
+    fn test_job(mode: SyncMode) -> SyncJob {
+        let local: crate::db::StorageConfig =
+            serde_json::from_value(serde_json::json!({ "type": "local" })).unwrap();
+        SyncJob::new("test".to_string(), local.clone(), local, mode, None)
+    }
+
+    #[test]
+    fn test_for_job_applies_job_settings() {
+        let mut job = test_job(SyncMode::Mirror);
+        job.chunkSizeMb = Some(4);
+        job.appendOnly = true;
+        job.maxRemoteOps = Some(3);
+        job.readConcurrency = Some(2);
+        job.writeConcurrency = Some(0);
+        job.caseFold = crate::core::CaseFold::Lower;
+        job.defaultConflictResolution = Some(ConflictResolution::KeepBoth);
+
+        let config = SyncConfig::default().for_job(&job);
+        assert_eq!(config.chunk_size, 4 * 1024 * 1024);
+        assert!(config.append_only);
+        assert_eq!(config.max_remote_ops, Some(3));
+        assert_eq!(config.read_concurrency, Some(2));
+        assert_eq!(config.write_concurrency, None);
+        assert_eq!(config.case_fold, crate::core::CaseFold::Lower);
+        assert_eq!(config.default_conflict_resolution, Some(ConflictResolution::KeepBoth));
+        // 两边不是 S3 兼容存储时不比较 ETag
+        job.compareEtags = true;
+        assert!(!SyncConfig::default().for_job(&job).compare_etags);
+
+        // 双向同步不转换大小写
+        job.syncMode = SyncMode::Bidirectional;
+        let config = SyncConfig::default().for_job(&job);
+        assert_eq!(config.case_fold, crate::core::CaseFold::Preserve);
+    }
+
+    /// 确定性的测试数据（每个位置的字节不同，便于发现错位）
+    fn pattern(len: usize, seed: u8) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8 ^ seed).collect()
+    }
+
+    #[tokio::test]
+    async fn test_append_rejects_rewritten_file_with_same_tail() {
+        let engine = test_engine(SyncConfig::default());
+        let mut job = test_job(SyncMode::Mirror);
+        job.appendOnly = true;
+        let params = engine.transfer_params(&job);
+
+        let old_size = 4 * APPEND_CHECK_BYTES as usize;
+        let source_data = pattern(old_size + 1000, 0);
+        // 目标的开头与源不同，只有末尾 64KB 相同
+        let mut dest_data = pattern(old_size, 0x5a);
+        let tail = old_size - APPEND_CHECK_BYTES as usize;
+        dest_data[tail..].copy_from_slice(&source_data[tail..old_size]);
+
+        let (source, dest) = (MemoryStorage::new(), MemoryStorage::new());
+        source.put("log.txt", &source_data, 0);
+        dest.put("log.txt", &dest_data, 0);
+
+        let result = SyncEngine::try_append(
+            &source,
+            &dest,
+            "log.txt",
+            "log.txt",
+            source_data.len() as u64,
+            None,
+            &params,
+        )
+        .await
+        .unwrap();
+        assert!(result.is_none(), "改写过的文件必须完整复制");
+        assert_eq!(dest.get("log.txt").unwrap(), dest_data);
+    }
+
+    #[tokio::test]
+    async fn test_append_extends_truly_appended_file() {
+        let engine = test_engine(SyncConfig::default());
+        let mut job = test_job(SyncMode::Mirror);
+        job.appendOnly = true;
+        let params = engine.transfer_params(&job);
+
+        let old_size = 4 * APPEND_CHECK_BYTES as usize;
+        let source_data = pattern(old_size + 1000, 0);
+        let (source, dest) = (MemoryStorage::new(), MemoryStorage::new());
+        source.put("log.txt", &source_data, 0);
+        dest.put("log.txt", &source_data[..old_size], 0);
+
+        let result = SyncEngine::try_append(
+            &source,
+            &dest,
+            "log.txt",
+            "log.txt",
+            source_data.len() as u64,
+            None,
+            &params,
+        )
+        .await
+        .unwrap();
+        assert!(result.is_some());
+        assert_eq!(dest.get("log.txt").unwrap(), source_data);
+    }
+
+    fn copy_action(path: &str, size: usize) -> SyncAction {
+        SyncAction::Copy {
+            source_path: path.to_string(),
+            dest_path: path.to_string(),
+            size: size as u64,
+            reverse: false,
+        }
+    }
+
+    fn delete_action(path: &str) -> SyncAction {
+        SyncAction::Delete {
+            path: path.to_string(),
+            from_dest: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quick_hash_ranged_matches_full_hash() {
+        let storage = MemoryStorage::new();
+        for (i, len) in [0usize, 1, 65536, 65537, 1_000_003].into_iter().enumerate() {
+            let data = pattern(len, i as u8);
+            let path = format!("f{}", i);
+            storage.put(&path, &data, 0);
+            let ranged = calculate_quick_hash_ranged(&storage, &path, len as u64).await.unwrap();
+            assert_eq!(ranged, calculate_quick_hash(&data), "len = {}", len);
+        }
+        assert_eq!(storage.full_reads.load(Ordering::SeqCst), 0);
+        // 大小与记录的不一致（文件已变化）时报错
+        assert!(calculate_quick_hash_ranged(&storage, "f1", 2).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_detect_renames_uses_ranged_reads() {
+        let len = 1024 * 1024;
+        let moved = pattern(len, 1);
+        let (source, dest) = (MemoryStorage::new(), MemoryStorage::new());
+        source.put("new/a.bin", &moved, 0);
+        source.put("new/b.bin", &pattern(len, 2), 0);
+        dest.put("old/a.bin", &moved, 0);
+        // 大小相同但内容不同，不能当作移动
+        dest.put("old/c.bin", &pattern(len, 3), 0);
+        let dest_tree: HashMap<String, FileInfo> = dest
+            .list_files(None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.path.clone(), f))
+            .collect();
+
+        let mut actions = vec![
+            copy_action("new/a.bin", len),
+            copy_action("new/b.bin", len),
+            delete_action("old/a.bin"),
+            delete_action("old/c.bin"),
+        ];
+        let renamed = SyncEngine::detect_renames(&mut actions, &dest_tree, &source, &dest).await;
+
+        assert_eq!(renamed, 1);
+        assert!(actions.iter().any(|a| matches!(a,
+            SyncAction::Rename { from, to, .. } if from == "old/a.bin" && to == "new/a.bin")));
+        assert!(actions.iter().any(|a| matches!(a,
+            SyncAction::Copy { source_path, .. } if source_path == "new/b.bin")));
+        assert!(actions.iter().any(|a| matches!(a,
+            SyncAction::Delete { path, .. } if path == "old/c.bin")));
+        assert!(!actions.iter().any(|a| matches!(a,
+            SyncAction::Delete { path, .. } if path == "old/a.bin")));
+        // 只读取了采样块，没有下载整个文件
+        assert_eq!(source.full_reads.load(Ordering::SeqCst), 0);
+        assert_eq!(dest.full_reads.load(Ordering::SeqCst), 0);
+        assert!(source.range_bytes.load(Ordering::SeqCst) < (len / 4) as u64);
+    }
+
+    #[tokio::test]
+    async fn test_retry_actions_fold_dest_paths() {
+        let (source, dest) = (MemoryStorage::new(), MemoryStorage::new());
+        source.put("Docs/A.txt", b"new", 0);
+        source.put("Docs/a.txt", b"other", 0);
+        dest.put("docs/a.txt", b"old", 0);
+        dest.put("docs/gone.txt", b"old", 0);
+        let paths = vec![
+            "Docs/a.txt".to_string(),
+            "Docs/A.txt".to_string(),
+            "docs/gone.txt".to_string(),
+        ];
+
+        let actions = SyncEngine::plan_retry_actions(
+            &source,
+            &dest,
+            &paths,
+            &SyncMode::Mirror,
+            CaseFold::Lower,
+        )
+        .await;
+
+        // 转换后重复：只保留排序最前的 Docs/A.txt
+        assert!(matches!(&actions[0], SyncAction::Skip { path } if path == "Docs/a.txt"));
+        assert!(matches!(&actions[1],
+            SyncAction::Copy { source_path, dest_path, .. }
+                if source_path == "Docs/A.txt" && dest_path == "docs/a.txt"));
+        // 转换大小写时不删除（可能是已转换的源文件对应的目标）
+        assert!(matches!(&actions[2], SyncAction::Skip { .. }));
+
+        let actions = SyncEngine::plan_retry_actions(
+            &source,
+            &dest,
+            &paths[2..],
+            &SyncMode::Mirror,
+            CaseFold::Preserve,
+        )
+        .await;
+        assert!(matches!(&actions[0], SyncAction::Delete { path, .. } if path == "docs/gone.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_delete_failures_fall_back_to_retry() {
+        let engine = test_engine(SyncConfig::default());
+        let params = engine.transfer_params(&test_job(SyncMode::Mirror));
+        let (source, dest) = (MemoryStorage::new(), MemoryStorage::new());
+        dest.set_batch_deletes(true);
+        for path in ["a.txt", "b.txt", "c.txt"] {
+            dest.put(path, b"data", 0);
+        }
+        dest.fail_delete("b.txt", 1);
+        let actions = ["a.txt", "b.txt", "c.txt"].map(delete_action).to_vec();
+
+        let stats = TransferStats::default();
+        let completed = RwLock::new(Vec::new());
+        let remaining = engine
+            .delete_in_batches(actions, &source, &dest, &stats, &completed, &params)
+            .await;
+
+        // 成功的删除逐个计入进度，失败的作为普通动作返回
+        assert_eq!(stats.files_completed.load(Ordering::Relaxed), 2);
+        assert_eq!(completed.read().await.len(), 2);
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].path(), "b.txt");
+
+        // 返回的动作按普通动作带重试执行
+        let cancelled = AtomicBool::new(false);
+        let result = SyncEngine::execute_action_with_retry(
+            &remaining[0],
+            &source,
+            &dest,
+            test_retry_config(2),
+            &cancelled,
+            "job",
+            None,
+            params,
+        )
+        .await;
+        assert!(result.is_ok());
+        assert!(!dest.contains("b.txt"));
+    }
+
+    #[test]
+    fn test_protect_patterns_cover_replaced_dirs() {
+        // 源中 dir 为文件，目标中为包含受保护文件的目录
+        let mut dest_tree = HashMap::new();
+        for (path, is_dir) in [("dir", true), ("dir/keep.txt", false), ("other", true)] {
+            dest_tree.insert(
+                path.to_string(),
+                FileInfo {
+                    path: path.to_string(),
+                    size: 0,
+                    modified_time: 0,
+                    is_dir,
+                    checksum: None,
+                    hidden: false,
+                },
+            );
+        }
+        let replace = |path: &str| SyncAction::Replace {
+            path: path.to_string(),
+            size: 4,
+            source_is_dir: false,
+        };
+        let mut actions = vec![replace("dir"), replace("other"), delete_action("dir/keep.txt")];
+        let patterns = vec!["dir/keep.txt".to_string()];
+
+        let protected = SyncEngine::apply_protect_patterns(&mut actions, &dest_tree, &patterns);
+        assert_eq!(protected, 2);
+        assert!(matches!(&actions[0], SyncAction::Skip { path } if path == "dir"));
+        assert!(matches!(&actions[1], SyncAction::Replace { path, .. } if path == "other"));
+        assert!(matches!(&actions[2], SyncAction::Skip { path } if path == "dir/keep.txt"));
+    }
+
+    #[test]
+    fn test_unconfirmed_replacements_are_skipped() {
+        let replace = |path: &str| SyncAction::Replace {
+            path: path.to_string(),
+            size: 4,
+            source_is_dir: false,
+        };
+        let mut actions = vec![replace("a"), replace("b"), delete_action("c"), delete_action("d")];
+        let confirmed = ["b", "d"].iter().map(|p| p.to_string()).collect();
+
+        assert_eq!(SyncEngine::apply_confirmed_deletes(&mut actions, &confirmed), 2);
+        assert!(matches!(&actions[0], SyncAction::Skip { path } if path == "a"));
+        assert!(matches!(&actions[1], SyncAction::Replace { path, .. } if path == "b"));
+        assert!(matches!(&actions[2], SyncAction::Skip { path } if path == "c"));
+        assert!(matches!(&actions[3], SyncAction::Delete { path, .. } if path == "d"));
+    }
+
+    #[tokio::test]
+    async fn test_identical_conflicts_compared_in_chunks() {
+        let (source, dest) = (MemoryStorage::new(), MemoryStorage::new());
+        let data = vec![7u8; CONFLICT_COMPARE_CHUNK as usize + 10];
+        let mut changed = data.clone();
+        *changed.last_mut().unwrap() = 8;
+        source.put("same.bin", &data, 100);
+        dest.put("same.bin", &data, 200);
+        source.put("diff.bin", &data, 100);
+        dest.put("diff.bin", &changed, 200);
+
+        let conflict = |path: &str, size: u64| {
+            let info = FileInfo {
+                path: path.to_string(),
+                size,
+                modified_time: 0,
+                is_dir: false,
+                checksum: None,
+                hidden: false,
+            };
+            SyncAction::Conflict {
+                path: path.to_string(),
+                source_info: Some(info.clone()),
+                dest_info: Some(info),
+                conflict_type: ConflictType::BothModified,
+            }
+        };
+        let size = data.len() as u64;
+        let mut actions = vec![conflict("same.bin", size), conflict("diff.bin", 0)];
+
+        let resolved = SyncEngine::resolve_identical_conflicts(&mut actions, &source, &dest).await;
+        assert_eq!(resolved, 1);
+        assert!(matches!(&actions[0], SyncAction::Skip { path } if path == "same.bin"));
+        assert!(matches!(&actions[1], SyncAction::Conflict { .. }));
+        // 只按范围读取，不读取整个文件
+        assert_eq!(source.full_reads.load(Ordering::SeqCst), 0);
+        assert_eq!(dest.full_reads.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_retry_error_reports_retries_made() {
+        let engine = test_engine(SyncConfig::default());
+        let params = engine.transfer_params(&test_job(SyncMode::Mirror));
+        let (source, dest) = (MemoryStorage::new(), MemoryStorage::new());
+        dest.put("a.txt", b"data", 0);
+        dest.fail_delete("a.txt", 10);
+        let cancelled = AtomicBool::new(false);
+
+        // 重试全部用完
+        let err = SyncEngine::execute_action_with_retry(
+            &delete_action("a.txt"),
+            &source,
+            &dest,
+            test_retry_config(2),
+            &cancelled,
+            "job",
+            None,
+            params.clone(),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(err.retries, 2);
+
+        // 重试预算用尽时提前停止，只计实际进行的重试
+        let retry_config = RetryConfig {
+            budget: Some(Arc::new(AtomicU32::new(1))),
+            ..test_retry_config(5)
+        };
+        let err = SyncEngine::execute_action_with_retry(
+            &delete_action("a.txt"),
+            &source,
+            &dest,
+            retry_config,
+            &cancelled,
+            "job",
+            None,
+            params,
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(err.retries, 1);
+        assert!(err.message.contains("a.txt"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_locked_files_block_deferred_deletes() {
+        let stats = TransferStats::default();
+        assert_eq!(stats.files_not_done(), 0);
+        // 被占用而跳过的文件同样没有复制，延后的删除不能执行
+        stats.files_locked.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(stats.files_not_done(), 1);
+        stats.files_failed.fetch_add(2, Ordering::Relaxed);
+        assert_eq!(stats.files_not_done(), 3);
+    }
+
+    #[test]
+    fn test_timed_out_report_keeps_partial_counts() {
+        let stats = TransferStats::default();
+        stats.files_completed.fetch_add(5, Ordering::Relaxed);
+        stats.files_copied_to_dest.fetch_add(3, Ordering::Relaxed);
+        stats.files_copied_to_source.fetch_add(1, Ordering::Relaxed);
+        stats.files_failed.fetch_add(2, Ordering::Relaxed);
+        stats.bytes_transferred.fetch_add(4096, Ordering::Relaxed);
+
+        let mut report = SyncReport::cancelled("job", 0);
+        stats.fill_report(&mut report);
+        assert_eq!(report.filesCopied, 4);
+        assert_eq!(report.filesCopiedToDest, 3);
+        assert_eq!(report.filesCopiedToSource, 1);
+        assert_eq!(report.filesDeleted, 1);
+        assert_eq!(report.filesFailed, 2);
+        assert_eq!(report.bytesTransferred, 4096);
+    }
+
+    /// 带传输记录表的内存数据库（单连接；关闭外键检查，无需创建任务记录）
+    async fn transfer_db() -> Arc<sqlx::SqlitePool> {
+        let db = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&db).await.unwrap();
+        sqlx::query("PRAGMA foreign_keys = OFF").execute(&db).await.unwrap();
+        Arc::new(db)
+    }
+
+    #[tokio::test]
+    async fn test_reopen_partial_download() {
+        let path = std::env::temp_dir().join(format!("synctools_test_{}.tmp", uuid::Uuid::new_v4()));
+        let data = pattern(1000, 7);
+        std::fs::write(&path, &data).unwrap();
+
+        assert!(SyncEngine::reopen_partial_download(&path, 0).await.unwrap().is_none());
+        // 临时文件比记录的进度短：从头下载
+        assert!(SyncEngine::reopen_partial_download(&path, 2000).await.unwrap().is_none());
+
+        // 截断到记录的进度，hash 覆盖已下载部分，写入位置在末尾
+        let (mut file, hasher) = SyncEngine::reopen_partial_download(&path, 600)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(hasher.finalize(), blake3::hash(&data[..600]));
+        {
+            use tokio::io::AsyncWriteExt;
+            file.write_all(b"next").await.unwrap();
+            file.flush().await.unwrap();
+        }
+        drop(file);
+        let content = std::fs::read(&path).unwrap();
+        assert_eq!(&content[..600], &data[..600]);
+        assert_eq!(&content[600..], b"next");
+
+        let _ = std::fs::remove_file(&path);
+        assert!(SyncEngine::reopen_partial_download(&path, 10).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_load_download_state_resumes_and_drops_stale() {
+        let manager = TransferManager::new(transfer_db().await);
+        let naming = TempFileNaming {
+            prefix: format!("synctools_test_{}_", uuid::Uuid::new_v4().simple()),
+            keep_extension: true,
+        };
+        let source = MemoryStorage::new();
+        let data = pattern(1000, 3);
+        source.put("big.bin", &data, 100);
+
+        let load = || SyncEngine::load_download_state(&manager, &naming, "job", &source, "big.bin", 1000);
+        let first = load().await.unwrap();
+        assert_eq!(first.id, TransferManager::download_transfer_id("job", "big.bin", 1000, 100));
+        assert_eq!(first.transferred_size, 0);
+
+        // 同一版本：从记录的进度继续
+        manager.update_progress(&first.id, 500).await.unwrap();
+        let resumed = load().await.unwrap();
+        assert_eq!(resumed.id, first.id);
+        assert_eq!(resumed.transferred_size, 500);
+
+        // 源文件变化：使用新的记录，旧记录和临时文件删除
+        let old_temp = naming.temp_path(&first.id, "big.bin");
+        std::fs::write(&old_temp, &data[..500]).unwrap();
+        source.put("big.bin", &data, 200);
+        let changed = load().await.unwrap();
+        assert_ne!(changed.id, first.id);
+        assert_eq!(changed.transferred_size, 0);
+        assert!(manager.get_transfer(&first.id).await.unwrap().is_none());
+        assert!(!old_temp.exists());
+
+        // 已完成的记录不再续接
+        manager.update_progress(&changed.id, 1000).await.unwrap();
+        manager.mark_completed(&changed.id).await.unwrap();
+        let restarted = load().await.unwrap();
+        assert_eq!(restarted.id, changed.id);
+        assert_eq!(restarted.transferred_size, 0);
+
+        // 源文件不存在时不使用断点
+        assert!(
+            SyncEngine::load_download_state(&manager, &naming, "job", &source, "gone.bin", 1000)
+                .await
+                .is_none()
+        );
+    }
+}
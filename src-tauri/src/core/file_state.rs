@@ -1,13 +1,52 @@
 //! 文件状态管理 - 用于增量同步
 
-use anyhow::Result;
+use crate::storage::{FileInfo, Storage};
+use anyhow::{bail, Result};
 use sqlx::SqlitePool;
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 use tracing::debug;
 
+/// 本地文件内容校验和前缀（用于区分 S3/WebDAV 的 ETag）
+pub const LOCAL_CHECKSUM_PREFIX: &str = "blake3:";
+/// 本地文件 MD5 校验和前缀（用于与 S3 ETag 比较）
+pub const MD5_CHECKSUM_PREFIX: &str = "md5:";
+/// Dropbox content_hash 校验和前缀（本地按同一规则计算后可与 Dropbox 比较）
+pub const DROPBOX_CHECKSUM_PREFIX: &str = "dropbox:";
+/// Dropbox content_hash 的分块大小
+const DROPBOX_HASH_BLOCK_SIZE: usize = 4 * 1024 * 1024;
+/// 快速 hash 的采样块大小（头部、中部、尾部各一块）
+const QUICK_HASH_CHUNK_SIZE: usize = 16384;
+/// 不超过该大小的文件计算完整哈希
+pub(crate) const QUICK_HASH_FULL_LIMIT: usize = 65536;
+/// 按范围读取计算完整 hash 时每次读取的大小
+const HASH_READ_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// 本地文件校验和算法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    #[default]
+    Blake3,
+    /// MD5，仅用于与 S3 ETag 比较
+    Md5,
+    /// Dropbox content_hash，仅用于与 Dropbox 比较
+    DropboxContentHash,
+}
+
+impl ChecksumAlgorithm {
+    /// 校验和前缀
+    pub fn prefix(self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Blake3 => LOCAL_CHECKSUM_PREFIX,
+            ChecksumAlgorithm::Md5 => MD5_CHECKSUM_PREFIX,
+            ChecksumAlgorithm::DropboxContentHash => DROPBOX_CHECKSUM_PREFIX,
+        }
+    }
+}
+
 /// 文件状态记录
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FileState {
     pub job_id: String,
     pub file_path: String,
@@ -43,6 +82,15 @@ impl From<FileStateRow> for FileState {
     }
 }
 
+/// 校验和缓存数据库行
+#[derive(Debug, sqlx::FromRow)]
+struct FileChecksumRow {
+    file_path: String,
+    file_size: i64,
+    modified_time: i64,
+    checksum: String,
+}
+
 /// 文件状态管理器
 pub struct FileStateManager {
     db: Arc<SqlitePool>,
@@ -157,16 +205,117 @@ impl FileStateManager {
         Ok(())
     }
 
-    /// 删除任务的所有文件状态
+    /// 批量删除文件状态，返回删除的记录数
+    pub async fn delete_file_states(&self, job_id: &str, file_paths: &[String]) -> Result<u64> {
+        let mut tx = self.db.begin().await?;
+        let mut deleted = 0;
+        for path in file_paths {
+            deleted += sqlx::query("DELETE FROM file_states WHERE job_id = ? AND file_path = ?")
+                .bind(job_id)
+                .bind(path)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+        tx.commit().await?;
+
+        Ok(deleted)
+    }
+
+    /// 删除任务的所有文件状态（含校验和缓存）
     pub async fn delete_job_states(&self, job_id: &str) -> Result<u64> {
         let result = sqlx::query("DELETE FROM file_states WHERE job_id = ?")
             .bind(job_id)
             .execute(&*self.db)
             .await?;
+        sqlx::query("DELETE FROM file_checksums WHERE job_id = ?")
+            .bind(job_id)
+            .execute(&*self.db)
+            .await?;
 
         Ok(result.rows_affected())
     }
 
+    /// 删除任务已不存在的文件状态和校验和缓存，返回删除的文件状态数
+    pub async fn delete_orphan_states(&self) -> Result<u64> {
+        let result =
+            sqlx::query("DELETE FROM file_states WHERE job_id NOT IN (SELECT id FROM sync_jobs)")
+                .execute(&*self.db)
+                .await?;
+        sqlx::query("DELETE FROM file_checksums WHERE job_id NOT IN (SELECT id FROM sync_jobs)")
+            .execute(&*self.db)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// 获取已缓存的本地文件校验和（side 为 "source" 或 "dest"）
+    pub async fn get_checksums(&self, job_id: &str, side: &str) -> Result<HashMap<String, FileInfo>> {
+        let rows = sqlx::query_as::<_, FileChecksumRow>(
+            "SELECT file_path, file_size, modified_time, checksum FROM file_checksums WHERE job_id = ? AND side = ?"
+        )
+        .bind(job_id)
+        .bind(side)
+        .fetch_all(&*self.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|r| {
+                let info = FileInfo {
+                    path: r.file_path.clone(),
+                    size: r.file_size as u64,
+                    modified_time: r.modified_time,
+                    is_dir: false,
+                    checksum: Some(r.checksum),
+                    hidden: false,
+                };
+                (r.file_path, info)
+            })
+            .collect())
+    }
+
+    /// 保存文件树中的校验和（替换该任务该侧的全部缓存记录）
+    pub async fn save_checksums(
+        &self,
+        job_id: &str,
+        side: &str,
+        files: &HashMap<String, FileInfo>,
+    ) -> Result<()> {
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query("DELETE FROM file_checksums WHERE job_id = ? AND side = ?")
+            .bind(job_id)
+            .bind(side)
+            .execute(&mut *tx)
+            .await?;
+
+        let mut saved = 0usize;
+        for file in files.values() {
+            let Some(checksum) = &file.checksum else {
+                continue;
+            };
+            sqlx::query(
+                r#"INSERT INTO file_checksums (job_id, side, file_path, file_size, modified_time, checksum)
+                   VALUES (?, ?, ?, ?, ?, ?)"#
+            )
+            .bind(job_id)
+            .bind(side)
+            .bind(&file.path)
+            .bind(file.size as i64)
+            .bind(file.modified_time)
+            .bind(checksum)
+            .execute(&mut *tx)
+            .await?;
+            saved += 1;
+        }
+
+        tx.commit().await?;
+
+        debug!("已缓存 {} 个文件的校验和 ({})", saved, side);
+        Ok(())
+    }
+
     /// 清理不存在的文件状态（文件已被删除）
     pub async fn cleanup_missing(&self, job_id: &str, existing_paths: &[String]) -> Result<u64> {
         if existing_paths.is_empty() {
@@ -203,23 +352,161 @@ pub fn calculate_hash(data: &[u8]) -> String {
     hash.to_hex()[..32].to_string()
 }
 
+/// 计算本地文件的完整内容校验和（流式读取，不将整个文件载入内存）
+pub fn calculate_file_checksum(path: &Path, algorithm: ChecksumAlgorithm) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    match algorithm {
+        ChecksumAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            std::io::copy(&mut file, &mut hasher)?;
+            Ok(format!("{}{}", LOCAL_CHECKSUM_PREFIX, &hasher.finalize().to_hex()[..32]))
+        }
+        ChecksumAlgorithm::Md5 => {
+            use md5::{Digest, Md5};
+            let mut hasher = Md5::new();
+            std::io::copy(&mut file, &mut hasher)?;
+            let hex: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+            Ok(format!("{}{}", MD5_CHECKSUM_PREFIX, hex))
+        }
+        ChecksumAlgorithm::DropboxContentHash => {
+            // 按 4MB 分块计算 SHA-256，再对各块摘要拼接后的结果计算 SHA-256
+            use sha2::{Digest, Sha256};
+            use std::io::Read;
+            let mut hasher = Sha256::new();
+            let mut block = vec![0u8; DROPBOX_HASH_BLOCK_SIZE];
+            loop {
+                let mut filled = 0;
+                while filled < block.len() {
+                    let n = file.read(&mut block[filled..])?;
+                    if n == 0 {
+                        break;
+                    }
+                    filled += n;
+                }
+                if filled == 0 {
+                    break;
+                }
+                hasher.update(Sha256::digest(&block[..filled]));
+                if filled < block.len() {
+                    break;
+                }
+            }
+            let hex: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+            Ok(format!("{}{}", DROPBOX_CHECKSUM_PREFIX, hex))
+        }
+    }
+}
+
+/// 快速 hash 的采样区间（起始位置、长度）：头部 + 中部 + 尾部
+fn quick_hash_samples(len: usize) -> [(usize, usize); 3] {
+    let chunk = QUICK_HASH_CHUNK_SIZE;
+    [(0, chunk), (len / 2 - chunk / 2, chunk), (len - chunk, chunk)]
+}
+
 /// 快速计算文件 hash（基于采样，适用于大文件）
 pub fn calculate_quick_hash(data: &[u8]) -> String {
     let len = data.len();
-    if len <= 65536 {
+    if len <= QUICK_HASH_FULL_LIMIT {
         // 小于 64KB，完整哈希
         return calculate_hash(data);
     }
-    
+
     // 大文件：采样哈希（头部 + 中部 + 尾部 + 大小）
     let mut hasher = blake3::Hasher::new();
-    let chunk_size = 16384; // 16KB
-    
-    hasher.update(&data[..chunk_size]); // 头部
-    hasher.update(&data[len / 2 - chunk_size / 2..len / 2 + chunk_size / 2]); // 中部
-    hasher.update(&data[len - chunk_size..]); // 尾部
+    for (offset, length) in quick_hash_samples(len) {
+        hasher.update(&data[offset..offset + length]);
+    }
     hasher.update(&len.to_le_bytes()); // 文件大小也参与哈希
-    
+
     let hash = hasher.finalize();
     hash.to_hex()[..32].to_string()
 }
+
+/// 按范围分块读取计算完整 hash，结果与 [`calculate_hash`] 相同，不将整个文件载入内存
+pub async fn calculate_hash_ranged(storage: &dyn Storage, path: &str, size: u64) -> Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    let mut offset = 0;
+    while offset < size {
+        let length = (size - offset).min(HASH_READ_CHUNK_SIZE);
+        let data = storage.read_range(path, offset, length).await?;
+        if data.len() as u64 != length {
+            bail!("文件大小已变化: {}", path);
+        }
+        hasher.update(&data);
+        offset += length;
+    }
+    Ok(hasher.finalize().to_hex()[..32].to_string())
+}
+
+/// 按范围读取采样块计算快速 hash，结果与 [`calculate_quick_hash`] 相同，不下载整个文件
+pub async fn calculate_quick_hash_ranged(storage: &dyn Storage, path: &str, size: u64) -> Result<String> {
+    let len = size as usize;
+    if len <= QUICK_HASH_FULL_LIMIT {
+        let data = if len == 0 { Vec::new() } else { storage.read_range(path, 0, size).await? };
+        if data.len() != len {
+            bail!("文件大小已变化: {}", path);
+        }
+        return Ok(calculate_hash(&data));
+    }
+
+    let mut hasher = blake3::Hasher::new();
+    for (offset, length) in quick_hash_samples(len) {
+        let data = storage.read_range(path, offset as u64, length as u64).await?;
+        if data.len() != length {
+            bail!("文件大小已变化: {}", path);
+        }
+        hasher.update(&data);
+    }
+    hasher.update(&len.to_le_bytes());
+
+    let hash = hasher.finalize();
+    Ok(hash.to_hex()[..32].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// 按 Dropbox 公布的算法（https://www.dropbox.com/developers/reference/content-hash）
+    /// 独立计算：每 4MB 一块的 SHA-256 摘要拼接后再计算 SHA-256
+    fn reference_content_hash(data: &[u8]) -> String {
+        let digests: Vec<u8> = data
+            .chunks(DROPBOX_HASH_BLOCK_SIZE)
+            .flat_map(|block| Sha256::digest(block).to_vec())
+            .collect();
+        format!("{}{}", DROPBOX_CHECKSUM_PREFIX, hex(&Sha256::digest(&digests)))
+    }
+
+    #[test]
+    fn test_dropbox_content_hash() {
+        let path = std::env::temp_dir().join(format!("synctools-hash-{}", uuid::Uuid::new_v4()));
+        let hash = |data: &[u8]| {
+            std::fs::write(&path, data).unwrap();
+            calculate_file_checksum(&path, ChecksumAlgorithm::DropboxContentHash).unwrap()
+        };
+
+        // 空文件没有块，结果为空输入的 SHA-256
+        assert_eq!(
+            hash(b""),
+            "dropbox:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        // 单块时为内容摘要的摘要
+        assert_eq!(hash(b"abc"), reference_content_hash(b"abc"));
+        // 刚好一块（不能多出空块）、跨块和多块
+        for len in [
+            DROPBOX_HASH_BLOCK_SIZE,
+            DROPBOX_HASH_BLOCK_SIZE + 1,
+            DROPBOX_HASH_BLOCK_SIZE * 2 + 123,
+        ] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            assert_eq!(hash(&data), reference_content_hash(&data), "长度 {}", len);
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
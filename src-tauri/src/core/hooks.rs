@@ -0,0 +1,248 @@
+//! 任务钩子 - 同步前后执行用户指定的命令
+
+use anyhow::{anyhow, Result};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::{info, warn};
+
+/// 默认钩子命令超时（秒）
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 300;
+/// 日志中保留的命令输出长度（字符）
+const MAX_LOGGED_OUTPUT: usize = 4000;
+/// 检查取消标志的间隔（毫秒）
+const CANCEL_POLL_INTERVAL_MS: u64 = 200;
+/// 命令退出后等待输出读取完毕的时间（秒），后台进程仍占用输出管道时不再等待
+const OUTPUT_DRAIN_SECS: u64 = 5;
+
+/// 构建通过系统 shell 执行的命令
+fn shell_command(command: &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    }
+    #[cfg(not(windows))]
+    {
+        use std::os::unix::process::CommandExt;
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        // 在独立的进程组中运行，超时或取消时可以结束命令启动的所有子进程
+        cmd.process_group(0);
+        cmd
+    }
+}
+
+/// 结束命令及其启动的子进程（Unix 结束整个进程组，Windows 结束进程树）
+async fn kill_process_tree(child: &mut tokio::process::Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // 进程组 ID 即命令的进程 ID
+        unsafe {
+            libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+        }
+    }
+    #[cfg(windows)]
+    if let Some(pid) = child.id() {
+        let _ = tokio::process::Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &pid.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await;
+    }
+    let _ = child.kill().await;
+}
+
+/// 在后台读取输出管道的全部内容
+fn read_pipe<R>(pipe: Option<R>) -> tokio::task::JoinHandle<Vec<u8>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf).await;
+        }
+        buf
+    })
+}
+
+/// 等待取消标志被设置（没有标志时永不返回）
+async fn wait_cancelled(cancel_flag: Option<&AtomicBool>) {
+    let Some(flag) = cancel_flag else {
+        return std::future::pending().await;
+    };
+    while !flag.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_millis(CANCEL_POLL_INTERVAL_MS)).await;
+    }
+}
+
+/// 截断过长的输出，只保留末尾（错误信息通常在最后）
+fn tail_output(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    let text = text.trim();
+    let count = text.chars().count();
+    if count <= MAX_LOGGED_OUTPUT {
+        return text.to_string();
+    }
+    let tail: String = text.chars().skip(count - MAX_LOGGED_OUTPUT).collect();
+    format!("...{}", tail)
+}
+
+/// 执行钩子命令，记录输出；退出码非 0、超时或取消时返回错误
+///
+/// `label` 用于日志（如 "前置命令"），`envs` 作为额外的环境变量传给命令；
+/// 超时或 `cancel_flag` 被设置时结束命令及其启动的子进程
+pub async fn run_hook(
+    label: &str,
+    command: &str,
+    envs: &[(&str, String)],
+    timeout: Duration,
+    cancel_flag: Option<&AtomicBool>,
+) -> Result<()> {
+    info!("执行{}: {}", label, command);
+
+    let mut cmd = shell_command(command);
+    cmd.envs(envs.iter().map(|(k, v)| (*k, v.as_str())))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    // 执行中丢弃 future 时结束子进程
+    let mut child = tokio::process::Command::from(cmd)
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow!("{}启动失败: {}", label, e))?;
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let result = tokio::select! {
+        status = child.wait() => status.map_err(|e| anyhow!("{}执行失败: {}", label, e)),
+        _ = tokio::time::sleep(timeout) => {
+            Err(anyhow!("{}超时（{} 秒）", label, timeout.as_secs()))
+        }
+        _ = wait_cancelled(cancel_flag) => Err(anyhow!("{}已取消", label)),
+    };
+    let status = match result {
+        Ok(status) => status,
+        Err(e) => {
+            kill_process_tree(&mut child).await;
+            return Err(e);
+        }
+    };
+
+    let drain = Duration::from_secs(OUTPUT_DRAIN_SECS);
+    let stdout = tokio::time::timeout(drain, stdout)
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .unwrap_or_default();
+    let stderr = tokio::time::timeout(drain, stderr)
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .unwrap_or_default();
+    let stdout = tail_output(&stdout);
+    let stderr = tail_output(&stderr);
+    if !stdout.is_empty() {
+        info!("{}输出: {}", label, stdout);
+    }
+    if !stderr.is_empty() {
+        warn!("{}错误输出: {}", label, stderr);
+    }
+
+    if status.success() {
+        return Ok(());
+    }
+    let code = status
+        .code()
+        .map_or_else(|| "被信号终止".to_string(), |c| format!("退出码 {}", c));
+    if stderr.is_empty() {
+        Err(anyhow!("{}失败（{}）", label, code))
+    } else {
+        Err(anyhow!("{}失败（{}）: {}", label, code, stderr))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    const TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[tokio::test]
+    async fn test_hook_exit_code_and_stderr() {
+        assert!(run_hook("测试命令", "true", &[], TEST_TIMEOUT, None).await.is_ok());
+
+        let err = run_hook("测试命令", "echo broken >&2; exit 3", &[], TEST_TIMEOUT, None)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("退出码 3"), "{}", err);
+        assert!(err.contains("broken"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_hook_receives_env() {
+        let envs = [("SYNCTOOLS_STATUS", "completed".to_string())];
+        let command = r#"test "$SYNCTOOLS_STATUS" = completed"#;
+        assert!(run_hook("测试命令", command, &envs, TEST_TIMEOUT, None).await.is_ok());
+
+        let envs = [("SYNCTOOLS_STATUS", "failed".to_string())];
+        assert!(run_hook("测试命令", command, &envs, TEST_TIMEOUT, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hook_timeout() {
+        let start = Instant::now();
+        let err = run_hook("测试命令", "sleep 30", &[], Duration::from_millis(300), None)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("超时"), "{}", err);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_hook_cancel() {
+        let flag = Arc::new(AtomicBool::new(false));
+        let setter = flag.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            setter.store(true, Ordering::SeqCst);
+        });
+
+        let start = Instant::now();
+        let err = run_hook("测试命令", "sleep 30", &[], TEST_TIMEOUT, Some(&flag))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("已取消"), "{}", err);
+        assert!(start.elapsed() < TEST_TIMEOUT);
+    }
+
+    /// 超时后命令启动的后台进程也被结束
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_hook_timeout_kills_children() {
+        let pid_file =
+            std::env::temp_dir().join(format!("synctools_hook_{}.pid", uuid::Uuid::new_v4()));
+        let command = format!("sleep 30 & echo $! > '{}'; wait", pid_file.display());
+        let result = run_hook("测试命令", &command, &[], Duration::from_millis(500), None).await;
+        assert!(result.is_err());
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap().trim().to_string();
+        let _ = std::fs::remove_file(&pid_file);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        // 进程已不存在，或只剩等待回收的僵尸进程
+        let alive = std::fs::read_to_string(format!("/proc/{}/stat", pid))
+            .map(|stat| !stat.contains(") Z "))
+            .unwrap_or(false);
+        assert!(!alive, "子进程 {} 仍在运行", pid);
+    }
+}
@@ -1,15 +1,29 @@
+pub mod bandwidth;
 pub mod cache;
 pub mod comparator;
 pub mod conflict;
+pub mod dedup;
 pub mod engine;
 pub mod file_state;
+pub mod hooks;
+pub mod rehash;
 pub mod scanner;
+pub mod scheduler;
 pub mod transfer;
+pub mod verify;
 
-pub use cache::{CacheResult, FileListCache};
-pub use comparator::{ActionSummary, CompareConfig, ConflictType, FileComparator, SyncAction};
+pub use bandwidth::{BandwidthLimiter, BandwidthWindow};
+pub use cache::{CacheFormat, CacheResult, CacheStatus, FileListCache};
+pub use comparator::{
+    ActionSummary, CaseFold, CompareConfig, ConflictType, DirSummary, FileComparator, OverwritePolicy,
+    SyncAction, ZeroByteCompare,
+};
 pub use conflict::{ConflictRecord, ConflictResolution, ConflictResolver};
-pub use engine::{SyncConfig, SyncEngine, SyncReport};
+pub use dedup::{DuplicateGroup, DuplicateReport};
+pub use scheduler::{JobLimiter, JobScheduler};
+pub use engine::{SingleFileResult, SyncConfig, SyncEngine, SyncReport};
 pub use file_state::{calculate_hash, calculate_quick_hash, FileState, FileStateManager};
-pub use scanner::{FileScanner, ScanConfig};
-pub use transfer::{TransferManager, TransferState, TransferStatus};
+pub use rehash::RehashReport;
+pub use scanner::{ExcludePreset, FileScanner, ScanConfig};
+pub use transfer::{TempFileNaming, TransferManager, TransferState, TransferStatus};
+pub use verify::{DriftKind, DriftedFile, VerifyReport};
@@ -0,0 +1,245 @@
+//! 重建文件状态 - 按源和目标的当前内容重新计算 hash，修正增量同步的基准
+
+use crate::core::comparator::CaseFold;
+use crate::core::file_state::{calculate_quick_hash_ranged, FileState};
+use crate::storage::{FileInfo, Storage};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{debug, info};
+
+/// 重建结果
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RehashReport {
+    pub files_checked: usize,
+    /// 与当前内容一致、未修改的记录数
+    pub unchanged: usize,
+    /// 重新计算 hash 后更新的记录数
+    pub updated: usize,
+    /// 删除的记录数（源文件已删除、两边内容不一致或无法读取）
+    pub removed: usize,
+}
+
+/// 重建后需要写回的状态
+#[derive(Debug, Default)]
+pub struct RehashChanges {
+    pub updated: Vec<FileState>,
+    pub removed: Vec<String>,
+}
+
+impl RehashChanges {
+    /// 只保留重建期间未被改动的记录（比较开始时的快照和写回前重新加载的状态），
+    /// 避免覆盖期间同步写入的新状态
+    pub fn retain_unchanged(
+        &mut self,
+        snapshot: &HashMap<String, FileState>,
+        current: &HashMap<String, FileState>,
+    ) {
+        let unchanged = |path: &String| snapshot.get(path) == current.get(path);
+        self.updated.retain(|s| unchanged(&s.file_path));
+        self.removed.retain(|p| unchanged(p));
+    }
+}
+
+/// 逐个检查已保存的文件状态：源和目标内容一致时按当前内容更新记录，否则删除记录
+///
+/// 只有两边内容一致的文件才保留记录，保证下次同步按 hash 跳过的文件确实无需传输；
+/// 被删除记录的文件下次同步时按正常比较处理；记录按源路径保存，目标路径按 `case_fold` 转换
+pub async fn rehash_states(
+    source: &dyn Storage,
+    dest: &dyn Storage,
+    source_tree: &HashMap<String, FileInfo>,
+    states: &HashMap<String, FileState>,
+    case_fold: CaseFold,
+    cancel_flag: Option<&AtomicBool>,
+) -> Result<(RehashReport, RehashChanges)> {
+    let mut paths: Vec<&String> = states.keys().collect();
+    paths.sort();
+
+    let mut report = RehashReport::default();
+    let mut changes = RehashChanges::default();
+
+    for path in paths {
+        if cancel_flag.is_some_and(|f| f.load(Ordering::Relaxed)) {
+            return Err(anyhow::anyhow!("操作已取消"));
+        }
+        report.files_checked += 1;
+        let state = &states[path];
+
+        match current_hash(source, dest, source_tree, path, &case_fold.apply(path)).await {
+            Some((info, hash)) => {
+                if state.file_size == info.size as i64
+                    && state.modified_time == info.modified_time
+                    && state.checksum.as_deref() == Some(hash.as_str())
+                {
+                    report.unchanged += 1;
+                    continue;
+                }
+                debug!("更新文件状态: {}", path);
+                changes.updated.push(FileState {
+                    file_size: info.size as i64,
+                    modified_time: info.modified_time,
+                    checksum: Some(hash),
+                    ..state.clone()
+                });
+            }
+            None => changes.removed.push(path.clone()),
+        }
+    }
+
+    report.updated = changes.updated.len();
+    report.removed = changes.removed.len();
+    info!(
+        "文件状态重建完成: 检查 {} 个, 更新 {} 个, 删除 {} 个",
+        report.files_checked, report.updated, report.removed
+    );
+
+    Ok((report, changes))
+}
+
+/// 源和目标内容一致时返回源文件信息和当前 hash，否则返回 None
+///
+/// 只按范围读取采样块计算快速哈希，不下载整个文件
+async fn current_hash<'a>(
+    source: &dyn Storage,
+    dest: &dyn Storage,
+    source_tree: &'a HashMap<String, FileInfo>,
+    path: &str,
+    dest_path: &str,
+) -> Option<(&'a FileInfo, String)> {
+    let Some(info) = source_tree.get(path).filter(|f| !f.is_dir) else {
+        debug!("源文件已不存在: {}", path);
+        return None;
+    };
+    match dest.stat(dest_path).await {
+        Ok(Some(meta)) if !meta.is_dir && meta.size == info.size => {}
+        _ => {
+            debug!("目标文件不存在或大小不同: {}", dest_path);
+            return None;
+        }
+    }
+
+    let (source_hash, dest_hash) = match tokio::join!(
+        calculate_quick_hash_ranged(source, path, info.size),
+        calculate_quick_hash_ranged(dest, dest_path, info.size)
+    ) {
+        (Ok(s), Ok(d)) => (s, d),
+        (Err(e), _) | (_, Err(e)) => {
+            debug!("读取文件失败: {} - {}", path, e);
+            return None;
+        }
+    };
+    if source_hash != dest_hash {
+        debug!("源和目标内容不一致: {}", path);
+        return None;
+    }
+    Some((info, source_hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+
+    fn state(path: &str, size: usize) -> FileState {
+        FileState {
+            job_id: "job".to_string(),
+            file_path: path.to_string(),
+            file_size: size as i64,
+            modified_time: 0,
+            checksum: None,
+            last_sync_time: None,
+        }
+    }
+
+    async fn rehash(
+        source: &MemoryStorage,
+        dest: &MemoryStorage,
+        states: &HashMap<String, FileState>,
+        case_fold: CaseFold,
+    ) -> (RehashReport, RehashChanges) {
+        let tree: HashMap<String, FileInfo> = source
+            .list_files(None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.path.clone(), f))
+            .collect();
+        rehash_states(source, dest, &tree, states, case_fold, None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rehash_updates_matching_and_removes_differing() {
+        let big = vec![7u8; 200_000];
+        let (source, dest) = (MemoryStorage::new(), MemoryStorage::new());
+        source.put("same.bin", &big, 10);
+        dest.put("same.bin", &big, 10);
+        source.put("changed.txt", b"new", 10);
+        dest.put("changed.txt", b"old", 10);
+        source.put("missing.txt", b"data", 10);
+
+        let states: HashMap<String, FileState> = [
+            state("same.bin", big.len()),
+            state("changed.txt", 3),
+            state("missing.txt", 4),
+            state("deleted.txt", 1),
+        ]
+        .into_iter()
+        .map(|s| (s.file_path.clone(), s))
+        .collect();
+        let (report, changes) = rehash(&source, &dest, &states, CaseFold::Preserve).await;
+
+        assert_eq!(report.files_checked, 4);
+        assert_eq!(report.updated, 1);
+        assert_eq!(changes.updated[0].file_path, "same.bin");
+        assert_eq!(changes.updated[0].modified_time, 10);
+        assert!(changes.updated[0].checksum.is_some());
+        let mut removed = changes.removed.clone();
+        removed.sort();
+        assert_eq!(removed, ["changed.txt", "deleted.txt", "missing.txt"]);
+        // 只读取采样块
+        assert_eq!(source.full_reads.load(Ordering::SeqCst), 0);
+        assert_eq!(dest.full_reads.load(Ordering::SeqCst), 0);
+        assert!(dest.range_bytes.load(Ordering::SeqCst) < big.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_rehash_uses_folded_dest_path() {
+        let (source, dest) = (MemoryStorage::new(), MemoryStorage::new());
+        source.put("Docs/Readme.TXT", b"hello", 10);
+        dest.put("docs/readme.txt", b"hello", 10);
+        let states: HashMap<String, FileState> = [state("Docs/Readme.TXT", 5)]
+            .into_iter()
+            .map(|s| (s.file_path.clone(), s))
+            .collect();
+
+        let (report, changes) = rehash(&source, &dest, &states, CaseFold::Lower).await;
+        assert_eq!(report.updated, 1);
+        // 记录仍按源路径保存
+        assert_eq!(changes.updated[0].file_path, "Docs/Readme.TXT");
+
+        let (report, _) = rehash(&source, &dest, &states, CaseFold::Preserve).await;
+        assert_eq!(report.removed, 1);
+    }
+
+    #[test]
+    fn test_retain_unchanged_skips_states_written_meanwhile() {
+        let snapshot: HashMap<String, FileState> = [state("a", 1), state("b", 1)]
+            .into_iter()
+            .map(|s| (s.file_path.clone(), s))
+            .collect();
+        let mut current = snapshot.clone();
+        // 重建期间同步更新了 a
+        current.get_mut("a").unwrap().last_sync_time = Some(100);
+
+        let mut changes = RehashChanges {
+            updated: vec![state("a", 2)],
+            removed: vec!["b".to_string()],
+        };
+        changes.retain_unchanged(&snapshot, &current);
+        assert!(changes.updated.is_empty());
+        assert_eq!(changes.removed, ["b"]);
+    }
+}
@@ -1,13 +1,102 @@
-use crate::storage::{FileInfo, Storage};
+use crate::storage::{FileInfo, ListCounter, Storage};
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Semaphore;
 use tracing::{debug, info, warn};
 
+/// 默认扫描并发数
+pub const DEFAULT_SCAN_CONCURRENCY: usize = 8;
+/// 删除文件的回收目录（位于存储根目录）
+pub const TRASH_DIR: &str = ".synctools-trash";
+/// 旧版本文件的保存目录（位于存储根目录）
+pub const VERSIONS_DIR: &str = ".synctools-versions";
+/// 工具内部维护目录，始终不参与扫描和同步（不受用户排除规则影响）
+pub const INTERNAL_DIRS: [&str; 2] = [TRASH_DIR, VERSIONS_DIR];
+
+/// 排除规则预设，展开后与任务自定义的排除规则合并
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExcludePreset {
+    /// 代码仓库：版本控制目录、依赖目录、临时文件
+    #[default]
+    Code,
+    /// 媒体库：系统缩略图和元数据文件、未完成的下载
+    Media,
+    /// 文档：Office/LibreOffice 锁文件、编辑器临时文件
+    Documents,
+    /// 不使用预设
+    None,
+}
+
+impl ExcludePreset {
+    /// 预设名称（与序列化名称一致）
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Code => "code",
+            Self::Media => "media",
+            Self::Documents => "documents",
+            Self::None => "none",
+        }
+    }
+
+    /// 按名称解析
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "code" => Some(Self::Code),
+            "media" => Some(Self::Media),
+            "documents" => Some(Self::Documents),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    /// 预设包含的排除规则
+    pub fn patterns(self) -> &'static [&'static str] {
+        match self {
+            Self::Code => &[
+                ".git/**",
+                ".svn/**",
+                "node_modules/**",
+                ".DS_Store",
+                "Thumbs.db",
+                "*.tmp",
+                "*.temp",
+                "~*",
+            ],
+            Self::Media => &[
+                ".DS_Store",
+                "._*",
+                "Thumbs.db",
+                "desktop.ini",
+                ".picasa.ini",
+                "@eaDir/**",
+                ".thumbnails/**",
+                "*.part",
+                "*.crdownload",
+                "*.tmp",
+            ],
+            Self::Documents => &[
+                ".DS_Store",
+                "Thumbs.db",
+                "desktop.ini",
+                "~*",
+                ".~lock.*",
+                "*.swp",
+                "*.tmp",
+                "*.temp",
+            ],
+            Self::None => &[],
+        }
+    }
+}
+
 /// 文件扫描器配置
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ScanConfig {
     /// 是否包含目录
     pub include_dirs: bool,
@@ -17,26 +106,59 @@ pub struct ScanConfig {
     pub max_file_size: u64,
     /// 仅包含的扩展名（空表示不限制）
     pub include_extensions: Vec<String>,
+    /// 扫描并发数（限速 API 可调低，本地高速磁盘可调高）
+    pub concurrency: usize,
+    /// 列表分页大小提示（None 表示使用后端默认值，仅 S3/WebDAV 支持）
+    pub list_page_size: Option<usize>,
+    /// 是否为本地存储计算并缓存内容校验和
+    pub local_checksums: bool,
+    /// 计算校验和的最大文件大小（0 表示不限制）
+    pub checksum_max_size: u64,
+    /// 目标为 S3 时计算本地源文件的 MD5 并与 ETag 比较（分段上传的 ETag 无法比较）
+    pub etag_checksums: bool,
+    /// 是否跳过隐藏文件（"." 开头的文件/目录，以及 Windows 隐藏/系统属性文件）
+    pub skip_hidden: bool,
+    /// 单次列出的最大条目数（包括目录和被排除的文件，None 表示不限制），在列出过程中检查
+    pub max_tree_files: Option<usize>,
+    /// 单侧扫描的最长时间（秒，None 表示不限制）
+    pub scan_timeout_secs: Option<u64>,
 }
 
 impl Default for ScanConfig {
     fn default() -> Self {
         Self {
             include_dirs: false,
-            exclude_patterns: vec![
-                // 常见的排除模式
-                ".git/**".to_string(),
-                ".svn/**".to_string(),
-                "node_modules/**".to_string(),
-                ".DS_Store".to_string(),
-                "Thumbs.db".to_string(),
-                "*.tmp".to_string(),
-                "*.temp".to_string(),
-                "~*".to_string(),
-            ],
+            exclude_patterns: ExcludePreset::default()
+                .patterns()
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
             max_file_size: 0,
             include_extensions: vec![],
+            concurrency: DEFAULT_SCAN_CONCURRENCY,
+            list_page_size: None,
+            local_checksums: false,
+            checksum_max_size: 0,
+            etag_checksums: false,
+            skip_hidden: false,
+            max_tree_files: None,
+            scan_timeout_secs: None,
+        }
+    }
+}
+
+impl ScanConfig {
+    /// 使用预设展开的排除规则，再追加自定义规则（去重，保持顺序）
+    pub fn with_excludes(mut self, preset: ExcludePreset, extra: &[String]) -> Self {
+        let mut patterns: Vec<String> = preset.patterns().iter().map(|p| p.to_string()).collect();
+        for pattern in extra {
+            let pattern = pattern.trim();
+            if !pattern.is_empty() && !patterns.iter().any(|p| p == pattern) {
+                patterns.push(pattern.to_string());
+            }
         }
+        self.exclude_patterns = patterns;
+        self
     }
 }
 
@@ -45,6 +167,10 @@ pub struct FileScanner {
     max_concurrent: usize,
     config: ScanConfig,
     cancel_flag: Option<Arc<AtomicBool>>,
+    /// 已列出的条目数（扫描进度），由调用方读取
+    listed: Option<Arc<ListCounter>>,
+    /// 与传输共享的远程操作并发上限，每次列出存储占用一个许可
+    ops_limiter: Option<Arc<Semaphore>>,
 }
 
 impl FileScanner {
@@ -53,6 +179,8 @@ impl FileScanner {
             max_concurrent,
             config: ScanConfig::default(),
             cancel_flag: None,
+            listed: None,
+            ops_limiter: None,
         }
     }
 
@@ -61,18 +189,40 @@ impl FileScanner {
             max_concurrent,
             config,
             cancel_flag: None,
+            listed: None,
+            ops_limiter: None,
         }
     }
 
     /// 创建带取消标志的扫描器
     pub fn with_cancel(cancel_flag: Arc<AtomicBool>) -> Self {
         Self {
-            max_concurrent: 8,
+            max_concurrent: DEFAULT_SCAN_CONCURRENCY,
             config: ScanConfig::default(),
             cancel_flag: Some(cancel_flag),
+            listed: None,
+            ops_limiter: None,
         }
     }
 
+    /// 为已有扫描器附加取消标志
+    pub fn with_cancel_flag(mut self, cancel_flag: Arc<AtomicBool>) -> Self {
+        self.cancel_flag = Some(cancel_flag);
+        self
+    }
+
+    /// 附加已列出条目的计数器，列出文件期间实时累加（计数器的上限优先于 `max_tree_files`）
+    pub fn with_listed_counter(mut self, listed: Arc<ListCounter>) -> Self {
+        self.listed = Some(listed);
+        self
+    }
+
+    /// 附加与传输共享的远程操作并发上限（None 表示不限制）
+    pub fn with_ops_limiter(mut self, limiter: Option<Arc<Semaphore>>) -> Self {
+        self.ops_limiter = limiter;
+        self
+    }
+
     /// 检查是否已取消
     fn is_cancelled(&self) -> bool {
         self.cancel_flag
@@ -83,8 +233,12 @@ impl FileScanner {
 
     /// 检查路径是否应该被排除
     fn should_exclude(&self, path: &str) -> bool {
+        if is_internal_path(path) {
+            return true;
+        }
+
         for pattern in &self.config.exclude_patterns {
-            if self.matches_pattern(path, pattern) {
+            if matches_glob(path, pattern) {
                 return true;
             }
         }
@@ -115,45 +269,36 @@ impl FileScanner {
         false
     }
 
-    /// 简单的 glob 模式匹配
-    fn matches_pattern(&self, path: &str, pattern: &str) -> bool {
-        let path = path.to_lowercase();
-        let pattern = pattern.to_lowercase();
-
-        // 处理 ** 通配符
-        if pattern.contains("**") {
-            let parts: Vec<&str> = pattern.split("**").collect();
-            if parts.len() == 2 {
-                let prefix = parts[0].trim_end_matches('/');
-                let suffix = parts[1].trim_start_matches('/');
-
-                if prefix.is_empty() && suffix.is_empty() {
-                    return true;
-                }
-
-                if !prefix.is_empty() && !path.starts_with(prefix) {
-                    return false;
-                }
-
-                if !suffix.is_empty() && !path.ends_with(suffix) {
-                    return false;
-                }
-
-                return true;
+    /// 扫描整个存储，超过配置的扫描时间上限时返回超时错误
+    ///
+    /// 超时后中止列出，在阻塞线程中运行的本地目录遍历不会在后台继续
+    pub async fn scan_storage_with_timeout(
+        &self,
+        storage: &dyn Storage,
+    ) -> Result<HashMap<String, FileInfo>> {
+        let own_counter;
+        let listed = match &self.listed {
+            Some(listed) => listed.as_ref(),
+            None => {
+                own_counter = self.new_counter();
+                &own_counter
             }
-        }
-
-        // 处理 * 通配符
-        if pattern.contains('*') {
-            let regex_pattern = pattern.replace('.', "\\.").replace('*', ".*");
-
-            if let Ok(re) = regex::Regex::new(&format!("^{}$", regex_pattern)) {
-                return re.is_match(&path);
+        };
+        let Some(secs) = self.config.scan_timeout_secs else {
+            return self.scan_counted(storage, None, listed).await;
+        };
+        let scan = self.scan_counted(storage, None, listed);
+        match tokio::time::timeout(Duration::from_secs(secs), scan).await {
+            Ok(result) => result,
+            Err(_) => {
+                listed.cancel();
+                Err(anyhow::anyhow!(
+                    "扫描超时：{} 超过 {} 秒仍未完成，请缩小同步范围或在扫描设置中调高扫描超时",
+                    storage.name(),
+                    secs
+                ))
             }
         }
-
-        // 精确匹配
-        path == pattern || path.ends_with(&format!("/{}", pattern))
     }
 
     /// 扫描存储并返回文件树
@@ -161,6 +306,24 @@ impl FileScanner {
         &self,
         storage: &dyn Storage,
         prefix: Option<&str>,
+    ) -> Result<HashMap<String, FileInfo>> {
+        match &self.listed {
+            Some(listed) => self.scan_counted(storage, prefix, listed).await,
+            None => self.scan_counted(storage, prefix, &self.new_counter()).await,
+        }
+    }
+
+    /// 按 `max_tree_files` 限制条目数的计数器（没有附加计数器时使用）
+    fn new_counter(&self) -> ListCounter {
+        ListCounter::new(self.config.max_tree_files.map(|n| n as u64))
+    }
+
+    /// 扫描存储，列出的条目累加到 `listed`（条目数上限在列出过程中检查，超出时列表不会完整载入内存）
+    async fn scan_counted(
+        &self,
+        storage: &dyn Storage,
+        prefix: Option<&str>,
+        listed: &ListCounter,
     ) -> Result<HashMap<String, FileInfo>> {
         // 检查是否已取消
         if self.is_cancelled() {
@@ -169,7 +332,14 @@ impl FileScanner {
 
         debug!("开始扫描存储: {}, prefix: {:?}", storage.name(), prefix);
 
-        let files = storage.list_files(prefix).await?;
+        let permit = match &self.ops_limiter {
+            Some(limiter) => Some(limiter.clone().acquire_owned().await?),
+            None => None,
+        };
+        let files = storage
+            .list_files_counted(prefix, self.config.list_page_size, listed)
+            .await?;
+        drop(permit);
         debug!("list_files 返回 {} 个条目", files.len());
 
         // 检查是否已取消
@@ -193,6 +363,13 @@ impl FileScanner {
                 continue;
             }
 
+            // 跳过隐藏文件
+            if self.config.skip_hidden && (file.hidden || is_dot_hidden(&file.path)) {
+                debug!("跳过隐藏文件: {}", file.path);
+                excluded_count += 1;
+                continue;
+            }
+
             // 检查排除规则
             if self.should_exclude(&file.path) {
                 debug!("排除文件: {}", file.path);
@@ -233,9 +410,11 @@ impl FileScanner {
             let permit = semaphore.clone().acquire_owned().await?;
             let storage = storage.clone();
             let scanner_config = self.config.clone();
+            let ops_limiter = self.ops_limiter.clone();
 
             let handle = tokio::spawn(async move {
-                let scanner = FileScanner::with_config(1, scanner_config);
+                let scanner =
+                    FileScanner::with_config(1, scanner_config).with_ops_limiter(ops_limiter);
                 let result = scanner.scan_storage(storage.as_ref(), Some(&path)).await;
                 drop(permit);
                 result
@@ -260,9 +439,159 @@ impl FileScanner {
 impl Default for FileScanner {
     fn default() -> Self {
         Self {
-            max_concurrent: 8,
+            max_concurrent: DEFAULT_SCAN_CONCURRENCY,
             config: ScanConfig::default(),
             cancel_flag: None,
+            listed: None,
+            ops_limiter: None,
+        }
+    }
+}
+
+/// 简单的 glob 模式匹配
+pub fn matches_glob(path: &str, pattern: &str) -> bool {
+    let path = path.to_lowercase();
+    let pattern = pattern.to_lowercase();
+
+    // 处理 ** 通配符
+    if pattern.contains("**") {
+        let parts: Vec<&str> = pattern.split("**").collect();
+        if parts.len() == 2 {
+            let prefix = parts[0].trim_end_matches('/');
+            let suffix = parts[1].trim_start_matches('/');
+
+            if prefix.is_empty() && suffix.is_empty() {
+                return true;
+            }
+
+            if !prefix.is_empty() && !path.starts_with(prefix) {
+                return false;
+            }
+
+            if !suffix.is_empty() && !path.ends_with(suffix) {
+                return false;
+            }
+
+            return true;
+        }
+    }
+
+    // 处理 * 通配符
+    if pattern.contains('*') {
+        let regex_pattern = pattern.replace('.', "\\.").replace('*', ".*");
+
+        if let Ok(re) = regex::Regex::new(&format!("^{}$", regex_pattern)) {
+            return re.is_match(&path);
+        }
+    }
+
+    // 精确匹配
+    path == pattern || path.ends_with(&format!("/{}", pattern))
+}
+
+/// 是否位于工具内部维护目录中（回收目录、版本目录）
+pub fn is_internal_path(path: &str) -> bool {
+    let first = path.trim_start_matches('/').split('/').next().unwrap_or("");
+    INTERNAL_DIRS.contains(&first)
+}
+
+/// 路径中是否有以 "." 开头的部分（Unix 风格的隐藏文件/目录）
+fn is_dot_hidden(path: &str) -> bool {
+    path.split('/').any(|part| part.starts_with('.') && part != "." && part != "..")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_max_tree_files_aborts_listing() {
+        let storage = MemoryStorage::new();
+        for i in 0..10 {
+            storage.put(&format!("dir/{}.txt", i), b"x", 0);
+        }
+        let config = |limit| ScanConfig {
+            max_tree_files: Some(limit),
+            ..ScanConfig::default()
+        };
+
+        // 11 个条目（目录和 10 个文件）
+        let scanner = FileScanner::with_config(1, config(11));
+        assert_eq!(scanner.scan_storage(&storage, None).await.unwrap().len(), 10);
+
+        let scanner = FileScanner::with_config(1, config(5));
+        let err = scanner.scan_storage(&storage, None).await.unwrap_err();
+        assert!(err.to_string().contains("超过上限 5"), "{}", err);
+
+        // 共享的计数器按其自身上限检查
+        let listed = Arc::new(ListCounter::new(Some(5)));
+        let scanner =
+            FileScanner::with_config(1, ScanConfig::default()).with_listed_counter(listed.clone());
+        assert!(scanner.scan_storage(&storage, None).await.is_err());
+        assert_eq!(listed.get(), 11);
+    }
+
+    #[tokio::test]
+    async fn test_scan_timeout_cancels_listing() {
+        let storage = MemoryStorage::new();
+        storage.put("a.txt", b"x", 0);
+        storage.set_list_delay(Duration::from_secs(3));
+        let config = ScanConfig {
+            scan_timeout_secs: Some(1),
+            ..ScanConfig::default()
+        };
+        let listed = Arc::new(ListCounter::default());
+        let scanner = FileScanner::with_config(1, config).with_listed_counter(listed.clone());
+
+        let started = std::time::Instant::now();
+        let err = scanner.scan_storage_with_timeout(&storage).await.unwrap_err();
+        assert!(err.to_string().contains("扫描超时"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(3));
+        // 仍在运行的列出在下一个条目处停止
+        assert!(listed.is_cancelled());
+        assert!(listed.add(1).is_err());
+    }
+
+    #[test]
+    fn test_with_excludes_merges_preset_and_custom_patterns() {
+        let config = ScanConfig::default().with_excludes(
+            ExcludePreset::Media,
+            &["*.tmp".to_string(), " raw/** ".to_string(), String::new()],
+        );
+        // 预设在前，自定义规则去掉空白、空规则和重复项后追加
+        let preset_len = ExcludePreset::Media.patterns().len();
+        assert_eq!(config.exclude_patterns.len(), preset_len + 1);
+        assert_eq!(config.exclude_patterns[preset_len], "raw/**");
+
+        let config = ScanConfig::default().with_excludes(ExcludePreset::None, &[]);
+        assert!(config.exclude_patterns.is_empty());
+
+        // 名称与序列化名称一致
+        for preset in [
+            ExcludePreset::Code,
+            ExcludePreset::Media,
+            ExcludePreset::Documents,
+            ExcludePreset::None,
+        ] {
+            assert_eq!(ExcludePreset::parse(preset.name()), Some(preset));
+            assert_eq!(serde_json::to_value(preset).unwrap(), preset.name());
+        }
+        assert_eq!(ExcludePreset::parse("Code"), None);
+    }
+
+    #[tokio::test]
+    async fn test_scan_applies_exclude_preset() {
+        let storage = MemoryStorage::new();
+        let paths = ["photo.jpg", "._photo.jpg", "@eaDir/thumb.jpg", "movie.mp4.part", "raw/a.cr2"];
+        for path in paths {
+            storage.put(path, b"x", 0);
         }
+        let config =
+            ScanConfig::default().with_excludes(ExcludePreset::Media, &["raw/**".to_string()]);
+        let scanner = FileScanner::with_config(1, config);
+        let files = scanner.scan_storage(&storage, None).await.unwrap();
+        let paths: Vec<_> = files.keys().map(String::as_str).collect();
+        assert_eq!(paths, vec!["photo.jpg"]);
     }
 }
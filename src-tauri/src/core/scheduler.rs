@@ -0,0 +1,418 @@
+//! 定时调度 - cron 表达式解析与调度循环
+
+use crate::db::SyncJob;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+
+/// 预览的触发次数
+pub const PREVIEW_FIRE_COUNT: usize = 5;
+
+/// 解析 cron 表达式
+///
+/// 支持标准 5 段格式（分 时 日 月 周），以及带秒的 6/7 段格式
+pub fn parse_schedule(expr: &str) -> Result<Schedule> {
+    let expr = expr.trim();
+    if expr.is_empty() {
+        return Err(anyhow!("cron 表达式为空"));
+    }
+
+    // cron 库要求首段为秒，标准 5 段格式补 0 秒
+    let normalized = if expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr)
+    } else {
+        expr.to_string()
+    };
+
+    Schedule::from_str(&normalized).map_err(|e| anyhow!("无效的 cron 表达式 '{}': {}", expr, e))
+}
+
+/// 计算接下来的若干次触发时间（Unix 时间戳，秒）
+pub fn next_fire_times(expr: &str, count: usize) -> Result<Vec<i64>> {
+    let schedule = parse_schedule(expr)?;
+    Ok(schedule
+        .upcoming(Utc)
+        .take(count)
+        .map(|t| t.timestamp())
+        .collect())
+}
+
+/// 调度循环最长休眠时间（秒），防止系统休眠或时钟调整后错过触发
+const MAX_SLEEP_SECS: u64 = 60;
+
+/// 已注册的定时任务
+struct ScheduledJob {
+    schedule: Schedule,
+    next_fire: Option<DateTime<Utc>>,
+    /// 被跳过的触发时间
+    skipped: Option<DateTime<Utc>>,
+}
+
+impl ScheduledJob {
+    /// 当前时间之后的下次触发时间（跳过被跳过的那一次）
+    fn upcoming(&self) -> Option<DateTime<Utc>> {
+        let next = self.schedule.upcoming(Utc).next();
+        match next {
+            Some(t) if Some(t) == self.skipped => self.schedule.after(&t).next(),
+            _ => next,
+        }
+    }
+}
+
+/// 定时调度器 - 维护已启用任务的下次触发时间，到点回调
+#[derive(Default)]
+pub struct JobScheduler {
+    jobs: Mutex<HashMap<String, ScheduledJob>>,
+    /// 任务变化时唤醒调度循环重新计算休眠时间
+    changed: Notify,
+}
+
+impl JobScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 按任务当前状态注册或移除（禁用或没有定时表达式时移除）
+    pub fn update_job(&self, job: &SyncJob) {
+        let schedule = match job.schedule.as_deref().map(str::trim) {
+            Some(expr) if job.enabled && !expr.is_empty() => match parse_schedule(expr) {
+                Ok(s) => Some(s),
+                Err(e) => {
+                    warn!("任务 {} 的定时表达式无效，不参与调度: {}", job.name, e);
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        {
+            let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+            match schedule {
+                Some(schedule) => {
+                    // 修改任务时保留已跳过的触发
+                    let skipped = jobs.get(&job.id).and_then(|j| j.skipped);
+                    let mut scheduled = ScheduledJob {
+                        schedule,
+                        next_fire: None,
+                        skipped,
+                    };
+                    scheduled.next_fire = scheduled.upcoming();
+                    debug!("调度任务 {}，下次触发: {:?}", job.name, scheduled.next_fire);
+                    jobs.insert(job.id.clone(), scheduled);
+                }
+                None => {
+                    if jobs.remove(&job.id).is_some() {
+                        debug!("已从调度中移除任务: {}", job.name);
+                    }
+                }
+            }
+        }
+        self.changed.notify_one();
+    }
+
+    /// 从调度中移除任务
+    pub fn remove_job(&self, job_id: &str) {
+        self.jobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(job_id);
+        self.changed.notify_one();
+    }
+
+    /// 任务的下次触发时间（Unix 时间戳，秒），未调度时返回 None
+    pub fn next_run(&self, job_id: &str) -> Option<i64> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(job_id)
+            .and_then(|j| j.next_fire)
+            .map(|t| t.timestamp())
+    }
+
+    /// 跳过任务的下一次触发，之后按计划正常触发
+    ///
+    /// 返回被跳过的触发时间和新的下次触发时间（Unix 时间戳，秒），没有待触发的计划时返回 None
+    pub fn skip_next(&self, job_id: &str) -> Option<(i64, Option<i64>)> {
+        let result = {
+            let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+            let job = jobs.get_mut(job_id)?;
+            let skipped = job.next_fire?;
+            job.skipped = Some(skipped);
+            job.next_fire = job.schedule.after(&skipped).next();
+            info!("跳过任务 {} 的下一次触发: {}", job_id, skipped);
+            (skipped.timestamp(), job.next_fire.map(|t| t.timestamp()))
+        };
+        self.changed.notify_one();
+        Some(result)
+    }
+
+    /// 恢复已保存的跳过记录（启动时调用，已过去的触发时间不受影响）
+    pub fn restore_skip(&self, job_id: &str, skipped_at: i64) {
+        let Some(skipped) = DateTime::from_timestamp(skipped_at, 0) else {
+            return;
+        };
+        {
+            let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(job) = jobs.get_mut(job_id) else {
+                return;
+            };
+            job.skipped = Some(skipped);
+            job.next_fire = job.upcoming();
+        }
+        self.changed.notify_one();
+    }
+
+    /// 取出已到期的任务并推进其下次触发时间，同时返回最近的下次触发时间
+    fn take_due(&self, now: DateTime<Utc>) -> (Vec<String>, Option<DateTime<Utc>>) {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let mut due = Vec::new();
+        for (id, job) in jobs.iter_mut() {
+            if job.next_fire.is_some_and(|t| t <= now) {
+                due.push(id.clone());
+                job.next_fire = job.schedule.after(&now).next();
+            }
+        }
+        let next_wakeup = jobs.values().filter_map(|j| j.next_fire).min();
+        (due, next_wakeup)
+    }
+
+    /// 调度循环：到期时以任务 ID 调用 `on_fire`
+    pub async fn run<F: Fn(String)>(&self, on_fire: F) {
+        info!("定时调度已启动");
+        loop {
+            let now = Utc::now();
+            let (due, next_wakeup) = self.take_due(now);
+            for job_id in due {
+                on_fire(job_id);
+            }
+
+            let max_sleep = Duration::from_secs(MAX_SLEEP_SECS);
+            let sleep = next_wakeup
+                .and_then(|t| (t - now).to_std().ok())
+                .map_or(max_sleep, |d| d.min(max_sleep));
+
+            tokio::select! {
+                _ = tokio::time::sleep(sleep) => {}
+                _ = self.changed.notified() => {}
+            }
+        }
+    }
+}
+
+/// 全局任务并发限制 - 所有同步任务（手动、定时、串联）共享同一个计数
+///
+/// 修改上限后已运行的任务继续运行直到结束，并计入新的上限
+pub struct JobLimiter {
+    state: Mutex<LimiterState>,
+    /// 有任务结束或上限提高时通知排队的任务
+    released: Notify,
+}
+
+struct LimiterState {
+    /// 0 表示不限制
+    max_jobs: usize,
+    running: usize,
+}
+
+/// 任务槽位，释放时唤醒排队的任务
+pub struct JobPermit {
+    limiter: Arc<JobLimiter>,
+}
+
+impl Drop for JobPermit {
+    fn drop(&mut self) {
+        self.limiter.lock().running -= 1;
+        self.limiter.released.notify_waiters();
+    }
+}
+
+impl JobLimiter {
+    /// 创建限制器（`max_jobs` 为 0 表示不限制）
+    pub fn new(max_jobs: usize) -> Self {
+        Self {
+            state: Mutex::new(LimiterState {
+                max_jobs,
+                running: 0,
+            }),
+            released: Notify::new(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LimiterState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 修改任务上限
+    pub fn set_limit(&self, max_jobs: usize) {
+        self.lock().max_jobs = max_jobs;
+        self.released.notify_waiters();
+        info!("同时运行的任务上限: {}", max_jobs);
+    }
+
+    /// 有空闲槽位时立即占用，否则返回 None
+    pub fn try_acquire(self: &Arc<Self>) -> Option<JobPermit> {
+        let mut state = self.lock();
+        if state.max_jobs > 0 && state.running >= state.max_jobs {
+            return None;
+        }
+        state.running += 1;
+        Some(JobPermit {
+            limiter: self.clone(),
+        })
+    }
+
+    /// 等待空闲槽位
+    pub async fn acquire(self: &Arc<Self>) -> JobPermit {
+        loop {
+            // 先注册通知再检查，避免错过检查之后的释放
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+            if let Some(permit) = self.try_acquire() {
+                return permit;
+            }
+            released.await;
+        }
+    }
+
+    /// 正在运行的任务数
+    pub fn running(&self) -> usize {
+        self.lock().running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{StorageConfig, SyncMode};
+
+    fn scheduled_job(schedule: &str) -> SyncJob {
+        let local: StorageConfig =
+            serde_json::from_value(serde_json::json!({ "type": "local" })).unwrap();
+        SyncJob::new(
+            "test".to_string(),
+            local.clone(),
+            local,
+            SyncMode::Mirror,
+            Some(schedule.to_string()),
+        )
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        // 5 段格式补 0 秒，与 6 段格式等价
+        let five = parse_schedule("30 2 * * *").unwrap();
+        let six = parse_schedule("0 30 2 * * *").unwrap();
+        assert_eq!(five.upcoming(Utc).next(), six.upcoming(Utc).next());
+        assert!(parse_schedule("  ").is_err());
+        assert!(parse_schedule("61 * * * *").is_err());
+
+        let times = next_fire_times("*/15 * * * *", PREVIEW_FIRE_COUNT).unwrap();
+        assert_eq!(times.len(), PREVIEW_FIRE_COUNT);
+        assert!(times.windows(2).all(|w| w[1] - w[0] == 15 * 60));
+        assert!(times.iter().all(|t| t % (15 * 60) == 0));
+    }
+
+    #[test]
+    fn test_next_run() {
+        let scheduler = JobScheduler::new();
+        let mut job = scheduled_job("*/5 * * * *");
+        scheduler.update_job(&job);
+        let next = scheduler.next_run(&job.id).unwrap();
+        assert_eq!(next % 300, 0);
+        assert!(next > Utc::now().timestamp() && next <= Utc::now().timestamp() + 300);
+
+        // 禁用、清空或表达式无效时不参与调度
+        job.enabled = false;
+        scheduler.update_job(&job);
+        assert_eq!(scheduler.next_run(&job.id), None);
+        job.enabled = true;
+        job.schedule = Some("not cron".to_string());
+        scheduler.update_job(&job);
+        assert_eq!(scheduler.next_run(&job.id), None);
+    }
+
+    #[test]
+    fn test_skip_next_survives_job_update_and_restart() {
+        const DAY: i64 = 24 * 3600;
+        let scheduler = JobScheduler::new();
+        let job = scheduled_job("0 3 * * *");
+        scheduler.update_job(&job);
+        let first = scheduler.next_run(&job.id).unwrap();
+
+        let (skipped, next) = scheduler.skip_next(&job.id).unwrap();
+        assert_eq!(skipped, first);
+        assert_eq!(next, Some(first + DAY));
+        // 修改任务后仍然跳过
+        scheduler.update_job(&job);
+        assert_eq!(scheduler.next_run(&job.id), Some(first + DAY));
+
+        // 重启后从保存的记录恢复
+        let restarted = JobScheduler::new();
+        restarted.update_job(&job);
+        assert_eq!(restarted.next_run(&job.id), Some(first));
+        restarted.restore_skip(&job.id, skipped);
+        assert_eq!(restarted.next_run(&job.id), Some(first + DAY));
+        // 已过去的跳过记录不影响之后的触发
+        restarted.restore_skip(&job.id, first - DAY);
+        assert_eq!(restarted.next_run(&job.id), Some(first));
+
+        assert_eq!(scheduler.skip_next("missing"), None);
+    }
+
+    /// 错过多次触发（如系统休眠）后只补触发一次，下次触发时间从当前时间算起
+    #[test]
+    fn test_missed_runs_fire_once() {
+        let scheduler = JobScheduler::new();
+        let job = scheduled_job("* * * * *");
+        scheduler.update_job(&job);
+
+        let woke_at = Utc::now() + chrono::Duration::hours(3);
+        let (due, next_wakeup) = scheduler.take_due(woke_at);
+        assert_eq!(due, vec![job.id.clone()]);
+        let next = scheduler.next_run(&job.id).unwrap();
+        assert!(next > woke_at.timestamp() && next <= woke_at.timestamp() + 60);
+        assert_eq!(next_wakeup.map(|t| t.timestamp()), Some(next));
+
+        let (due, _) = scheduler.take_due(woke_at);
+        assert!(due.is_empty());
+    }
+
+    /// 降低上限后，已运行的任务仍计入新的上限
+    #[tokio::test]
+    async fn test_lowered_limit_counts_running_jobs() {
+        let limiter = Arc::new(JobLimiter::new(3));
+        let first = limiter.try_acquire().unwrap();
+        let second = limiter.try_acquire().unwrap();
+
+        limiter.set_limit(1);
+        assert!(limiter.try_acquire().is_none());
+        drop(first);
+        assert!(limiter.try_acquire().is_none());
+        drop(second);
+        let third = limiter.try_acquire().unwrap();
+        assert_eq!(limiter.running(), 1);
+
+        // 提高上限时唤醒排队的任务
+        let waiter = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire().await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiter.is_finished());
+        limiter.set_limit(0);
+        let fourth = tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(limiter.running(), 2);
+        drop((third, fourth));
+        assert_eq!(limiter.running(), 0);
+    }
+}
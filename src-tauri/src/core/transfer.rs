@@ -3,10 +3,140 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
+/// 流式传输临时文件的默认名称前缀
+pub const DEFAULT_TEMP_FILE_PREFIX: &str = "synctools_";
+/// 临时文件名中保留的原扩展名最大长度
+const MAX_TEMP_EXTENSION_LEN: usize = 16;
+/// 清理遗留临时文件时跳过最近修改过的文件（秒），避免删除正在传输的文件
+pub const TEMP_SWEEP_MIN_AGE_SECS: u64 = 3600;
+
+/// 流式传输临时文件的命名方式（位于系统临时目录）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TempFileNaming {
+    pub prefix: String,
+    /// 在 `.tmp` 前保留原文件的扩展名（便于安全软件按名称放行）
+    pub keep_extension: bool,
+}
+
+impl Default for TempFileNaming {
+    fn default() -> Self {
+        Self {
+            prefix: DEFAULT_TEMP_FILE_PREFIX.to_string(),
+            keep_extension: false,
+        }
+    }
+}
+
+impl TempFileNaming {
+    /// 临时文件路径：`<前缀><id>[.<原扩展名>].tmp`
+    pub fn temp_path(&self, id: &str, file_path: &str) -> PathBuf {
+        let name = match self.keep_extension.then(|| file_extension(file_path)).flatten() {
+            Some(ext) => format!("{}{}.{}.tmp", self.prefix, id, ext),
+            None => format!("{}{}.tmp", self.prefix, id),
+        };
+        std::env::temp_dir().join(name)
+    }
+
+    /// 清理系统临时目录中遗留的临时文件（程序中途退出时未删除），返回删除的数量
+    ///
+    /// `keep` 中的传输 ID（断点续传仍在使用）和最近 `min_age` 内修改过的文件保留
+    pub fn sweep(&self, keep: &HashSet<String>, min_age: Duration) -> usize {
+        remove_temp_files_in(&std::env::temp_dir(), &self.prefixes(), min_age, |id| {
+            !keep.contains(id)
+        })
+    }
+
+    /// 删除指定传输的临时文件（传输记录已删除时调用），返回删除的数量
+    pub fn remove_temp_files(&self, ids: &HashSet<String>) -> usize {
+        if ids.is_empty() {
+            return 0;
+        }
+        remove_temp_files_in(&std::env::temp_dir(), &self.prefixes(), Duration::ZERO, |id| {
+            ids.contains(id)
+        })
+    }
+
+    /// 可能出现在临时文件名中的前缀（修改前缀之前的文件使用默认前缀）
+    fn prefixes(&self) -> [&str; 2] {
+        [self.prefix.as_str(), DEFAULT_TEMP_FILE_PREFIX]
+    }
+}
+
+/// 临时文件名中的传输 ID（`<前缀><id>[.<原扩展名>].tmp`），不是本程序生成的临时文件时返回 None
+fn temp_file_id<'a>(name: &'a str, prefixes: &[&str]) -> Option<&'a str> {
+    prefixes.iter().filter(|p| !p.is_empty()).find_map(|prefix| {
+        let rest = name.strip_prefix(prefix)?.strip_suffix(".tmp")?;
+        // ID 中没有 '.'，之后的部分是保留的原扩展名
+        let id = rest.split('.').next()?;
+        is_transfer_id(id).then_some(id)
+    })
+}
+
+/// 是否为临时文件使用的传输 ID（UUID 或下载断点 ID）
+fn is_transfer_id(id: &str) -> bool {
+    uuid::Uuid::parse_str(id).is_ok()
+        || id
+            .strip_prefix("dl-")
+            .is_some_and(|hash| hash.len() == 32 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// 删除目录中按任一前缀命名、传输 ID 满足 `should_remove` 的临时文件，返回删除的数量
+///
+/// 最近 `min_age` 内修改过的文件保留
+fn remove_temp_files_in(
+    dir: &Path,
+    prefixes: &[&str],
+    min_age: Duration,
+    should_remove: impl Fn(&str) -> bool,
+) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(id) = name.to_str().and_then(|n| temp_file_id(n, prefixes)) else {
+            continue;
+        };
+        if !should_remove(id) {
+            continue;
+        }
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        let age = meta.modified().ok().and_then(|t| t.elapsed().ok());
+        if !meta.is_file() || age.map_or(true, |age| age < min_age) {
+            continue;
+        }
+        match std::fs::remove_file(entry.path()) {
+            Ok(()) => removed += 1,
+            Err(e) => tracing::debug!("删除临时文件失败: {:?} - {}", entry.path(), e),
+        }
+    }
+    if removed > 0 {
+        tracing::info!("已删除 {} 个传输临时文件", removed);
+    }
+    removed
+}
+
+/// 文件扩展名（只保留由字母和数字组成的扩展名，避免特殊字符进入临时文件名）
+fn file_extension(file_path: &str) -> Option<&str> {
+    let name = file_path.rsplit(['/', '\\']).next()?;
+    let (stem, ext) = name.rsplit_once('.')?;
+    let valid = !stem.is_empty()
+        && !ext.is_empty()
+        && ext.len() <= MAX_TEMP_EXTENSION_LEN
+        && ext.chars().all(|c| c.is_ascii_alphanumeric());
+    valid.then_some(ext)
+}
+
 /// 传输状态
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TransferStatus {
@@ -226,18 +356,64 @@ impl TransferManager {
         Ok(result.rows_affected())
     }
 
-    /// 清理所有传输记录
-    pub async fn cleanup_all(&self, job_id: &str) -> Result<u64> {
-        let result = sqlx::query("DELETE FROM transfer_states WHERE job_id = ?")
-            .bind(job_id)
-            .execute(&*self.db)
-            .await?;
+    /// 清理所有传输记录，返回删除的传输 ID（用于删除对应的临时文件）
+    pub async fn cleanup_all(&self, job_id: &str) -> Result<HashSet<String>> {
+        let ids: Vec<String> =
+            sqlx::query_scalar("DELETE FROM transfer_states WHERE job_id = ? RETURNING id")
+                .bind(job_id)
+                .fetch_all(&*self.db)
+                .await?;
 
         // 清理缓存
         let mut cache = self.cache.write().await;
         cache.retain(|_, v| v.job_id != job_id);
 
-        Ok(result.rows_affected())
+        Ok(ids.into_iter().collect())
+    }
+
+    /// 清理任务已不存在的传输记录，返回删除的传输 ID
+    pub async fn cleanup_orphans(&self) -> Result<HashSet<String>> {
+        let ids: Vec<String> = sqlx::query_scalar(
+            "DELETE FROM transfer_states WHERE job_id NOT IN (SELECT id FROM sync_jobs) RETURNING id",
+        )
+        .fetch_all(&*self.db)
+        .await?;
+
+        self.cache.write().await.clear();
+
+        Ok(ids.into_iter().collect())
+    }
+
+    /// 删除同一文件旧版本的下载断点记录（源文件已变化，旧的部分下载不会再续接），返回删除的传输 ID
+    pub async fn remove_stale_downloads(
+        &self,
+        job_id: &str,
+        file_path: &str,
+        current_id: &str,
+    ) -> Result<HashSet<String>> {
+        let ids: Vec<String> = sqlx::query_scalar(
+            "DELETE FROM transfer_states WHERE job_id = ? AND file_path = ? AND id != ? AND id LIKE 'dl-%' RETURNING id",
+        )
+        .bind(job_id)
+        .bind(file_path)
+        .bind(current_id)
+        .fetch_all(&*self.db)
+        .await?;
+
+        let mut cache = self.cache.write().await;
+        for id in &ids {
+            cache.remove(id);
+        }
+
+        Ok(ids.into_iter().collect())
+    }
+
+    /// 所有传输记录的 ID（对应的断点续传临时文件需要保留）
+    pub async fn transfer_ids(&self) -> Result<HashSet<String>> {
+        let ids: Vec<String> = sqlx::query_scalar("SELECT id FROM transfer_states")
+            .fetch_all(&*self.db)
+            .await?;
+        Ok(ids.into_iter().collect())
     }
 
     /// 获取传输状态
@@ -260,6 +436,18 @@ impl TransferManager {
         Ok(row.map(|r| r.into()))
     }
 
+    /// 下载断点的传输 ID（同一源文件的同一版本始终得到相同 ID，临时文件按此命名）
+    pub fn download_transfer_id(
+        job_id: &str,
+        file_path: &str,
+        total_size: u64,
+        modified_time: i64,
+    ) -> String {
+        let key = format!("{}\0{}\0{}\0{}", job_id, file_path, total_size, modified_time);
+        let hash = blake3::hash(key.as_bytes()).to_hex();
+        format!("dl-{}", &hash[..32])
+    }
+
     /// 创建新的传输状态
     pub fn create_transfer_state(job_id: &str, file_path: &str, total_size: u64) -> TransferState {
         TransferState {
@@ -276,3 +464,90 @@ impl TransferManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_extension() {
+        assert_eq!(file_extension("dir/photo.jpg"), Some("jpg"));
+        assert_eq!(file_extension("dir\\Photo.PNG"), Some("PNG"));
+        assert_eq!(file_extension("archive.tar.gz"), Some("gz"));
+        // 没有扩展名、点文件、以点结尾
+        assert_eq!(file_extension("dir/README"), None);
+        assert_eq!(file_extension("dir.d/README"), None);
+        assert_eq!(file_extension(".bashrc"), None);
+        assert_eq!(file_extension("file."), None);
+        // 过长或含特殊字符的扩展名
+        assert_eq!(file_extension("a.abcdefghijklmnopq"), None);
+        assert_eq!(file_extension("a.abcdefghijklmnop"), Some("abcdefghijklmnop"));
+        assert_eq!(file_extension("a.t-x"), None);
+        assert_eq!(file_extension("a.tx t"), None);
+        assert_eq!(file_extension("a.日本"), None);
+    }
+
+    #[test]
+    fn test_temp_path() {
+        let naming = TempFileNaming {
+            prefix: "st_".to_string(),
+            keep_extension: true,
+        };
+        let name = |p: PathBuf| p.file_name().unwrap().to_str().unwrap().to_string();
+        assert_eq!(name(naming.temp_path("id1", "a/b.mkv")), "st_id1.mkv.tmp");
+        assert_eq!(name(naming.temp_path("id1", "a/.hidden")), "st_id1.tmp");
+        assert_eq!(name(naming.temp_path("id1", "a/b.t-x")), "st_id1.tmp");
+
+        let naming = TempFileNaming::default();
+        assert_eq!(name(naming.temp_path("id1", "a/b.mkv")), "synctools_id1.tmp");
+    }
+
+    #[test]
+    fn test_temp_file_id() {
+        let uuid = uuid::Uuid::new_v4().to_string();
+        let dl = TransferManager::download_transfer_id("job", "a.txt", 1, 0);
+        let prefixes = ["st_", DEFAULT_TEMP_FILE_PREFIX];
+
+        assert_eq!(temp_file_id(&format!("st_{}.tmp", uuid), &prefixes), Some(uuid.as_str()));
+        assert_eq!(temp_file_id(&format!("st_{}.mkv.tmp", dl), &prefixes), Some(dl.as_str()));
+        // 修改前缀之前的旧文件
+        assert_eq!(
+            temp_file_id(&format!("synctools_{}.tmp", dl), &prefixes),
+            Some(dl.as_str())
+        );
+        // 不是本程序生成的文件
+        assert_eq!(temp_file_id("synctools_notes.tmp", &prefixes), None);
+        assert_eq!(temp_file_id(&format!("other_{}.tmp", uuid), &prefixes), None);
+        assert_eq!(temp_file_id(&format!("st_{}.part", uuid), &prefixes), None);
+    }
+
+    #[test]
+    fn test_remove_temp_files_in() {
+        let dir = std::env::temp_dir().join(format!("synctools-sweep-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let stale = format!("st_{}.tmp", uuid::Uuid::new_v4());
+        let kept_id = TransferManager::download_transfer_id("job", "a.txt", 1, 0);
+        let kept = format!("st_{}.bin.tmp", kept_id);
+        let unrelated = "st_notes.tmp";
+        for name in [stale.as_str(), kept.as_str(), unrelated] {
+            std::fs::write(dir.join(name), b"data").unwrap();
+        }
+
+        let keep = HashSet::from([kept_id]);
+        // 最近修改的文件不删除
+        let stale_only = |id: &str| !keep.contains(id);
+        assert_eq!(remove_temp_files_in(&dir, &["st_"], Duration::from_secs(3600), stale_only), 0);
+        assert_eq!(remove_temp_files_in(&dir, &["st_"], Duration::ZERO, stale_only), 1);
+        assert!(!dir.join(&stale).exists());
+        assert!(dir.join(&kept).exists());
+        assert!(dir.join(unrelated).exists());
+
+        // 按 ID 删除时不受修改时间限制
+        let kept_id = TransferManager::download_transfer_id("job", "a.txt", 1, 0);
+        let by_id = |id: &str| id == kept_id;
+        assert_eq!(remove_temp_files_in(&dir, &["st_"], Duration::ZERO, by_id), 1);
+        assert!(!dir.join(&kept).exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
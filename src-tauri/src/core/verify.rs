@@ -0,0 +1,188 @@
+//! 目标校验 - 按已保存的文件状态检查目标存储是否仍与上次同步一致
+
+use crate::core::comparator::CaseFold;
+use crate::core::file_state::{calculate_quick_hash_ranged, FileState};
+use crate::storage::Storage;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{debug, info};
+
+/// 不一致的原因
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftKind {
+    /// 目标中已不存在
+    Missing,
+    /// 大小与上次同步不同
+    SizeMismatch,
+    /// 内容校验和与上次同步不同
+    ChecksumMismatch,
+    /// 读取失败，无法确认
+    Unreadable,
+}
+
+/// 与上次同步不一致的文件
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriftedFile {
+    pub path: String,
+    pub kind: DriftKind,
+    pub expected_size: u64,
+    /// 目标中的实际大小（不存在或无法读取时为 None）
+    pub actual_size: Option<u64>,
+    /// 附加说明（读取失败时为错误信息）
+    pub detail: Option<String>,
+}
+
+/// 目标校验结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+    pub files_checked: usize,
+    /// 没有保存校验和、只比较了大小的文件数
+    pub size_only: usize,
+    pub drifted: Vec<DriftedFile>,
+}
+
+/// 逐个检查已同步文件在目标中的大小和内容（与同步时相同的采样快速 hash），不修改任何文件
+///
+/// 文件状态按源路径保存，目标路径按 `case_fold` 转换
+pub async fn verify_destination(
+    storage: &dyn Storage,
+    states: &HashMap<String, FileState>,
+    case_fold: CaseFold,
+    cancel_flag: Option<&AtomicBool>,
+) -> Result<VerifyReport> {
+    let mut paths: Vec<&String> = states.keys().collect();
+    paths.sort();
+
+    let mut drifted = Vec::new();
+    let mut files_checked = 0;
+    let mut size_only = 0;
+
+    for path in paths {
+        if cancel_flag.is_some_and(|f| f.load(Ordering::Relaxed)) {
+            return Err(anyhow::anyhow!("操作已取消"));
+        }
+        let state = &states[path];
+        let dest_path = case_fold.apply(path);
+        let expected_size = state.file_size.max(0) as u64;
+        let drift = |kind, actual_size, detail| DriftedFile {
+            path: dest_path.clone(),
+            kind,
+            expected_size,
+            actual_size,
+            detail,
+        };
+        files_checked += 1;
+
+        let meta = match storage.stat(&dest_path).await {
+            Ok(Some(meta)) if !meta.is_dir => meta,
+            Ok(_) => {
+                drifted.push(drift(DriftKind::Missing, None, None));
+                continue;
+            }
+            Err(e) => {
+                drifted.push(drift(DriftKind::Unreadable, None, Some(e.to_string())));
+                continue;
+            }
+        };
+
+        if meta.size != expected_size {
+            drifted.push(drift(DriftKind::SizeMismatch, Some(meta.size), None));
+            continue;
+        }
+
+        let Some(expected_hash) = &state.checksum else {
+            size_only += 1;
+            continue;
+        };
+        // 保存的校验和是采样的快速 hash，只按范围读取采样块，不下载整个文件
+        match calculate_quick_hash_ranged(storage, &dest_path, meta.size).await {
+            Ok(hash) => {
+                if &hash != expected_hash {
+                    debug!("目标文件内容已变化: {}", dest_path);
+                    drifted.push(drift(DriftKind::ChecksumMismatch, Some(meta.size), None));
+                }
+            }
+            Err(e) => {
+                drifted.push(drift(DriftKind::Unreadable, Some(meta.size), Some(e.to_string())));
+            }
+        }
+    }
+
+    info!(
+        "目标校验完成: 检查 {} 个文件, {} 个不一致",
+        files_checked,
+        drifted.len()
+    );
+
+    Ok(VerifyReport {
+        files_checked,
+        size_only,
+        drifted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::file_state::calculate_quick_hash;
+    use crate::storage::memory::MemoryStorage;
+
+    fn states_for(files: &[(&str, &[u8])]) -> HashMap<String, FileState> {
+        files
+            .iter()
+            .map(|(path, data)| {
+                let state = FileState {
+                    job_id: "job".to_string(),
+                    file_path: path.to_string(),
+                    file_size: data.len() as i64,
+                    modified_time: 0,
+                    checksum: Some(calculate_quick_hash(data)),
+                    last_sync_time: None,
+                };
+                (path.to_string(), state)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_verify_uses_folded_dest_path() {
+        let dest = MemoryStorage::new();
+        dest.put("docs/readme.txt", b"hello", 0);
+        dest.put("docs/changed.txt", b"HELLO", 0);
+        let states = states_for(&[("Docs/Readme.txt", &b"hello"[..]), ("Docs/Changed.txt", &b"hello"[..])]);
+
+        let report = verify_destination(&dest, &states, CaseFold::Lower, None).await.unwrap();
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.drifted.len(), 1);
+        assert_eq!(report.drifted[0].path, "docs/changed.txt");
+        assert!(matches!(report.drifted[0].kind, DriftKind::ChecksumMismatch));
+
+        // 不转换时按源路径查找，两个文件都不存在
+        let report = verify_destination(&dest, &states, CaseFold::Preserve, None).await.unwrap();
+        assert!(report.drifted.iter().all(|d| matches!(d.kind, DriftKind::Missing)));
+        assert_eq!(report.drifted.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_verify_reads_only_sampled_ranges() {
+        let data: Vec<u8> = (0..1_000_000u32).map(|i| (i % 253) as u8).collect();
+        let mut changed = data.clone();
+        changed[0] ^= 1;
+        let dest = MemoryStorage::new();
+        dest.put("big.bin", &data, 0);
+        dest.put("changed.bin", &changed, 0);
+        let states = states_for(&[("big.bin", &data[..]), ("changed.bin", &data[..])]);
+
+        let report = verify_destination(&dest, &states, CaseFold::Preserve, None).await.unwrap();
+        assert_eq!(report.drifted.len(), 1);
+        assert_eq!(report.drifted[0].path, "changed.bin");
+        assert!(matches!(report.drifted[0].kind, DriftKind::ChecksumMismatch));
+        assert_eq!(dest.full_reads.load(Ordering::SeqCst), 0);
+        assert!(dest.range_bytes.load(Ordering::SeqCst) < 2 * 65536);
+    }
+}
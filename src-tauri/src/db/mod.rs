@@ -5,6 +5,7 @@ pub use models::*;
 
 use anyhow::Result;
 pub use sqlx::SqlitePool;
+use std::collections::HashSet;
 
 impl SyncJob {
     /// 从数据库加载所有任务
@@ -39,11 +40,21 @@ impl SyncJob {
         let source_config = serde_json::to_string(&self.sourceConfig)?;
         let dest_config = serde_json::to_string(&self.destConfig)?;
         let sync_mode = serde_json::to_string(&self.syncMode)?;
+        let additional_sources = if self.additionalSources.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&self.additionalSources)?)
+        };
+        let exclude_patterns = if self.excludePatterns.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&self.excludePatterns)?)
+        };
 
         sqlx::query(
             r#"
-            INSERT INTO sync_jobs (id, name, source_type, source_config, dest_type, dest_config, sync_mode, schedule, enabled, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO sync_jobs (id, name, source_type, source_config, dest_type, dest_config, sync_mode, schedule, enabled, run_after, run_after_always, max_runtime_secs, additional_sources, chunk_size_mb, stream_threshold_mb, exclude_preset, exclude_patterns, append_only, pre_command, post_command, compare_etags, max_remote_ops, default_conflict_resolution, read_concurrency, write_concurrency, check_before_run, case_fold, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             ON CONFLICT(id) DO UPDATE SET
                 name = excluded.name,
                 source_type = excluded.source_type,
@@ -53,6 +64,24 @@ impl SyncJob {
                 sync_mode = excluded.sync_mode,
                 schedule = excluded.schedule,
                 enabled = excluded.enabled,
+                run_after = excluded.run_after,
+                run_after_always = excluded.run_after_always,
+                max_runtime_secs = excluded.max_runtime_secs,
+                additional_sources = excluded.additional_sources,
+                chunk_size_mb = excluded.chunk_size_mb,
+                stream_threshold_mb = excluded.stream_threshold_mb,
+                exclude_preset = excluded.exclude_preset,
+                exclude_patterns = excluded.exclude_patterns,
+                append_only = excluded.append_only,
+                pre_command = excluded.pre_command,
+                post_command = excluded.post_command,
+                compare_etags = excluded.compare_etags,
+                max_remote_ops = excluded.max_remote_ops,
+                default_conflict_resolution = excluded.default_conflict_resolution,
+                read_concurrency = excluded.read_concurrency,
+                write_concurrency = excluded.write_concurrency,
+                check_before_run = excluded.check_before_run,
+                case_fold = excluded.case_fold,
                 updated_at = excluded.updated_at
             "#
         )
@@ -65,6 +94,24 @@ impl SyncJob {
         .bind(&sync_mode)
         .bind(&self.schedule)
         .bind(self.enabled)
+        .bind(&self.runAfter)
+        .bind(self.runAfterAlways)
+        .bind(self.maxRuntimeSecs.map(|s| s as i64))
+        .bind(&additional_sources)
+        .bind(self.chunkSizeMb.map(|v| v as i64))
+        .bind(self.streamThresholdMb.map(|v| v as i64))
+        .bind(self.excludePreset.name())
+        .bind(&exclude_patterns)
+        .bind(self.appendOnly)
+        .bind(&self.preCommand)
+        .bind(&self.postCommand)
+        .bind(self.compareEtags)
+        .bind(self.maxRemoteOps.map(|v| v as i64))
+        .bind(self.defaultConflictResolution.map(|r| r.to_string()))
+        .bind(self.readConcurrency.map(|v| v as i64))
+        .bind(self.writeConcurrency.map(|v| v as i64))
+        .bind(self.checkBeforeRun)
+        .bind(self.caseFold.name())
         .bind(self.createdAt)
         .bind(self.updatedAt)
         .execute(pool)
@@ -73,6 +120,148 @@ impl SyncJob {
         Ok(())
     }
 
+    /// 校验存储角色：只读存储不能作为目标，也不能用于双向同步
+    pub fn validate_storage_roles(&self) -> Result<()> {
+        if self.destConfig.typ.is_read_only() {
+            return Err(anyhow::anyhow!("只读存储不能作为同步目标"));
+        }
+        if self.syncMode == SyncMode::Bidirectional
+            && (self.sourceConfig.typ.is_read_only()
+                || self.additionalSources.iter().any(|s| s.config.typ.is_read_only()))
+        {
+            return Err(anyhow::anyhow!("只读存储不能用于双向同步"));
+        }
+        self.validate_additional_sources()
+    }
+
+    /// 校验附加源的挂载路径：不能为空，且互不重叠（不能相同或互为父子路径）
+    fn validate_additional_sources(&self) -> Result<()> {
+        let prefixes: Vec<String> = self
+            .additionalSources
+            .iter()
+            .map(|s| {
+                crate::storage::normalize_path(&s.prefix)
+                    .trim_end_matches('/')
+                    .to_string()
+            })
+            .collect();
+        for (i, prefix) in prefixes.iter().enumerate() {
+            if prefix.is_empty() || prefix.split('/').any(|part| part == "..") {
+                return Err(anyhow::anyhow!(
+                    "附加源的挂载路径无效: '{}'",
+                    self.additionalSources[i].prefix
+                ));
+            }
+            for other in &prefixes[i + 1..] {
+                let nested = |a: &str, b: &str| a == b || b.starts_with(&format!("{}/", a));
+                if nested(prefix, other) || nested(other, prefix) {
+                    return Err(anyhow::anyhow!(
+                        "附加源的挂载路径重叠: '{}' 与 '{}'",
+                        prefix,
+                        other
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 源端是否全部为本地存储（本地存储不使用扫描缓存）
+    pub fn source_is_local(&self) -> bool {
+        self.sourceConfig.typ == StorageType::Local
+            && self.additionalSources.iter().all(|s| s.config.typ == StorageType::Local)
+    }
+
+    /// 是否直接比较两边的 ETag（任务开启且两边都是 S3 兼容存储）
+    pub fn compares_etags(&self) -> bool {
+        self.compareEtags
+            && self.sourceConfig.typ.has_content_etag()
+            && self.destConfig.typ.has_content_etag()
+            && self.additionalSources.is_empty()
+    }
+
+    /// 实际使用的目标路径大小写转换（双向同步需要两边路径一一对应，不转换）
+    pub fn effective_case_fold(&self) -> crate::core::CaseFold {
+        if self.syncMode == SyncMode::Bidirectional {
+            crate::core::CaseFold::Preserve
+        } else {
+            self.caseFold
+        }
+    }
+
+    /// 源端缓存键使用的配置（包含附加源，附加源变化时缓存失效）
+    pub fn source_config_json(&self) -> String {
+        let json = if self.additionalSources.is_empty() {
+            serde_json::to_string(&self.sourceConfig)
+        } else {
+            serde_json::to_string(&(&self.sourceConfig, &self.additionalSources))
+        };
+        json.unwrap_or_default()
+    }
+
+    /// 校验前置任务：必须存在，且串联后不能形成循环（A→B→A）
+    pub async fn validate_run_after(pool: &SqlitePool, job_id: &str, run_after: &str) -> Result<()> {
+        let mut visited = std::collections::HashSet::new();
+        let mut current = Some(run_after.to_string());
+
+        while let Some(id) = current {
+            if id == job_id {
+                return Err(anyhow::anyhow!("任务串联存在循环"));
+            }
+            if !visited.insert(id.clone()) {
+                // 已有链路中的循环与本任务无关，停止遍历
+                break;
+            }
+            let job = Self::load(pool, &id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("前置任务不存在: {}", id))?;
+            current = job.runAfter;
+        }
+
+        Ok(())
+    }
+
+    /// 加载以指定任务为前置任务的所有任务
+    pub async fn load_chained(pool: &SqlitePool, run_after: &str) -> Result<Vec<SyncJob>> {
+        let rows = sqlx::query_as::<_, SyncJobRow>("SELECT * FROM sync_jobs WHERE run_after = ?")
+            .bind(run_after)
+            .fetch_all(pool)
+            .await?;
+
+        let mut jobs = Vec::new();
+        for row in rows {
+            jobs.push(row.try_into()?);
+        }
+        Ok(jobs)
+    }
+
+    /// 所有任务 ID
+    pub async fn load_ids(pool: &SqlitePool) -> Result<HashSet<String>> {
+        let ids: Vec<String> = sqlx::query_scalar("SELECT id FROM sync_jobs")
+            .fetch_all(pool)
+            .await?;
+        Ok(ids.into_iter().collect())
+    }
+
+    /// 记录被跳过的定时触发时间（Unix 时间戳，秒）
+    pub async fn save_skipped_run(pool: &SqlitePool, id: &str, skipped_at: i64) -> Result<()> {
+        sqlx::query("UPDATE sync_jobs SET skipped_run_at = ? WHERE id = ?")
+            .bind(skipped_at)
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 所有任务被跳过的定时触发时间（任务 ID，Unix 时间戳）
+    pub async fn load_skipped_runs(pool: &SqlitePool) -> Result<Vec<(String, i64)>> {
+        Ok(sqlx::query_as(
+            "SELECT id, skipped_run_at FROM sync_jobs WHERE skipped_run_at IS NOT NULL",
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+
     /// 从数据库删除
     pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
         sqlx::query("DELETE FROM sync_jobs WHERE id = ?")
@@ -99,6 +288,24 @@ impl SyncJob {
             syncMode,
             schedule,
             enabled: true,
+            runAfter: None,
+            runAfterAlways: false,
+            maxRuntimeSecs: None,
+            additionalSources: Vec::new(),
+            chunkSizeMb: None,
+            streamThresholdMb: None,
+            excludePreset: crate::core::ExcludePreset::default(),
+            excludePatterns: Vec::new(),
+            appendOnly: false,
+            preCommand: None,
+            postCommand: None,
+            compareEtags: false,
+            maxRemoteOps: None,
+            defaultConflictResolution: None,
+            readConcurrency: None,
+            writeConcurrency: None,
+            checkBeforeRun: false,
+            caseFold: crate::core::CaseFold::default(),
             createdAt: now,
             updatedAt: now,
         }
@@ -9,6 +9,113 @@ pub enum StorageType {
     Local,
     S3,
     WebDav,
+    B2,
+    /// 只读 HTTP 源（静态文件服务器 / 目录列表）
+    Http,
+    /// OneDrive / SharePoint（Microsoft Graph）
+    OneDrive,
+    /// Dropbox
+    Dropbox,
+    /// Azure Blob Storage
+    AzBlob,
+    /// Google Cloud Storage
+    Gcs,
+}
+
+impl StorageType {
+    /// 所有支持的存储类型
+    pub const ALL: [StorageType; 9] = [
+        StorageType::Local,
+        StorageType::S3,
+        StorageType::WebDav,
+        StorageType::B2,
+        StorageType::Http,
+        StorageType::OneDrive,
+        StorageType::Dropbox,
+        StorageType::AzBlob,
+        StorageType::Gcs,
+    ];
+
+    /// 显示名称
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            StorageType::Local => "本地磁盘",
+            StorageType::S3 => "S3 兼容存储",
+            StorageType::WebDav => "WebDAV",
+            StorageType::B2 => "Backblaze B2",
+            StorageType::Http => "HTTP（只读）",
+            StorageType::OneDrive => "OneDrive",
+            StorageType::Dropbox => "Dropbox",
+            StorageType::AzBlob => "Azure Blob Storage",
+            StorageType::Gcs => "Google Cloud Storage",
+        }
+    }
+
+    /// 创建存储时必填的配置字段（与 StorageConfig 的序列化字段名一致）
+    pub fn required_fields(&self) -> &'static [&'static str] {
+        match self {
+            StorageType::Local => &["path"],
+            StorageType::S3 => &["bucket", "region", "accessKey", "secretKey"],
+            StorageType::WebDav => &["webdavEndpoint", "username", "password"],
+            StorageType::B2 => &["keyId", "applicationKey", "bucket", "bucketId"],
+            StorageType::Http => &["httpUrl"],
+            StorageType::OneDrive | StorageType::Dropbox => &["clientId", "refreshToken"],
+            StorageType::AzBlob => &["accountName", "bucket"],
+            // 另需 serviceAccountJson 或 accessKey + secretKey
+            StorageType::Gcs => &["bucket"],
+        }
+    }
+
+    /// 是否支持流式上传（否则大文件需要完整读入内存后写入）
+    pub fn supports_streaming(&self) -> bool {
+        matches!(
+            self,
+            StorageType::WebDav
+                | StorageType::B2
+                | StorageType::OneDrive
+                | StorageType::Dropbox
+                | StorageType::AzBlob
+                | StorageType::Gcs
+        )
+    }
+
+    /// 是否支持在存储内重命名（不经过本地传输数据）
+    pub fn supports_rename(&self) -> bool {
+        !self.is_read_only()
+    }
+
+    /// 列出的文件是否带有可比较的内容校验和（本地存储为扫描时计算）
+    ///
+    /// Azure Blob 和 GCS 为对象的 Content-MD5，分块上传的 Azure Blob 没有校验和
+    pub fn has_checksums(&self) -> bool {
+        matches!(
+            self,
+            StorageType::Local
+                | StorageType::S3
+                | StorageType::OneDrive
+                | StorageType::Dropbox
+                | StorageType::AzBlob
+                | StorageType::Gcs
+        )
+    }
+
+    /// 是否为只读存储（只能作为源存储）
+    pub fn is_read_only(&self) -> bool {
+        matches!(self, StorageType::Http)
+    }
+
+    /// ETag 是否由对象内容决定（S3 兼容接口，单段上传时为内容 MD5）
+    pub fn has_content_etag(&self) -> bool {
+        matches!(self, StorageType::S3 | StorageType::Gcs)
+    }
+
+    /// 目录是否真实存在（对象存储的目录只是路径前缀，删除文件后自然消失）
+    pub fn has_real_dirs(&self) -> bool {
+        matches!(
+            self,
+            StorageType::Local | StorageType::WebDav | StorageType::OneDrive | StorageType::Dropbox
+        )
+    }
 }
 
 /// 存储配置
@@ -29,6 +136,9 @@ pub struct StorageConfig {
     pub secretKey: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub endpoint: Option<String>,
+    /// S3 使用路径风格地址（MinIO 等需要），未设置时自定义 endpoint 默认开启
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forcePathStyle: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub prefix: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -39,6 +149,63 @@ pub struct StorageConfig {
     pub password: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub root: Option<String>,
+    /// HTTP 源地址
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub httpUrl: Option<String>,
+    /// B2 应用密钥 ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyId: Option<String>,
+    /// B2 应用密钥
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub applicationKey: Option<String>,
+    /// B2 bucket ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bucketId: Option<String>,
+    /// 挂载标记文件（本地源存储），文件不存在时视为未挂载并中止同步
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mountMarker: Option<String>,
+    /// OAuth 应用 ID（OneDrive 客户端 ID / Dropbox App Key）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clientId: Option<String>,
+    /// OAuth 应用密钥（OneDrive 客户端密钥 / Dropbox App Secret，公共客户端应用不需要）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clientSecret: Option<String>,
+    /// OAuth 刷新令牌（OneDrive / Dropbox）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refreshToken: Option<String>,
+    /// OneDrive 租户（默认 common）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// OneDrive 驱动器 ID（SharePoint 文档库），为空时使用当前用户的 OneDrive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driveId: Option<String>,
+    /// Azure 存储账户名（容器名与 S3 bucket 共用）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accountName: Option<String>,
+    /// Azure 账户密钥
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accountKey: Option<String>,
+    /// Azure SAS 令牌（与账户密钥二选一）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sasToken: Option<String>,
+    /// GCS 服务账号 JSON 凭据（支持 ${file:/path} 引用）；为空时使用 accessKey/secretKey（HMAC）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serviceAccountJson: Option<String>,
+    /// 请求标记（远程存储），附加到 User-Agent 并作为请求头发送，便于服务端识别和限流
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requestTag: Option<String>,
+    /// 对象自定义元数据（S3），上传时附加到每个对象
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub objectMetadata: Option<std::collections::HashMap<String, String>>,
+}
+
+/// 附加源：挂载到合并命名空间的子路径下
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdditionalSource {
+    /// 挂载子路径（不能为空，不能与其他附加源重叠）
+    pub prefix: String,
+    pub config: StorageConfig,
 }
 
 /// 同步模式
@@ -48,6 +215,8 @@ pub enum SyncMode {
     Bidirectional,
     Mirror,
     Backup,
+    /// 补齐：只复制目标中不存在的文件，不覆盖、不删除、不反向复制
+    Seed,
 }
 
 /// 同步状态
@@ -55,6 +224,8 @@ pub enum SyncMode {
 #[serde(rename_all = "lowercase")]
 pub enum SyncStatus {
     Idle,
+    /// 等待全局任务槽位
+    Queued,
     Scanning,
     Comparing,
     Syncing,
@@ -74,6 +245,60 @@ pub struct SyncJob {
     pub syncMode: SyncMode,
     pub schedule: Option<String>,
     pub enabled: bool,
+    /// 前置任务 ID，前置任务成功完成后自动执行本任务
+    #[serde(default)]
+    pub runAfter: Option<String>,
+    /// 前置任务失败时是否仍然执行
+    #[serde(default)]
+    pub runAfterAlways: bool,
+    /// 最长运行时间（秒），超时后自动取消
+    #[serde(default)]
+    pub maxRuntimeSecs: Option<u64>,
+    /// 附加源，与主源合并后同步到同一目标
+    #[serde(default)]
+    pub additionalSources: Vec<AdditionalSource>,
+    /// 分块大小（MB），None 时使用全局传输配置
+    #[serde(default)]
+    pub chunkSizeMb: Option<u64>,
+    /// 流式传输阈值（MB），None 时使用全局传输配置
+    #[serde(default)]
+    pub streamThresholdMb: Option<u64>,
+    /// 排除规则预设
+    #[serde(default)]
+    pub excludePreset: crate::core::ExcludePreset,
+    /// 自定义排除规则（glob），与预设合并
+    #[serde(default)]
+    pub excludePatterns: Vec<String>,
+    /// 仅追加模式（日志类文件）：目标是源的前缀时只上传新增部分
+    #[serde(default)]
+    pub appendOnly: bool,
+    /// 同步前执行的命令，退出码非 0 时不执行同步（需要在设置中允许钩子）
+    #[serde(default)]
+    pub preCommand: Option<String>,
+    /// 同步结束后执行的命令，通过 SYNCTOOLS_STATUS 环境变量获得同步结果
+    #[serde(default)]
+    pub postCommand: Option<String>,
+    /// 两边都是 S3 兼容存储时直接比较 ETag，不再比较大小和修改时间
+    #[serde(default)]
+    pub compareEtags: bool,
+    /// 扫描和传输共享的远程操作并发上限（限流严格的 API 调低），None 时不限制
+    #[serde(default)]
+    pub maxRemoteOps: Option<u32>,
+    /// 没有按路径指定解决方式的冲突使用的默认解决方式，None 时需要手动处理
+    #[serde(default)]
+    pub defaultConflictResolution: Option<crate::core::ConflictResolution>,
+    /// 同时读取源文件的传输数，None 时使用传输并发数
+    #[serde(default)]
+    pub readConcurrency: Option<u32>,
+    /// 同时写入目标的传输数，None 时使用传输并发数
+    #[serde(default)]
+    pub writeConcurrency: Option<u32>,
+    /// 定时触发时先检查存储连接，不可用时稍后重试
+    #[serde(default)]
+    pub checkBeforeRun: bool,
+    /// 目标路径的大小写转换（双向同步时不生效）
+    #[serde(default)]
+    pub caseFold: crate::core::CaseFold,
     pub createdAt: i64,
     pub updatedAt: i64,
 }
@@ -96,6 +321,15 @@ pub struct SyncProgress {
     pub speed: u64,
     pub startTime: i64,
     pub endTime: i64,  // 完成时间（0 表示未完成）
+    /// 扫描阶段剩余时间估计（秒），没有上次同步的条目数时为 None
+    #[serde(default)]
+    pub scanEtaSecs: Option<u64>,
+    /// 已从目标复制到源的文件数（双向同步的反向复制）
+    #[serde(default)]
+    pub filesCopiedToSource: u32,
+    /// 已从源复制到目标的文件数（含移动和替换）
+    #[serde(default)]
+    pub filesCopiedToDest: u32,
 }
 
 /// 同步报告
@@ -130,6 +364,24 @@ pub struct SyncJobRow {
     pub enabled: bool,
     pub created_at: i64,
     pub updated_at: i64,
+    pub run_after: Option<String>,
+    pub run_after_always: bool,
+    pub max_runtime_secs: Option<i64>,
+    pub additional_sources: Option<String>,
+    pub chunk_size_mb: Option<i64>,
+    pub stream_threshold_mb: Option<i64>,
+    pub exclude_preset: Option<String>,
+    pub exclude_patterns: Option<String>,
+    pub append_only: bool,
+    pub pre_command: Option<String>,
+    pub post_command: Option<String>,
+    pub compare_etags: bool,
+    pub max_remote_ops: Option<i64>,
+    pub default_conflict_resolution: Option<String>,
+    pub read_concurrency: Option<i64>,
+    pub write_concurrency: Option<i64>,
+    pub check_before_run: bool,
+    pub case_fold: Option<String>,
 }
 
 impl TryFrom<SyncJobRow> for SyncJob {
@@ -142,11 +394,37 @@ impl TryFrom<SyncJobRow> for SyncJob {
             "bidirectional" => SyncMode::Bidirectional,
             "mirror" => SyncMode::Mirror,
             "backup" => SyncMode::Backup,
+            "seed" => SyncMode::Seed,
             _ => return Err(anyhow::anyhow!("Invalid sync mode: {}", row.sync_mode)),
         };
 
         let source_config: StorageConfig = serde_json::from_str(&row.source_config)?;
         let dest_config: StorageConfig = serde_json::from_str(&row.dest_config)?;
+        let additional_sources = match row.additional_sources.as_deref() {
+            Some(json) if !json.is_empty() => serde_json::from_str(json)?,
+            _ => Vec::new(),
+        };
+        let exclude_preset = match row.exclude_preset.as_deref() {
+            Some(name) => crate::core::ExcludePreset::parse(name)
+                .ok_or_else(|| anyhow::anyhow!("Invalid exclude preset: {}", name))?,
+            None => crate::core::ExcludePreset::default(),
+        };
+        let case_fold = match row.case_fold.as_deref() {
+            Some(name) => crate::core::CaseFold::parse(name)
+                .ok_or_else(|| anyhow::anyhow!("Invalid case fold: {}", name))?,
+            None => crate::core::CaseFold::default(),
+        };
+        let exclude_patterns = match row.exclude_patterns.as_deref() {
+            Some(json) if !json.is_empty() => serde_json::from_str(json)?,
+            _ => Vec::new(),
+        };
+        let default_conflict_resolution = match row.default_conflict_resolution.as_deref() {
+            Some(name) => Some(
+                crate::core::ConflictResolution::parse(name)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid conflict resolution: {}", name))?,
+            ),
+            None => None,
+        };
 
         Ok(SyncJob {
             id: row.id,
@@ -156,6 +434,24 @@ impl TryFrom<SyncJobRow> for SyncJob {
             syncMode: sync_mode,
             schedule: row.schedule,
             enabled: row.enabled,
+            runAfter: row.run_after,
+            runAfterAlways: row.run_after_always,
+            maxRuntimeSecs: row.max_runtime_secs.map(|s| s.max(0) as u64),
+            additionalSources: additional_sources,
+            chunkSizeMb: row.chunk_size_mb.filter(|v| *v > 0).map(|v| v as u64),
+            streamThresholdMb: row.stream_threshold_mb.filter(|v| *v > 0).map(|v| v as u64),
+            excludePreset: exclude_preset,
+            excludePatterns: exclude_patterns,
+            appendOnly: row.append_only,
+            preCommand: row.pre_command,
+            postCommand: row.post_command,
+            compareEtags: row.compare_etags,
+            maxRemoteOps: row.max_remote_ops.filter(|v| *v > 0).map(|v| v as u32),
+            defaultConflictResolution: default_conflict_resolution,
+            readConcurrency: row.read_concurrency.filter(|v| *v > 0).map(|v| v as u32),
+            writeConcurrency: row.write_concurrency.filter(|v| *v > 0).map(|v| v as u32),
+            checkBeforeRun: row.check_before_run,
+            caseFold: case_fold,
             createdAt: row.created_at,
             updatedAt: row.updated_at,
         })
@@ -1,7 +1,7 @@
 use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::SqlitePool;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
@@ -16,16 +16,33 @@ pub mod storage;
 pub use core::{SyncConfig, SyncEngine, SyncReport};
 pub use db::models::{StorageConfig, StorageType, SyncJob, SyncMode};
 
+/// 数据库文件名
+pub const DB_FILE_NAME: &str = "synctools.db";
+
+/// 数据目录及其中数据库的连接池
+struct DataDir {
+    path: PathBuf,
+    pool: Arc<SqlitePool>,
+}
+
 /// 应用状态，在 Tauri 命令中共享
 #[derive(Clone)]
 pub struct AppState {
-    pub db: Arc<SqlitePool>,
+    /// 数据目录和数据库连接池（迁移数据目录时一起替换，通过 [`AppState::config_dir`] 和 [`AppState::db`] 获取）
+    data: Arc<std::sync::RwLock<DataDir>>,
+    /// 同步、分析等长时间操作持有读锁，迁移数据目录时持有写锁
+    data_lock: Arc<tokio::sync::RwLock<()>>,
     pub sync_engine: Arc<Mutex<Option<SyncEngine>>>,
-    pub config_dir: PathBuf,
     /// 同步任务取消信号
     pub cancel_signals: Arc<Mutex<HashMap<String, tokio::sync::oneshot::Sender<()>>>>,
     /// 分析任务取消标志（使用 AtomicBool 便于跨线程检查）
     pub analyze_cancels: Arc<Mutex<HashMap<String, Arc<std::sync::atomic::AtomicBool>>>>,
+    /// 定时调度器
+    pub scheduler: Arc<core::JobScheduler>,
+    /// 全局任务并发限制
+    pub job_limiter: Arc<core::JobLimiter>,
+    /// 全局带宽限制
+    pub bandwidth_limiter: Arc<core::BandwidthLimiter>,
 }
 
 impl AppState {
@@ -49,8 +66,28 @@ impl AppState {
 
         std::fs::create_dir_all(&config_dir)?;
 
-        // 初始化数据库（带连接池配置）
-        let db_path = config_dir.join("synctools.db");
+        let db = Self::open_db(&config_dir).await?;
+        let max_jobs = config::SchedulerConfig::load(&config_dir).max_concurrent_jobs;
+        let bandwidth_schedule = config::TransferConfig::load(&config_dir).bandwidth_schedule;
+
+        Ok(Self {
+            data: Arc::new(std::sync::RwLock::new(DataDir {
+                path: config_dir,
+                pool: Arc::new(db),
+            })),
+            data_lock: Arc::new(tokio::sync::RwLock::new(())),
+            sync_engine: Arc::new(Mutex::new(None)),
+            cancel_signals: Arc::new(Mutex::new(HashMap::new())),
+            analyze_cancels: Arc::new(Mutex::new(HashMap::new())),
+            scheduler: Arc::new(core::JobScheduler::new()),
+            job_limiter: Arc::new(core::JobLimiter::new(max_jobs)),
+            bandwidth_limiter: Arc::new(core::BandwidthLimiter::new(bandwidth_schedule)),
+        })
+    }
+
+    /// 打开数据目录中的数据库（带连接池配置）并运行迁移
+    pub async fn open_db(data_dir: &Path) -> anyhow::Result<SqlitePool> {
+        let db_path = data_dir.join(DB_FILE_NAME);
         // SQLite 连接字符串格式: sqlite://path 或 sqlite:path
         // Windows 路径需要转换反斜杠为正斜杠
         let db_path_str = db_path
@@ -68,13 +105,40 @@ impl AppState {
         // 运行数据库迁移
         sqlx::migrate!("./migrations").run(&db).await?;
 
-        Ok(Self {
-            db: Arc::new(db),
-            sync_engine: Arc::new(Mutex::new(None)),
-            config_dir,
-            cancel_signals: Arc::new(Mutex::new(HashMap::new())),
-            analyze_cancels: Arc::new(Mutex::new(HashMap::new())),
-        })
+        Ok(db)
+    }
+
+    /// 当前数据库连接池
+    pub fn db(&self) -> Arc<SqlitePool> {
+        self.data.read().unwrap_or_else(|e| e.into_inner()).pool.clone()
+    }
+
+    /// 当前数据存储目录（配置、缓存和数据库所在目录）
+    pub fn config_dir(&self) -> PathBuf {
+        self.data.read().unwrap_or_else(|e| e.into_inner()).path.clone()
+    }
+
+    /// 同时替换数据目录和数据库连接池，返回旧连接池（由调用方关闭）
+    pub fn replace_data_dir(&self, path: PathBuf, pool: SqlitePool) -> Arc<SqlitePool> {
+        let mut data = self.data.write().unwrap_or_else(|e| e.into_inner());
+        data.path = path;
+        std::mem::replace(&mut data.pool, Arc::new(pool))
+    }
+
+    /// 开始一个长时间使用数据库的操作（同步、分析、校验等），返回的守卫释放前不能迁移数据目录
+    pub fn begin_task(&self) -> Result<tokio::sync::OwnedRwLockReadGuard<()>, String> {
+        self.data_lock
+            .clone()
+            .try_read_owned()
+            .map_err(|_| "正在迁移数据目录，请稍后再试".to_string())
+    }
+
+    /// 开始迁移数据目录，有操作正在进行时失败
+    pub fn begin_migration(&self) -> Result<tokio::sync::OwnedRwLockWriteGuard<()>, String> {
+        self.data_lock
+            .clone()
+            .try_write_owned()
+            .map_err(|_| "有同步、分析或校验任务正在进行，请等待完成后再迁移数据".to_string())
     }
 
     /// 清理资源（应用关闭时调用）
@@ -101,7 +165,7 @@ impl AppState {
 
         // 3. 关闭数据库连接池
         tracing::debug!("关闭数据库连接池...");
-        self.db.close().await;
+        self.db().close().await;
 
         tracing::info!("资源清理完成");
     }
@@ -4,9 +4,16 @@ use serde::{Deserialize, Serialize};
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, BufWriter, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use tokio::sync::mpsc;
 use tracing_subscriber::fmt::MakeWriter;
 
+/// 日志文件名
+pub const LOG_FILE_NAME: &str = "app.log";
+/// 实时日志通道容量，界面来不及处理时丢弃新的日志行
+pub const LIVE_LOG_CHANNEL_CAPACITY: usize = 1000;
+
 /// 日志配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -93,18 +100,28 @@ impl LogConfig {
     }
 }
 
+/// 共享的日志文件写入器（轮转期间为 None）
+type SharedWriter = Mutex<Option<BufWriter<File>>>;
+
+/// 获取写入器的锁
+///
+/// 某次日志调用 panic 会使锁中毒，此后仍继续使用其中的写入器，避免所有后续日志调用连锁 panic
+fn lock_writer(writer: &SharedWriter) -> MutexGuard<'_, Option<BufWriter<File>>> {
+    writer.lock().unwrap_or_else(|e| e.into_inner())
+}
+
 /// 带大小限制的日志写入器
 pub struct SizeRotatingWriter {
     file_path: PathBuf,
     max_size: u64,
-    writer: Arc<Mutex<Option<BufWriter<File>>>>,
+    writer: Arc<SharedWriter>,
 }
 
 impl SizeRotatingWriter {
     pub fn new(log_dir: &Path, max_size_mb: u32) -> io::Result<Self> {
         fs::create_dir_all(log_dir)?;
         
-        let file_path = log_dir.join("app.log");
+        let file_path = log_dir.join(LOG_FILE_NAME);
         let max_size = (max_size_mb as u64) * 1024 * 1024;
         
         let writer = Self::open_file(&file_path, max_size)?;
@@ -150,13 +167,23 @@ impl SizeRotatingWriter {
         Ok(())
     }
     
+    /// 将缓冲区中的日志写入磁盘（应用退出前调用，避免丢失最后的日志）
+    pub fn flush(&self) -> io::Result<()> {
+        let mut guard = lock_writer(&self.writer);
+        if let Some(ref mut writer) = *guard {
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+        }
+        Ok(())
+    }
+
     /// 检查并轮转日志
     fn check_and_rotate(&self) -> io::Result<()> {
         if self.file_path.exists() {
             if let Ok(metadata) = fs::metadata(&self.file_path) {
                 if metadata.len() > self.max_size {
                     // 需要轮转
-                    let mut writer_guard = self.writer.lock().unwrap();
+                    let mut writer_guard = lock_writer(&self.writer);
                     
                     // 关闭当前写入器
                     if let Some(mut w) = writer_guard.take() {
@@ -188,14 +215,14 @@ impl Clone for SizeRotatingWriter {
 
 /// 日志写入器包装
 pub struct LogWriter {
-    inner: Arc<Mutex<Option<BufWriter<File>>>>,
+    inner: Arc<SharedWriter>,
     file_path: PathBuf,
     max_size: u64,
 }
 
 impl Write for LogWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let mut guard = self.inner.lock().unwrap();
+        let mut guard = lock_writer(&self.inner);
         
         if let Some(ref mut writer) = *guard {
             let result = writer.write(buf)?;
@@ -207,7 +234,7 @@ impl Write for LogWriter {
                 if let Ok(metadata) = fs::metadata(&self.file_path) {
                     if metadata.len() > self.max_size {
                         // 重新获取锁进行轮转
-                        let mut guard = self.inner.lock().unwrap();
+                        let mut guard = lock_writer(&self.inner);
                         if let Some(mut w) = guard.take() {
                             let _ = w.flush();
                         }
@@ -228,7 +255,7 @@ impl Write for LogWriter {
     }
     
     fn flush(&mut self) -> io::Result<()> {
-        let mut guard = self.inner.lock().unwrap();
+        let mut guard = lock_writer(&self.inner);
         if let Some(ref mut writer) = *guard {
             writer.flush()
         } else {
@@ -252,6 +279,118 @@ impl<'a> MakeWriter<'a> for SizeRotatingWriter {
     }
 }
 
+/// 实时日志写入器：把格式化后的日志行发送到有界通道，通道满时丢弃并计数，不阻塞调用方
+#[derive(Clone)]
+pub struct LiveLogWriter {
+    sender: mpsc::Sender<String>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl LiveLogWriter {
+    /// 创建写入器及对应的接收端
+    pub fn channel(capacity: usize) -> (Self, mpsc::Receiver<String>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        let writer = Self {
+            sender,
+            dropped: Arc::new(AtomicU64::new(0)),
+        };
+        (writer, receiver)
+    }
+
+    /// 取出自上次调用以来丢弃的日志行数
+    pub fn take_dropped(&self) -> u64 {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+}
+
+/// 单条日志的缓冲，写入完成（drop）时作为一行发送
+pub struct LiveLogLine {
+    writer: LiveLogWriter,
+    buf: Vec<u8>,
+}
+
+impl Write for LiveLogLine {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LiveLogLine {
+    fn drop(&mut self) {
+        let line = String::from_utf8_lossy(&self.buf).trim_end().to_string();
+        if line.is_empty() {
+            return;
+        }
+        if self.writer.sender.try_send(line).is_err() {
+            self.writer.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for LiveLogWriter {
+    type Writer = LiveLogLine;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LiveLogLine {
+            writer: self.clone(),
+            buf: Vec::new(),
+        }
+    }
+}
+
+/// 是否向界面转发实时日志（调试开关，默认关闭，不保存到配置）
+static LIVE_LOG_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 全局实时日志写入器及尚未被取走的接收端
+type LiveLog = (LiveLogWriter, Mutex<Option<mpsc::Receiver<String>>>);
+
+static LIVE_LOG: OnceLock<LiveLog> = OnceLock::new();
+
+fn live_log() -> &'static LiveLog {
+    LIVE_LOG.get_or_init(|| {
+        let (writer, receiver) = LiveLogWriter::channel(LIVE_LOG_CHANNEL_CAPACITY);
+        (writer, Mutex::new(Some(receiver)))
+    })
+}
+
+/// 开启或关闭实时日志转发
+pub fn set_live_log_enabled(enabled: bool) {
+    LIVE_LOG_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// 实时日志转发是否开启
+pub fn is_live_log_enabled() -> bool {
+    LIVE_LOG_ENABLED.load(Ordering::Relaxed)
+}
+
+/// 取走实时日志的接收端（只能取一次），同时返回写入器用于读取丢弃计数
+pub fn take_live_log_receiver() -> Option<(LiveLogWriter, mpsc::Receiver<String>)> {
+    let (writer, receiver) = live_log();
+    let receiver = receiver.lock().unwrap_or_else(|e| e.into_inner()).take()?;
+    Some((writer.clone(), receiver))
+}
+
+/// 实时日志层：只在开关打开时格式化并转发日志
+pub fn live_log_layer<S>() -> impl tracing_subscriber::Layer<S>
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    use tracing_subscriber::Layer;
+
+    tracing_subscriber::fmt::layer()
+        .with_writer(live_log().0.clone())
+        .with_ansi(false)
+        .with_target(false)
+        .with_thread_ids(false)
+        .with_thread_names(false)
+        .with_filter(tracing_subscriber::filter::filter_fn(|_| is_live_log_enabled()))
+}
+
 /// 获取日志目录路径（跟随数据存储位置）
 pub fn get_log_dir() -> PathBuf {
     // 获取默认配置目录
@@ -276,3 +415,186 @@ pub fn get_log_dir() -> PathBuf {
     
     default_config_dir
 }
+
+/// 获取当前日志文件路径
+pub fn get_log_file() -> PathBuf {
+    get_log_dir().join(LOG_FILE_NAME)
+}
+
+/// 读取文件末尾的若干行（从末尾分块向前读取，避免加载整个文件）
+pub fn read_tail_lines(path: &Path, lines: usize) -> io::Result<Vec<String>> {
+    const BLOCK_SIZE: u64 = 64 * 1024;
+    read_tail_lines_in_blocks(path, lines, BLOCK_SIZE)
+}
+
+fn read_tail_lines_in_blocks(
+    path: &Path,
+    lines: usize,
+    block_size: u64,
+) -> io::Result<Vec<String>> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut pos = len;
+    // 从后往前读到的块，每块只统计一次换行符，最后一次性拼接
+    let mut blocks: Vec<Vec<u8>> = Vec::new();
+    let mut newlines = 0;
+
+    // 需要 lines + 1 个换行符才能确定最早一行的起点
+    while pos > 0 && newlines <= lines {
+        let read_size = block_size.min(pos);
+        pos -= read_size;
+        file.seek(SeekFrom::Start(pos))?;
+        let mut block = vec![0u8; read_size as usize];
+        file.read_exact(&mut block)?;
+        newlines += block.iter().filter(|&&b| b == b'\n').count();
+        blocks.push(block);
+    }
+    blocks.reverse();
+    let buf = blocks.concat();
+
+    let text = String::from_utf8_lossy(&buf);
+    let all: Vec<&str> = text.lines().collect();
+    let skip = all.len().saturating_sub(lines);
+    Ok(all[skip..].iter().map(|l| l.to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 在独立的临时目录中创建写入器，使用字节级的大小上限便于触发轮转
+    fn test_writer(name: &str, max_size: u64) -> (PathBuf, SizeRotatingWriter) {
+        let dir = std::env::temp_dir().join(format!(
+            "synctools-log-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let mut writer = SizeRotatingWriter::new(&dir, 1).unwrap();
+        writer.max_size = max_size;
+        (dir, writer)
+    }
+
+    #[test]
+    fn test_rotates_past_max_size() {
+        let (dir, writer) = test_writer("rotate", 100);
+        let line = format!("{}\n", "a".repeat(150));
+
+        writer.make_writer().write_all(line.as_bytes()).unwrap();
+
+        // 超过上限的写入完成后轮转：旧内容移到 app.log.old，新的 app.log 为空
+        let old_path = dir.join(LOG_FILE_NAME).with_extension("log.old");
+        assert_eq!(fs::read_to_string(&old_path).unwrap(), line);
+        assert_eq!(fs::metadata(dir.join(LOG_FILE_NAME)).unwrap().len(), 0);
+
+        writer.make_writer().write_all(b"fresh\n").unwrap();
+        assert_eq!(fs::read_to_string(dir.join(LOG_FILE_NAME)).unwrap(), "fresh\n");
+        assert_eq!(fs::read_to_string(&old_path).unwrap(), line);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_concurrent_writers_rotate_without_panic() {
+        let (dir, writer) = test_writer("concurrent", 1024);
+        let line = "0123456789012345678901234567890123456789\n";
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let writer = writer.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..200 {
+                        // 与 tracing 相同，每条日志创建一个写入器
+                        writer.make_writer().write_all(line.as_bytes()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join().is_ok());
+        }
+        writer.flush().unwrap();
+
+        // 多次轮转后两个文件中的每一行仍然完整
+        let old_path = dir.join(LOG_FILE_NAME).with_extension("log.old");
+        assert!(old_path.exists());
+        for path in [dir.join(LOG_FILE_NAME), old_path] {
+            let content = fs::read_to_string(&path).unwrap();
+            assert!(content.lines().all(|l| format!("{}\n", l) == line));
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_live_log_drops_when_full() {
+        let (writer, mut receiver) = LiveLogWriter::channel(2);
+        for i in 0..3 {
+            // 与 tracing 相同，一条日志可能分多次写入
+            let mut line = writer.make_writer();
+            write!(line, "line {}", i).unwrap();
+            line.write_all(b"\n").unwrap();
+        }
+
+        // 通道满后丢弃新的日志行而不是阻塞
+        assert_eq!(receiver.try_recv().unwrap(), "line 0");
+        assert_eq!(receiver.try_recv().unwrap(), "line 1");
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(writer.take_dropped(), 1);
+        assert_eq!(writer.take_dropped(), 0);
+    }
+
+    #[test]
+    fn test_poisoned_lock_does_not_cascade() {
+        let (dir, writer) = test_writer("poison", 1024 * 1024);
+
+        // 持有锁时 panic，使锁中毒
+        let shared = writer.writer.clone();
+        let result = std::thread::spawn(move || {
+            let _guard = shared.lock().unwrap();
+            panic!("日志调用中 panic");
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(writer.writer.is_poisoned());
+
+        let mut log = writer.make_writer();
+        log.write_all(b"after poison\n").unwrap();
+        log.flush().unwrap();
+        writer.flush().unwrap();
+        assert_eq!(fs::read_to_string(dir.join(LOG_FILE_NAME)).unwrap(), "after poison\n");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_read_tail_lines() {
+        let dir = std::env::temp_dir()
+            .join(format!("synctools-log-test-tail-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(LOG_FILE_NAME);
+        let content: String = (0..100).map(|i| format!("line {}\n", i)).collect();
+        fs::write(&path, &content).unwrap();
+
+        let expected: Vec<String> = (97..100).map(|i| format!("line {}", i)).collect();
+        assert_eq!(read_tail_lines(&path, 3).unwrap(), expected);
+        // 行跨越多个块，以及块大小小于一行
+        for block_size in [1, 5, 16, 1000] {
+            assert_eq!(read_tail_lines_in_blocks(&path, 3, block_size).unwrap(), expected);
+        }
+        // 请求的行数超过文件行数时返回全部
+        assert_eq!(read_tail_lines_in_blocks(&path, 500, 7).unwrap().len(), 100);
+        assert!(read_tail_lines(&path, 0).unwrap().is_empty());
+
+        fs::write(&path, "").unwrap();
+        assert!(read_tail_lines(&path, 3).unwrap().is_empty());
+        // 末尾没有换行符
+        fs::write(&path, "a\nb\nc").unwrap();
+        assert_eq!(read_tail_lines_in_blocks(&path, 2, 2).unwrap(), vec!["b", "c"]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
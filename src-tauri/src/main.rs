@@ -1,7 +1,7 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use synctools_lib::logging::{get_log_dir, LogConfig, SizeRotatingWriter};
+use synctools_lib::logging::{get_log_dir, live_log_layer, LogConfig, SizeRotatingWriter};
 use synctools_lib::AppState;
 use std::sync::Arc;
 use tauri::{
@@ -19,28 +19,31 @@ fn show_main_window(app: &AppHandle) {
     }
 }
 
-/// 初始化日志系统
-fn init_logging() {
+/// 初始化日志系统，返回文件日志写入器（用于退出时刷新）
+fn init_logging() -> Option<SizeRotatingWriter> {
     let log_dir = get_log_dir();
     let _ = std::fs::create_dir_all(&log_dir);
     let config = LogConfig::load(&log_dir);
 
-    if !config.enabled {
-        let _ = tracing::subscriber::set_global_default(tracing_subscriber::registry());
-        return;
-    }
-
     let env_filter = tracing_subscriber::EnvFilter::from_default_env()
         .add_directive(config.tracing_level().into())
         .add_directive("tao=error".parse().unwrap())
         .add_directive("hyper=warn".parse().unwrap())
         .add_directive("reqwest=warn".parse().unwrap());
 
+    // 不写日志文件时仍保留实时日志（仅在界面开启时转发）
+    if !config.enabled {
+        let subscriber = tracing_subscriber::registry().with(env_filter).with(live_log_layer());
+        let _ = tracing::subscriber::set_global_default(subscriber);
+        return None;
+    }
+
     let Ok(file_writer) = SizeRotatingWriter::new(&log_dir, config.max_size_mb) else {
         #[cfg(debug_assertions)]
         tracing_subscriber::fmt().with_env_filter(env_filter).init();
-        return;
+        return None;
     };
+    let writer_handle = file_writer.clone();
 
     let file_layer = tracing_subscriber::fmt::layer()
         .with_writer(file_writer)
@@ -53,18 +56,23 @@ fn init_logging() {
     let subscriber = tracing_subscriber::registry()
         .with(env_filter)
         .with(file_layer)
+        .with(live_log_layer())
         .with(tracing_subscriber::fmt::layer().with_target(false).with_thread_ids(false).with_thread_names(false));
 
     #[cfg(not(debug_assertions))]
-    let subscriber = tracing_subscriber::registry().with(env_filter).with(file_layer);
+    let subscriber = tracing_subscriber::registry()
+        .with(env_filter)
+        .with(file_layer)
+        .with(live_log_layer());
 
     let _ = tracing::subscriber::set_global_default(subscriber);
+    Some(writer_handle)
 }
 
 #[tokio::main]
 async fn main() {
     // 初始化日志系统
-    init_logging();
+    let log_writer = init_logging();
 
     let state = AppState::new()
         .await
@@ -105,6 +113,15 @@ async fn main() {
                 })
                 .build(app)?;
 
+            // 启动定时调度
+            synctools_lib::commands::sync::start_scheduler(app.handle());
+
+            // 清理上次运行遗留的临时文件
+            synctools_lib::commands::transfer::start_temp_sweep(app.handle());
+
+            // 转发实时日志到前端
+            synctools_lib::commands::log::start_live_log(app.handle());
+
             // 监听前端 ready 事件后显示窗口
             let app_handle = app.handle().clone();
             app.listen("frontend-ready", move |_| {
@@ -129,6 +146,8 @@ async fn main() {
             synctools_lib::commands::job::delete_job,
             synctools_lib::commands::job::get_data_path,
             synctools_lib::commands::job::set_data_path,
+            synctools_lib::commands::job::validate_schedule,
+            synctools_lib::commands::job::toggle_job,
             synctools_lib::commands::sync::start_sync,
             synctools_lib::commands::sync::cancel_sync,
             synctools_lib::commands::sync::cancel_analyze,
@@ -136,14 +155,43 @@ async fn main() {
             synctools_lib::commands::sync::get_pending_transfers,
             synctools_lib::commands::sync::get_sync_history,
             synctools_lib::commands::sync::analyze_job,
+            synctools_lib::commands::sync::diff_summary_by_dir,
+            synctools_lib::commands::sync::analyze_adhoc,
+            synctools_lib::commands::sync::get_effective_config,
             synctools_lib::commands::sync::clear_scan_cache,
+            synctools_lib::commands::sync::sync_single_file,
+            synctools_lib::commands::sync::retry_failed,
+            synctools_lib::commands::sync::find_duplicates,
+            synctools_lib::commands::sync::scan_endpoint,
+            synctools_lib::commands::sync::verify_destination,
+            synctools_lib::commands::sync::check_job,
+            synctools_lib::commands::sync::rehash_job_state,
+            synctools_lib::commands::sync::estimate_upload,
             synctools_lib::commands::test::test_connection,
+            synctools_lib::commands::benchmark::benchmark_storage,
+            synctools_lib::commands::storage::list_storage_types,
+            synctools_lib::commands::rclone::import_rclone_remotes,
             synctools_lib::commands::log::get_log_config,
             synctools_lib::commands::log::set_log_config,
+            synctools_lib::commands::log::get_log_path,
+            synctools_lib::commands::log::reveal_log,
+            synctools_lib::commands::log::read_log_tail,
+            synctools_lib::commands::log::get_live_log,
+            synctools_lib::commands::log::set_live_log,
             synctools_lib::commands::cache::get_cache_config,
             synctools_lib::commands::cache::set_cache_config,
+            synctools_lib::commands::cache::get_cache_status,
+            synctools_lib::commands::cache::gc_orphans,
             synctools_lib::commands::transfer::get_transfer_config,
             synctools_lib::commands::transfer::set_transfer_config,
+            synctools_lib::commands::scanner::get_scanner_config,
+            synctools_lib::commands::scanner::set_scanner_config,
+            synctools_lib::commands::scheduler::get_scheduler_config,
+            synctools_lib::commands::scheduler::set_scheduler_config,
+            synctools_lib::commands::scheduler::skip_next_run,
+            synctools_lib::commands::scheduler::run_now,
+            synctools_lib::commands::hook::get_hook_config,
+            synctools_lib::commands::hook::set_hook_config,
             synctools_lib::commands::shell::show_in_folder,
             synctools_lib::commands::shell::rename_file,
             synctools_lib::commands::shell::delete_file,
@@ -162,6 +210,10 @@ async fn main() {
                     state.cleanup().await;
                 });
             });
+            // 刷新日志缓冲区，确保退出前的日志完整写入
+            if let Some(writer) = &log_writer {
+                let _ = writer.flush();
+            }
         }
     });
 }
@@ -0,0 +1,315 @@
+use super::{
+    dedup_listing, normalize_path, opendal_content_md5, opendal_http_client, FileInfo, FileMeta,
+    ListCounter, Storage, IO_TIMEOUT_SECS, OP_TIMEOUT_SECS,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::{Stream, StreamExt, TryStreamExt};
+use opendal::{layers::TimeoutLayer, Metadata, Metakey, Operator};
+use std::pin::Pin;
+use std::time::Duration;
+
+/// 分块上传的块大小（块 Blob 最多 50000 块，16MB 可上传约 800GB）
+const AZBLOB_BLOCK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Blob 校验和：只使用 Content-MD5
+///
+/// Azure 的 ETag 是不透明的版本标识，与内容无关（内容相同的两次上传 ETag 不同），不能作为校验和；
+/// 分块上传的 Blob 默认没有 Content-MD5，此时没有校验和
+fn blob_checksum(meta: &Metadata) -> Option<String> {
+    opendal_content_md5(meta)
+}
+
+pub struct AzureBlobStorage {
+    operator: Operator,
+    name: String,
+}
+
+impl AzureBlobStorage {
+    /// 创建 Azure Blob 存储，`account_key` 与 `sas_token` 至少提供一个
+    pub async fn new(
+        account_name: &str,
+        account_key: Option<&str>,
+        sas_token: Option<&str>,
+        container: &str,
+        endpoint: Option<String>,
+        prefix: Option<String>,
+        request_tag: Option<&str>,
+    ) -> Result<Self> {
+        use opendal::services::Azblob;
+
+        if account_key.is_none() && sas_token.is_none() {
+            return Err(anyhow!("Azure Blob 需要账户密钥或 SAS 令牌"));
+        }
+
+        // 未指定时使用公有云默认地址
+        let endpoint = endpoint
+            .filter(|e| !e.is_empty())
+            .unwrap_or_else(|| format!("https://{}.blob.core.windows.net", account_name));
+
+        let mut builder = Azblob::default()
+            .container(container)
+            .account_name(account_name)
+            .endpoint(&endpoint)
+            .http_client(opendal_http_client(request_tag)?);
+
+        if let Some(key) = account_key {
+            builder = builder.account_key(key);
+        }
+
+        if let Some(token) = sas_token {
+            builder = builder.sas_token(token.trim_start_matches('?'));
+        }
+
+        if let Some(ref p) = prefix {
+            builder = builder.root(p);
+        }
+
+        // 添加超时层
+        let operator = Operator::new(builder)?
+            .layer(
+                TimeoutLayer::default()
+                    .with_timeout(Duration::from_secs(OP_TIMEOUT_SECS))
+                    .with_io_timeout(Duration::from_secs(IO_TIMEOUT_SECS))
+            )
+            .finish();
+
+        let name = format!(
+            "azblob://{}/{}{}",
+            account_name,
+            container,
+            prefix
+                .as_deref()
+                .map(|p| format!("/{}", p))
+                .unwrap_or_default()
+        );
+
+        Ok(Self { operator, name })
+    }
+
+    /// 验证凭据和容器可访问（列出容器根目录）
+    pub async fn verify_container(&self) -> Result<()> {
+        self.operator.list("").await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for AzureBlobStorage {
+    async fn list_files(&self, prefix: Option<&str>) -> Result<Vec<FileInfo>> {
+        self.list_files_paged(prefix, None).await
+    }
+
+    async fn list_files_paged(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+    ) -> Result<Vec<FileInfo>> {
+        self.list_files_counted(prefix, page_size, &ListCounter::default()).await
+    }
+
+    async fn list_files_counted(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+        listed: &ListCounter,
+    ) -> Result<Vec<FileInfo>> {
+        let mut files = Vec::new();
+        let path = prefix.map(normalize_path).unwrap_or_default();
+
+        let mut list_op = self.operator.lister_with(&path).recursive(true).metakey(
+            Metakey::ContentLength | Metakey::LastModified | Metakey::Mode | Metakey::ContentMd5,
+        );
+        if let Some(limit) = page_size {
+            list_op = list_op.limit(limit);
+        }
+        let mut lister = list_op.await?;
+
+        while let Some(entry) = lister.try_next().await? {
+            let path_str = entry.path().to_string();
+
+            // 跳过根目录
+            if path_str.is_empty() || path_str == "/" {
+                continue;
+            }
+
+            let meta = entry.metadata();
+            // 目录占位 Blob（"dir/"）按目录处理，不作为文件同步
+            let is_dir = meta.is_dir() || path_str.ends_with('/');
+
+            files.push(FileInfo {
+                path: normalize_path(&path_str),
+                size: if is_dir { 0 } else { meta.content_length() },
+                modified_time: meta.last_modified().map_or(0, |t| t.timestamp()),
+                is_dir,
+                checksum: if is_dir { None } else { blob_checksum(meta) },
+                hidden: false,
+            });
+            listed.add(1)?;
+        }
+
+        Ok(dedup_listing(files))
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<FileMeta>> {
+        match self.operator.stat(&normalize_path(path)).await {
+            Ok(meta) => Ok(Some(FileMeta {
+                size: meta.content_length(),
+                modified_time: meta.last_modified().map_or(0, |t| t.timestamp()),
+                is_dir: meta.is_dir(),
+                etag: meta.etag().map(|s| s.trim_matches('"').to_string()),
+            })),
+            Err(e) if e.kind() == opendal::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 列出根目录的第一页（stat 根路径不会发出请求）
+    async fn probe_root(&self) -> Result<()> {
+        let mut lister = self.operator.lister_with("").limit(1).await?;
+        lister.try_next().await?;
+        Ok(())
+    }
+
+    async fn root_is_empty(&self) -> Result<Option<bool>> {
+        Ok(Some(super::opendal_root_is_empty(&self.operator).await?))
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let data = self.operator.read(&normalize_path(path)).await?;
+        Ok(data.to_vec())
+    }
+
+    async fn read_range(&self, path: &str, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let data = self
+            .operator
+            .read_with(&normalize_path(path))
+            .range(offset..offset + length)
+            .await?;
+        Ok(data.to_vec())
+    }
+
+    async fn write(&self, path: &str, data: Vec<u8>) -> Result<()> {
+        let path = normalize_path(path);
+        let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+        self.operator
+            .write_with(&path, data)
+            .content_type(content_type.essence_str())
+            .await?;
+        Ok(())
+    }
+
+    async fn write_stream(
+        &self,
+        path: &str,
+        mut stream: Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>,
+        _total_size: Option<u64>,
+    ) -> Result<()> {
+        // 按块大小写入，OpenDAL 会使用 Put Block / Put Block List 分块上传块 Blob
+        let path = normalize_path(path);
+        let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+        let mut writer = self
+            .operator
+            .writer_with(&path)
+            .chunk(AZBLOB_BLOCK_SIZE)
+            .content_type(content_type.essence_str())
+            .await?;
+
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(data) => writer.write(data).await?,
+                Err(e) => {
+                    // 未提交的块 7 天后由服务端清理
+                    let _ = writer.abort().await;
+                    return Err(e);
+                }
+            }
+        }
+
+        writer.close().await?;
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        // 删除不存在的 Blob 不会报错
+        self.operator.delete(&normalize_path(path)).await?;
+        Ok(())
+    }
+
+    /// 对象存储没有真正的目录，只删除 "dir/" 占位对象，其下的文件不受影响
+    async fn remove_empty_dir(&self, path: &str) -> Result<()> {
+        let path = normalize_path(path);
+        self.operator
+            .delete(&format!("{}/", path.trim_end_matches('/')))
+            .await?;
+        Ok(())
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        // Blob 存储没有真正的目录，创建占位对象以保持兼容
+        let path = normalize_path(path);
+        let dir_path = if path.ends_with('/') {
+            path
+        } else {
+            format!("{}/", path)
+        };
+        self.operator.write(&dir_path, Vec::<u8>::new()).await?;
+        Ok(())
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        // Blob 没有重命名，使用服务端复制后删除
+        let from = normalize_path(from);
+        self.operator.copy(&from, &normalize_path(to)).await?;
+        self.operator.delete(&from).await?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opendal::EntryMode;
+
+    #[test]
+    fn test_blob_checksum_ignores_etag() {
+        let meta = Metadata::new(EntryMode::FILE).with_etag("\"0x8DC1234567890AB\"".to_string());
+        assert_eq!(blob_checksum(&meta), None);
+
+        // MD5("hello") 的 base64
+        let meta = meta.with_content_md5("XUFAKrxLKna5cZ2REBfFkg==".to_string());
+        assert_eq!(
+            blob_checksum(&meta).as_deref(),
+            Some("md5:5d41402abc4b2a76b9719d911017c592")
+        );
+        // 长度不是 16 字节的值不是 MD5
+        let meta = Metadata::new(EntryMode::FILE).with_content_md5("aGVsbG8=".to_string());
+        assert_eq!(blob_checksum(&meta), None);
+    }
+
+    #[tokio::test]
+    async fn test_new_requires_credentials_and_names_storage() {
+        let err = AzureBlobStorage::new("acct", None, None, "box", None, None, None)
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("SAS"), "{}", err);
+
+        let storage = AzureBlobStorage::new(
+            "acct",
+            None,
+            Some("?sv=2024&sig=x"),
+            "box",
+            None,
+            Some("backup".to_string()),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(storage.name(), "azblob://acct/box/backup");
+    }
+}
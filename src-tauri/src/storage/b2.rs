@@ -0,0 +1,223 @@
+use super::{
+    dedup_listing, normalize_path, opendal_http_client, FileInfo, FileMeta, ListCounter, Storage,
+    IO_TIMEOUT_SECS, OP_TIMEOUT_SECS,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt, TryStreamExt};
+use opendal::{layers::TimeoutLayer, Metakey, Operator};
+use std::pin::Pin;
+use std::time::Duration;
+
+/// B2 大文件分片大小（B2 要求除最后一片外不小于 5MB）
+const B2_PART_SIZE: usize = 16 * 1024 * 1024;
+
+pub struct B2Storage {
+    operator: Operator,
+    name: String,
+}
+
+impl B2Storage {
+    pub async fn new(
+        key_id: &str,
+        application_key: &str,
+        bucket: &str,
+        bucket_id: &str,
+        prefix: Option<String>,
+        request_tag: Option<&str>,
+    ) -> Result<Self> {
+        use opendal::services::B2;
+
+        let mut builder = B2::default()
+            .application_key_id(key_id)
+            .application_key(application_key)
+            .bucket(bucket)
+            .bucket_id(bucket_id)
+            .http_client(opendal_http_client(request_tag)?);
+
+        if let Some(ref p) = prefix {
+            builder = builder.root(p);
+        }
+
+        // 添加超时层
+        let operator = Operator::new(builder)?
+            .layer(
+                TimeoutLayer::default()
+                    .with_timeout(Duration::from_secs(OP_TIMEOUT_SECS))
+                    .with_io_timeout(Duration::from_secs(IO_TIMEOUT_SECS))
+            )
+            .finish();
+
+        let name = format!(
+            "b2://{}{}",
+            bucket,
+            prefix
+                .as_deref()
+                .map(|p| format!("/{}", p))
+                .unwrap_or_default()
+        );
+
+        Ok(Self { operator, name })
+    }
+}
+
+#[async_trait]
+impl Storage for B2Storage {
+    async fn list_files(&self, prefix: Option<&str>) -> Result<Vec<FileInfo>> {
+        self.list_files_paged(prefix, None).await
+    }
+
+    async fn list_files_paged(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+    ) -> Result<Vec<FileInfo>> {
+        self.list_files_counted(prefix, page_size, &ListCounter::default()).await
+    }
+
+    async fn list_files_counted(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+        listed: &ListCounter,
+    ) -> Result<Vec<FileInfo>> {
+        let mut files = Vec::new();
+        let path = prefix.unwrap_or("");
+
+        let mut list_op = self
+            .operator
+            .lister_with(path)
+            .recursive(true)
+            .metakey(Metakey::ContentLength | Metakey::LastModified | Metakey::Mode);
+        if let Some(limit) = page_size {
+            list_op = list_op.limit(limit);
+        }
+        let mut lister = list_op.await?;
+
+        while let Some(entry) = lister.try_next().await? {
+            let path_str = entry.path().to_string();
+
+            // 跳过根目录
+            if path_str.is_empty() || path_str == "/" {
+                continue;
+            }
+
+            let meta = entry.metadata();
+
+            files.push(FileInfo {
+                path: path_str.trim_start_matches('/').to_string(),
+                size: meta.content_length(),
+                modified_time: meta.last_modified().map_or(0, |t| t.timestamp()),
+                is_dir: meta.is_dir(),
+                checksum: meta.etag().map(|s| s.trim_matches('"').to_string()),
+                hidden: false,
+            });
+            listed.add(1)?;
+        }
+
+        Ok(dedup_listing(files))
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<FileMeta>> {
+        match self.operator.stat(path).await {
+            Ok(meta) => Ok(Some(FileMeta {
+                size: meta.content_length(),
+                modified_time: meta.last_modified().map_or(0, |t| t.timestamp()),
+                is_dir: meta.is_dir(),
+                etag: meta.etag().map(|s| s.trim_matches('"').to_string()),
+            })),
+            Err(e) if e.kind() == opendal::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 列出根目录的第一页（stat 根路径不会发出请求）
+    async fn probe_root(&self) -> Result<()> {
+        let mut lister = self.operator.lister_with("").limit(1).await?;
+        lister.try_next().await?;
+        Ok(())
+    }
+
+    async fn root_is_empty(&self) -> Result<Option<bool>> {
+        Ok(Some(super::opendal_root_is_empty(&self.operator).await?))
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let data = self.operator.read(path).await?;
+        Ok(data.to_vec())
+    }
+
+    async fn read_range(&self, path: &str, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let data = self
+            .operator
+            .read_with(path)
+            .range(offset..offset + length)
+            .await?;
+        Ok(data.to_vec())
+    }
+
+    async fn write(&self, path: &str, data: Vec<u8>) -> Result<()> {
+        self.operator.write(path, data).await?;
+        Ok(())
+    }
+
+    async fn write_stream(
+        &self,
+        path: &str,
+        mut stream: Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>,
+        _total_size: Option<u64>,
+    ) -> Result<()> {
+        // 按分片大小写入，OpenDAL 会使用 B2 大文件接口（start_large_file / upload_part）
+        let mut writer = self.operator.writer_with(path).chunk(B2_PART_SIZE).await?;
+
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(data) => writer.write(data).await?,
+                Err(e) => {
+                    // 中止未完成的大文件上传，避免残留分片
+                    let _ = writer.abort().await;
+                    return Err(e);
+                }
+            }
+        }
+
+        writer.close().await?;
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        self.operator.delete(path).await?;
+        Ok(())
+    }
+
+    /// 对象存储没有真正的目录，只删除 "dir/" 占位对象，其下的文件不受影响
+    async fn remove_empty_dir(&self, path: &str) -> Result<()> {
+        self.operator
+            .delete(&format!("{}/", path.trim_end_matches('/')))
+            .await?;
+        Ok(())
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        // B2 没有真正的目录，创建占位对象以保持兼容
+        let dir_path = if path.ends_with('/') {
+            path.to_string()
+        } else {
+            format!("{}/", path)
+        };
+        self.operator.write(&dir_path, Vec::<u8>::new()).await?;
+        Ok(())
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        // B2 没有重命名，使用服务端复制后删除
+        let (from, to) = (normalize_path(from), normalize_path(to));
+        self.operator.copy(&from, &to).await?;
+        self.operator.delete(&from).await?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
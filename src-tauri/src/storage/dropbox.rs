@@ -0,0 +1,732 @@
+//! Dropbox 存储 - 通过 Dropbox HTTP API 访问，使用刷新令牌自动获取访问令牌
+
+use super::{
+    dedup_listing, http_client_builder, normalize_path, FileInfo, FileMeta, ListCounter, Storage,
+    SyncError,
+};
+use crate::core::file_state::DROPBOX_CHECKSUM_PREFIX;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// RPC 接口地址
+const API_ENDPOINT: &str = "https://api.dropboxapi.com/2";
+/// 内容上传/下载接口地址
+const CONTENT_ENDPOINT: &str = "https://content.dropboxapi.com/2";
+/// 令牌端点
+const TOKEN_URL: &str = "https://api.dropboxapi.com/oauth2/token";
+/// 访问令牌提前刷新的时间（秒），避免长时间同步中途过期
+const TOKEN_REFRESH_MARGIN_SECS: u64 = 300;
+/// HTTP 连接超时（秒）
+const HTTP_CONNECT_TIMEOUT_SECS: u64 = 30;
+/// HTTP 请求超时（秒）
+const HTTP_REQUEST_TIMEOUT_SECS: u64 = 300;
+/// 单次上传的最大文件大小（Dropbox 限制 150MB），超过时使用上传会话
+const SIMPLE_UPLOAD_MAX: usize = 150 * 1024 * 1024;
+/// 上传会话的分片大小（8MB）
+const UPLOAD_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+/// list_folder 单页条目数上限
+const LIST_LIMIT_MAX: usize = 2000;
+
+/// 令牌端点响应
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+/// 文件/目录元数据
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    /// file / folder / deleted
+    #[serde(rename = ".tag")]
+    tag: String,
+    path_display: Option<String>,
+    #[serde(default)]
+    size: u64,
+    server_modified: Option<String>,
+    content_hash: Option<String>,
+    rev: Option<String>,
+}
+
+impl Metadata {
+    fn is_dir(&self) -> bool {
+        self.tag == "folder"
+    }
+
+    fn modified_time(&self) -> i64 {
+        self.server_modified
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map_or(0, |t| t.timestamp())
+    }
+
+    fn checksum(&self) -> Option<String> {
+        let hash = self.content_hash.as_ref()?;
+        Some(format!("{}{}", DROPBOX_CHECKSUM_PREFIX, hash))
+    }
+}
+
+/// list_folder 结果（一页）
+#[derive(Debug, Deserialize)]
+struct ListFolderResult {
+    entries: Vec<Metadata>,
+    cursor: String,
+    has_more: bool,
+}
+
+/// 上传会话
+#[derive(Debug, Deserialize)]
+struct UploadSessionStart {
+    session_id: String,
+}
+
+/// 访问令牌及其有效期
+struct AccessToken {
+    token: String,
+    expires_at: Instant,
+}
+
+pub struct DropboxStorage {
+    client: reqwest::Client,
+    app_key: String,
+    app_secret: Option<String>,
+    refresh_token: String,
+    access: Mutex<Option<AccessToken>>,
+    /// Dropbox 内的根目录，不含首尾斜杠
+    root: String,
+    name: String,
+}
+
+/// 序列化 Dropbox-API-Arg 请求头，非 ASCII 字符转义为 \uXXXX（请求头只允许 ASCII）
+fn api_arg(arg: &serde_json::Value) -> String {
+    let mut out = String::new();
+    for c in arg.to_string().chars() {
+        if c.is_ascii() {
+            out.push(c);
+        } else {
+            let mut buf = [0u16; 2];
+            for unit in c.encode_utf16(&mut buf) {
+                out.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+    out
+}
+
+/// 请求失败时转换为分类错误
+///
+/// 路径类错误统一返回 409，按 error_summary 区分不存在和冲突
+async fn check(response: reqwest::Response, context: &str) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    let message = format!("{} ({}): {}", context, status, body);
+    let err = if status == reqwest::StatusCode::CONFLICT {
+        if body.contains("not_found") {
+            SyncError::NotFound(message)
+        } else {
+            SyncError::Conflict(message)
+        }
+    } else {
+        SyncError::from_status(status, message)
+    };
+    Err(err.into())
+}
+
+fn is_not_found(err: &anyhow::Error) -> bool {
+    matches!(err.downcast_ref::<SyncError>(), Some(SyncError::NotFound(_)))
+}
+
+impl DropboxStorage {
+    pub fn new(
+        app_key: &str,
+        app_secret: Option<String>,
+        refresh_token: &str,
+        root: Option<&str>,
+        request_tag: Option<&str>,
+    ) -> Result<Self> {
+        let client = http_client_builder(request_tag)?
+            .connect_timeout(Duration::from_secs(HTTP_CONNECT_TIMEOUT_SECS))
+            .timeout(Duration::from_secs(HTTP_REQUEST_TIMEOUT_SECS))
+            .build()?;
+
+        let root = root
+            .map(normalize_path)
+            .unwrap_or_default()
+            .trim_end_matches('/')
+            .to_string();
+        let name = format!("dropbox:/{}", root);
+
+        Ok(Self {
+            client,
+            app_key: app_key.to_string(),
+            app_secret,
+            refresh_token: refresh_token.to_string(),
+            access: Mutex::new(None),
+            root,
+            name,
+        })
+    }
+
+    /// 获取访问令牌，即将过期或 `force_refresh` 时用刷新令牌换取新令牌
+    async fn access_token(&self, force_refresh: bool) -> Result<String> {
+        let mut access = self.access.lock().await;
+        if !force_refresh {
+            if let Some(token) = access.as_ref() {
+                if token.expires_at > Instant::now() {
+                    return Ok(token.token.clone());
+                }
+            }
+        }
+
+        let mut form = vec![
+            ("grant_type", "refresh_token"),
+            ("refresh_token", self.refresh_token.as_str()),
+            ("client_id", self.app_key.as_str()),
+        ];
+        if let Some(secret) = &self.app_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+        let response = self.client.post(TOKEN_URL).form(&form).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let message = format!("Dropbox 刷新访问令牌失败 ({}): {}", status, body);
+            // 刷新令牌无效或已吊销时返回 400 invalid_grant，重试无济于事
+            return Err(match status.as_u16() {
+                400 | 401 => SyncError::Auth(message),
+                _ => SyncError::from_status(status, message),
+            }
+            .into());
+        }
+
+        let token: TokenResponse = response.json().await?;
+        let lifetime = token.expires_in.saturating_sub(TOKEN_REFRESH_MARGIN_SECS);
+        *access = Some(AccessToken {
+            token: token.access_token.clone(),
+            expires_at: Instant::now() + Duration::from_secs(lifetime),
+        });
+        tracing::debug!("Dropbox 访问令牌已刷新，{} 秒后过期", token.expires_in);
+        Ok(token.access_token)
+    }
+
+    /// 发送请求，访问令牌失效（401）时刷新后重试一次
+    async fn send<F>(&self, build: F) -> Result<reqwest::Response>
+    where
+        F: Fn() -> reqwest::RequestBuilder + Send + Sync,
+    {
+        let token = self.access_token(false).await?;
+        let response = build().bearer_auth(&token).send().await?;
+        if response.status() != reqwest::StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        let token = self.access_token(true).await?;
+        Ok(build().bearer_auth(&token).send().await?)
+    }
+
+    /// 调用 RPC 接口（JSON 参数和返回值）
+    async fn rpc(
+        &self,
+        endpoint: &str,
+        body: serde_json::Value,
+        context: &str,
+    ) -> Result<reqwest::Response> {
+        let url = format!("{}/{}", API_ENDPOINT, endpoint);
+        let response = self.send(|| self.client.post(&url).json(&body)).await?;
+        check(response, context).await
+    }
+
+    /// 服务端移动/复制到 `to`（`endpoint` 为 files/move_v2 或 files/copy_v2）
+    ///
+    /// 目标已存在时接口报冲突，因此先把原目标移到备份位置，成功后删除备份，
+    /// 失败时移回原位，避免中途失败丢失原目标
+    async fn replace_with(
+        &self,
+        endpoint: &str,
+        from: &str,
+        to: &str,
+        context: &str,
+    ) -> Result<()> {
+        let relocate = |from: &str, to: &str| {
+            json!({
+                "from_path": self.api_path(from),
+                "to_path": self.api_path(to),
+                "autorename": false,
+            })
+        };
+        let backup = format!("{}.synctools-{}.bak", to, uuid::Uuid::new_v4().simple());
+        let backed_up = match self
+            .rpc("files/move_v2", relocate(to, &backup), "Dropbox 备份目标文件失败")
+            .await
+        {
+            Ok(_) => true,
+            Err(e) if is_not_found(&e) => false,
+            Err(e) => return Err(e),
+        };
+
+        if let Err(e) = self.rpc(endpoint, relocate(from, to), context).await {
+            if backed_up {
+                if let Err(restore) = self
+                    .rpc("files/move_v2", relocate(&backup, to), "Dropbox 恢复目标文件失败")
+                    .await
+                {
+                    tracing::warn!("恢复目标文件失败，原文件保留在 {}: {}", backup, restore);
+                }
+            }
+            return Err(e);
+        }
+        if backed_up {
+            if let Err(e) = self.delete(&backup).await {
+                tracing::warn!("删除备份文件失败 {}: {}", backup, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// 验证令牌有效（获取当前账户信息）
+    pub async fn verify_account(&self) -> Result<()> {
+        self.rpc(
+            "users/get_current_account",
+            serde_json::Value::Null,
+            "Dropbox 获取账户信息失败",
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// 调用内容上传接口（参数放在 Dropbox-API-Arg 请求头）
+    async fn upload(
+        &self,
+        endpoint: &str,
+        arg: serde_json::Value,
+        data: Vec<u8>,
+        context: &str,
+    ) -> Result<reqwest::Response> {
+        let url = format!("{}/{}", CONTENT_ENDPOINT, endpoint);
+        let arg = api_arg(&arg);
+        let response = self
+            .send(|| {
+                self.client
+                    .post(&url)
+                    .header("Dropbox-API-Arg", &arg)
+                    .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+                    .body(data.clone())
+            })
+            .await?;
+        check(response, context).await
+    }
+
+    /// 下载文件内容，`range` 为 HTTP Range 请求头
+    async fn download(&self, path: &str, range: Option<String>) -> Result<Vec<u8>> {
+        let url = format!("{}/files/download", CONTENT_ENDPOINT);
+        let arg = api_arg(&json!({ "path": self.api_path(path) }));
+        let response = self
+            .send(|| {
+                let request = self.client.post(&url).header("Dropbox-API-Arg", &arg);
+                match &range {
+                    Some(range) => request.header(reqwest::header::RANGE, range),
+                    None => request,
+                }
+            })
+            .await?;
+        let response = check(response, "Dropbox 读取文件失败").await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// 存储路径加上根目录，得到 API 路径（Dropbox 根目录为空字符串，其余以 / 开头）
+    fn api_path(&self, path: &str) -> String {
+        let path = normalize_path(path);
+        let path = path.trim_end_matches('/');
+        match (self.root.is_empty(), path.is_empty()) {
+            (true, true) => String::new(),
+            (true, false) => format!("/{}", path),
+            (false, true) => format!("/{}", self.root),
+            (false, false) => format!("/{}/{}", self.root, path),
+        }
+    }
+
+    /// 去掉根目录前缀，得到存储内相对路径（Dropbox 路径不区分大小写）
+    fn relative_path(&self, path_display: &str) -> Option<String> {
+        let path = path_display.trim_start_matches('/');
+        if self.root.is_empty() {
+            return Some(path.to_string());
+        }
+        let head = path.get(..self.root.len())?;
+        if head.to_lowercase() != self.root.to_lowercase() {
+            return None;
+        }
+        let rest = &path[self.root.len()..];
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+        Some(rest.trim_start_matches('/').to_string())
+    }
+
+    /// 列出结果中的条目转为文件信息（跳过已删除的条目、根目录外的条目和起始目录本身）
+    fn entry_info(&self, entry: &Metadata) -> Option<FileInfo> {
+        if entry.tag == "deleted" {
+            return None;
+        }
+        let path = self.relative_path(entry.path_display.as_deref()?)?;
+        // 递归列出时结果包含起始目录本身
+        if path.is_empty() {
+            return None;
+        }
+        let is_dir = entry.is_dir();
+        Some(FileInfo {
+            path,
+            size: if is_dir { 0 } else { entry.size },
+            modified_time: entry.modified_time(),
+            is_dir,
+            checksum: entry.checksum(),
+            hidden: false,
+        })
+    }
+
+    /// 通过上传会话分片上传大文件
+    async fn upload_session(
+        &self,
+        path: &str,
+        mut stream: Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>,
+    ) -> Result<()> {
+        let session: UploadSessionStart = self
+            .upload(
+                "files/upload_session/start",
+                json!({ "close": false }),
+                Vec::new(),
+                "Dropbox 创建上传会话失败",
+            )
+            .await?
+            .json()
+            .await?;
+
+        let mut buffer: Vec<u8> = Vec::with_capacity(UPLOAD_CHUNK_SIZE);
+        let mut offset = 0u64;
+        loop {
+            let next = stream.next().await.transpose()?;
+            let finished = next.is_none();
+            if let Some(chunk) = next {
+                buffer.extend_from_slice(&chunk);
+            }
+
+            while buffer.len() >= UPLOAD_CHUNK_SIZE || (finished && !buffer.is_empty()) {
+                let len = buffer.len().min(UPLOAD_CHUNK_SIZE);
+                let part: Vec<u8> = buffer.drain(..len).collect();
+                self.upload(
+                    "files/upload_session/append_v2",
+                    json!({
+                        "cursor": { "session_id": session.session_id, "offset": offset },
+                        "close": false,
+                    }),
+                    part,
+                    "Dropbox 上传分片失败",
+                )
+                .await?;
+                offset += len as u64;
+            }
+
+            if finished {
+                break;
+            }
+        }
+
+        // 未完成的会话由服务端在 7 天后自动清理
+        self.upload(
+            "files/upload_session/finish",
+            json!({
+                "cursor": { "session_id": session.session_id, "offset": offset },
+                "commit": { "path": self.api_path(path), "mode": "overwrite", "mute": true },
+            }),
+            Vec::new(),
+            "Dropbox 提交上传会话失败",
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for DropboxStorage {
+    async fn list_files(&self, prefix: Option<&str>) -> Result<Vec<FileInfo>> {
+        self.list_files_paged(prefix, None).await
+    }
+
+    async fn list_files_paged(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+    ) -> Result<Vec<FileInfo>> {
+        self.list_files_counted(prefix, page_size, &ListCounter::default()).await
+    }
+
+    async fn list_files_counted(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+        listed: &ListCounter,
+    ) -> Result<Vec<FileInfo>> {
+        let mut body = json!({
+            "path": self.api_path(prefix.unwrap_or("")),
+            "recursive": true,
+            "include_deleted": false,
+        });
+        if let Some(limit) = page_size {
+            body["limit"] = json!(limit.clamp(1, LIST_LIMIT_MAX));
+        }
+
+        let mut page: ListFolderResult =
+            match self.rpc("files/list_folder", body, "Dropbox 列出目录失败").await {
+                Ok(response) => response.json().await?,
+                // 起始目录不存在时视为空
+                Err(e) if is_not_found(&e) => return Ok(Vec::new()),
+                Err(e) => return Err(e),
+            };
+
+        let mut files = Vec::new();
+        loop {
+            for file in page.entries.iter().filter_map(|e| self.entry_info(e)) {
+                files.push(file);
+                listed.add(1)?;
+            }
+
+            if !page.has_more {
+                break;
+            }
+            page = self
+                .rpc(
+                    "files/list_folder/continue",
+                    json!({ "cursor": page.cursor }),
+                    "Dropbox 列出目录失败",
+                )
+                .await?
+                .json()
+                .await?;
+        }
+
+        // 列出期间条目发生变化时，同一条目可能在多页中重复出现
+        let files = dedup_listing(files);
+        tracing::info!("Dropbox 扫描完成: {} 个条目", files.len());
+        Ok(files)
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<FileMeta>> {
+        let api_path = self.api_path(path);
+        // Dropbox 根目录不支持 get_metadata，始终存在
+        if api_path.is_empty() {
+            return Ok(Some(FileMeta {
+                size: 0,
+                modified_time: 0,
+                is_dir: true,
+                etag: None,
+            }));
+        }
+
+        let metadata: Metadata = match self
+            .rpc(
+                "files/get_metadata",
+                json!({ "path": api_path }),
+                "Dropbox 获取文件信息失败",
+            )
+            .await
+        {
+            Ok(response) => response.json().await?,
+            Err(e) if is_not_found(&e) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(Some(FileMeta {
+            size: if metadata.is_dir() { 0 } else { metadata.size },
+            modified_time: metadata.modified_time(),
+            is_dir: metadata.is_dir(),
+            etag: metadata.rev.clone(),
+        }))
+    }
+
+    /// 根目录的 stat 不发请求，列出根目录的一个条目确认可访问
+    async fn probe_root(&self) -> Result<()> {
+        let body = json!({ "path": self.api_path(""), "limit": 1 });
+        self.rpc("files/list_folder", body, "Dropbox 列出目录失败").await?;
+        Ok(())
+    }
+
+    async fn root_is_empty(&self) -> Result<Option<bool>> {
+        let body = json!({ "path": self.api_path(""), "limit": 1 });
+        let page: ListFolderResult = self
+            .rpc("files/list_folder", body, "Dropbox 列出目录失败")
+            .await?
+            .json()
+            .await?;
+        Ok(Some(page.entries.is_empty()))
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        self.download(path, None).await
+    }
+
+    async fn read_range(&self, path: &str, offset: u64, length: u64) -> Result<Vec<u8>> {
+        if length == 0 {
+            return Ok(Vec::new());
+        }
+        let range = format!("bytes={}-{}", offset, offset + length - 1);
+        self.download(path, Some(range)).await
+    }
+
+    async fn write(&self, path: &str, data: Vec<u8>) -> Result<()> {
+        if data.len() > SIMPLE_UPLOAD_MAX {
+            let stream = futures::stream::iter(
+                data.chunks(UPLOAD_CHUNK_SIZE)
+                    .map(|c| Ok(c.to_vec()))
+                    .collect::<Vec<_>>(),
+            );
+            return self.upload_session(path, Box::pin(stream)).await;
+        }
+
+        self.upload(
+            "files/upload",
+            json!({ "path": self.api_path(path), "mode": "overwrite", "mute": true }),
+            data,
+            "Dropbox 上传文件失败",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn write_stream(
+        &self,
+        path: &str,
+        mut stream: Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>,
+        total_size: Option<u64>,
+    ) -> Result<()> {
+        match total_size {
+            // 小文件收集后整体上传
+            Some(total) if total <= UPLOAD_CHUNK_SIZE as u64 => {
+                let mut data = Vec::new();
+                while let Some(chunk) = stream.next().await {
+                    data.extend(chunk?);
+                }
+                self.write(path, data).await
+            }
+            // 大文件或大小未知时边读边上传，不在内存中缓存整个文件
+            _ => self.upload_session(path, stream).await,
+        }
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        match self
+            .rpc(
+                "files/delete_v2",
+                json!({ "path": self.api_path(path) }),
+                "Dropbox 删除失败",
+            )
+            .await
+        {
+            // 删除不存在的文件不报错
+            Err(e) if is_not_found(&e) => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        let api_path = self.api_path(path);
+        if api_path.is_empty() {
+            return Ok(());
+        }
+        // 父目录自动创建，目录已存在时返回冲突
+        match self
+            .rpc(
+                "files/create_folder_v2",
+                json!({ "path": api_path, "autorename": false }),
+                "Dropbox 创建目录失败",
+            )
+            .await
+        {
+            Err(e) if matches!(e.downcast_ref::<SyncError>(), Some(SyncError::Conflict(_))) => {
+                Ok(())
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<()> {
+        // 服务端复制，不经过本地传输数据
+        self.replace_with("files/copy_v2", from, to, "Dropbox 复制文件失败").await
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        self.replace_with("files/move_v2", from, to, "Dropbox 移动文件失败").await
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths() {
+        let storage = DropboxStorage::new("key", None, "token", Some("/Backup/"), None).unwrap();
+        assert_eq!(storage.api_path(""), "/Backup");
+        assert_eq!(storage.api_path("a b/c.txt"), "/Backup/a b/c.txt");
+        assert_eq!(
+            storage.relative_path("/backup/a b/c.txt").as_deref(),
+            Some("a b/c.txt")
+        );
+        assert_eq!(storage.relative_path("/Backup").as_deref(), Some(""));
+        assert_eq!(storage.relative_path("/Backups/x"), None);
+
+        let storage = DropboxStorage::new("key", None, "token", None, None).unwrap();
+        assert_eq!(storage.api_path(""), "");
+        assert_eq!(storage.api_path("x"), "/x");
+    }
+
+    #[test]
+    fn test_repeated_entries_are_merged() {
+        let storage = DropboxStorage::new("key", None, "token", Some("/Backup"), None).unwrap();
+        // 第二页重复返回了第一页中的条目
+        let pages: Vec<ListFolderResult> = serde_json::from_value(json!([
+            {
+                "entries": [
+                    { ".tag": "folder", "path_display": "/Backup" },
+                    { ".tag": "folder", "path_display": "/Backup/docs" },
+                    { ".tag": "file", "path_display": "/Backup/docs/a.txt", "size": 3 },
+                ],
+                "cursor": "c1",
+                "has_more": true,
+            },
+            {
+                "entries": [
+                    { ".tag": "file", "path_display": "/Backup/docs/a.txt", "size": 3 },
+                    { ".tag": "deleted", "path_display": "/Backup/old.txt" },
+                    { ".tag": "file", "path_display": "/Backup/b.txt", "size": 1 },
+                ],
+                "cursor": "c2",
+                "has_more": false,
+            },
+        ]))
+        .unwrap();
+        let files: Vec<FileInfo> = pages
+            .iter()
+            .flat_map(|page| page.entries.iter().filter_map(|e| storage.entry_info(e)))
+            .collect();
+        assert_eq!(files.len(), 4);
+        let paths: Vec<_> = dedup_listing(files).into_iter().map(|f| f.path).collect();
+        assert_eq!(paths, vec!["docs", "docs/a.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn test_api_arg_escapes_non_ascii() {
+        let arg = api_arg(&json!({ "path": "/文档/😀.txt" }));
+        assert!(arg.is_ascii());
+        assert_eq!(arg, r#"{"path":"/\u6587\u6863/\ud83d\ude00.txt"}"#);
+    }
+}
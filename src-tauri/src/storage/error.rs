@@ -0,0 +1,228 @@
+//! 存储错误分类 - 各后端把底层错误映射为统一的类别，引擎据此决定是否重试、是否自动创建目录
+
+use thiserror::Error;
+
+/// 存储操作错误（错误信息保持原样，类别用于程序判断）
+#[derive(Debug, Clone, Error)]
+pub enum SyncError {
+    /// 文件或目录不存在
+    #[error("{0}")]
+    NotFound(String),
+    /// 与目标当前状态冲突（如已存在、条件不满足）
+    #[error("{0}")]
+    Conflict(String),
+    /// 父目录不存在（如 WebDAV PUT/MKCOL 返回 409），创建父目录后可重试
+    #[error("{0}")]
+    ParentMissing(String),
+    /// 认证失败或无权限
+    #[error("{0}")]
+    Auth(String),
+    /// 请求超时
+    #[error("{0}")]
+    Timeout(String),
+    /// 网络错误或服务端暂时不可用
+    #[error("{0}")]
+    Network(String),
+    /// 本地 IO 错误
+    #[error("{0}")]
+    Io(String),
+    /// 文件被其他程序占用（Windows 共享冲突），文件关闭前重试不会成功
+    #[error("{0}")]
+    Locked(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl SyncError {
+    /// 按 HTTP 状态码分类
+    pub fn from_status(status: reqwest::StatusCode, message: String) -> Self {
+        match status.as_u16() {
+            401 | 403 => SyncError::Auth(message),
+            404 | 410 => SyncError::NotFound(message),
+            409 | 412 => SyncError::Conflict(message),
+            408 | 504 => SyncError::Timeout(message),
+            429 | 500..=599 => SyncError::Network(message),
+            _ => SyncError::Other(message),
+        }
+    }
+
+    /// 按 reqwest 错误类型分类，使用给定的错误信息
+    pub fn from_reqwest(e: &reqwest::Error, message: String) -> Self {
+        if e.is_timeout() {
+            SyncError::Timeout(message)
+        } else if let Some(status) = e.status() {
+            Self::from_status(status, message)
+        } else if e.is_connect() || e.is_request() || e.is_body() {
+            SyncError::Network(message)
+        } else {
+            SyncError::Other(message)
+        }
+    }
+
+    /// 对任意错误分类：沿错误链查找已分类的错误或可识别的底层错误，都没有时归为 Other
+    pub fn classify(err: &anyhow::Error) -> Self {
+        for cause in err.chain() {
+            if let Some(e) = cause.downcast_ref::<SyncError>() {
+                return e.clone();
+            }
+            if let Some(e) = cause.downcast_ref::<opendal::Error>() {
+                return Self::from_opendal(e);
+            }
+            if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+                return Self::from_io(e);
+            }
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                return Self::from_reqwest(e, err.to_string());
+            }
+        }
+        SyncError::Other(err.to_string())
+    }
+
+    fn from_opendal(e: &opendal::Error) -> Self {
+        use opendal::ErrorKind;
+
+        let message = e.to_string();
+        match e.kind() {
+            ErrorKind::NotFound => SyncError::NotFound(message),
+            ErrorKind::PermissionDenied => SyncError::Auth(message),
+            ErrorKind::AlreadyExists | ErrorKind::ConditionNotMatch => SyncError::Conflict(message),
+            ErrorKind::RateLimited => SyncError::Network(message),
+            // 其余类别（主要是 Unexpected）按 HTTP 状态码分类
+            _ => match opendal_http_status(e) {
+                Some(status) => Self::from_status(status, message),
+                None if e.is_temporary() => SyncError::Network(message),
+                None => SyncError::Other(message),
+            },
+        }
+    }
+
+    fn from_io(e: &std::io::Error) -> Self {
+        use std::io::ErrorKind;
+
+        let message = e.to_string();
+        if is_sharing_violation(e) {
+            return SyncError::Locked(message);
+        }
+        match e.kind() {
+            ErrorKind::NotFound => SyncError::NotFound(message),
+            ErrorKind::PermissionDenied => SyncError::Auth(message),
+            ErrorKind::AlreadyExists => SyncError::Conflict(message),
+            ErrorKind::TimedOut => SyncError::Timeout(message),
+            ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::BrokenPipe => SyncError::Network(message),
+            _ => SyncError::Io(message),
+        }
+    }
+
+    /// 重试是否可能成功（文件不存在、认证失败、文件被占用时重试也无济于事）
+    pub fn is_retryable(&self) -> bool {
+        !matches!(
+            self,
+            SyncError::NotFound(_) | SyncError::Auth(_) | SyncError::Locked(_)
+        )
+    }
+
+    /// 是否表示目标目录不存在（自动创建目录后重试）
+    pub fn is_dir_missing(&self) -> bool {
+        matches!(self, SyncError::NotFound(_) | SyncError::ParentMissing(_))
+    }
+}
+
+/// OpenDAL 错误对应的 HTTP 状态码（没有响应时为 None）
+///
+/// OpenDAL 不单独提供状态码，只在 `response` 上下文中记录响应头（`Parts { status: 409, .. }`）
+pub fn opendal_http_status(e: &opendal::Error) -> Option<reqwest::StatusCode> {
+    let text = e.to_string();
+    let parts = &text[text.find("response: Parts {")?..];
+    let status = parts.split_once("status: ")?.1;
+    let code: String = status.chars().take_while(|c| c.is_ascii_digit()).collect();
+    reqwest::StatusCode::from_u16(code.parse().ok()?).ok()
+}
+
+/// 是否为 Windows 共享冲突/锁冲突（ERROR_SHARING_VIOLATION / ERROR_LOCK_VIOLATION）
+fn is_sharing_violation(e: &std::io::Error) -> bool {
+    cfg!(windows) && matches!(e.raw_os_error(), Some(32) | Some(33))
+}
+
+impl From<opendal::Error> for SyncError {
+    fn from(e: opendal::Error) -> Self {
+        Self::from_opendal(&e)
+    }
+}
+
+impl From<std::io::Error> for SyncError {
+    fn from(e: std::io::Error) -> Self {
+        Self::from_io(&e)
+    }
+}
+
+impl From<reqwest::Error> for SyncError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::from_reqwest(&e, e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let err: anyhow::Error = std::io::Error::from(std::io::ErrorKind::NotFound).into();
+        assert!(matches!(SyncError::classify(&err), SyncError::NotFound(_)));
+
+        let err: anyhow::Error = SyncError::ParentMissing("WebDAV PUT 失败".to_string()).into();
+        let kind = SyncError::classify(&err.context("上传失败"));
+        assert!(kind.is_dir_missing() && kind.is_retryable());
+
+        // 一般的冲突不是目录不存在
+        let kind = SyncError::from_status(reqwest::StatusCode::CONFLICT, String::new());
+        assert!(matches!(kind, SyncError::Conflict(_)) && !kind.is_dir_missing());
+
+        // 无法识别的错误不依赖错误信息内容
+        let err = anyhow::anyhow!("404 Not Found");
+        assert!(matches!(SyncError::classify(&err), SyncError::Other(_)));
+        assert!(!SyncError::Auth(String::new()).is_retryable());
+    }
+    fn opendal_error(kind: opendal::ErrorKind, status: Option<u16>) -> opendal::Error {
+        let err = opendal::Error::new(kind, "服务端返回错误");
+        match status {
+            Some(status) => err.with_context(
+                "response",
+                format!("Parts {{ status: {}, version: HTTP/1.1, headers: {{}} }}", status),
+            ),
+            None => err,
+        }
+    }
+
+    #[test]
+    fn test_classify_opendal_by_kind_and_status() {
+        use opendal::ErrorKind;
+
+        let classify = |e: opendal::Error| SyncError::classify(&anyhow::Error::from(e));
+        assert!(matches!(classify(opendal_error(ErrorKind::NotFound, Some(404))), SyncError::NotFound(_)));
+        assert!(matches!(
+            classify(opendal_error(ErrorKind::PermissionDenied, Some(403))),
+            SyncError::Auth(_)
+        ));
+        // Unexpected 按状态码分类
+        assert!(matches!(classify(opendal_error(ErrorKind::Unexpected, Some(409))), SyncError::Conflict(_)));
+        assert!(matches!(classify(opendal_error(ErrorKind::Unexpected, Some(401))), SyncError::Auth(_)));
+        assert!(matches!(classify(opendal_error(ErrorKind::Unexpected, Some(503))), SyncError::Network(_)));
+        assert!(matches!(classify(opendal_error(ErrorKind::Unexpected, Some(504))), SyncError::Timeout(_)));
+        assert!(matches!(classify(opendal_error(ErrorKind::Unexpected, None)), SyncError::Other(_)));
+        assert!(matches!(
+            classify(opendal_error(ErrorKind::Unexpected, None).set_temporary()),
+            SyncError::Network(_)
+        ));
+
+        assert_eq!(
+            opendal_http_status(&opendal_error(ErrorKind::Unexpected, Some(423))),
+            Some(reqwest::StatusCode::LOCKED)
+        );
+        assert_eq!(opendal_http_status(&opendal_error(ErrorKind::Unexpected, None)), None);
+    }
+}
@@ -0,0 +1,292 @@
+use super::{
+    dedup_listing, normalize_path, opendal_content_md5, opendal_http_client, FileInfo, FileMeta,
+    ListCounter, Storage, IO_TIMEOUT_SECS, OP_TIMEOUT_SECS,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::Engine;
+use futures::{Stream, StreamExt, TryStreamExt};
+use opendal::{layers::TimeoutLayer, Metadata, Metakey, Operator};
+use std::pin::Pin;
+use std::time::Duration;
+
+/// GCS 的 S3 兼容（XML API）地址，使用 HMAC 密钥时访问
+const GCS_XML_ENDPOINT: &str = "https://storage.googleapis.com";
+/// 可续传上传的分片大小（GCS 要求为 256KB 的整数倍）
+const GCS_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+pub struct GcsStorage {
+    operator: Operator,
+    name: String,
+}
+
+/// 对象校验和：md5Hash 转成十六进制后可与本地 MD5 直接比较
+///
+/// 组合对象没有 md5Hash，此时使用 ETag；OpenDAL 不提供 crc32c
+fn object_checksum(meta: &Metadata) -> Option<String> {
+    opendal_content_md5(meta).or_else(|| meta.etag().map(|s| s.trim_matches('"').to_string()))
+}
+
+impl GcsStorage {
+    /// 使用服务账号 JSON 凭据访问（JSON API）
+    pub async fn new(
+        bucket: &str,
+        credential_json: &str,
+        endpoint: Option<String>,
+        prefix: Option<String>,
+        request_tag: Option<&str>,
+    ) -> Result<Self> {
+        use opendal::services::Gcs;
+
+        // OpenDAL 要求凭据为 base64 编码的 JSON
+        let credential = base64::engine::general_purpose::STANDARD.encode(credential_json);
+        let mut builder = Gcs::default()
+            .bucket(bucket)
+            .credential(&credential)
+            .http_client(opendal_http_client(request_tag)?);
+
+        if let Some(ref ep) = endpoint {
+            builder = builder.endpoint(ep);
+        }
+
+        if let Some(ref p) = prefix {
+            builder = builder.root(p);
+        }
+
+        Self::build(Operator::new(builder)?.finish(), bucket, prefix)
+    }
+
+    /// 使用 HMAC 密钥通过 S3 兼容接口访问
+    pub async fn with_hmac(
+        bucket: &str,
+        access_key: &str,
+        secret_key: &str,
+        endpoint: Option<String>,
+        prefix: Option<String>,
+        request_tag: Option<&str>,
+    ) -> Result<Self> {
+        use opendal::services::S3;
+
+        let mut builder = S3::default()
+            .bucket(bucket)
+            .region("auto")
+            .endpoint(endpoint.as_deref().unwrap_or(GCS_XML_ENDPOINT))
+            .access_key_id(access_key)
+            .secret_access_key(secret_key)
+            .http_client(opendal_http_client(request_tag)?);
+
+        if let Some(ref p) = prefix {
+            builder = builder.root(p);
+        }
+
+        Self::build(Operator::new(builder)?.finish(), bucket, prefix)
+    }
+
+    fn build(operator: Operator, bucket: &str, prefix: Option<String>) -> Result<Self> {
+        // 添加超时层
+        let operator = operator.layer(
+            TimeoutLayer::default()
+                .with_timeout(Duration::from_secs(OP_TIMEOUT_SECS))
+                .with_io_timeout(Duration::from_secs(IO_TIMEOUT_SECS)),
+        );
+
+        let name = format!(
+            "gs://{}{}",
+            bucket,
+            prefix
+                .as_deref()
+                .map(|p| format!("/{}", p))
+                .unwrap_or_default()
+        );
+
+        Ok(Self { operator, name })
+    }
+
+    /// 验证凭据和 bucket 可访问（列出根目录）
+    pub async fn verify_bucket(&self) -> Result<()> {
+        self.operator.list("").await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for GcsStorage {
+    async fn list_files(&self, prefix: Option<&str>) -> Result<Vec<FileInfo>> {
+        self.list_files_paged(prefix, None).await
+    }
+
+    async fn list_files_paged(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+    ) -> Result<Vec<FileInfo>> {
+        self.list_files_counted(prefix, page_size, &ListCounter::default()).await
+    }
+
+    async fn list_files_counted(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+        listed: &ListCounter,
+    ) -> Result<Vec<FileInfo>> {
+        let mut files = Vec::new();
+        let path = prefix.map(normalize_path).unwrap_or_default();
+
+        let mut list_op = self.operator.lister_with(&path).recursive(true).metakey(
+            Metakey::ContentLength
+                | Metakey::LastModified
+                | Metakey::Mode
+                | Metakey::ContentMd5
+                | Metakey::Etag,
+        );
+        if let Some(limit) = page_size {
+            list_op = list_op.limit(limit);
+        }
+        let mut lister = list_op.await?;
+
+        while let Some(entry) = lister.try_next().await? {
+            let path_str = entry.path().to_string();
+
+            // 跳过根目录
+            if path_str.is_empty() || path_str == "/" {
+                continue;
+            }
+
+            let meta = entry.metadata();
+            // 目录占位对象（"dir/"）按目录处理，不作为文件同步
+            let is_dir = meta.is_dir() || path_str.ends_with('/');
+
+            files.push(FileInfo {
+                path: normalize_path(&path_str),
+                size: if is_dir { 0 } else { meta.content_length() },
+                modified_time: meta.last_modified().map_or(0, |t| t.timestamp()),
+                is_dir,
+                checksum: if is_dir { None } else { object_checksum(meta) },
+                hidden: false,
+            });
+            listed.add(1)?;
+        }
+
+        Ok(dedup_listing(files))
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<FileMeta>> {
+        match self.operator.stat(&normalize_path(path)).await {
+            Ok(meta) => Ok(Some(FileMeta {
+                size: meta.content_length(),
+                modified_time: meta.last_modified().map_or(0, |t| t.timestamp()),
+                is_dir: meta.is_dir(),
+                etag: meta.etag().map(|s| s.trim_matches('"').to_string()),
+            })),
+            Err(e) if e.kind() == opendal::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 列出根目录的第一页（stat 根路径不会发出请求）
+    async fn probe_root(&self) -> Result<()> {
+        let mut lister = self.operator.lister_with("").limit(1).await?;
+        lister.try_next().await?;
+        Ok(())
+    }
+
+    async fn root_is_empty(&self) -> Result<Option<bool>> {
+        Ok(Some(super::opendal_root_is_empty(&self.operator).await?))
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let data = self.operator.read(&normalize_path(path)).await?;
+        Ok(data.to_vec())
+    }
+
+    async fn read_range(&self, path: &str, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let data = self
+            .operator
+            .read_with(&normalize_path(path))
+            .range(offset..offset + length)
+            .await?;
+        Ok(data.to_vec())
+    }
+
+    async fn write(&self, path: &str, data: Vec<u8>) -> Result<()> {
+        let path = normalize_path(path);
+        let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+        self.operator
+            .write_with(&path, data)
+            .content_type(content_type.essence_str())
+            .await?;
+        Ok(())
+    }
+
+    async fn write_stream(
+        &self,
+        path: &str,
+        mut stream: Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>,
+        _total_size: Option<u64>,
+    ) -> Result<()> {
+        // 按分片大小写入，OpenDAL 会使用可续传上传（HMAC 模式下为分段上传）
+        let path = normalize_path(path);
+        let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+        let mut writer = self
+            .operator
+            .writer_with(&path)
+            .chunk(GCS_CHUNK_SIZE)
+            .content_type(content_type.essence_str())
+            .await?;
+
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(data) => writer.write(data).await?,
+                Err(e) => {
+                    // 取消未完成的上传会话
+                    let _ = writer.abort().await;
+                    return Err(e);
+                }
+            }
+        }
+
+        writer.close().await?;
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        match self.operator.delete(&normalize_path(path)).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == opendal::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 对象存储没有真正的目录，只删除 "dir/" 占位对象，其下的文件不受影响
+    async fn remove_empty_dir(&self, path: &str) -> Result<()> {
+        let path = normalize_path(path);
+        self.operator
+            .delete(&format!("{}/", path.trim_end_matches('/')))
+            .await?;
+        Ok(())
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        // GCS 没有真正的目录，创建占位对象以保持兼容
+        let path = normalize_path(path);
+        let dir_path = if path.ends_with('/') {
+            path
+        } else {
+            format!("{}/", path)
+        };
+        self.operator.write(&dir_path, Vec::<u8>::new()).await?;
+        Ok(())
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        // GCS 没有重命名，使用服务端复制后删除
+        let from = normalize_path(from);
+        self.operator.copy(&from, &normalize_path(to)).await?;
+        self.operator.delete(&from).await?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
@@ -0,0 +1,382 @@
+use super::{
+    dedup_listing, http_client_builder, normalize_path, FileInfo, FileMeta, Storage,
+    StorageCapabilities, SyncError,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::StreamExt;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// HTTP 连接超时（秒）
+const HTTP_CONNECT_TIMEOUT_SECS: u64 = 30;
+/// HTTP 请求超时（秒）
+const HTTP_REQUEST_TIMEOUT_SECS: u64 = 300;
+/// 索引文件名（存在时优先使用，不再爬取目录列表）
+const INDEX_FILE_NAME: &str = "index.json";
+/// 爬取目录列表的最大深度
+const MAX_CRAWL_DEPTH: usize = 32;
+/// 获取文件元数据（HEAD）的并发数
+const HEAD_CONCURRENCY: usize = 8;
+
+/// index.json 条目
+#[derive(Debug, Deserialize)]
+struct IndexEntry {
+    path: String,
+    #[serde(default)]
+    size: u64,
+    /// 修改时间（Unix 时间戳，秒）
+    #[serde(default)]
+    modified: i64,
+}
+
+impl IndexEntry {
+    fn into_file_info(self) -> FileInfo {
+        FileInfo {
+            // 与爬取结果一致："./a.txt"、"/a.txt" 与 "a.txt" 是同一个文件
+            path: normalize_path(&self.path),
+            size: self.size,
+            modified_time: self.modified,
+            is_dir: false,
+            checksum: None,
+            hidden: false,
+        }
+    }
+}
+
+/// 只读 HTTP 存储（静态文件服务器 / 目录列表站点），仅可作为源存储
+pub struct HttpStorage {
+    client: reqwest::Client,
+    base_url: String,
+    username: Option<String>,
+    password: Option<String>,
+    name: String,
+}
+
+impl HttpStorage {
+    pub fn new(
+        url: &str,
+        username: Option<String>,
+        password: Option<String>,
+        request_tag: Option<&str>,
+    ) -> Result<Self> {
+        let client = http_client_builder(request_tag)?
+            .connect_timeout(Duration::from_secs(HTTP_CONNECT_TIMEOUT_SECS))
+            .timeout(Duration::from_secs(HTTP_REQUEST_TIMEOUT_SECS))
+            .build()?;
+
+        let base_url = format!("{}/", url.trim_end_matches('/'));
+        let name = format!("http:{}", url);
+
+        Ok(Self {
+            client,
+            base_url,
+            username,
+            password,
+            name,
+        })
+    }
+
+    /// 拼接文件 URL（对路径各段做 URL 编码）
+    fn url_for(&self, path: &str) -> String {
+        let encoded: Vec<String> = path
+            .trim_start_matches('/')
+            .split('/')
+            .map(|seg| urlencoding::encode(seg).into_owned())
+            .collect();
+        format!("{}{}", self.base_url, encoded.join("/"))
+    }
+
+    fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.username {
+            Some(user) => request.basic_auth(user, self.password.as_ref()),
+            None => request,
+        }
+    }
+
+    /// 读取 index.json（不存在时返回 None）
+    async fn load_index(&self) -> Result<Option<Vec<FileInfo>>> {
+        let url = format!("{}{}", self.base_url, INDEX_FILE_NAME);
+        let response = self.request(reqwest::Method::GET, &url).send().await?;
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+
+        let entries: Vec<IndexEntry> = match response.json().await {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::debug!("index.json 解析失败，改为爬取目录列表: {}", e);
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(entries.into_iter().map(IndexEntry::into_file_info).collect()))
+    }
+
+    /// 爬取目录列表页面，返回文件相对路径（同一文件的多个链接只返回一次）
+    async fn crawl(&self, prefix: &str) -> Result<Vec<String>> {
+        let href_re = Regex::new(r#"(?i)href\s*=\s*"([^"]+)""#)?;
+        let mut files = Vec::new();
+        let mut seen_files = HashSet::new();
+        let mut visited = HashSet::new();
+        let mut pending = vec![(prefix.trim_matches('/').to_string(), 0usize)];
+
+        while let Some((dir, depth)) = pending.pop() {
+            if !visited.insert(dir.clone()) || depth > MAX_CRAWL_DEPTH {
+                continue;
+            }
+
+            let url = if dir.is_empty() {
+                self.base_url.clone()
+            } else {
+                format!("{}/", self.url_for(&dir))
+            };
+            let response = self.request(reqwest::Method::GET, &url).send().await?;
+            if !response.status().is_success() {
+                let status = response.status();
+                return Err(SyncError::from_status(
+                    status,
+                    format!("获取目录列表失败: {} ({})", url, status),
+                )
+                .into());
+            }
+            let html = response.text().await?;
+
+            for cap in href_re.captures_iter(&html) {
+                match child_link(&dir, &cap[1]) {
+                    Some((child, true)) => pending.push((child, depth + 1)),
+                    Some((child, false)) => {
+                        // 避免对同一文件重复发送 HEAD 请求
+                        if seen_files.insert(child.clone()) {
+                            files.push(child);
+                        }
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+/// 解析目录列表页面中的链接，返回子条目的相对路径及是否为目录
+///
+/// 只处理当前目录下的相对链接；"./a.txt" 与 "a.txt" 得到相同的路径，指向目录自身的链接返回 None
+fn child_link(dir: &str, href: &str) -> Option<(String, bool)> {
+    if href.starts_with('?')
+        || href.starts_with('#')
+        || href.starts_with('/')
+        || href.starts_with("..")
+        || href.contains("://")
+        || href.starts_with("mailto:")
+    {
+        return None;
+    }
+
+    let href = href.split(['?', '#']).next().unwrap_or_default();
+    let name = urlencoding::decode(href)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| href.to_string());
+    let joined = if dir.is_empty() {
+        name.clone()
+    } else {
+        format!("{}/{}", dir, name)
+    };
+    let child = normalize_path(&joined).trim_end_matches('/').to_string();
+    if child.is_empty() || child == dir || child.split('/').any(|seg| seg == "..") {
+        return None;
+    }
+    Some((child, name.ends_with('/')))
+}
+
+/// 解析 HTTP Last-Modified 头（RFC 2822 格式）
+fn parse_last_modified(headers: &reqwest::header::HeaderMap) -> i64 {
+    headers
+        .get(reqwest::header::LAST_MODIFIED)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok())
+        .map_or(0, |t| t.timestamp())
+}
+
+/// 只读存储的写操作错误
+fn read_only_error() -> anyhow::Error {
+    anyhow!("HTTP 存储为只读，只能作为源存储")
+}
+
+#[async_trait]
+impl Storage for HttpStorage {
+    async fn list_files(&self, prefix: Option<&str>) -> Result<Vec<FileInfo>> {
+        let prefix = prefix.unwrap_or("").trim_matches('/');
+
+        // 优先使用 index.json
+        if let Some(files) = self.load_index().await? {
+            tracing::info!("HTTP 索引加载完成: {} 个文件", files.len());
+            return Ok(dedup_listing(
+                files
+                    .into_iter()
+                    .filter(|f| prefix.is_empty() || f.path.starts_with(prefix))
+                    .collect(),
+            ));
+        }
+
+        // 爬取目录列表，并通过 HEAD 获取大小和修改时间
+        let paths = self.crawl(prefix).await?;
+        let files: Vec<FileInfo> = futures::stream::iter(paths)
+            .map(|path| async move {
+                let meta = self.stat(&path).await.ok().flatten();
+                FileInfo {
+                    size: meta.as_ref().map_or(0, |m| m.size),
+                    modified_time: meta.as_ref().map_or(0, |m| m.modified_time),
+                    checksum: meta.and_then(|m| m.etag),
+                    path,
+                    is_dir: false,
+                    hidden: false,
+                }
+            })
+            .buffer_unordered(HEAD_CONCURRENCY)
+            .collect()
+            .await;
+
+        // 同一文件可能以 "./a.txt" 和 "a.txt" 等多个链接出现
+        let files = dedup_listing(files);
+        tracing::info!("HTTP 目录爬取完成: {} 个文件", files.len());
+        Ok(files)
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<FileMeta>> {
+        let url = self.url_for(path);
+        let response = self.request(reqwest::Method::HEAD, &url).send().await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(
+                SyncError::from_status(status, format!("获取文件信息失败: {} ({})", path, status))
+                    .into(),
+            );
+        }
+
+        let headers = response.headers();
+        Ok(Some(FileMeta {
+            size: response.content_length().unwrap_or(0),
+            modified_time: parse_last_modified(headers),
+            is_dir: false,
+            etag: headers
+                .get(reqwest::header::ETAG)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.trim_start_matches("W/").trim_matches('"').to_string()),
+        }))
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let response = self
+            .request(reqwest::Method::GET, &self.url_for(path))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn read_range(&self, path: &str, offset: u64, length: u64) -> Result<Vec<u8>> {
+        if length == 0 {
+            return Ok(Vec::new());
+        }
+        let response = self
+            .request(reqwest::Method::GET, &self.url_for(path))
+            .header(
+                reqwest::header::RANGE,
+                format!("bytes={}-{}", offset, offset + length - 1),
+            )
+            .send()
+            .await?
+            .error_for_status()?;
+
+        // 服务器不支持 Range 时返回完整内容，手动截取
+        let partial = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+        let data = response.bytes().await?;
+        if partial {
+            Ok(data.to_vec())
+        } else {
+            let start = (offset as usize).min(data.len());
+            let end = (start + length as usize).min(data.len());
+            Ok(data[start..end].to_vec())
+        }
+    }
+
+    async fn write(&self, _path: &str, _data: Vec<u8>) -> Result<()> {
+        Err(read_only_error())
+    }
+
+    async fn delete(&self, _path: &str) -> Result<()> {
+        Err(read_only_error())
+    }
+
+    async fn create_dir(&self, _path: &str) -> Result<()> {
+        Err(read_only_error())
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        StorageCapabilities::READ_ONLY
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_child_link() {
+        assert_eq!(child_link("", "a.txt"), Some(("a.txt".to_string(), false)));
+        assert_eq!(child_link("", "./a.txt"), Some(("a.txt".to_string(), false)));
+        assert_eq!(child_link("docs", "./sub/"), Some(("docs/sub".to_string(), true)));
+        assert_eq!(
+            child_link("docs", "my%20file.txt?x=1"),
+            Some(("docs/my file.txt".to_string(), false))
+        );
+        // 指向目录自身、上级目录或其他站点的链接
+        assert_eq!(child_link("docs", "./"), None);
+        assert_eq!(child_link("docs", "."), None);
+        assert_eq!(child_link("docs", "../"), None);
+        assert_eq!(child_link("docs", "/docs/a.txt"), None);
+        assert_eq!(child_link("docs", "https://example.com/"), None);
+    }
+
+    #[test]
+    fn test_crawled_duplicates_are_merged() {
+        let files: Vec<FileInfo> = ["a.txt", "./a.txt", "sub/./b.txt", "sub/b.txt"]
+            .into_iter()
+            .filter_map(|href| child_link("", href))
+            .map(|(path, is_dir)| FileInfo {
+                path,
+                size: 1,
+                modified_time: 0,
+                is_dir,
+                checksum: None,
+                hidden: false,
+            })
+            .collect();
+        let paths: Vec<_> = dedup_listing(files).into_iter().map(|f| f.path).collect();
+        assert_eq!(paths, vec!["a.txt", "sub/b.txt"]);
+    }
+
+    #[test]
+    fn test_index_duplicates_are_merged() {
+        let entries: Vec<IndexEntry> = serde_json::from_str(
+            r#"[{"path":"./a.txt","size":3},{"path":"a.txt","size":3},{"path":"/sub/./b.txt"}]"#,
+        )
+        .unwrap();
+        let files = entries.into_iter().map(IndexEntry::into_file_info).collect();
+        let paths: Vec<_> = dedup_listing(files).into_iter().map(|f| f.path).collect();
+        assert_eq!(paths, vec!["a.txt", "sub/b.txt"]);
+    }
+}
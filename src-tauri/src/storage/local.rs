@@ -1,11 +1,21 @@
-use super::{FileInfo, FileMeta, Storage};
+use super::{
+    dedup_listing, FileInfo, FileMeta, ListCounter, Storage, StorageCapabilities, SyncError,
+};
+use crate::core::file_state::{calculate_file_checksum, ChecksumAlgorithm};
 use anyhow::Result;
 use async_trait::async_trait;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use tokio::fs;
-use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::Semaphore;
 use walkdir::WalkDir;
 
+/// 检测目录是否可写时创建的探测文件名前缀
+const WRITE_PROBE_PREFIX: &str = ".synctools_write_probe";
+
 pub struct LocalStorage {
     base_path: PathBuf,
     name: String,
@@ -21,6 +31,30 @@ impl LocalStorage {
         Ok(Self { base_path, name })
     }
 
+    /// 打开已存在的本地目录（用于源存储，不会自动创建）
+    ///
+    /// 可移动磁盘或网络驱动器未挂载时，自动创建出的空目录会让镜像模式删除目标中的全部文件。
+    /// 配置了挂载标记文件时，还要求该文件存在于目录中。
+    pub fn open_existing(path: &str, mount_marker: Option<&str>) -> Result<Self> {
+        let base_path = PathBuf::from(path);
+        if !base_path.is_dir() {
+            return Err(SyncError::NotFound(format!("本地目录不存在或未挂载: {}", path)).into());
+        }
+
+        if let Some(marker) = mount_marker.filter(|m| !m.is_empty()) {
+            if !base_path.join(marker).exists() {
+                return Err(SyncError::NotFound(format!(
+                    "未找到挂载标记文件 {}，目录可能未挂载: {}",
+                    marker, path
+                ))
+                .into());
+            }
+        }
+
+        let name = format!("local:{}", path);
+        Ok(Self { base_path, name })
+    }
+
     fn resolve_path(&self, path: &str) -> PathBuf {
         let path = path.trim_start_matches('/').trim_start_matches('\\');
         if path.is_empty() {
@@ -30,15 +64,15 @@ impl LocalStorage {
         }
     }
 
-    /// 规范化路径分隔符（统一使用 /）
-    fn normalize_path(path: &str) -> String {
-        path.replace('\\', "/")
-    }
-}
-
-#[async_trait]
-impl Storage for LocalStorage {
-    async fn list_files(&self, prefix: Option<&str>) -> Result<Vec<FileInfo>> {
+    /// 遍历目录列出条目，最多列出 `max_entries + 1` 个（多出的一个用于判断是否超出上限）
+    ///
+    /// `cancelled` 被设置时（如扫描超时）遍历在下一个条目处停止并返回错误
+    async fn walk(
+        &self,
+        prefix: Option<&str>,
+        max_entries: Option<u64>,
+        cancelled: Option<Arc<AtomicBool>>,
+    ) -> Result<Vec<FileInfo>> {
         let base = prefix.map_or_else(|| self.base_path.clone(), |p| self.resolve_path(p));
 
         if !base.exists() {
@@ -49,15 +83,26 @@ impl Storage for LocalStorage {
         let base_path = self.base_path.clone();
 
         // 使用 spawn_blocking 避免阻塞 async runtime
+        let is_cancelled = move || cancelled.as_ref().is_some_and(|f| f.load(Ordering::Relaxed));
         let entries: Vec<_> = tokio::task::spawn_blocking(move || {
-            WalkDir::new(&base)
+            // 隐藏目录下的条目同样视为隐藏
+            let mut hidden_dirs: Vec<PathBuf> = Vec::new();
+
+            let entries: Vec<_> = WalkDir::new(&base)
                 .follow_links(false)
                 .into_iter()
+                .take_while(|_| !is_cancelled())
                 .filter_map(|e| e.ok())
                 .filter_map(|entry| {
                     let path = entry.path();
                     let metadata = entry.metadata().ok()?;
 
+                    let hidden = is_hidden_or_system(&metadata)
+                        || hidden_dirs.iter().any(|d| path.starts_with(d));
+                    if hidden && metadata.is_dir() {
+                        hidden_dirs.push(path.to_path_buf());
+                    }
+
                     let relative_path = path.strip_prefix(&base_path).ok()?.to_str()?.to_string();
 
                     // 跳过根目录本身
@@ -78,16 +123,157 @@ impl Storage for LocalStorage {
                         modified_time: modified,
                         is_dir: metadata.is_dir(),
                         checksum: None,
+                        hidden,
                     })
                 })
-                .collect()
+                .take(max_entries.map_or(usize::MAX, |n| n.saturating_add(1) as usize))
+                .collect();
+            (!is_cancelled()).then_some(entries)
         })
-        .await?;
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("列出已取消"))?;
 
         files.extend(entries);
+        // 与远程后端使用相同的规则合并重复条目
+        Ok(dedup_listing(files))
+    }
+
+    /// 规范化路径分隔符（统一使用 /）
+    fn normalize_path(path: &str) -> String {
+        path.replace('\\', "/")
+    }
+}
+
+/// 是否带有 Windows 隐藏/系统文件属性
+#[cfg(windows)]
+fn is_hidden_or_system(metadata: &std::fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+    metadata.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0
+}
+
+/// 非 Windows 平台没有隐藏属性，隐藏文件由扫描器按 "." 前缀判断
+#[cfg(not(windows))]
+fn is_hidden_or_system(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// 把源文件的扩展属性（xattr，如 macOS Finder 标签）复制到目标文件，返回复制的属性数
+///
+/// 单个属性失败时继续复制其余属性，最后一并返回所有失败的属性
+#[cfg(unix)]
+pub fn copy_xattrs(from: &std::path::Path, to: &std::path::Path) -> Result<usize> {
+    if !xattr::SUPPORTED_PLATFORM {
+        return Ok(0);
+    }
+    let mut copied = 0;
+    let mut failures = Vec::new();
+    for name in xattr::list(from)? {
+        let result = xattr::get(from, &name).and_then(|value| match value {
+            Some(value) => xattr::set(to, &name, &value).map(|_| true),
+            None => Ok(false),
+        });
+        match result {
+            Ok(true) => copied += 1,
+            Ok(false) => {}
+            Err(e) => failures.push(format!("{} ({})", name.to_string_lossy(), e)),
+        }
+    }
+    if !failures.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{} 个扩展属性复制失败（成功 {} 个）: {}",
+            failures.len(),
+            copied,
+            failures.join("; ")
+        ));
+    }
+    Ok(copied)
+}
+
+/// 非 Unix 平台不支持扩展属性
+#[cfg(not(unix))]
+pub fn copy_xattrs(_from: &std::path::Path, _to: &std::path::Path) -> Result<usize> {
+    Ok(0)
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn list_files(&self, prefix: Option<&str>) -> Result<Vec<FileInfo>> {
+        self.walk(prefix, None, None).await
+    }
+
+    /// 遍历时按剩余额度停止，超出上限的目录树不会完整载入内存
+    async fn list_files_counted(
+        &self,
+        prefix: Option<&str>,
+        _page_size: Option<usize>,
+        listed: &ListCounter,
+    ) -> Result<Vec<FileInfo>> {
+        let remaining = listed.limit().map(|limit| limit.saturating_sub(listed.get()));
+        let files = self.walk(prefix, remaining, Some(listed.cancel_flag())).await?;
+        listed.add(files.len() as u64)?;
         Ok(files)
     }
 
+    async fn fill_checksums(
+        &self,
+        files: &mut HashMap<String, FileInfo>,
+        known: &HashMap<String, FileInfo>,
+        max_size: u64,
+        concurrency: usize,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<usize> {
+        // 大小和修改时间都未变化（且算法相同）的文件复用已有校验和，其余的需要重新计算
+        let mut to_hash = Vec::new();
+        for (path, file) in files.iter_mut() {
+            if file.is_dir || (max_size > 0 && file.size > max_size) {
+                continue;
+            }
+            match known.get(path) {
+                Some(k)
+                    if k.size == file.size
+                        && k.modified_time == file.modified_time
+                        && k
+                            .checksum
+                            .as_deref()
+                            .is_some_and(|c| c.starts_with(algorithm.prefix())) =>
+                {
+                    file.checksum = k.checksum.clone();
+                }
+                _ => to_hash.push(path.clone()),
+            }
+        }
+
+        let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+        let mut handles = Vec::with_capacity(to_hash.len());
+        for path in to_hash {
+            let permit = semaphore.clone().acquire_owned().await?;
+            let full_path = self.resolve_path(&path);
+            handles.push(tokio::task::spawn_blocking(move || {
+                let result = calculate_file_checksum(&full_path, algorithm);
+                drop(permit);
+                (path, result)
+            }));
+        }
+
+        let mut hashed = 0;
+        for handle in handles {
+            let (path, result) = handle.await?;
+            match result {
+                Ok(checksum) => {
+                    if let Some(file) = files.get_mut(&path) {
+                        file.checksum = Some(checksum);
+                        hashed += 1;
+                    }
+                }
+                Err(e) => tracing::debug!("计算校验和失败: {} - {}", path, e),
+            }
+        }
+
+        Ok(hashed)
+    }
+
     async fn stat(&self, path: &str) -> Result<Option<FileMeta>> {
         let full_path = self.resolve_path(path);
 
@@ -110,14 +296,18 @@ impl Storage for LocalStorage {
         }
     }
 
+    async fn root_is_empty(&self) -> Result<Option<bool>> {
+        Ok(Some(self.walk(None, Some(0), None).await?.is_empty()))
+    }
+
     async fn read(&self, path: &str) -> Result<Vec<u8>> {
-        let data = fs::read(self.resolve_path(path)).await?;
+        let data = fs::read(self.resolve_path(path)).await.map_err(SyncError::from)?;
         Ok(data)
     }
 
     async fn read_range(&self, path: &str, offset: u64, length: u64) -> Result<Vec<u8>> {
         let full_path = self.resolve_path(path);
-        let mut file = fs::File::open(&full_path).await?;
+        let mut file = fs::File::open(&full_path).await.map_err(SyncError::from)?;
 
         file.seek(std::io::SeekFrom::Start(offset)).await?;
 
@@ -133,7 +323,7 @@ impl Storage for LocalStorage {
                 file.read_to_end(&mut buffer).await?;
                 Ok(buffer)
             }
-            Err(e) => Err(e.into()),
+            Err(e) => Err(SyncError::from(e).into()),
         }
     }
 
@@ -146,12 +336,46 @@ impl Storage for LocalStorage {
 
         // 使用临时文件写入，然后原子重命名
         let temp_path = full_path.with_extension("tmp");
-        fs::write(&temp_path, data).await?;
-        fs::rename(&temp_path, &full_path).await?;
+        fs::write(&temp_path, data).await.map_err(SyncError::from)?;
+        // 目标文件被其他程序打开时替换会失败（Windows 共享冲突），清理临时文件
+        if let Err(e) = fs::rename(&temp_path, &full_path).await {
+            let _ = fs::remove_file(&temp_path).await;
+            return Err(SyncError::from(e).into());
+        }
 
         Ok(())
     }
 
+    async fn append(&self, path: &str, offset: u64, data: Vec<u8>) -> Result<()> {
+        let full_path = self.resolve_path(path);
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(&full_path)
+            .await
+            .map_err(SyncError::from)?;
+        let current = file.metadata().await?.len();
+        if current != offset {
+            return Err(SyncError::Conflict(format!(
+                "追加位置不匹配: {} 当前大小 {}，预期 {}",
+                full_path.display(),
+                current,
+                offset
+            ))
+            .into());
+        }
+        file.write_all(&data).await.map_err(SyncError::from)?;
+        file.flush().await?;
+        Ok(())
+    }
+
+    fn supports_append(&self) -> bool {
+        true
+    }
+
+    fn local_path(&self, path: &str) -> Option<PathBuf> {
+        Some(self.resolve_path(path))
+    }
+
     async fn delete(&self, path: &str) -> Result<()> {
         let full_path = self.resolve_path(path);
 
@@ -168,13 +392,180 @@ impl Storage for LocalStorage {
         Ok(())
     }
 
+    async fn remove_empty_dir(&self, path: &str) -> Result<()> {
+        // remove_dir 在目录不为空时失败，不会删除列表中遗漏的文件
+        fs::remove_dir(self.resolve_path(path))
+            .await
+            .map_err(SyncError::from)?;
+        Ok(())
+    }
+
     async fn create_dir(&self, path: &str) -> Result<()> {
         let full_path = self.resolve_path(path);
         fs::create_dir_all(&full_path).await?;
         Ok(())
     }
 
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        let from_path = self.resolve_path(from);
+        let to_path = self.resolve_path(to);
+        if let Some(parent) = to_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::rename(&from_path, &to_path).await?;
+        Ok(())
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        match std::fs::metadata(&self.base_path) {
+            Ok(meta) if meta.is_dir() => {}
+            _ => {
+                return StorageCapabilities {
+                    read: false,
+                    write: false,
+                    delete: false,
+                }
+            }
+        }
+        // 实际创建并删除探测文件：权限位无法反映只读挂载、ACL 和运行用户
+        let probe = self.base_path.join(format!(
+            "{}_{}_{}",
+            WRITE_PROBE_PREFIX,
+            std::process::id(),
+            uuid::Uuid::new_v4().simple()
+        ));
+        match std::fs::write(&probe, b"") {
+            Ok(()) => {
+                let _ = std::fs::remove_file(&probe);
+                StorageCapabilities::FULL
+            }
+            Err(e) => {
+                tracing::debug!("本地目录不可写: {:?} - {}", self.base_path, e);
+                StorageCapabilities::READ_ONLY
+            }
+        }
+    }
+
     fn name(&self) -> &str {
         &self.name
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("synctools-local-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_capabilities_probe_writability() {
+        let dir = test_dir();
+        let storage = LocalStorage::new(dir.to_str().unwrap()).unwrap();
+        assert_eq!(storage.capabilities(), StorageCapabilities::FULL);
+        // 探测文件已删除
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(!storage.capabilities().read);
+    }
+
+    #[tokio::test]
+    async fn test_list_counted_stops_at_limit() {
+        let dir = test_dir();
+        let storage = LocalStorage::new(dir.to_str().unwrap()).unwrap();
+        for i in 0..10 {
+            std::fs::write(dir.join(format!("{}.txt", i)), b"x").unwrap();
+        }
+
+        // 遍历在上限之后一个条目处停止
+        assert_eq!(storage.walk(None, Some(3), None).await.unwrap().len(), 4);
+        let listed = ListCounter::new(Some(3));
+        assert!(storage.list_files_counted(None, None, &listed).await.is_err());
+
+        let listed = ListCounter::new(Some(10));
+        assert_eq!(storage.list_files_counted(None, None, &listed).await.unwrap().len(), 10);
+        assert_eq!(listed.get(), 10);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_list_counted_stops_when_cancelled() {
+        let dir = test_dir();
+        let storage = LocalStorage::new(dir.to_str().unwrap()).unwrap();
+        std::fs::write(dir.join("a.txt"), b"x").unwrap();
+
+        let listed = ListCounter::default();
+        listed.cancel();
+        let err = storage.list_files_counted(None, None, &listed).await.unwrap_err();
+        assert!(err.to_string().contains("已取消"), "{}", err);
+        assert_eq!(listed.get(), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_list_prefix_paths_are_unique() {
+        let dir = test_dir();
+        let storage = LocalStorage::new(dir.to_str().unwrap()).unwrap();
+        std::fs::create_dir_all(dir.join("docs/sub")).unwrap();
+        std::fs::write(dir.join("docs/a.txt"), b"x").unwrap();
+        std::fs::write(dir.join("docs/sub/b.txt"), b"x").unwrap();
+
+        let paths = |files: Vec<FileInfo>| {
+            let mut paths: Vec<_> = files.into_iter().map(|f| f.path).collect();
+            paths.sort();
+            paths
+        };
+        let expected = vec!["docs", "docs/a.txt", "docs/sub", "docs/sub/b.txt"];
+        assert_eq!(paths(storage.list_files(Some("docs")).await.unwrap()), expected);
+        assert_eq!(paths(storage.list_files(Some("./docs/")).await.unwrap()), expected);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_capabilities_read_only_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // root 不受权限位限制
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let dir = test_dir();
+        let storage = LocalStorage::new(dir.to_str().unwrap()).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+        assert_eq!(storage.capabilities(), StorageCapabilities::READ_ONLY);
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// 单个属性失败时继续复制，并报告全部失败的属性
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_xattrs_reports_all_failures() {
+        let dir = test_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        let (from, to) = (dir.join("from.txt"), dir.join("to.txt"));
+        std::fs::write(&from, b"x").unwrap();
+        std::fs::write(&to, b"x").unwrap();
+        // 文件系统不支持用户扩展属性时跳过
+        if xattr::set(&from, "user.synctools.a", b"1").is_err() {
+            let _ = std::fs::remove_dir_all(&dir);
+            return;
+        }
+        xattr::set(&from, "user.synctools.b", b"2").unwrap();
+
+        assert_eq!(copy_xattrs(&from, &to).unwrap(), 2);
+        assert_eq!(xattr::get(&to, "user.synctools.b").unwrap(), Some(b"2".to_vec()));
+
+        let err = copy_xattrs(&from, &dir.join("missing.txt")).unwrap_err().to_string();
+        assert!(err.contains("user.synctools.a") && err.contains("user.synctools.b"), "{}", err);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,272 @@
+//! 内存存储（仅测试使用）：模拟目录语义，并可注入列表遗漏、创建目录失败、慢速列表等情况
+
+use super::{FileInfo, FileMeta, Storage, SyncError};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Clone)]
+struct Entry {
+    data: Vec<u8>,
+    modified_time: i64,
+    is_dir: bool,
+}
+
+#[derive(Default)]
+pub struct MemoryStorage {
+    entries: Mutex<BTreeMap<String, Entry>>,
+    /// 列出时遗漏的路径（模拟无法读取元数据、非 UTF-8 文件名等被跳过的条目）
+    unlisted: Mutex<HashSet<String>>,
+    /// 根目录是否存在（不存在时列出和 stat 根目录都返回 NotFound）
+    root_missing: AtomicBool,
+    /// 接下来的 create_dir 调用失败的次数
+    create_dir_failures: AtomicU32,
+    /// create_dir 调用次数
+    pub create_dir_calls: AtomicU32,
+    /// 每次列出前的延迟（毫秒）
+    list_delay_ms: AtomicU64,
+    /// 读取整个文件的次数
+    pub full_reads: AtomicUsize,
+    /// 按范围读取的字节数
+    pub range_bytes: AtomicU64,
+    /// 删除失败的路径及剩余失败次数
+    delete_failures: Mutex<HashMap<String, u32>>,
+    /// 是否声明支持批量删除（使用默认的逐个删除实现）
+    batch_deletes: AtomicBool,
+}
+
+fn normalize(path: &str) -> String {
+    super::normalize_path(path).trim_end_matches('/').to_string()
+}
+
+fn parent(path: &str) -> Option<&str> {
+    path.rsplit_once('/').map(|(p, _)| p)
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 写入文件（自动创建上级目录），修改时间为 `modified_time`
+    pub fn put(&self, path: &str, data: &[u8], modified_time: i64) {
+        let path = normalize(path);
+        let mut entries = self.entries.lock().unwrap();
+        let mut dir = parent(&path);
+        while let Some(d) = dir {
+            entries.entry(d.to_string()).or_insert(Entry {
+                data: Vec::new(),
+                modified_time,
+                is_dir: true,
+            });
+            dir = parent(d);
+        }
+        entries.insert(
+            path,
+            Entry {
+                data: data.to_vec(),
+                modified_time,
+                is_dir: false,
+            },
+        );
+    }
+
+    /// 创建目录（自动创建上级目录）
+    pub fn put_dir(&self, path: &str) {
+        let path = normalize(path);
+        let mut entries = self.entries.lock().unwrap();
+        let mut dir = Some(path.as_str());
+        while let Some(d) = dir {
+            entries.entry(d.to_string()).or_insert(Entry {
+                data: Vec::new(),
+                modified_time: 0,
+                is_dir: true,
+            });
+            dir = parent(d);
+        }
+    }
+
+    /// 文件内容（不存在或为目录时返回 None）
+    pub fn get(&self, path: &str) -> Option<Vec<u8>> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(&normalize(path))
+            .filter(|e| !e.is_dir)
+            .map(|e| e.data.clone())
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.entries.lock().unwrap().contains_key(&normalize(path))
+    }
+
+    pub fn hide_from_listing(&self, path: &str) {
+        self.unlisted.lock().unwrap().insert(normalize(path));
+    }
+
+    pub fn set_root_missing(&self, missing: bool) {
+        self.root_missing.store(missing, Ordering::SeqCst);
+    }
+
+    pub fn fail_create_dir(&self, times: u32) {
+        self.create_dir_failures.store(times, Ordering::SeqCst);
+    }
+
+    /// 接下来删除 `path` 失败 `times` 次
+    pub fn fail_delete(&self, path: &str, times: u32) {
+        self.delete_failures.lock().unwrap().insert(normalize(path), times);
+    }
+
+    pub fn set_batch_deletes(&self, enabled: bool) {
+        self.batch_deletes.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn set_list_delay(&self, delay: Duration) {
+        self.list_delay_ms.store(delay.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    fn not_found(path: &str) -> anyhow::Error {
+        SyncError::NotFound(format!("memory: {} 不存在", path)).into()
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn list_files(&self, prefix: Option<&str>) -> Result<Vec<FileInfo>> {
+        let delay = self.list_delay_ms.load(Ordering::SeqCst);
+        if delay > 0 {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+        }
+        if self.root_missing.load(Ordering::SeqCst) {
+            return Err(Self::not_found("/"));
+        }
+        let prefix = prefix.map(normalize).filter(|p| !p.is_empty());
+        let unlisted = self.unlisted.lock().unwrap().clone();
+        let entries = self.entries.lock().unwrap();
+        Ok(entries
+            .iter()
+            .filter(|(path, _)| {
+                prefix
+                    .as_deref()
+                    .map_or(true, |p| path.starts_with(&format!("{}/", p)))
+            })
+            .filter(|(path, _)| !unlisted.contains(*path))
+            .map(|(path, e)| FileInfo {
+                path: path.clone(),
+                size: e.data.len() as u64,
+                modified_time: e.modified_time,
+                is_dir: e.is_dir,
+                checksum: None,
+                hidden: false,
+            })
+            .collect())
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<FileMeta>> {
+        let path = normalize(path);
+        if path.is_empty() {
+            return Ok((!self.root_missing.load(Ordering::SeqCst)).then_some(FileMeta {
+                size: 0,
+                modified_time: 0,
+                is_dir: true,
+                etag: None,
+            }));
+        }
+        Ok(self.entries.lock().unwrap().get(&path).map(|e| FileMeta {
+            size: e.data.len() as u64,
+            modified_time: e.modified_time,
+            is_dir: e.is_dir,
+            etag: None,
+        }))
+    }
+
+    async fn root_is_empty(&self) -> Result<Option<bool>> {
+        Ok(Some(self.entries.lock().unwrap().is_empty()))
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        self.full_reads.fetch_add(1, Ordering::SeqCst);
+        self.get(path).ok_or_else(|| Self::not_found(path))
+    }
+
+    async fn read_range(&self, path: &str, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let data = self.get(path).ok_or_else(|| Self::not_found(path))?;
+        let start = (offset as usize).min(data.len());
+        let end = (offset.saturating_add(length) as usize).min(data.len());
+        self.range_bytes.fetch_add((end - start) as u64, Ordering::SeqCst);
+        Ok(data[start..end].to_vec())
+    }
+
+    async fn write(&self, path: &str, data: Vec<u8>) -> Result<()> {
+        self.put(path, &data, chrono::Utc::now().timestamp());
+        Ok(())
+    }
+
+    async fn append(&self, path: &str, offset: u64, data: Vec<u8>) -> Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries
+            .get_mut(&normalize(path))
+            .ok_or_else(|| Self::not_found(path))?;
+        if entry.data.len() as u64 != offset {
+            return Err(SyncError::Conflict(format!("追加位置不匹配: {}", path)).into());
+        }
+        entry.data.extend(data);
+        Ok(())
+    }
+
+    fn supports_append(&self) -> bool {
+        true
+    }
+
+    /// 与本地存储一致：删除目录时连同其中的内容一起删除
+    async fn delete(&self, path: &str) -> Result<()> {
+        let path = normalize(path);
+        if let Some(remaining) = self.delete_failures.lock().unwrap().get_mut(&path) {
+            if *remaining > 0 {
+                *remaining -= 1;
+                return Err(SyncError::Network(format!("删除失败: {}", path)).into());
+            }
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let children = format!("{}/", path);
+        entries.retain(|p, _| *p != path && !p.starts_with(&children));
+        Ok(())
+    }
+
+    fn supports_delete_batch(&self) -> bool {
+        self.batch_deletes.load(Ordering::SeqCst)
+    }
+
+    async fn remove_empty_dir(&self, path: &str) -> Result<()> {
+        let path = normalize(path);
+        let mut entries = self.entries.lock().unwrap();
+        let children = format!("{}/", path);
+        if entries.keys().any(|p| p.starts_with(&children)) {
+            return Err(SyncError::Conflict(format!("目录不为空: {}", path)).into());
+        }
+        entries.remove(&path);
+        Ok(())
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        self.create_dir_calls.fetch_add(1, Ordering::SeqCst);
+        let remaining = self.create_dir_failures.load(Ordering::SeqCst);
+        if remaining > 0 {
+            self.create_dir_failures.store(remaining - 1, Ordering::SeqCst);
+            return Err(SyncError::Network("423 Locked".to_string()).into());
+        }
+        let path = normalize(path);
+        if path.is_empty() {
+            self.root_missing.store(false, Ordering::SeqCst);
+        } else {
+            self.put_dir(&path);
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "memory"
+    }
+}
@@ -1,14 +1,37 @@
+pub mod azblob;
+pub mod b2;
+pub mod dropbox;
+pub mod error;
+pub mod gcs;
+pub mod http;
 pub mod local;
+#[cfg(test)]
+pub mod memory;
+pub mod multi;
+pub mod onedrive;
 pub mod s3;
+pub mod secret;
 pub mod webdav;
 
+use crate::core::file_state::ChecksumAlgorithm;
 use anyhow::Result;
 use async_trait::async_trait;
 use futures::Stream;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
 
+pub use azblob::AzureBlobStorage;
+pub use b2::B2Storage;
+pub use dropbox::DropboxStorage;
+pub use error::SyncError;
+pub use gcs::GcsStorage;
+pub use http::HttpStorage;
 pub use local::LocalStorage;
+pub use multi::MultiSourceStorage;
+pub use onedrive::OneDriveStorage;
 pub use s3::S3Storage;
 pub use webdav::WebDavStorage;
 
@@ -18,6 +41,226 @@ pub use webdav::WebDavStorage;
 pub const OP_TIMEOUT_SECS: u64 = 60;
 /// IO 操作超时（秒）- read, write 等
 pub const IO_TIMEOUT_SECS: u64 = 300;
+/// 请求使用的 User-Agent，便于服务端在访问日志中识别本应用的流量
+pub const USER_AGENT: &str = concat!("SyncTools/", env!("CARGO_PKG_VERSION"));
+/// 携带任务请求标记的请求头
+pub const REQUEST_TAG_HEADER: &str = "x-synctools-tag";
+/// 每次批量删除的最大路径数（S3 DeleteObjects 的上限，同步时按此合并删除）
+pub const DELETE_BATCH_SIZE: usize = 1000;
+
+/// 生成 User-Agent（有请求标记时附加在括号中）
+pub fn user_agent(request_tag: Option<&str>) -> String {
+    match request_tag {
+        Some(tag) => format!("{} ({})", USER_AGENT, tag),
+        None => USER_AGENT.to_string(),
+    }
+}
+
+/// 创建带 User-Agent 和请求标记头的 HTTP 客户端构建器
+pub fn http_client_builder(request_tag: Option<&str>) -> Result<reqwest::ClientBuilder> {
+    let mut builder = reqwest::Client::builder().user_agent(user_agent(request_tag));
+    if let Some(tag) = request_tag {
+        let value = reqwest::header::HeaderValue::from_str(tag)
+            .map_err(|_| anyhow::anyhow!("请求标记包含无效字符: {}", tag))?;
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(REQUEST_TAG_HEADER, value);
+        builder = builder.default_headers(headers);
+    }
+    Ok(builder)
+}
+
+/// 创建 OpenDAL 使用的 HTTP 客户端（与其他请求使用相同的 User-Agent 和请求标记）
+pub fn opendal_http_client(request_tag: Option<&str>) -> Result<opendal::raw::HttpClient> {
+    Ok(opendal::raw::HttpClient::build(http_client_builder(request_tag)?)?)
+}
+
+/// 根目录是否为空：列出顶层的前两个条目，忽略根目录自身
+pub async fn opendal_root_is_empty(operator: &opendal::Operator) -> Result<bool> {
+    use futures::TryStreamExt;
+    let mut lister = operator.lister_with("").limit(2).await.map_err(SyncError::from)?;
+    while let Some(entry) = lister.try_next().await.map_err(SyncError::from)? {
+        if !matches!(entry.path(), "" | "/") {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// 对象的 Content-MD5（base64）转换为校验和，转成十六进制后可与本地 MD5 直接比较
+pub fn opendal_content_md5(meta: &opendal::Metadata) -> Option<String> {
+    use base64::Engine;
+    let bytes = meta
+        .content_md5()
+        .and_then(|b64| base64::engine::general_purpose::STANDARD.decode(b64).ok())
+        .filter(|bytes| bytes.len() == 16)?;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    Some(format!("{}{}", crate::core::file_state::MD5_CHECKSUM_PREFIX, hex))
+}
+
+/// 规范化远程存储路径
+///
+/// 统一使用正斜杠，去除前导斜杠、重复斜杠和 "." 段；保留末尾斜杠（目录标记）。
+/// 所有远程后端在 list/stat/read/write 前都应使用同一规则，保证列出的路径可以原样读写。
+pub fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let is_dir = path.ends_with('/');
+    let mut normalized = path
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/");
+    if is_dir && !normalized.is_empty() {
+        normalized.push('/');
+    }
+    normalized
+}
+
+/// 列出条目的计数（用于扫描进度）；设置上限时超出后中止列出，避免超大目录树在载入内存前耗尽内存
+///
+/// 扫描超时后设置取消标志，仍在后台运行的列出（如本地目录遍历）在下一个条目处停止
+#[derive(Debug, Default)]
+pub struct ListCounter {
+    listed: AtomicU64,
+    limit: Option<u64>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ListCounter {
+    /// `limit` 为列出条目数的上限（包括目录和之后被排除的文件），None 表示不限制
+    pub fn new(limit: Option<u64>) -> Self {
+        Self {
+            listed: AtomicU64::new(0),
+            limit,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// 已列出的条目数
+    pub fn get(&self) -> u64 {
+        self.listed.load(Ordering::Relaxed)
+    }
+
+    pub fn reset(&self) {
+        self.listed.store(0, Ordering::Relaxed);
+    }
+
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    /// 计数加 `n`，超出上限或已取消时返回错误
+    pub fn add(&self, n: u64) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(anyhow::anyhow!("列出已取消"));
+        }
+        let listed = self.listed.fetch_add(n, Ordering::Relaxed) + n;
+        match self.limit {
+            Some(limit) if listed > limit => Err(list_limit_error(limit)),
+            _ => Ok(()),
+        }
+    }
+
+    /// 中止列出（之后的 `add` 都返回错误）
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// 取消标志，供在阻塞线程中运行的列出检查
+    pub fn cancel_flag(&self) -> Arc<AtomicBool> {
+        self.cancelled.clone()
+    }
+}
+
+/// 列出条目数超过上限的错误
+pub fn list_limit_error(limit: u64) -> anyhow::Error {
+    SyncError::Other(format!(
+        "列出的条目数超过上限 {}，请用排除规则或附加源缩小同步范围、拆分为多个任务，或在扫描设置中调高上限",
+        limit
+    ))
+    .into()
+}
+
+/// 整理列出的条目：以 "/" 结尾的路径视为目录并去掉末尾斜杠，
+/// 只差末尾斜杠的重复条目（S3 的 "dir/" 占位对象、WebDAV 同时返回 "dir" 和 "dir/"）合并为一个目录条目
+pub fn dedup_listing(files: Vec<FileInfo>) -> Vec<FileInfo> {
+    let mut index: HashMap<String, usize> = HashMap::with_capacity(files.len());
+    let mut result: Vec<FileInfo> = Vec::with_capacity(files.len());
+    for mut file in files {
+        if file.path.ends_with('/') {
+            file.is_dir = true;
+            file.path.truncate(file.path.trim_end_matches('/').len());
+        }
+        if file.path.is_empty() {
+            continue;
+        }
+        if file.is_dir {
+            file.size = 0;
+            file.checksum = None;
+        }
+
+        let Some(&i) = index.get(&file.path) else {
+            index.insert(file.path.clone(), result.len());
+            result.push(file);
+            continue;
+        };
+        let existing = &mut result[i];
+        if existing.is_dir != file.is_dir {
+            // 同名的文件和目录无法同时存在于本地，保留目录
+            let file_size = if existing.is_dir { file.size } else { existing.size };
+            if file_size > 0 {
+                tracing::warn!("路径同时是文件和目录，忽略文件条目: {}", file.path);
+            }
+            existing.is_dir = true;
+            existing.size = 0;
+            existing.checksum = None;
+        }
+        existing.modified_time = existing.modified_time.max(file.modified_time);
+    }
+    result
+}
+
+/// 列表中可以删除的空目录（只包含可删除空目录的目录也算），按由深到浅的顺序返回
+///
+/// `only` 不为 None 时只考虑其中的目录；不在其中的空目录保留，其父目录也因此不为空
+pub fn empty_dirs(files: &[FileInfo], only: Option<&HashSet<String>>) -> Vec<String> {
+    let parent = |path: &str| path.rsplit_once('/').map(|(p, _)| p.to_string());
+
+    // 每个目录下的直接子项数
+    let mut children: HashMap<String, usize> = HashMap::new();
+    for file in files {
+        if let Some(p) = parent(file.path.trim_end_matches('/')) {
+            *children.entry(p).or_default() += 1;
+        }
+    }
+
+    let mut dirs: Vec<&str> = files
+        .iter()
+        .filter(|f| f.is_dir)
+        .map(|f| f.path.trim_end_matches('/'))
+        .filter(|p| !p.is_empty())
+        .collect();
+    let depth = |p: &str| p.matches('/').count();
+    dirs.sort_by(|a, b| depth(b).cmp(&depth(a)).then(a.cmp(b)));
+    dirs.dedup();
+
+    let mut result = Vec::new();
+    for dir in dirs {
+        if children.get(dir).copied().unwrap_or(0) > 0
+            || only.is_some_and(|only| !only.contains(dir))
+        {
+            continue;
+        }
+        if let Some(count) = parent(dir).and_then(|p| children.get_mut(&p)) {
+            *count -= 1;
+        }
+        result.push(dir.to_string());
+    }
+    result
+}
 
 /// 文件信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +270,32 @@ pub struct FileInfo {
     pub modified_time: i64,
     pub is_dir: bool,
     pub checksum: Option<String>,
+    /// 是否为隐藏/系统文件（Windows 文件属性，仅本地存储设置）
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+/// 存储支持的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StorageCapabilities {
+    pub read: bool,
+    pub write: bool,
+    pub delete: bool,
+}
+
+impl StorageCapabilities {
+    /// 支持读写删除
+    pub const FULL: Self = Self {
+        read: true,
+        write: true,
+        delete: true,
+    };
+    /// 只读
+    pub const READ_ONLY: Self = Self {
+        read: true,
+        write: false,
+        delete: false,
+    };
 }
 
 /// 文件元数据（用于快速检查）
@@ -49,12 +318,64 @@ pub struct FileChunk {
 /// 存储抽象接口
 #[async_trait]
 pub trait Storage: Send + Sync {
-    /// 递归列出所有文件
+    /// 递归列出所有文件（目录条目的路径不带末尾斜杠，同一路径只出现一次）
     async fn list_files(&self, prefix: Option<&str>) -> Result<Vec<FileInfo>>;
 
+    /// 递归列出所有文件（带分页大小提示，不支持分页的后端忽略该参数）
+    async fn list_files_paged(
+        &self,
+        prefix: Option<&str>,
+        _page_size: Option<usize>,
+    ) -> Result<Vec<FileInfo>> {
+        self.list_files(prefix).await
+    }
+
+    /// 递归列出所有文件，每列出一个条目把 `listed` 加一（用于扫描进度），超出计数上限时中止。
+    /// 默认列出完成后一次性计数
+    async fn list_files_counted(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+        listed: &ListCounter,
+    ) -> Result<Vec<FileInfo>> {
+        let files = self.list_files_paged(prefix, page_size).await?;
+        listed.add(files.len() as u64)?;
+        Ok(files)
+    }
+
+    /// 为文件树补充内容校验和，返回实际计算的文件数
+    ///
+    /// `known` 中大小和修改时间都未变化的文件直接复用已有校验和；
+    /// 超过 `max_size`（0 表示不限制）的文件不计算。默认不支持，不做任何处理。
+    async fn fill_checksums(
+        &self,
+        _files: &mut HashMap<String, FileInfo>,
+        _known: &HashMap<String, FileInfo>,
+        _max_size: u64,
+        _concurrency: usize,
+        _algorithm: ChecksumAlgorithm,
+    ) -> Result<usize> {
+        Ok(0)
+    }
+
     /// 获取文件元数据
     async fn stat(&self, path: &str) -> Result<Option<FileMeta>>;
 
+    /// 发出真实请求确认根目录可访问，根目录不存在时返回 NotFound 错误
+    ///
+    /// 不能用 `stat("")` 代替：OpenDAL 对根路径的 stat 不发请求，直接返回目录。默认使用 stat
+    async fn probe_root(&self) -> Result<()> {
+        match self.stat("").await? {
+            Some(_) => Ok(()),
+            None => Err(SyncError::NotFound(format!("{} 的根目录不存在", self.name())).into()),
+        }
+    }
+
+    /// 根目录是否为空（只列出顶层的少量条目），无法低成本判断时返回 None
+    async fn root_is_empty(&self) -> Result<Option<bool>> {
+        Ok(None)
+    }
+
     /// 读取整个文件
     async fn read(&self, path: &str) -> Result<Vec<u8>>;
 
@@ -80,9 +401,50 @@ pub trait Storage: Send + Sync {
         self.write(path, data).await
     }
 
+    /// 在文件末尾追加数据，`offset` 为追加前的文件大小（与实际大小不符时报冲突）。默认不支持
+    async fn append(&self, path: &str, _offset: u64, _data: Vec<u8>) -> Result<()> {
+        Err(anyhow::anyhow!("{} 不支持追加写入: {}", self.name(), path))
+    }
+
+    /// 是否支持追加写入
+    fn supports_append(&self) -> bool {
+        false
+    }
+
     /// 删除文件或目录
     async fn delete(&self, path: &str) -> Result<()>;
 
+    /// 批量删除，按输入顺序返回每个路径的结果；每删除成功一个路径调用一次 `on_deleted`（用于进度）。
+    /// 默认逐个调用 `delete`
+    async fn delete_batch(
+        &self,
+        paths: &[String],
+        on_deleted: &(dyn Fn(&str) + Send + Sync),
+    ) -> Vec<Result<()>> {
+        let mut results = Vec::with_capacity(paths.len());
+        for path in paths {
+            let result = self.delete(path).await;
+            if result.is_ok() {
+                on_deleted(path);
+            }
+            results.push(result);
+        }
+        results
+    }
+
+    /// 批量删除是否比逐个删除更快（同步时据此决定是否合并删除操作）
+    fn supports_delete_batch(&self) -> bool {
+        false
+    }
+
+    /// 删除空目录（非递归），目录不为空时失败。默认不支持
+    ///
+    /// 清理空目录时使用：`delete` 对本地目录和 WebDAV 集合是递归删除，
+    /// 列表遗漏的文件或列出后新写入的文件会被一并删除
+    async fn remove_empty_dir(&self, path: &str) -> Result<()> {
+        Err(anyhow::anyhow!("{} 不支持删除空目录: {}", self.name(), path))
+    }
+
     /// 检查文件是否存在
     async fn exists(&self, path: &str) -> Result<bool> {
         Ok(self.stat(path).await?.is_some())
@@ -97,6 +459,22 @@ pub trait Storage: Send + Sync {
         self.write(to, data).await
     }
 
+    /// 重命名/移动文件（同一存储内，默认复制后删除原文件）
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        self.copy(from, to).await?;
+        self.delete(from).await
+    }
+
+    /// 文件在本地文件系统中的路径（仅本地存储），用于复制扩展属性等文件系统元数据
+    fn local_path(&self, _path: &str) -> Option<std::path::PathBuf> {
+        None
+    }
+
+    /// 存储支持的操作（默认支持读写删除）
+    fn capabilities(&self) -> StorageCapabilities {
+        StorageCapabilities::FULL
+    }
+
     /// 获取存储名称（用于日志）
     fn name(&self) -> &str;
 }
@@ -105,6 +483,11 @@ pub trait Storage: Send + Sync {
 pub async fn create_storage(
     config: &crate::db::StorageConfig,
 ) -> Result<std::sync::Arc<dyn Storage>> {
+    let request_tag = config
+        .requestTag
+        .as_deref()
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty());
     match config.typ {
         crate::db::StorageType::Local => {
             let path = config
@@ -123,25 +506,24 @@ pub async fn create_storage(
                 .region
                 .as_ref()
                 .ok_or_else(|| anyhow::anyhow!("S3 storage requires region"))?;
-            let access_key = config
-                .accessKey
-                .as_ref()
+            let access_key = secret::resolve_optional(&config.accessKey)?
                 .ok_or_else(|| anyhow::anyhow!("S3 storage requires accessKey"))?;
-            let secret_key = config
-                .secretKey
-                .as_ref()
+            let secret_key = secret::resolve_optional(&config.secretKey)?
                 .ok_or_else(|| anyhow::anyhow!("S3 storage requires secretKey"))?;
             tracing::info!("初始化S3存储: bucket={}, region={}", bucket, region);
             Ok(std::sync::Arc::new(
                 S3Storage::new(
                     bucket,
                     region,
-                    access_key,
-                    secret_key,
+                    &access_key,
+                    &secret_key,
                     config.endpoint.clone(),
+                    config.forcePathStyle,
                     config.prefix.clone(),
+                    request_tag,
                 )
-                .await?,
+                .await?
+                .with_metadata(config.objectMetadata.clone().unwrap_or_default()),
             ) as std::sync::Arc<dyn Storage>)
         }
         crate::db::StorageType::WebDav => {
@@ -153,14 +535,344 @@ pub async fn create_storage(
                 .username
                 .as_ref()
                 .ok_or_else(|| anyhow::anyhow!("WebDAV storage requires username"))?;
-            let password = config
-                .password
-                .as_ref()
+            let password = secret::resolve_optional(&config.password)?
                 .ok_or_else(|| anyhow::anyhow!("WebDAV storage requires password"))?;
             tracing::info!("创建WebDAV存储: endpoint={}, root={:?}", endpoint, config.root);
             Ok(std::sync::Arc::new(
-                WebDavStorage::new(endpoint, username, password, config.root.clone()).await?,
+                WebDavStorage::new(endpoint, username, &password, config.root.clone(), request_tag)
+                    .await?,
             ) as std::sync::Arc<dyn Storage>)
         }
+        crate::db::StorageType::Http => {
+            let url = config
+                .httpUrl
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("HTTP storage requires httpUrl"))?;
+            let password = secret::resolve_optional(&config.password)?;
+            tracing::info!("创建HTTP只读存储: {}", url);
+            Ok(std::sync::Arc::new(HttpStorage::new(
+                url,
+                config.username.clone(),
+                password,
+                request_tag,
+            )?) as std::sync::Arc<dyn Storage>)
+        }
+        crate::db::StorageType::B2 => {
+            let key_id = config
+                .keyId
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("B2 storage requires keyId"))?;
+            let application_key = secret::resolve_optional(&config.applicationKey)?
+                .ok_or_else(|| anyhow::anyhow!("B2 storage requires applicationKey"))?;
+            let bucket = config
+                .bucket
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("B2 storage requires bucket"))?;
+            let bucket_id = config
+                .bucketId
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("B2 storage requires bucketId"))?;
+            tracing::info!("初始化B2存储: bucket={}", bucket);
+            Ok(std::sync::Arc::new(
+                B2Storage::new(
+                    key_id,
+                    &application_key,
+                    bucket,
+                    bucket_id,
+                    config.prefix.clone(),
+                    request_tag,
+                )
+                .await?,
+            ) as std::sync::Arc<dyn Storage>)
+        }
+        crate::db::StorageType::OneDrive => {
+            let client_id = config
+                .clientId
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("OneDrive storage requires clientId"))?;
+            let refresh_token = secret::resolve_optional(&config.refreshToken)?
+                .ok_or_else(|| anyhow::anyhow!("OneDrive storage requires refreshToken"))?;
+            let client_secret = secret::resolve_optional(&config.clientSecret)?;
+            tracing::info!("初始化OneDrive存储: drive={:?}, root={:?}", config.driveId, config.root);
+            Ok(std::sync::Arc::new(OneDriveStorage::new(
+                client_id,
+                client_secret,
+                &refresh_token,
+                config.tenant.as_deref(),
+                config.driveId.as_deref(),
+                config.root.as_deref(),
+                request_tag,
+            )?) as std::sync::Arc<dyn Storage>)
+        }
+        crate::db::StorageType::AzBlob => {
+            let account_name = config
+                .accountName
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Azure Blob storage requires accountName"))?;
+            let container = config
+                .bucket
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Azure Blob storage requires container"))?;
+            let account_key = secret::resolve_optional(&config.accountKey)?;
+            let sas_token = secret::resolve_optional(&config.sasToken)?;
+            tracing::info!("初始化Azure Blob存储: account={}, container={}", account_name, container);
+            Ok(std::sync::Arc::new(
+                AzureBlobStorage::new(
+                    account_name,
+                    account_key.as_deref(),
+                    sas_token.as_deref(),
+                    container,
+                    config.endpoint.clone(),
+                    config.prefix.clone(),
+                    request_tag,
+                )
+                .await?,
+            ) as std::sync::Arc<dyn Storage>)
+        }
+        crate::db::StorageType::Gcs => {
+            let bucket = config
+                .bucket
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("GCS storage requires bucket"))?;
+            tracing::info!("初始化GCS存储: bucket={}", bucket);
+            let storage = match secret::resolve_optional(&config.serviceAccountJson)? {
+                Some(credential) => {
+                    GcsStorage::new(
+                        bucket,
+                        &credential,
+                        config.endpoint.clone(),
+                        config.prefix.clone(),
+                        request_tag,
+                    )
+                    .await?
+                }
+                None => {
+                    let access_key = secret::resolve_optional(&config.accessKey)?.ok_or_else(
+                        || anyhow::anyhow!("GCS storage requires serviceAccountJson or accessKey"),
+                    )?;
+                    let secret_key = secret::resolve_optional(&config.secretKey)?
+                        .ok_or_else(|| anyhow::anyhow!("GCS storage requires secretKey"))?;
+                    GcsStorage::with_hmac(
+                        bucket,
+                        &access_key,
+                        &secret_key,
+                        config.endpoint.clone(),
+                        config.prefix.clone(),
+                        request_tag,
+                    )
+                    .await?
+                }
+            };
+            Ok(std::sync::Arc::new(storage) as std::sync::Arc<dyn Storage>)
+        }
+        crate::db::StorageType::Dropbox => {
+            let app_key = config
+                .clientId
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Dropbox storage requires clientId"))?;
+            let refresh_token = secret::resolve_optional(&config.refreshToken)?
+                .ok_or_else(|| anyhow::anyhow!("Dropbox storage requires refreshToken"))?;
+            let app_secret = secret::resolve_optional(&config.clientSecret)?;
+            tracing::info!("初始化Dropbox存储: root={:?}", config.root);
+            Ok(std::sync::Arc::new(DropboxStorage::new(
+                app_key,
+                app_secret,
+                &refresh_token,
+                config.root.as_deref(),
+                request_tag,
+            )?) as std::sync::Arc<dyn Storage>)
+        }
+    }
+}
+
+/// 根据配置创建源存储实例
+///
+/// 与 [`create_storage`] 的区别：本地路径必须已存在（并通过挂载标记检查），不会自动创建
+pub async fn create_source_storage(
+    config: &crate::db::StorageConfig,
+) -> Result<std::sync::Arc<dyn Storage>> {
+    if config.typ == crate::db::StorageType::Local {
+        let path = config
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Local storage requires path"))?;
+        tracing::info!("打开本地源存储: {}", path);
+        return Ok(std::sync::Arc::new(LocalStorage::open_existing(
+            path,
+            config.mountMarker.as_deref(),
+        )?) as std::sync::Arc<dyn Storage>);
+    }
+    create_storage(config).await
+}
+
+/// 创建任务的源存储：配置了附加源时，把它们挂载到各自的子路径下合并为一个源
+pub async fn create_job_source_storage(
+    job: &crate::db::SyncJob,
+) -> Result<std::sync::Arc<dyn Storage>> {
+    let primary = create_source_storage(&job.sourceConfig).await?;
+    if job.additionalSources.is_empty() {
+        return Ok(primary);
+    }
+
+    let mut mounts = Vec::with_capacity(job.additionalSources.len());
+    for source in &job.additionalSources {
+        let storage = create_source_storage(&source.config)
+            .await
+            .map_err(|e| anyhow::anyhow!("附加源 {} 连接失败: {}", source.prefix, e))?;
+        mounts.push((source.prefix.clone(), storage));
+    }
+    Ok(std::sync::Arc::new(MultiSourceStorage::new(primary, mounts)) as std::sync::Arc<dyn Storage>)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/a/b.txt"), "a/b.txt");
+        assert_eq!(normalize_path("a\\b\\c.txt"), "a/b/c.txt");
+        assert_eq!(normalize_path("a//./b.txt"), "a/b.txt");
+        assert_eq!(normalize_path("/dir/"), "dir/");
+        assert_eq!(normalize_path("/"), "");
+        assert_eq!(normalize_path(""), "");
+    }
+
+    fn entry(path: &str, size: u64, is_dir: bool) -> FileInfo {
+        FileInfo {
+            path: path.to_string(),
+            size,
+            modified_time: 0,
+            is_dir,
+            checksum: (!is_dir).then(|| "etag".to_string()),
+            hidden: false,
+        }
+    }
+
+    #[test]
+    fn test_dedup_listing() {
+        let files = dedup_listing(vec![
+            // S3：create_dir 写入的 "dir/" 占位对象
+            entry("photos/", 0, false),
+            entry("photos/a.jpg", 10, false),
+            // WebDAV：同一目录同时以 "docs" 和 "docs/" 列出
+            entry("docs", 0, true),
+            entry("docs/", 0, true),
+            // 多源：挂载点父目录与主源列出的目录重复
+            entry("docs", 0, true),
+            entry("docs/b.txt", 5, false),
+            // 空文件与同名目录占位对象
+            entry("logs", 0, false),
+            entry("logs/", 0, false),
+        ]);
+
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["photos", "photos/a.jpg", "docs", "docs/b.txt", "logs"]);
+        for dir in ["photos", "docs", "logs"] {
+            let f = files.iter().find(|f| f.path == dir).unwrap();
+            assert!(f.is_dir && f.size == 0 && f.checksum.is_none(), "{} 应为目录", dir);
+        }
+        // 目录占位对象不会作为可同步文件出现
+        assert!(files.iter().filter(|f| !f.is_dir).all(|f| !f.path.ends_with('/')));
+        assert_eq!(files.iter().filter(|f| !f.is_dir).count(), 2);
+    }
+
+    #[test]
+    fn test_empty_dirs() {
+        let files = vec![
+            entry("a", 0, true),
+            entry("a/b", 0, true),
+            entry("a/b/c", 0, true),
+            entry("a/keep.txt", 1, false),
+            entry("x", 0, true),
+            entry("x/y", 0, true),
+            entry("old", 0, true),
+            entry("hidden", 0, true),
+            entry("hidden/.DS_Store", 0, false),
+        ];
+
+        // 只包含空目录的目录同样删除，先删深层目录
+        assert_eq!(empty_dirs(&files, None), vec!["a/b/c", "a/b", "x/y", "old", "x"]);
+
+        // 限定范围时，范围外的空目录保留，其父目录也保留
+        let only: HashSet<String> = ["a/b", "x", "old"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(empty_dirs(&files, Some(&only)), vec!["old"]);
+        let only: HashSet<String> = ["x/y", "x"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(empty_dirs(&files, Some(&only)), vec!["x/y", "x"]);
+    }
+
+    #[test]
+    fn test_storage_type_required_fields() {
+        use crate::db::{StorageConfig, StorageType};
+        for typ in StorageType::ALL {
+            // 必填字段名必须是 StorageConfig 的字段
+            let mut json = serde_json::json!({ "type": typ });
+            for field in typ.required_fields() {
+                json[*field] = serde_json::json!("x");
+            }
+            let config: StorageConfig = serde_json::from_value(json).unwrap();
+            let round_trip = serde_json::to_value(&config).unwrap();
+            for field in typ.required_fields() {
+                assert_eq!(round_trip[*field], "x", "{:?} 的字段 {} 无效", typ, field);
+            }
+        }
+    }
+
+    #[test]
+    fn test_s3_path_style() {
+        use s3::use_path_style;
+        assert!(!use_path_style(None, None, "photos"));
+        assert!(use_path_style(None, Some("http://minio:9000"), "photos"));
+        assert!(use_path_style(None, None, "backup.example.com"));
+        assert!(!use_path_style(Some(false), Some("http://minio:9000"), "photos"));
+        assert!(use_path_style(Some(true), None, "photos"));
+    }
+
+    #[test]
+    fn test_s3_directory_placeholders() {
+        use opendal::{EntryMode, Metadata};
+        use s3::object_info;
+
+        // create_dir 创建的 "dir/" 空对象按目录处理
+        let placeholder = Metadata::new(EntryMode::FILE).with_content_length(0);
+        let info = object_info("photos/2024/", &placeholder);
+        assert!(info.is_dir);
+        assert_eq!(info.size, 0);
+        assert_eq!(info.path, "photos/2024/");
+
+        let object = Metadata::new(EntryMode::FILE)
+            .with_content_length(3)
+            .with_etag("\"9e107d9d372bb6826bd81d3542a419d6\"".to_string());
+        let info = object_info("photos/2024/a.jpg", &object);
+        assert!(!info.is_dir);
+        assert_eq!(info.size, 3);
+        assert_eq!(info.checksum.as_deref(), Some("9e107d9d372bb6826bd81d3542a419d6"));
+    }
+
+    #[test]
+    fn test_user_agent() {
+        assert!(USER_AGENT.starts_with("SyncTools/"));
+        assert_eq!(user_agent(None), USER_AGENT);
+        assert_eq!(user_agent(Some("nas-backup")), format!("{} (nas-backup)", USER_AGENT));
+        assert!(http_client_builder(Some("bad\ntag")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_default_delete_batch_reports_each_path() {
+        let storage = memory::MemoryStorage::new();
+        for path in ["a.txt", "b.txt", "c.txt"] {
+            storage.put(path, b"data", 0);
+        }
+        storage.fail_delete("b.txt", 1);
+        let paths: Vec<String> = ["a.txt", "b.txt", "c.txt"].map(String::from).to_vec();
+
+        let deleted = std::sync::Mutex::new(Vec::new());
+        let on_deleted = |path: &str| deleted.lock().unwrap().push(path.to_string());
+        let results = storage.delete_batch(&paths, &on_deleted).await;
+
+        // 结果与输入顺序一致，失败的路径不影响其他路径
+        assert!(results[0].is_ok() && results[1].is_err() && results[2].is_ok());
+        assert_eq!(*deleted.lock().unwrap(), ["a.txt", "c.txt"]);
+        assert!(!storage.contains("a.txt") && storage.contains("b.txt"));
     }
 }
@@ -0,0 +1,341 @@
+//! 多源合并存储 - 把附加源挂载到主源命名空间的子路径下，作为一个源参与同步
+
+use super::{dedup_listing, normalize_path, FileInfo, FileMeta, Storage, StorageCapabilities};
+use crate::core::file_state::ChecksumAlgorithm;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::Stream;
+use std::collections::{HashMap, HashSet};
+use std::pin::Pin;
+use std::sync::Arc;
+
+pub struct MultiSourceStorage {
+    primary: Arc<dyn Storage>,
+    /// (挂载前缀, 存储)，前缀已规范化，不含首尾斜杠
+    mounts: Vec<(String, Arc<dyn Storage>)>,
+    name: String,
+}
+
+/// 去掉挂载前缀，返回存储内部路径（路径不在该前缀下时返回 None）
+fn strip_mount<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = path.trim_start_matches('/').strip_prefix(prefix)?;
+    if rest.is_empty() {
+        Some("")
+    } else {
+        rest.strip_prefix('/')
+    }
+}
+
+/// 存储内部路径加上挂载前缀
+fn join_mount(prefix: &str, inner: &str) -> String {
+    let inner = inner.trim_start_matches('/');
+    if inner.is_empty() {
+        prefix.to_string()
+    } else {
+        format!("{}/{}", prefix, inner)
+    }
+}
+
+impl MultiSourceStorage {
+    pub fn new(primary: Arc<dyn Storage>, mounts: Vec<(String, Arc<dyn Storage>)>) -> Self {
+        let mounts: Vec<_> = mounts
+            .into_iter()
+            .map(|(prefix, storage)| {
+                (normalize_path(&prefix).trim_end_matches('/').to_string(), storage)
+            })
+            .collect();
+        let name = format!("{} (+{} 个附加源)", primary.name(), mounts.len());
+        Self {
+            primary,
+            mounts,
+            name,
+        }
+    }
+
+    /// 路径所属的挂载序号（None 表示主源）
+    fn mount_index(&self, path: &str) -> Option<usize> {
+        self.mounts
+            .iter()
+            .position(|(prefix, _)| strip_mount(path, prefix).is_some())
+    }
+
+    /// 路径所属的存储及其内部路径
+    fn route<'a>(&'a self, path: &'a str) -> (&'a dyn Storage, &'a str) {
+        for (prefix, storage) in &self.mounts {
+            if let Some(inner) = strip_mount(path, prefix) {
+                return (storage.as_ref(), inner);
+            }
+        }
+        (self.primary.as_ref(), path)
+    }
+
+    /// 挂载前缀对应的目录条目（含各级父目录），保证合并后的树中挂载点是目录
+    fn mount_dirs(&self) -> Vec<FileInfo> {
+        let mut dirs = HashSet::new();
+        for (prefix, _) in &self.mounts {
+            let mut current = prefix.as_str();
+            loop {
+                dirs.insert(current.to_string());
+                match current.rsplit_once('/') {
+                    Some((parent, _)) => current = parent,
+                    None => break,
+                }
+            }
+        }
+        dirs.into_iter()
+            .map(|path| FileInfo {
+                path,
+                size: 0,
+                modified_time: 0,
+                is_dir: true,
+                checksum: None,
+                hidden: false,
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Storage for MultiSourceStorage {
+    async fn list_files(&self, prefix: Option<&str>) -> Result<Vec<FileInfo>> {
+        self.list_files_paged(prefix, None).await
+    }
+
+    async fn list_files_paged(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+    ) -> Result<Vec<FileInfo>> {
+        // 前缀位于某个挂载点内，只列出该附加源
+        if let Some(p) = prefix {
+            for (mount, storage) in &self.mounts {
+                if let Some(inner) = strip_mount(p, mount) {
+                    let inner = (!inner.is_empty()).then_some(inner);
+                    let files = storage.list_files_paged(inner, page_size).await?;
+                    return Ok(files
+                        .into_iter()
+                        .map(|f| FileInfo {
+                            path: join_mount(mount, &f.path),
+                            ..f
+                        })
+                        .collect());
+                }
+            }
+        }
+
+        // 主源中位于挂载点下的条目被附加源覆盖
+        let mut files: Vec<FileInfo> = self
+            .primary
+            .list_files_paged(prefix, page_size)
+            .await?
+            .into_iter()
+            .filter(|f| self.mount_index(&f.path).is_none())
+            .collect();
+
+        let prefix = prefix.map(|p| normalize_path(p).trim_end_matches('/').to_string());
+        let in_prefix = |path: &str| match &prefix {
+            Some(p) if !p.is_empty() => strip_mount(path, p).is_some(),
+            _ => true,
+        };
+
+        for (mount, storage) in &self.mounts {
+            if !in_prefix(mount.as_str()) {
+                continue;
+            }
+            let mounted = storage.list_files_paged(None, page_size).await?;
+            tracing::debug!("附加源 {} 列出 {} 个条目", mount, mounted.len());
+            files.extend(mounted.into_iter().filter(|f| !f.path.is_empty()).map(|f| FileInfo {
+                path: join_mount(mount, &f.path),
+                ..f
+            }));
+        }
+        files.extend(self.mount_dirs().into_iter().filter(|d| in_prefix(&d.path)));
+
+        // 挂载点的父目录可能已由主源列出
+        Ok(dedup_listing(files))
+    }
+
+    async fn fill_checksums(
+        &self,
+        files: &mut HashMap<String, FileInfo>,
+        known: &HashMap<String, FileInfo>,
+        max_size: u64,
+        concurrency: usize,
+        algorithm: ChecksumAlgorithm,
+    ) -> Result<usize> {
+        let mut computed = 0;
+        // 0 为主源，其余为各附加源；按所属存储拆分后分别计算，再写回合并的树
+        for index in 0..=self.mounts.len() {
+            let (storage, mount) = match index {
+                0 => (&self.primary, None),
+                i => (&self.mounts[i - 1].1, Some(self.mounts[i - 1].0.as_str())),
+            };
+            let owner = |path: &str| self.mount_index(path).map(|i| i + 1).unwrap_or(0) == index;
+            let to_inner = |path: &str| match mount {
+                Some(m) => strip_mount(path, m).unwrap_or(path).to_string(),
+                None => path.to_string(),
+            };
+            let to_outer = |path: &str| match mount {
+                Some(m) => join_mount(m, path),
+                None => path.to_string(),
+            };
+
+            let owned: Vec<String> = files.keys().filter(|p| owner(p.as_str())).cloned().collect();
+            if owned.is_empty() {
+                continue;
+            }
+            let mut part: HashMap<String, FileInfo> = owned
+                .iter()
+                .filter_map(|p| files.remove(p))
+                .map(|f| {
+                    let inner = to_inner(&f.path);
+                    (inner.clone(), FileInfo { path: inner, ..f })
+                })
+                .collect();
+            let part_known: HashMap<String, FileInfo> = known
+                .iter()
+                .filter(|(p, _)| owner(p.as_str()))
+                .map(|(p, f)| {
+                    let inner = to_inner(p.as_str());
+                    (inner.clone(), FileInfo { path: inner, ..f.clone() })
+                })
+                .collect();
+
+            let result = storage
+                .fill_checksums(&mut part, &part_known, max_size, concurrency, algorithm)
+                .await;
+            for (_, f) in part {
+                let outer = to_outer(&f.path);
+                files.insert(outer.clone(), FileInfo { path: outer, ..f });
+            }
+            computed += result?;
+        }
+        Ok(computed)
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<FileMeta>> {
+        let (storage, inner) = self.route(path);
+        storage.stat(inner).await
+    }
+
+    /// 主源和所有附加源都必须可访问
+    async fn probe_root(&self) -> Result<()> {
+        self.primary.probe_root().await?;
+        for (prefix, storage) in &self.mounts {
+            storage
+                .probe_root()
+                .await
+                .map_err(|e| anyhow::anyhow!("附加源 {} 无法访问: {}", prefix, e))?;
+        }
+        Ok(())
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let (storage, inner) = self.route(path);
+        storage.read(inner).await
+    }
+
+    async fn read_range(&self, path: &str, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let (storage, inner) = self.route(path);
+        storage.read_range(inner, offset, length).await
+    }
+
+    async fn write(&self, path: &str, data: Vec<u8>) -> Result<()> {
+        let (storage, inner) = self.route(path);
+        storage.write(inner, data).await
+    }
+
+    async fn write_stream(
+        &self,
+        path: &str,
+        stream: Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>,
+        total_size: Option<u64>,
+    ) -> Result<()> {
+        let (storage, inner) = self.route(path);
+        storage.write_stream(inner, stream, total_size).await
+    }
+
+    async fn append(&self, path: &str, offset: u64, data: Vec<u8>) -> Result<()> {
+        let (storage, inner) = self.route(path);
+        storage.append(inner, offset, data).await
+    }
+
+    fn supports_append(&self) -> bool {
+        std::iter::once(&self.primary)
+            .chain(self.mounts.iter().map(|(_, s)| s))
+            .all(|s| s.supports_append())
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        let (storage, inner) = self.route(path);
+        storage.delete(inner).await
+    }
+
+    async fn remove_empty_dir(&self, path: &str) -> Result<()> {
+        let (storage, inner) = self.route(path);
+        storage.remove_empty_dir(inner).await
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        let (storage, inner) = self.route(path);
+        storage.create_dir(inner).await
+    }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<()> {
+        if self.mount_index(from) == self.mount_index(to) {
+            let (storage, inner_from) = self.route(from);
+            let (_, inner_to) = self.route(to);
+            return storage.copy(inner_from, inner_to).await;
+        }
+        // 跨存储复制只能读出后写入
+        let data = self.read(from).await?;
+        self.write(to, data).await
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        if self.mount_index(from) == self.mount_index(to) {
+            let (storage, inner_from) = self.route(from);
+            let (_, inner_to) = self.route(to);
+            return storage.rename(inner_from, inner_to).await;
+        }
+        self.copy(from, to).await?;
+        self.delete(from).await
+    }
+
+    fn local_path(&self, path: &str) -> Option<std::path::PathBuf> {
+        let (storage, inner) = self.route(path);
+        storage.local_path(inner)
+    }
+
+    fn capabilities(&self) -> StorageCapabilities {
+        // 合并后的能力取所有源的交集
+        self.mounts
+            .iter()
+            .map(|(_, s)| s.capabilities())
+            .fold(self.primary.capabilities(), |acc, c| StorageCapabilities {
+                read: acc.read && c.read,
+                write: acc.write && c.write,
+                delete: acc.delete && c.delete,
+            })
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_mount() {
+        assert_eq!(strip_mount("docs/a.txt", "docs"), Some("a.txt"));
+        assert_eq!(strip_mount("/docs/sub/a.txt", "docs"), Some("sub/a.txt"));
+        assert_eq!(strip_mount("docs", "docs"), Some(""));
+        assert_eq!(strip_mount("docs2/a.txt", "docs"), None);
+        assert_eq!(strip_mount("other/docs/a.txt", "docs"), None);
+        assert_eq!(join_mount("photos/2024", "a.jpg"), "photos/2024/a.jpg");
+        assert_eq!(join_mount("photos", ""), "photos");
+    }
+}
@@ -0,0 +1,836 @@
+//! OneDrive / SharePoint 存储 - 通过 Microsoft Graph API 访问，使用刷新令牌自动获取访问令牌
+
+use super::{
+    dedup_listing, http_client_builder, normalize_path, secret, FileInfo, FileMeta, ListCounter,
+    Storage, SyncError,
+};
+use crate::db::{StorageType, SyncJob};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::pin::Pin;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Graph API 地址
+const GRAPH_ENDPOINT: &str = "https://graph.microsoft.com/v1.0";
+/// Microsoft 登录地址（令牌端点位于 /{tenant}/oauth2/v2.0/token）
+const LOGIN_ENDPOINT: &str = "https://login.microsoftonline.com";
+/// 默认租户（同时支持个人账户和工作/学校账户）
+const DEFAULT_TENANT: &str = "common";
+/// 刷新令牌时请求的权限
+const OAUTH_SCOPE: &str = "offline_access Files.ReadWrite.All";
+/// 访问令牌提前刷新的时间（秒），避免长时间同步中途过期
+const TOKEN_REFRESH_MARGIN_SECS: u64 = 300;
+/// HTTP 连接超时（秒）
+const HTTP_CONNECT_TIMEOUT_SECS: u64 = 30;
+/// HTTP 请求超时（秒）
+const HTTP_REQUEST_TIMEOUT_SECS: u64 = 300;
+/// 简单上传的最大文件大小（Graph 限制 4MB），超过时使用上传会话
+const SIMPLE_UPLOAD_MAX: usize = 4 * 1024 * 1024;
+/// 上传会话的分片大小（Graph 要求为 320KB 的整数倍，10MB）
+const UPLOAD_CHUNK_SIZE: usize = 32 * 320 * 1024;
+/// quickXorHash 校验和前缀（只在 OneDrive 之间比较）
+const QUICKXOR_PREFIX: &str = "quickxor:";
+/// 上传分片遇到 5xx、429 或网络错误时的重试次数
+const UPLOAD_CHUNK_RETRIES: u32 = 3;
+/// 上传分片重试的基础等待时间（毫秒），按重试次数指数增长
+const UPLOAD_RETRY_BASE_DELAY_MS: u64 = 1000;
+
+/// 服务端轮换后尚未写回配置的刷新令牌：配置中的令牌 -> 最新令牌（进程内所有实例共享）
+static ROTATED_REFRESH_TOKENS: OnceLock<std::sync::Mutex<HashMap<String, String>>> =
+    OnceLock::new();
+
+fn rotated_tokens() -> std::sync::MutexGuard<'static, HashMap<String, String>> {
+    ROTATED_REFRESH_TOKENS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// 令牌端点响应
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+    /// 服务端可能轮换刷新令牌
+    refresh_token: Option<String>,
+}
+
+/// 驱动器条目
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DriveItem {
+    name: String,
+    #[serde(default)]
+    size: u64,
+    last_modified_date_time: Option<String>,
+    e_tag: Option<String>,
+    folder: Option<serde_json::Value>,
+    file: Option<FileFacet>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FileFacet {
+    hashes: Option<Hashes>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Hashes {
+    quick_xor_hash: Option<String>,
+}
+
+impl DriveItem {
+    fn is_dir(&self) -> bool {
+        self.folder.is_some()
+    }
+
+    fn modified_time(&self) -> i64 {
+        self.last_modified_date_time
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map_or(0, |t| t.timestamp())
+    }
+
+    fn checksum(&self) -> Option<String> {
+        let hash = self.file.as_ref()?.hashes.as_ref()?.quick_xor_hash.as_ref()?;
+        Some(format!("{}{}", QUICKXOR_PREFIX, hash))
+    }
+
+    /// `dir` 目录下该条目的文件信息
+    fn file_info(&self, dir: &str) -> FileInfo {
+        let is_dir = self.is_dir();
+        FileInfo {
+            path: if dir.is_empty() {
+                self.name.clone()
+            } else {
+                format!("{}/{}", dir, self.name)
+            },
+            size: if is_dir { 0 } else { self.size },
+            modified_time: self.modified_time(),
+            is_dir,
+            checksum: self.checksum(),
+            hidden: false,
+        }
+    }
+}
+
+/// 子条目列表（一页）
+#[derive(Debug, Deserialize)]
+struct ChildrenPage {
+    value: Vec<DriveItem>,
+    #[serde(rename = "@odata.nextLink")]
+    next_link: Option<String>,
+}
+
+/// 上传会话
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadSession {
+    upload_url: String,
+}
+
+/// 上传会话状态
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadSessionStatus {
+    /// 服务端尚未收到的范围，如 "10485760-"
+    #[serde(default)]
+    next_expected_ranges: Vec<String>,
+}
+
+/// 访问令牌及其有效期
+struct AccessToken {
+    token: String,
+    expires_at: Instant,
+}
+
+/// 授权状态（刷新令牌可能被服务端轮换，保存最新值）
+struct AuthState {
+    refresh_token: String,
+    access: Option<AccessToken>,
+}
+
+pub struct OneDriveStorage {
+    client: reqwest::Client,
+    token_url: String,
+    client_id: String,
+    client_secret: Option<String>,
+    /// 配置中的刷新令牌（轮换后按它记录最新令牌）
+    configured_token: String,
+    auth: Mutex<AuthState>,
+    /// 驱动器 API 地址（/me/drive 或 /drives/{id}）
+    drive_url: String,
+    /// 驱动器在 parentReference.path 中的前缀
+    drive_path: String,
+    /// 驱动器内的根目录，不含首尾斜杠
+    root: String,
+    name: String,
+}
+
+/// 对路径各段做 URL 编码
+fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(|seg| urlencoding::encode(seg).into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 上传会话 nextExpectedRanges 中最小的起始位置
+fn parse_next_expected(ranges: &[String]) -> Option<u64> {
+    ranges
+        .iter()
+        .filter_map(|r| r.split('-').next()?.parse::<u64>().ok())
+        .min()
+}
+
+/// 把轮换后的刷新令牌写回使用该令牌的任务配置
+///
+/// 令牌来自环境变量引用时无法写回，返回错误提醒用户手动更新（本进程内仍使用新令牌）
+pub async fn persist_rotated_tokens(pool: &SqlitePool) -> Result<()> {
+    let pending: HashMap<String, String> = rotated_tokens().clone();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let mut errors = Vec::new();
+    let mut failed: HashSet<&str> = HashSet::new();
+    for mut job in SyncJob::load_all(pool).await? {
+        let mut changed = false;
+        let configs = std::iter::once(&mut job.sourceConfig)
+            .chain(std::iter::once(&mut job.destConfig))
+            .chain(job.additionalSources.iter_mut().map(|s| &mut s.config));
+        for config in configs {
+            if config.typ != StorageType::OneDrive {
+                continue;
+            }
+            let Some(raw) = config.refreshToken.clone() else {
+                continue;
+            };
+            let Ok(current) = secret::resolve_secret(&raw) else {
+                continue;
+            };
+            let Some((old, new)) = pending.get_key_value(&current) else {
+                continue;
+            };
+            match secret::store_secret(&raw, new) {
+                Ok(Some(value)) => {
+                    config.refreshToken = Some(value);
+                    changed = true;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    errors.push(format!("{}: {}", job.name, e));
+                    failed.insert(old.as_str());
+                }
+            }
+        }
+        if changed {
+            job.save(pool).await?;
+        }
+    }
+
+    // 已写回的令牌不再需要记录；写回失败的继续保留，本进程内仍使用新令牌
+    rotated_tokens().retain(|old, _| failed.contains(old.as_str()));
+    if !errors.is_empty() {
+        return Err(anyhow!(
+            "OneDrive 刷新令牌已轮换，但无法保存新令牌: {}",
+            errors.join("; ")
+        ));
+    }
+    Ok(())
+}
+
+/// 请求失败时按状态码转换为分类错误
+async fn check(response: reqwest::Response, context: &str) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(SyncError::from_status(status, format!("{} ({}): {}", context, status, body)).into())
+}
+
+impl OneDriveStorage {
+    pub fn new(
+        client_id: &str,
+        client_secret: Option<String>,
+        refresh_token: &str,
+        tenant: Option<&str>,
+        drive_id: Option<&str>,
+        root: Option<&str>,
+        request_tag: Option<&str>,
+    ) -> Result<Self> {
+        let client = http_client_builder(request_tag)?
+            .connect_timeout(Duration::from_secs(HTTP_CONNECT_TIMEOUT_SECS))
+            .timeout(Duration::from_secs(HTTP_REQUEST_TIMEOUT_SECS))
+            .build()?;
+
+        let tenant = tenant.filter(|t| !t.is_empty()).unwrap_or(DEFAULT_TENANT);
+        let (drive_url, drive_path) = match drive_id.filter(|d| !d.is_empty()) {
+            Some(id) => (
+                format!("{}/drives/{}", GRAPH_ENDPOINT, urlencoding::encode(id)),
+                format!("/drives/{}", id),
+            ),
+            None => (format!("{}/me/drive", GRAPH_ENDPOINT), "/drive".to_string()),
+        };
+        let root = root
+            .map(normalize_path)
+            .unwrap_or_default()
+            .trim_end_matches('/')
+            .to_string();
+        let name = format!("onedrive:{}/{}", drive_id.unwrap_or("me"), root);
+
+        Ok(Self {
+            client,
+            token_url: format!("{}/{}/oauth2/v2.0/token", LOGIN_ENDPOINT, tenant),
+            client_id: client_id.to_string(),
+            client_secret,
+            configured_token: refresh_token.to_string(),
+            auth: Mutex::new(AuthState {
+                // 本进程中已轮换过的令牌优先使用最新值
+                refresh_token: rotated_tokens()
+                    .get(refresh_token)
+                    .cloned()
+                    .unwrap_or_else(|| refresh_token.to_string()),
+                access: None,
+            }),
+            drive_url,
+            drive_path,
+            root,
+            name,
+        })
+    }
+
+    /// 获取访问令牌，即将过期或 `force_refresh` 时用刷新令牌换取新令牌
+    async fn access_token(&self, force_refresh: bool) -> Result<String> {
+        let mut auth = self.auth.lock().await;
+        if !force_refresh {
+            if let Some(access) = &auth.access {
+                if access.expires_at > Instant::now() {
+                    return Ok(access.token.clone());
+                }
+            }
+        }
+
+        let response = {
+            let mut form = vec![
+                ("client_id", self.client_id.as_str()),
+                ("grant_type", "refresh_token"),
+                ("refresh_token", auth.refresh_token.as_str()),
+                ("scope", OAUTH_SCOPE),
+            ];
+            if let Some(secret) = &self.client_secret {
+                form.push(("client_secret", secret.as_str()));
+            }
+            self.client.post(&self.token_url).form(&form).send().await?
+        };
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let message = format!("OneDrive 刷新访问令牌失败 ({}): {}", status, body);
+            // 刷新令牌无效或已吊销时返回 400 invalid_grant，重试无济于事
+            return Err(match status.as_u16() {
+                400 | 401 => SyncError::Auth(message),
+                _ => SyncError::from_status(status, message),
+            }
+            .into());
+        }
+
+        let token: TokenResponse = response.json().await?;
+        if let Some(refresh_token) = token.refresh_token.filter(|t| *t != auth.refresh_token) {
+            // 旧令牌可能随后失效，记录新令牌，同步结束后写回任务配置
+            tracing::info!("OneDrive 刷新令牌已轮换");
+            rotated_tokens().insert(self.configured_token.clone(), refresh_token.clone());
+            auth.refresh_token = refresh_token;
+        }
+        let lifetime = token.expires_in.saturating_sub(TOKEN_REFRESH_MARGIN_SECS);
+        auth.access = Some(AccessToken {
+            token: token.access_token.clone(),
+            expires_at: Instant::now() + Duration::from_secs(lifetime),
+        });
+        tracing::debug!("OneDrive 访问令牌已刷新，{} 秒后过期", token.expires_in);
+        Ok(token.access_token)
+    }
+
+    /// 发送 Graph 请求，访问令牌失效（401）时刷新后重试一次
+    async fn send<F>(&self, build: F) -> Result<reqwest::Response>
+    where
+        F: Fn() -> reqwest::RequestBuilder + Send + Sync,
+    {
+        let token = self.access_token(false).await?;
+        let response = build().bearer_auth(&token).send().await?;
+        if response.status() != reqwest::StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        let token = self.access_token(true).await?;
+        Ok(build().bearer_auth(&token).send().await?)
+    }
+
+    /// 存储路径加上根目录，得到驱动器内的完整路径
+    fn full_path(&self, path: &str) -> String {
+        let path = normalize_path(path);
+        let path = path.trim_end_matches('/');
+        match (self.root.is_empty(), path.is_empty()) {
+            (true, _) => path.to_string(),
+            (false, true) => self.root.clone(),
+            (false, false) => format!("{}/{}", self.root, path),
+        }
+    }
+
+    /// 驱动器内完整路径对应的条目地址
+    fn drive_item_url(&self, full_path: &str) -> String {
+        if full_path.is_empty() {
+            format!("{}/root", self.drive_url)
+        } else {
+            format!("{}/root:/{}:", self.drive_url, encode_path(full_path))
+        }
+    }
+
+    /// 存储路径对应的条目地址
+    fn item_url(&self, path: &str) -> String {
+        self.drive_item_url(&self.full_path(path))
+    }
+
+    /// 获取条目信息（不存在时返回 None）
+    async fn get_item(&self, path: &str) -> Result<Option<DriveItem>> {
+        let url = self.item_url(path);
+        let response = self.send(|| self.client.get(&url)).await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = check(response, "OneDrive 获取文件信息失败").await?;
+        Ok(Some(response.json().await?))
+    }
+
+    /// 通过上传会话分片上传大文件
+    async fn upload_large(
+        &self,
+        path: &str,
+        stream: Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>,
+        total_size: u64,
+    ) -> Result<()> {
+        let url = format!("{}/createUploadSession", self.item_url(path));
+        let body = serde_json::json!({
+            "item": { "@microsoft.graph.conflictBehavior": "replace" }
+        });
+        let response = self.send(|| self.client.post(&url).json(&body)).await?;
+        let session: UploadSession = check(response, "OneDrive 创建上传会话失败")
+            .await?
+            .json()
+            .await?;
+
+        let result = self.upload_chunks(&session.upload_url, stream, total_size).await;
+        if result.is_err() {
+            // 取消未完成的上传会话
+            let _ = self.client.delete(&session.upload_url).send().await;
+        }
+        result
+    }
+
+    /// 把数据流按分片大小上传到上传会话
+    async fn upload_chunks(
+        &self,
+        upload_url: &str,
+        mut stream: Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>,
+        total_size: u64,
+    ) -> Result<()> {
+        let mut buffer: Vec<u8> = Vec::with_capacity(UPLOAD_CHUNK_SIZE);
+        let mut offset = 0u64;
+        loop {
+            let next = stream.next().await.transpose()?;
+            let finished = next.is_none();
+            if let Some(chunk) = next {
+                buffer.extend_from_slice(&chunk);
+            }
+
+            while buffer.len() >= UPLOAD_CHUNK_SIZE || (finished && !buffer.is_empty()) {
+                let len = buffer.len().min(UPLOAD_CHUNK_SIZE);
+                let part: Vec<u8> = buffer.drain(..len).collect();
+                self.upload_part(upload_url, part, offset, total_size).await?;
+                offset += len as u64;
+            }
+
+            if finished {
+                break;
+            }
+        }
+
+        if offset != total_size {
+            return Err(anyhow!(
+                "OneDrive 上传大小不符: 已上传 {} 字节，预期 {} 字节",
+                offset,
+                total_size
+            ));
+        }
+        Ok(())
+    }
+
+    /// 上传一个分片，遇到 5xx、429 或网络错误时查询会话进度后重试
+    async fn upload_part(
+        &self,
+        upload_url: &str,
+        part: Vec<u8>,
+        offset: u64,
+        total_size: u64,
+    ) -> Result<()> {
+        let end = offset + part.len() as u64 - 1;
+        let mut attempt = 0;
+        loop {
+            // 上传地址自带授权，不能附加访问令牌
+            let result = self
+                .client
+                .put(upload_url)
+                .header(
+                    reqwest::header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", offset, end, total_size),
+                )
+                .body(part.clone())
+                .send()
+                .await;
+            let (error, retry_after) = match result {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    let retry_after = response
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<u64>().ok());
+                    let error = check(response, "OneDrive 上传分片失败").await.unwrap_err();
+                    let retryable = status.is_server_error()
+                        || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+                    if !retryable {
+                        return Err(error);
+                    }
+                    (error, retry_after)
+                }
+                Err(e) => (e.into(), None),
+            };
+            if attempt >= UPLOAD_CHUNK_RETRIES {
+                return Err(error);
+            }
+            attempt += 1;
+            let delay = retry_after.map(Duration::from_secs).unwrap_or_else(|| {
+                Duration::from_millis(UPLOAD_RETRY_BASE_DELAY_MS << (attempt - 1))
+            });
+            tracing::warn!(
+                "OneDrive 上传分片失败，{:?} 后重试 ({}/{}): {}",
+                delay,
+                attempt,
+                UPLOAD_CHUNK_RETRIES,
+                error
+            );
+            tokio::time::sleep(delay).await;
+
+            // 请求可能已被服务端处理：按会话进度决定是否还需要重传
+            if let Some(next) = self.next_expected_offset(upload_url).await {
+                if next > end {
+                    return Ok(());
+                }
+                if next != offset {
+                    return Err(anyhow!(
+                        "OneDrive 上传会话进度异常: 应从 {} 继续，服务端要求从 {} 继续",
+                        offset,
+                        next
+                    ));
+                }
+            }
+        }
+    }
+
+    /// 上传会话中服务端期望的下一个字节位置（查询失败时返回 None）
+    async fn next_expected_offset(&self, upload_url: &str) -> Option<u64> {
+        let response = self.client.get(upload_url).send().await.ok()?;
+        let status: UploadSessionStatus = response.error_for_status().ok()?.json().await.ok()?;
+        parse_next_expected(&status.next_expected_ranges)
+    }
+
+    /// 逐级创建驱动器内的目录（已存在的目录跳过）
+    async fn create_dirs(&self, full_path: &str) -> Result<()> {
+        let mut parent = String::new();
+        for segment in full_path.split('/').filter(|s| !s.is_empty()) {
+            let url = format!("{}/children", self.drive_item_url(&parent));
+            let body = serde_json::json!({
+                "name": segment,
+                "folder": {},
+                "@microsoft.graph.conflictBehavior": "fail",
+            });
+            let response = self.send(|| self.client.post(&url).json(&body)).await?;
+            if response.status() != reqwest::StatusCode::CONFLICT {
+                check(response, "OneDrive 创建目录失败").await?;
+            }
+            parent = if parent.is_empty() {
+                segment.to_string()
+            } else {
+                format!("{}/{}", parent, segment)
+            };
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for OneDriveStorage {
+    async fn list_files(&self, prefix: Option<&str>) -> Result<Vec<FileInfo>> {
+        self.list_files_paged(prefix, None).await
+    }
+
+    async fn list_files_paged(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+    ) -> Result<Vec<FileInfo>> {
+        self.list_files_counted(prefix, page_size, &ListCounter::default()).await
+    }
+
+    async fn list_files_counted(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+        listed: &ListCounter,
+    ) -> Result<Vec<FileInfo>> {
+        let start = prefix
+            .map(normalize_path)
+            .unwrap_or_default()
+            .trim_end_matches('/')
+            .to_string();
+        let mut files = Vec::new();
+        let mut pending = vec![start.clone()];
+        // 分页期间目录内容变化时，同一子目录可能在多页中重复出现，只列出一次
+        let mut queued: HashSet<String> = HashSet::new();
+
+        while let Some(dir) = pending.pop() {
+            let mut next = Some(match page_size {
+                Some(limit) => format!("{}/children?$top={}", self.item_url(&dir), limit),
+                None => format!("{}/children", self.item_url(&dir)),
+            });
+
+            // 分页读取，直到没有 @odata.nextLink
+            while let Some(url) = next.take() {
+                let response = self.send(|| self.client.get(&url)).await?;
+                // 起始目录不存在时视为空
+                if response.status() == reqwest::StatusCode::NOT_FOUND && dir == start {
+                    break;
+                }
+                let page: ChildrenPage = check(response, "OneDrive 列出目录失败")
+                    .await?
+                    .json()
+                    .await?;
+
+                for item in page.value {
+                    let file = item.file_info(&dir);
+                    if file.is_dir && queued.insert(file.path.clone()) {
+                        pending.push(file.path.clone());
+                    }
+                    files.push(file);
+                    listed.add(1)?;
+                }
+                next = page.next_link;
+            }
+        }
+
+        let files = dedup_listing(files);
+        tracing::info!("OneDrive 扫描完成: {} 个条目", files.len());
+        Ok(files)
+    }
+
+    async fn stat(&self, path: &str) -> Result<Option<FileMeta>> {
+        Ok(self.get_item(path).await?.map(|item| FileMeta {
+            size: if item.is_dir() { 0 } else { item.size },
+            modified_time: item.modified_time(),
+            is_dir: item.is_dir(),
+            etag: item.e_tag.clone(),
+        }))
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>> {
+        let url = format!("{}/content", self.item_url(path));
+        let response = self.send(|| self.client.get(&url)).await?;
+        let response = check(response, "OneDrive 读取文件失败").await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn read_range(&self, path: &str, offset: u64, length: u64) -> Result<Vec<u8>> {
+        if length == 0 {
+            return Ok(Vec::new());
+        }
+        let url = format!("{}/content", self.item_url(path));
+        let range = format!("bytes={}-{}", offset, offset + length - 1);
+        let response = self
+            .send(|| self.client.get(&url).header(reqwest::header::RANGE, &range))
+            .await?;
+        let response = check(response, "OneDrive 读取文件失败").await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn write(&self, path: &str, data: Vec<u8>) -> Result<()> {
+        if data.len() > SIMPLE_UPLOAD_MAX {
+            let total_size = data.len() as u64;
+            let stream = futures::stream::iter(
+                data.chunks(UPLOAD_CHUNK_SIZE)
+                    .map(|c| Ok(c.to_vec()))
+                    .collect::<Vec<_>>(),
+            );
+            return self.upload_large(path, Box::pin(stream), total_size).await;
+        }
+
+        let url = format!("{}/content", self.item_url(path));
+        let response = self
+            .send(|| self.client.put(&url).body(data.clone()))
+            .await?;
+        check(response, "OneDrive 上传文件失败").await?;
+        Ok(())
+    }
+
+    async fn write_stream(
+        &self,
+        path: &str,
+        mut stream: Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>,
+        total_size: Option<u64>,
+    ) -> Result<()> {
+        match total_size {
+            Some(total) if total > SIMPLE_UPLOAD_MAX as u64 => {
+                self.upload_large(path, stream, total).await
+            }
+            // 小文件或大小未知时收集后整体上传
+            _ => {
+                let mut data = Vec::new();
+                while let Some(chunk) = stream.next().await {
+                    data.extend(chunk?);
+                }
+                self.write(path, data).await
+            }
+        }
+    }
+
+    async fn delete(&self, path: &str) -> Result<()> {
+        let url = self.item_url(path);
+        let response = self.send(|| self.client.delete(&url)).await?;
+        // 删除不存在的文件不报错
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        check(response, "OneDrive 删除失败").await?;
+        Ok(())
+    }
+
+    async fn create_dir(&self, path: &str) -> Result<()> {
+        self.create_dirs(&self.full_path(path)).await
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        // 服务端移动，不经过本地传输数据
+        let to_full = self.full_path(to);
+        let (parent, name) = match to_full.rsplit_once('/') {
+            Some((parent, name)) => (parent.to_string(), name.to_string()),
+            None => (String::new(), to_full.clone()),
+        };
+        if !parent.is_empty() {
+            self.create_dirs(&parent).await?;
+        }
+        let parent_ref = if parent.is_empty() {
+            format!("{}/root:", self.drive_path)
+        } else {
+            format!("{}/root:/{}", self.drive_path, parent)
+        };
+
+        let url = format!(
+            "{}?@microsoft.graph.conflictBehavior=replace",
+            self.item_url(from)
+        );
+        let body = serde_json::json!({
+            "name": name,
+            "parentReference": { "path": parent_ref },
+        });
+        let response = self.send(|| self.client.patch(&url).json(&body)).await?;
+        check(response, "OneDrive 移动文件失败").await?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_urls() {
+        let storage =
+            OneDriveStorage::new("id", None, "token", None, None, Some("/Backup/"), None).unwrap();
+        assert_eq!(storage.full_path("a b/c.txt"), "Backup/a b/c.txt");
+        assert_eq!(storage.full_path(""), "Backup");
+        assert_eq!(
+            storage.item_url("a b/c.txt"),
+            "https://graph.microsoft.com/v1.0/me/drive/root:/Backup/a%20b/c.txt:"
+        );
+
+        let storage =
+            OneDriveStorage::new("id", None, "token", Some("contoso"), Some("b!x"), None, None)
+                .unwrap();
+        assert_eq!(
+            storage.item_url(""),
+            "https://graph.microsoft.com/v1.0/drives/b%21x/root"
+        );
+        assert_eq!(
+            storage.token_url,
+            "https://login.microsoftonline.com/contoso/oauth2/v2.0/token"
+        );
+    }
+
+    #[test]
+    fn test_repeated_items_are_merged() {
+        let pages: Vec<ChildrenPage> = serde_json::from_value(serde_json::json!([
+            {
+                "value": [
+                    { "name": "docs", "folder": { "childCount": 1 } },
+                    { "name": "a.txt", "size": 3 },
+                ],
+                "@odata.nextLink": "https://graph.microsoft.com/next",
+            },
+            {
+                "value": [
+                    { "name": "a.txt", "size": 3 },
+                    { "name": "b.txt", "size": 1 },
+                ],
+            },
+        ]))
+        .unwrap();
+        let files: Vec<FileInfo> = pages
+            .iter()
+            .flat_map(|page| page.value.iter().map(|item| item.file_info("sub")))
+            .collect();
+        assert_eq!(files.len(), 4);
+        let files = dedup_listing(files);
+        let paths: Vec<_> = files.iter().map(|f| (f.path.as_str(), f.is_dir)).collect();
+        assert_eq!(
+            paths,
+            vec![("sub/docs", true), ("sub/a.txt", false), ("sub/b.txt", false)]
+        );
+    }
+
+    #[test]
+    fn test_parse_next_expected() {
+        let ranges = vec!["20971520-".to_string(), "10485760-15000000".to_string()];
+        assert_eq!(parse_next_expected(&ranges), Some(10485760));
+        assert_eq!(parse_next_expected(&[]), None);
+    }
+
+    #[tokio::test]
+    async fn test_new_instance_uses_rotated_token() {
+        rotated_tokens().insert("configured-token".to_string(), "rotated-token".to_string());
+        let storage =
+            OneDriveStorage::new("id", None, "configured-token", None, None, None, None).unwrap();
+        assert_eq!(storage.auth.lock().await.refresh_token, "rotated-token");
+        assert_eq!(storage.configured_token, "configured-token");
+        rotated_tokens().remove("configured-token");
+    }
+}
@@ -1,23 +1,60 @@
-use super::{FileInfo, FileMeta, Storage, IO_TIMEOUT_SECS, OP_TIMEOUT_SECS};
+use super::{
+    dedup_listing, normalize_path, opendal_http_client, FileInfo, FileMeta, ListCounter, Storage,
+    SyncError, DELETE_BATCH_SIZE, IO_TIMEOUT_SECS, OP_TIMEOUT_SECS,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use futures::TryStreamExt;
-use opendal::{layers::TimeoutLayer, Metakey, Operator};
+use opendal::{layers::TimeoutLayer, Metadata, Metakey, Operator};
+use std::collections::HashMap;
 use std::time::Duration;
 
+/// 列出的对象转换为文件信息
+///
+/// 目录占位对象（"dir/"，如 create_dir 创建的空对象）按目录处理，不作为文件同步
+pub fn object_info(path: &str, meta: &Metadata) -> FileInfo {
+    let is_dir = meta.is_dir() || path.ends_with('/');
+    FileInfo {
+        path: normalize_path(path),
+        size: if is_dir { 0 } else { meta.content_length() },
+        modified_time: meta.last_modified().map_or(0, |t| t.timestamp()),
+        is_dir,
+        checksum: meta.etag().map(|s| s.trim_matches('"').to_string()),
+        hidden: false,
+    }
+}
+
 pub struct S3Storage {
     operator: Operator,
     name: String,
+    /// 上传时附加的自定义元数据
+    metadata: HashMap<String, String>,
+}
+
+/// 是否使用路径风格地址（`endpoint/bucket/key`）
+///
+/// 未指定时：自定义 endpoint（MinIO 等网关）使用路径风格；AWS 使用虚拟主机风格，
+/// 但 bucket 名含 "." 时虚拟主机域名与证书不匹配，仍使用路径风格
+pub fn use_path_style(
+    force_path_style: Option<bool>,
+    endpoint: Option<&str>,
+    bucket: &str,
+) -> bool {
+    force_path_style
+        .unwrap_or_else(|| endpoint.is_some_and(|ep| !ep.is_empty()) || bucket.contains('.'))
 }
 
 impl S3Storage {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         bucket: &str,
         region: &str,
         access_key: &str,
         secret_key: &str,
         endpoint: Option<String>,
+        force_path_style: Option<bool>,
         prefix: Option<String>,
+        request_tag: Option<&str>,
     ) -> Result<Self> {
         use opendal::services::S3;
 
@@ -25,12 +62,18 @@ impl S3Storage {
             .bucket(bucket)
             .region(region)
             .access_key_id(access_key)
-            .secret_access_key(secret_key);
+            .secret_access_key(secret_key)
+            .http_client(opendal_http_client(request_tag)?);
 
         if let Some(ref ep) = endpoint {
             builder = builder.endpoint(ep);
         }
 
+        // OpenDAL 默认使用路径风格
+        if !use_path_style(force_path_style, endpoint.as_deref(), bucket) {
+            builder = builder.enable_virtual_host_style();
+        }
+
         if let Some(ref p) = prefix {
             builder = builder.root(p);
         }
@@ -53,23 +96,53 @@ impl S3Storage {
                 .unwrap_or_default()
         );
 
-        Ok(Self { operator, name })
+        Ok(Self {
+            operator,
+            name,
+            metadata: HashMap::new(),
+        })
+    }
+
+    /// 设置上传时附加到每个对象的自定义元数据
+    pub fn with_metadata(mut self, metadata: HashMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
     }
 }
 
 #[async_trait]
 impl Storage for S3Storage {
     async fn list_files(&self, prefix: Option<&str>) -> Result<Vec<FileInfo>> {
+        self.list_files_paged(prefix, None).await
+    }
+
+    async fn list_files_paged(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+    ) -> Result<Vec<FileInfo>> {
+        self.list_files_counted(prefix, page_size, &ListCounter::default()).await
+    }
+
+    async fn list_files_counted(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+        listed: &ListCounter,
+    ) -> Result<Vec<FileInfo>> {
         let mut files = Vec::new();
-        let path = prefix.unwrap_or("");
+        let path = prefix.map(normalize_path).unwrap_or_default();
 
         // 使用 lister_with 进行递归列表
-        let mut lister = self
+        let mut list_op = self
             .operator
-            .lister_with(path)
+            .lister_with(&path)
             .recursive(true)
-            .metakey(Metakey::ContentLength | Metakey::LastModified | Metakey::Mode)
-            .await?;
+            .metakey(Metakey::ContentLength | Metakey::LastModified | Metakey::Mode);
+        if let Some(limit) = page_size {
+            list_op = list_op.limit(limit);
+        }
+        let mut lister = list_op.await?;
 
         while let Some(entry) = lister.try_next().await? {
             let path_str = entry.path().to_string();
@@ -79,22 +152,15 @@ impl Storage for S3Storage {
                 continue;
             }
 
-            let meta = entry.metadata();
-
-            files.push(FileInfo {
-                path: path_str.trim_start_matches('/').to_string(),
-                size: meta.content_length(),
-                modified_time: meta.last_modified().map_or(0, |t| t.timestamp()),
-                is_dir: meta.is_dir(),
-                checksum: meta.etag().map(|s| s.trim_matches('"').to_string()),
-            });
+            files.push(object_info(&path_str, entry.metadata()));
+            listed.add(1)?;
         }
 
-        Ok(files)
+        Ok(dedup_listing(files))
     }
 
     async fn stat(&self, path: &str) -> Result<Option<FileMeta>> {
-        match self.operator.stat(path).await {
+        match self.operator.stat(&normalize_path(path)).await {
             Ok(meta) => Ok(Some(FileMeta {
                 size: meta.content_length(),
                 modified_time: meta.last_modified().map_or(0, |t| t.timestamp()),
@@ -106,35 +172,120 @@ impl Storage for S3Storage {
         }
     }
 
+    /// 列出根目录的第一页（stat 根路径不会发出请求）
+    async fn probe_root(&self) -> Result<()> {
+        let mut lister = self.operator.lister_with("").limit(1).await?;
+        lister.try_next().await?;
+        Ok(())
+    }
+
+    async fn root_is_empty(&self) -> Result<Option<bool>> {
+        Ok(Some(super::opendal_root_is_empty(&self.operator).await?))
+    }
+
     async fn read(&self, path: &str) -> Result<Vec<u8>> {
-        let data = self.operator.read(path).await?;
+        let data = self.operator.read(&normalize_path(path)).await?;
         Ok(data.to_vec())
     }
 
     async fn read_range(&self, path: &str, offset: u64, length: u64) -> Result<Vec<u8>> {
         let data = self
             .operator
-            .read_with(path)
+            .read_with(&normalize_path(path))
             .range(offset..offset + length)
             .await?;
         Ok(data.to_vec())
     }
 
     async fn write(&self, path: &str, data: Vec<u8>) -> Result<()> {
-        self.operator.write(path, data).await?;
+        let path = normalize_path(path);
+        // 按扩展名设置 Content-Type，静态网站托管时浏览器才能正确渲染
+        let content_type = mime_guess::from_path(&path).first_or_octet_stream();
+        let mut op = self
+            .operator
+            .write_with(&path, data)
+            .content_type(content_type.essence_str());
+        if !self.metadata.is_empty() {
+            op = op.user_metadata(self.metadata.clone());
+        }
+        op.await?;
         Ok(())
     }
 
+    async fn append(&self, path: &str, offset: u64, data: Vec<u8>) -> Result<()> {
+        let path = normalize_path(path);
+        let current = self.operator.stat(&path).await?.content_length();
+        if current != offset {
+            return Err(SyncError::Conflict(format!(
+                "追加位置不匹配: {} 当前大小 {}，预期 {}",
+                path, current, offset
+            ))
+            .into());
+        }
+        self.operator.write_with(&path, data).append(true).await?;
+        Ok(())
+    }
+
+    fn supports_append(&self) -> bool {
+        // 仅部分兼容 S3 的服务支持追加写入
+        self.operator.info().full_capability().write_can_append
+    }
+
     async fn delete(&self, path: &str) -> Result<()> {
         // S3 删除不存在的文件不会报错
-        self.operator.delete(path).await?;
+        self.operator.delete(&normalize_path(path)).await?;
+        Ok(())
+    }
+
+    async fn delete_batch(
+        &self,
+        paths: &[String],
+        on_deleted: &(dyn Fn(&str) + Send + Sync),
+    ) -> Vec<Result<()>> {
+        let mut results = Vec::with_capacity(paths.len());
+        for chunk in paths.chunks(DELETE_BATCH_SIZE) {
+            let keys = chunk.iter().map(|p| normalize_path(p)).collect();
+            match self.operator.remove(keys).await {
+                Ok(()) => {
+                    for path in chunk {
+                        on_deleted(path);
+                        results.push(Ok(()));
+                    }
+                }
+                Err(e) => {
+                    // 批量删除只返回第一个错误，逐个删除以得到每个对象的结果
+                    tracing::warn!("批量删除失败，改为逐个删除 {} 个对象: {}", chunk.len(), e);
+                    for path in chunk {
+                        let result = self.delete(path).await;
+                        if result.is_ok() {
+                            on_deleted(path);
+                        }
+                        results.push(result);
+                    }
+                }
+            }
+        }
+        results
+    }
+
+    fn supports_delete_batch(&self) -> bool {
+        true
+    }
+
+    /// 对象存储没有真正的目录，只删除 "dir/" 占位对象，其下的文件不受影响
+    async fn remove_empty_dir(&self, path: &str) -> Result<()> {
+        let path = normalize_path(path);
+        self.operator
+            .delete(&format!("{}/", path.trim_end_matches('/')))
+            .await?;
         Ok(())
     }
 
     async fn create_dir(&self, path: &str) -> Result<()> {
         // S3 不需要真正创建目录，但为了兼容性，创建一个占位对象
+        let path = normalize_path(path);
         let dir_path = if path.ends_with('/') {
-            path.to_string()
+            path
         } else {
             format!("{}/", path)
         };
@@ -142,6 +293,14 @@ impl Storage for S3Storage {
         Ok(())
     }
 
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        // S3 没有重命名，使用服务端复制后删除（不经过本地传输数据）
+        let from = normalize_path(from);
+        self.operator.copy(&from, &normalize_path(to)).await?;
+        self.operator.delete(&from).await?;
+        Ok(())
+    }
+
     fn name(&self) -> &str {
         &self.name
     }
@@ -0,0 +1,64 @@
+//! 凭据引用解析
+//!
+//! 存储配置中的密钥字段可以填写引用而非明文，在连接时解析：
+//! - `${env:NAME}`：读取环境变量 NAME
+//! - `${file:/path}`：读取文件内容（去除首尾空白）
+//!
+//! 数据库中保存、导出的始终是引用本身。
+
+use anyhow::{anyhow, Result};
+
+/// 引用格式的凭据值去掉 `${` `}` 后的内容，非引用格式返回 None
+fn parse_reference(value: &str) -> Option<&str> {
+    value
+        .trim()
+        .strip_prefix("${")
+        .and_then(|v| v.strip_suffix('}'))
+}
+
+/// 解析凭据值，非引用格式原样返回
+pub fn resolve_secret(value: &str) -> Result<String> {
+    let Some(reference) = parse_reference(value) else {
+        return Ok(value.to_string());
+    };
+
+    if let Some(name) = reference.strip_prefix("env:") {
+        return std::env::var(name.trim())
+            .map_err(|_| anyhow!("环境变量未设置: {}", name.trim()));
+    }
+
+    if let Some(path) = reference.strip_prefix("file:") {
+        return std::fs::read_to_string(path.trim())
+            .map(|s| s.trim().to_string())
+            .map_err(|e| anyhow!("读取凭据文件失败 {}: {}", path.trim(), e));
+    }
+
+    Err(anyhow!("不支持的凭据引用: {}", value))
+}
+
+/// 更新凭据值（如服务端轮换了刷新令牌）
+///
+/// 明文返回新值，由调用方保存到配置；文件引用直接写入文件，配置不变，返回 None；
+/// 环境变量引用无法更新，返回错误
+pub fn store_secret(value: &str, new_value: &str) -> Result<Option<String>> {
+    let Some(reference) = parse_reference(value) else {
+        return Ok(Some(new_value.to_string()));
+    };
+
+    if let Some(name) = reference.strip_prefix("env:") {
+        return Err(anyhow!("凭据来自环境变量 {}，无法自动更新，请手动更新", name.trim()));
+    }
+
+    if let Some(path) = reference.strip_prefix("file:") {
+        std::fs::write(path.trim(), new_value)
+            .map_err(|e| anyhow!("写入凭据文件失败 {}: {}", path.trim(), e))?;
+        return Ok(None);
+    }
+
+    Err(anyhow!("不支持的凭据引用: {}", value))
+}
+
+/// 解析可选的凭据值
+pub fn resolve_optional(value: &Option<String>) -> Result<Option<String>> {
+    value.as_deref().map(resolve_secret).transpose()
+}
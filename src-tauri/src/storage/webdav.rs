@@ -1,4 +1,8 @@
-use super::{FileInfo, FileMeta, Storage, IO_TIMEOUT_SECS, OP_TIMEOUT_SECS};
+use super::error::opendal_http_status;
+use super::{
+    dedup_listing, http_client_builder, normalize_path, opendal_http_client, FileInfo, FileMeta,
+    ListCounter, Storage, SyncError, IO_TIMEOUT_SECS, OP_TIMEOUT_SECS,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use futures::TryStreamExt;
@@ -13,6 +17,8 @@ use tokio::sync::RwLock;
 const HTTP_CONNECT_TIMEOUT_SECS: u64 = 30;
 /// 目录缓存最大条目数（防止内存泄漏）
 const MAX_DIR_CACHE_SIZE: usize = 10000;
+/// 批量删除时同时发出的 DELETE 请求数
+const DELETE_BATCH_CONCURRENCY: usize = 8;
 
 pub struct WebDavStorage {
     operator: Operator,
@@ -34,6 +40,7 @@ impl WebDavStorage {
         username: &str,
         password: &str,
         root: Option<String>,
+        request_tag: Option<&str>,
     ) -> Result<Self> {
         use opendal::services::Webdav;
 
@@ -54,7 +61,8 @@ impl WebDavStorage {
         let builder = Webdav::default()
             .endpoint(&final_endpoint)
             .username(username)
-            .password(password);
+            .password(password)
+            .http_client(opendal_http_client(request_tag)?);
 
         // 添加超时层
         let operator = Operator::new(builder)?
@@ -68,7 +76,7 @@ impl WebDavStorage {
         let name = format!("webdav://{}", final_endpoint.trim_start_matches("https://").trim_start_matches("http://"));
 
         // 创建复用的 HTTP 客户端，带超时设置（用于流式传输）
-        let http_client = reqwest::Client::builder()
+        let http_client = http_client_builder(request_tag)?
             .timeout(Duration::from_secs(IO_TIMEOUT_SECS))
             .connect_timeout(Duration::from_secs(HTTP_CONNECT_TIMEOUT_SECS))
             .pool_max_idle_per_host(4)
@@ -97,18 +105,17 @@ impl WebDavStorage {
         })
     }
 
-    /// 规范化路径：统一使用正斜杠，去除前导斜杠
+    /// 规范化路径（与列表返回的路径使用相同规则）
     #[inline]
     fn normalize_path(path: &str) -> String {
-        path.replace('\\', "/").trim_start_matches('/').to_string()
+        normalize_path(path)
     }
 
     /// 确保目录存在（带缓存，避免重复创建）
     async fn ensure_parent_dirs(&self, file_path: &str) -> Result<()> {
-        let path = file_path.replace('\\', "/");
-        let path = path.trim_start_matches('/');
+        let path = Self::normalize_path(file_path);
         
-        if let Some(parent) = std::path::Path::new(path).parent() {
+        if let Some(parent) = std::path::Path::new(&path).parent() {
             let parent_str = parent.to_string_lossy().replace('\\', "/");
             if parent_str.is_empty() || parent_str == "." {
                 return Ok(());
@@ -154,18 +161,39 @@ impl WebDavStorage {
     }
 }
 
+/// 写入类请求（PUT/MKCOL/MOVE）的错误分类：WebDAV 对这些请求返回 409 表示父目录不存在（RFC 4918）
+fn map_write_error(e: opendal::Error) -> SyncError {
+    match opendal_http_status(&e) {
+        Some(reqwest::StatusCode::CONFLICT) => SyncError::ParentMissing(e.to_string()),
+        _ => e.into(),
+    }
+}
+
 #[async_trait]
 impl Storage for WebDavStorage {
     async fn list_files(&self, prefix: Option<&str>) -> Result<Vec<FileInfo>> {
+        self.list_files_paged(prefix, None).await
+    }
+
+    async fn list_files_paged(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+    ) -> Result<Vec<FileInfo>> {
+        self.list_files_counted(prefix, page_size, &ListCounter::default()).await
+    }
+
+    async fn list_files_counted(
+        &self,
+        prefix: Option<&str>,
+        page_size: Option<usize>,
+        listed: &ListCounter,
+    ) -> Result<Vec<FileInfo>> {
         let mut files = Vec::new();
-        let start_path = prefix.unwrap_or("").to_string();
-        
-        // 计算 root 前缀（用于剥离服务器返回的完整路径）
-        let root_prefix = self.root_path.trim_start_matches('/').trim_end_matches('/');
-        let root_prefix_with_slash = if root_prefix.is_empty() {
-            String::new()
-        } else {
-            format!("{}/", root_prefix)
+        let start_path = match prefix.map(Self::normalize_path) {
+            Some(p) if !p.is_empty() && !p.ends_with('/') => format!("{}/", p),
+            Some(p) => p,
+            None => String::new(),
         };
         
         // 使用栈进行手动递归扫描（某些 WebDAV 服务器不支持 recursive）
@@ -180,12 +208,14 @@ impl Storage for WebDavStorage {
             scanned_dirs.insert(current_dir.clone());
             
             // 列出当前目录
-            let mut lister = match self
+            let mut list_op = self
                 .operator
                 .lister_with(&current_dir)
-                .metakey(Metakey::ContentLength | Metakey::LastModified | Metakey::Mode)
-                .await
-            {
+                .metakey(Metakey::ContentLength | Metakey::LastModified | Metakey::Mode);
+            if let Some(limit) = page_size {
+                list_op = list_op.limit(limit);
+            }
+            let mut lister = match list_op.await {
                 Ok(l) => l,
                 Err(e) => {
                     tracing::warn!("无法列出目录 {}: {}", current_dir, e);
@@ -201,44 +231,38 @@ impl Storage for WebDavStorage {
                     continue;
                 }
 
-                // URL 解码路径（WebDAV 服务器可能返回编码后的路径）
-                let decoded_path = urlencoding::decode(&path_str)
-                    .map(|s| s.into_owned())
-                    .unwrap_or_else(|_| path_str.clone());
-                
-                // 剥离 root 前缀（服务器可能返回包含 root 的完整路径）
-                let relative_path = decoded_path
-                    .trim_start_matches('/')
-                    .strip_prefix(&root_prefix_with_slash)
-                    .unwrap_or(decoded_path.trim_start_matches('/'));
+                let relative_path = relative_list_path(&path_str, &self.root_path);
                 
                 let meta = entry.metadata();
                 let is_dir = meta.is_dir() || path_str.ends_with('/');
+
+                // 跳过空路径（root 本身）
+                if relative_path.is_empty() {
+                    continue;
+                }
                 
                 if is_dir {
                     // 将子目录加入待扫描队列（使用相对路径）
-                    let dir_path = relative_path.trim_end_matches('/').to_string() + "/";
-                    if !scanned_dirs.contains(&dir_path) && !dir_path.is_empty() && dir_path != "/" {
+                    let dir_path = format!("{}/", relative_path);
+                    if !scanned_dirs.contains(&dir_path) {
                         dirs_to_scan.push(dir_path);
                     }
                 }
 
-                // 跳过空路径（root 本身）
-                let final_path = relative_path.trim_end_matches('/');
-                if final_path.is_empty() {
-                    continue;
-                }
-
                 files.push(FileInfo {
-                    path: final_path.to_string(),
+                    path: relative_path,
                     size: meta.content_length(),
                     modified_time: meta.last_modified().map_or(0, |t| t.timestamp()),
                     is_dir,
                     checksum: meta.etag().map(|s| s.trim_matches('"').to_string()),
+                    hidden: false,
                 });
+                listed.add(1)?;
             }
         }
 
+        // 部分服务器会把同一目录同时列为 "dir" 和 "dir/"
+        let files = dedup_listing(files);
         tracing::info!("WebDAV 扫描完成: {} 个条目 ({} 个目录已扫描)", files.len(), scanned_dirs.len());
         Ok(files)
     }
@@ -253,10 +277,21 @@ impl Storage for WebDavStorage {
                 etag: meta.etag().map(|s| s.trim_matches('"').to_string()),
             })),
             Err(e) if e.kind() == opendal::ErrorKind::NotFound => Ok(None),
-            Err(e) => Err(e.into()),
+            Err(e) => Err(SyncError::from(e).into()),
         }
     }
 
+    /// 列出根目录的第一页（stat 根路径不会发出请求）
+    async fn probe_root(&self) -> Result<()> {
+        let mut lister = self.operator.lister_with("").limit(1).await.map_err(SyncError::from)?;
+        lister.try_next().await.map_err(SyncError::from)?;
+        Ok(())
+    }
+
+    async fn root_is_empty(&self) -> Result<Option<bool>> {
+        Ok(Some(super::opendal_root_is_empty(&self.operator).await?))
+    }
+
     async fn read(&self, path: &str) -> Result<Vec<u8>> {
         // 规范化路径，移除可能的前缀（如 webdav/Sync/...）
         let normalized_path = Self::normalize_path(path);
@@ -282,7 +317,10 @@ impl Storage for WebDavStorage {
         // 确保父目录存在（使用缓存避免重复创建）
         self.ensure_parent_dirs(&normalized_path).await?;
         
-        self.operator.write(&normalized_path, data).await?;
+        self.operator
+            .write(&normalized_path, data)
+            .await
+            .map_err(map_write_error)?;
         Ok(())
     }
     
@@ -328,14 +366,19 @@ impl Storage for WebDavStorage {
         }
         
         let response = request.send().await
-            .map_err(|e| anyhow::anyhow!("WebDAV 请求失败: {}", e))?;
+            .map_err(|e| SyncError::from_reqwest(&e, format!("WebDAV 请求失败: {}", e)))?;
         
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!(
+        let status = response.status();
+        if !status.is_success() {
+            let message = format!(
                 "WebDAV PUT 失败: {} - {}",
-                response.status(),
+                status,
                 response.text().await.unwrap_or_default()
-            ));
+            );
+            if status == reqwest::StatusCode::CONFLICT {
+                return Err(SyncError::ParentMissing(message).into());
+            }
+            return Err(SyncError::from_status(status, message).into());
         }
         
         Ok(())
@@ -350,6 +393,45 @@ impl Storage for WebDavStorage {
         }
     }
 
+    /// WebDAV 没有批量删除，并发发出 DELETE 请求
+    async fn delete_batch(
+        &self,
+        paths: &[String],
+        on_deleted: &(dyn Fn(&str) + Send + Sync),
+    ) -> Vec<Result<()>> {
+        use futures::StreamExt;
+
+        futures::stream::iter(paths)
+            .map(|path| async move {
+                let result = self.delete(path).await;
+                if result.is_ok() {
+                    on_deleted(path);
+                }
+                result
+            })
+            .buffered(DELETE_BATCH_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    fn supports_delete_batch(&self) -> bool {
+        true
+    }
+
+    /// WebDAV 删除集合是递归的：先以 Depth: 1 列出目录，确认没有子项再删除
+    async fn remove_empty_dir(&self, path: &str) -> Result<()> {
+        let dir_path = format!("{}/", Self::normalize_path(path).trim_end_matches('/'));
+        let entries = self.operator.list(&dir_path).await.map_err(SyncError::from)?;
+        let has_children = entries.iter().any(|entry| {
+            relative_list_path(entry.path(), &self.root_path)
+                != relative_list_path(&dir_path, &self.root_path)
+        });
+        if has_children {
+            return Err(SyncError::Conflict(format!("目录不为空: {}", dir_path)).into());
+        }
+        self.delete(&dir_path).await
+    }
+
     async fn create_dir(&self, path: &str) -> Result<()> {
         let normalized_path = Self::normalize_path(path);
         let dir_path = if normalized_path.ends_with('/') {
@@ -357,7 +439,17 @@ impl Storage for WebDavStorage {
         } else {
             format!("{}/", normalized_path)
         };
-        self.operator.create_dir(&dir_path).await?;
+        self.operator.create_dir(&dir_path).await.map_err(map_write_error)?;
+        Ok(())
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<()> {
+        // WebDAV MOVE 要求目标父目录存在
+        self.ensure_parent_dirs(to).await?;
+        self.operator
+            .rename(&Self::normalize_path(from), &Self::normalize_path(to))
+            .await
+            .map_err(map_write_error)?;
         Ok(())
     }
 
@@ -365,3 +457,98 @@ impl Storage for WebDavStorage {
         &self.name
     }
 }
+
+/// 将服务器返回的列表路径转换为相对于存储根的路径
+///
+/// 服务器可能返回 URL 编码的路径，也可能返回包含 root 的完整路径；
+/// 结果不带前后斜杠，且与 `normalize_path` 规则一致，可直接用于 stat/read/write。
+fn relative_list_path(entry_path: &str, root: &str) -> String {
+    // URL 解码路径（WebDAV 服务器可能返回编码后的路径）
+    let decoded = urlencoding::decode(entry_path)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| entry_path.to_string());
+    let path = normalize_path(&decoded);
+    let path = path.trim_end_matches('/');
+
+    // 剥离 root 前缀（按路径段匹配，避免 "data" 误剥离 "database/..."）
+    let root = normalize_path(root);
+    let root = root.trim_end_matches('/');
+    if root.is_empty() {
+        return path.to_string();
+    }
+    if path == root {
+        return String::new();
+    }
+    path.strip_prefix(root)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .unwrap_or(path)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_conflict_means_parent_missing() {
+        let error = |status: u16| {
+            opendal::Error::new(opendal::ErrorKind::Unexpected, "").with_context(
+                "response",
+                format!("Parts {{ status: {}, version: HTTP/1.1, headers: {{}} }}", status),
+            )
+        };
+        assert!(matches!(map_write_error(error(409)), SyncError::ParentMissing(_)));
+        assert!(matches!(map_write_error(error(423)), SyncError::Other(_)));
+        assert!(matches!(map_write_error(error(507)), SyncError::Network(_)));
+        // 读取类请求的 409 不表示父目录不存在
+        assert!(!SyncError::from(error(409)).is_dir_missing());
+    }
+
+    #[test]
+    fn test_list_path_without_root() {
+        assert_eq!(relative_list_path("/docs/a.txt", ""), "docs/a.txt");
+        assert_eq!(relative_list_path("docs/sub/", ""), "docs/sub");
+        assert_eq!(relative_list_path("/docs/my%20file.txt", ""), "docs/my file.txt");
+        assert_eq!(relative_list_path("/", ""), "");
+    }
+
+    #[test]
+    fn test_list_path_with_root() {
+        // 服务器返回包含 root 的完整路径
+        assert_eq!(relative_list_path("/backup/docs/a.txt", "/backup/"), "docs/a.txt");
+        assert_eq!(relative_list_path("backup/", "backup"), "");
+        // 服务器已返回相对路径
+        assert_eq!(relative_list_path("docs/a.txt", "backup"), "docs/a.txt");
+        // 只按完整路径段剥离
+        assert_eq!(relative_list_path("backups/a.txt", "backup"), "backups/a.txt");
+        // 多级 root，含反斜杠
+        assert_eq!(relative_list_path("/sync/data/x/y.bin", "\\sync\\data"), "x/y.bin");
+    }
+
+    #[test]
+    fn test_dir_listed_twice_is_merged() {
+        let listed = ["/backup/docs", "/backup/docs/", "/backup/docs/a.txt"]
+            .into_iter()
+            .map(|p| FileInfo {
+                path: relative_list_path(p, "backup"),
+                size: 0,
+                modified_time: 0,
+                is_dir: !p.ends_with(".txt"),
+                checksum: None,
+                hidden: false,
+            })
+            .collect();
+        let files = dedup_listing(listed);
+        let paths: Vec<_> = files.iter().map(|f| (f.path.as_str(), f.is_dir)).collect();
+        assert_eq!(paths, vec![("docs", true), ("docs/a.txt", false)]);
+    }
+
+    #[test]
+    fn test_list_path_round_trips() {
+        for root in ["", "backup", "/sync/data/"] {
+            let listed = relative_list_path("/sync/data/dir//./file.txt", root);
+            // list 返回的路径经过 normalize_path 后不变，stat/read/write 使用同一路径
+            assert_eq!(normalize_path(&listed), listed);
+        }
+    }
+}
@@ -14,7 +14,7 @@ import {
   ArrowLeftRight,
   RotateCcw,
 } from "lucide-react";
-import { cn, getStorageTypeLabel, getSyncModeLabel } from "./lib/utils";
+import { cn, formatEta, getStorageTypeLabel, getSyncModeLabel } from "./lib/utils";
 import { NEW_JOB_THRESHOLD_SECONDS } from "./lib/constants";
 import {
   CreateJobDialog,
@@ -641,6 +641,11 @@ function App() {
                                 : "")}
                           </span>
                           <div className="flex items-center gap-2 flex-shrink-0">
+                            {/* 扫描剩余时间（按上次同步的条目数估计） */}
+                            {jobProgress.status === "scanning" &&
+                              jobProgress.scanEtaSecs != null && (
+                                <span>约剩 {formatEta(jobProgress.scanEtaSecs)}</span>
+                              )}
                             {/* 已传输字节数（带动画） */}
                             {jobProgress.bytesTotal > 0 && (
                               <AnimatedBytes 
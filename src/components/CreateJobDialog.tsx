@@ -61,6 +61,12 @@ const STORAGE_ICONS: Record<StorageType, React.ReactNode> = {
   local: <Folder className="w-4 h-4" />,
   s3: <Cloud className="w-4 h-4" />,
   webdav: <Server className="w-4 h-4" />,
+  b2: <Cloud className="w-4 h-4" />,
+  http: <Server className="w-4 h-4" />,
+  onedrive: <Cloud className="w-4 h-4" />,
+  dropbox: <Cloud className="w-4 h-4" />,
+  azblob: <Cloud className="w-4 h-4" />,
+  gcs: <Cloud className="w-4 h-4" />,
 };
 
 export function CreateJobDialog({
@@ -29,6 +29,7 @@ export interface DiffAction {
   reverse: boolean; // true = 从目标到源
   sourceExists: boolean;
   destExists: boolean;
+  reason: string; // 归为该动作的原因，如 "源文件较新（新 3小时）"
 }
 
 export interface DiffResult {
@@ -42,6 +43,7 @@ export interface DiffResult {
   skipCount: number;
   conflictCount: number;
   totalBytes: number;
+  deletePaths: string[]; // 执行前需要确认的删除路径（含类型不一致时被替换的目标条目）
   /** 源缓存时间（Unix时间戳，0表示未使用缓存） */
   sourceCachedAt: number;
   /** 目标缓存时间（Unix时间戳，0表示未使用缓存） */
@@ -387,7 +389,7 @@ export function DiffViewDialog({
                   </div>
 
                   {/* 操作指示 */}
-                  <div className="flex items-center gap-1.5 px-2">
+                  <div className="flex items-center gap-1.5 px-2" title={action.reason}>
                     {getActionIcon(action)}
                     <ArrowRight
                       className={cn(
@@ -146,6 +146,11 @@ export function HistoryPanel({
                       <p className="text-slate-500">复制</p>
                       <p className="font-medium text-slate-900 dark:text-white">
                         {entry.files_copied}
+                        {!!entry.files_copied_to_source && (
+                          <span className="ml-1 text-slate-500">
+                            (→目标 {entry.files_copied_to_dest ?? 0} / ←源 {entry.files_copied_to_source})
+                          </span>
+                        )}
                       </p>
                     </div>
                     {entry.files_deleted !== null && (
@@ -1,28 +1,68 @@
 // 存储类型
-export type StorageType = "local" | "s3" | "webdav";
+export type StorageType = "local" | "s3" | "webdav" | "b2" | "http" | "onedrive" | "dropbox" | "azblob" | "gcs";
 
 // 同步模式
-export type SyncMode = "bidirectional" | "mirror" | "backup";
+export type SyncMode = "bidirectional" | "mirror" | "backup" | "seed";
+
+// 目标路径大小写转换
+export type CaseFold = "preserve" | "lower" | "upper";
 
 // 存储配置
 export interface StorageConfig {
   type: StorageType;
   // 本地存储
   path?: string;
+  mountMarker?: string; // 挂载标记文件，不存在时视为未挂载
   // S3 配置
   bucket?: string;
   region?: string;
   accessKey?: string;
   secretKey?: string;
   endpoint?: string;
+  forcePathStyle?: boolean; // S3 路径风格地址，未设置时自定义 endpoint 默认开启
   prefix?: string;
   // WebDAV 配置
   webdavEndpoint?: string;
   username?: string;
   password?: string;
   root?: string;
+  // B2 配置（bucket/prefix 与 S3 共用）
+  keyId?: string;
+  applicationKey?: string;
+  bucketId?: string;
+  // HTTP 只读源（username/password 与 WebDAV 共用）
+  httpUrl?: string;
+  // OneDrive（Microsoft Graph，root 与 WebDAV 共用）
+  // Dropbox 共用 clientId（App Key）/ clientSecret（App Secret）/ refreshToken / root
+  clientId?: string;
+  clientSecret?: string;
+  refreshToken?: string;
+  tenant?: string; // 默认 common
+  driveId?: string; // SharePoint 文档库 ID，为空时使用当前用户的 OneDrive
+  // Azure Blob（容器名与 S3 bucket 共用，endpoint/prefix 同 S3）
+  accountName?: string;
+  accountKey?: string;
+  sasToken?: string; // 与 accountKey 二选一
+  // GCS（bucket/prefix/endpoint 同 S3；无服务账号凭据时用 accessKey/secretKey 作为 HMAC 密钥）
+  serviceAccountJson?: string; // 支持 ${file:/path} 引用
+  // 远程存储请求标记（附加到 User-Agent 并作为 X-SyncTools-Tag 请求头发送）
+  requestTag?: string;
+  // S3 对象自定义元数据，上传时附加到每个对象
+  objectMetadata?: Record<string, string>;
+}
+
+// 附加源（挂载到合并命名空间的子路径下）
+export interface AdditionalSource {
+  prefix: string;
+  config: StorageConfig;
 }
 
+// 排除规则预设
+export type ExcludePreset = "code" | "media" | "documents" | "none";
+
+// 冲突解决方式
+export type ConflictResolution = "keep_source" | "keep_dest" | "keep_both" | "skip" | "newer_wins";
+
 // 同步任务
 export interface SyncJob {
   id: string;
@@ -32,6 +72,24 @@ export interface SyncJob {
   syncMode: SyncMode;
   schedule?: string | null;
   enabled: boolean;
+  runAfter?: string | null; // 前置任务 ID，前置任务成功完成后自动执行
+  runAfterAlways?: boolean; // 前置任务失败时是否仍然执行
+  maxRuntimeSecs?: number | null; // 最长运行时间（秒），超时后自动取消
+  additionalSources?: AdditionalSource[]; // 附加源，与主源合并后同步到同一目标
+  chunkSizeMb?: number | null; // 分块大小（MB），为空时使用全局传输配置
+  streamThresholdMb?: number | null; // 流式传输阈值（MB），为空时使用全局传输配置
+  excludePreset?: ExcludePreset; // 排除规则预设，默认 code
+  excludePatterns?: string[]; // 自定义排除规则（glob），与预设合并
+  appendOnly?: boolean; // 仅追加模式：目标是源的前缀时只上传新增部分
+  preCommand?: string | null; // 同步前执行的命令，失败时不执行同步（需在设置中允许钩子）
+  postCommand?: string | null; // 同步后执行的命令，SYNCTOOLS_STATUS 环境变量为同步结果
+  compareEtags?: boolean; // 两边都是 S3 兼容存储时直接比较 ETag
+  maxRemoteOps?: number | null; // 扫描和传输共享的远程操作并发上限，为空时不限制
+  defaultConflictResolution?: ConflictResolution | null; // 冲突的默认解决方式，为空时需要手动处理
+  readConcurrency?: number | null; // 同时读取源文件的传输数，为空时使用传输并发数
+  writeConcurrency?: number | null; // 同时写入目标的传输数，为空时使用传输并发数
+  checkBeforeRun?: boolean; // 定时触发时先检查存储连接，不可用时稍后重试
+  caseFold?: CaseFold; // 目标路径大小写转换，默认 preserve，双向同步时不生效
   createdAt?: number;
   updatedAt?: number;
 }
@@ -41,6 +99,7 @@ export interface SyncProgress {
   jobId: string;
   status:
     | "idle"
+    | "queued"
     | "scanning"
     | "comparing"
     | "syncing"
@@ -60,6 +119,9 @@ export interface SyncProgress {
   speed: number;
   startTime: number;
   endTime: number;  // 完成时间（0 表示未完成）
+  scanEtaSecs?: number | null; // 扫描阶段剩余时间估计（秒），无法估计时为空
+  filesCopiedToSource?: number; // 已从目标复制到源的文件数
+  filesCopiedToDest?: number; // 已从源复制到目标的文件数
 }
 
 // 同步历史记录
@@ -71,6 +133,8 @@ export interface SyncHistoryEntry {
   status: string;
   files_scanned: number;
   files_copied: number;
+  files_copied_to_source: number | null; // 从目标复制到源（旧记录为空）
+  files_copied_to_dest: number | null; // 从源复制到目标（旧记录为空）
   files_deleted: number | null;
   files_skipped: number | null;
   files_failed: number | null;
@@ -85,6 +149,26 @@ export interface TestConnectionResult {
   details: string | null;
 }
 
+// rclone 配置导入
+export interface RcloneRemote {
+  name: string;
+  remoteType: string; // rclone 中的类型
+  config: StorageConfig;
+  missing: string[]; // 创建任务前需要补充的字段（如 bucket）
+}
+
+export interface SkippedRemote {
+  name: string;
+  remoteType: string;
+  reason: string;
+}
+
+export interface RcloneImport {
+  path: string;
+  remotes: RcloneRemote[];
+  skipped: SkippedRemote[];
+}
+
 // 日志配置
 export interface LogConfig {
   enabled: boolean;
@@ -95,10 +179,242 @@ export interface LogConfig {
 // 缓存配置
 export interface CacheConfig {
   remoteTtl: number; // 远程存储缓存 TTL（秒），本地存储不使用缓存
+  format: "json" | "binary"; // 文件列表缓存格式
+}
+
+// 存储性能测试结果（benchmark_storage 返回）
+export interface BenchmarkResult {
+  storage: string;
+  sizeBytes: number;
+  listMs: number; // 列出测试目录的耗时
+  uploadMs: number;
+  uploadMbps: number; // 上传速度（MB/s）
+  downloadMs: number;
+  downloadMbps: number; // 下载速度（MB/s）
+  deleteMs: number;
+}
+
+// 存储类型及其支持的功能（list_storage_types 返回）
+export interface StorageTypeInfo {
+  type: StorageType;
+  displayName: string;
+  requiredFields: string[]; // 必填的配置字段
+  supportsStreaming: boolean;
+  supportsRename: boolean;
+  supportsChecksums: boolean;
+  readOnly: boolean;
+}
+
+// 缓存目录状态（get_cache_status 返回）
+export interface CacheStatus {
+  cacheDir: string;
+  writable: boolean; // 不可写时缓存被禁用，每次同步都会完整扫描
+  error?: string | null;
+}
+
+// 任务实际使用的扫描配置
+export interface EffectiveScanConfig {
+  includeDirs: boolean;
+  excludePatterns: string[]; // 预设与自定义规则合并后的排除规则
+  maxFileSize: number;
+  includeExtensions: string[];
+  concurrency: number;
+  listPageSize: number | null;
+  localChecksums: boolean;
+  checksumMaxSize: number;
+  etagChecksums: boolean;
+  skipHidden: boolean;
+}
+
+// 任务实际使用的同步配置（全局配置 + 任务级设置）
+export interface EffectiveSyncConfig {
+  maxConcurrentTransfers: number;
+  largeFileThreshold: number; // 字节
+  chunkSize: number; // 字节
+  maxRetries: number;
+  retryBaseDelayMs: number;
+  enableResume: boolean;
+  scanConfig: EffectiveScanConfig;
+  autoCreateDir: boolean;
+  conflictResolutions: Record<string, string>;
+  forceRefresh: boolean;
+  cacheDir: string | null;
+  remoteCacheTtl: number; // 秒
+  cacheFormat: "json" | "binary";
+  allowEmptySource: boolean;
+  assumeEmptyDest: boolean; // 视目标为空，跳过目标扫描（目标顶层为空时自动启用；目标不为空或双向同步时忽略）
+  maxRuntimeSecs: number | null;
+  confirmedDeletes: string[] | null;
+  failFast: boolean;
+  protectPatterns: string[];
+  autoResolveIdenticalConflicts: boolean;
+  deleteAfterCopy: boolean;
+  detectRenames: boolean;
+  overwritePolicy: "always" | "newer-only" | "larger-only";
+  zeroByteCompare: "normal" | "always-equal";
+  skipLockedFiles: boolean;
+  speedSmoothing: number;
+  instantSpeed: boolean;
+  preserveXattrs: boolean;
+}
+
+// 按目录汇总的差异（统计包含子目录中的文件）
+export interface DirSummary {
+  path: string; // 目录路径，根目录为空字符串
+  copyCount: number;
+  deleteCount: number;
+  skipCount: number;
+  conflictCount: number;
+  totalBytes: number; // 需要传输的字节数
+  children: DirSummary[];
+}
+
+// 孤立数据清理结果
+export interface GcReport {
+  cacheJobIds: string[]; // 已删除任务残留的缓存文件所属任务 ID
+  fileStatesRemoved: number;
+  transferStatesRemoved: number;
+  tempFilesRemoved: number; // 删除的遗留传输临时文件数
 }
 
 // 传输配置
 export interface TransferConfig {
   chunkSizeMb: number; // 分块大小（MB），默认 8
   streamThresholdMb: number; // 启用流式传输阈值（MB），默认 128
+  deleteAfterCopy: boolean; // 所有复制完成后再执行删除
+  detectRenames: boolean; // 识别移动的文件，在目标内重命名而不是重新上传
+  overwritePolicy: "always" | "newer-only" | "larger-only"; // 备份模式覆盖策略
+  zeroByteCompare: "normal" | "always-equal"; // 两边都是空文件时的比较方式
+  skipLockedFiles: boolean; // 文件被其他程序占用时跳过而不是计为失败
+  speedSmoothing: number; // 速度平滑系数（0~1，新速度的权重），默认 0.3
+  instantSpeed: boolean; // 显示瞬时速度而不是平滑后的速度
+  bandwidthSchedule: BandwidthWindow[]; // 按时间段限速，不在任何时间段内时不限速
+  preserveXattrs: boolean; // 本地到本地同步时复制扩展属性（xattr）
+  retryBudget: number; // 一次同步中所有文件共享的重试总次数，0 表示不限制
+  pruneEmptyDirs: boolean; // 镜像同步后删除因删除文件而变空的目标目录（对象存储无效）
+  pruneExistingEmptyDirs: boolean; // 清理空目录时同时删除同步前就已为空的目录
+  tempFilePrefix: string; // 流式传输临时文件的名称前缀，默认 "synctools_"
+  tempFileKeepExtension: boolean; // 临时文件名保留原扩展名（<前缀><id>.<扩展名>.tmp）
+}
+
+// 限速时间段（本地时间）
+export interface BandwidthWindow {
+  start: string; // "HH:MM"
+  end: string; // "HH:MM"，早于开始时间时跨越午夜
+  maxBytesPerSec: number; // 速度上限（字节/秒），0 表示不限制
+}
+
+// 扫描配置
+export interface ScannerConfig {
+  concurrency: number; // 扫描并发数，默认 8
+  listPageSize: number; // 列表分页大小，0 表示使用后端默认值
+  localChecksums: boolean; // 是否为本地存储计算内容校验和
+  checksumMaxSizeMb: number; // 计算校验和的最大文件大小（MB），0 表示不限制
+  etagChecksums: boolean; // 目标为 S3/GCS 时用本地 MD5 与 ETag/md5Hash 比较（分段上传的对象回退到大小/时间比较）
+  skipHidden: boolean; // 是否跳过隐藏文件（"." 开头及 Windows 隐藏/系统文件）
+  maxTreeFiles: number; // 单侧列出的最大条目数（含目录和被排除的文件），0 表示不限制
+  scanTimeoutSecs: number; // 单侧扫描的最长时间（秒），0 表示不限制
+}
+
+// 调度配置
+export interface SchedulerConfig {
+  maxConcurrentJobs: number; // 同时运行的同步任务上限，0 表示不限制
+  checkRetries: number; // 定时触发前连接检查失败后的重试次数，用尽后照常同步并记录失败
+  checkRetrySecs: number; // 定时触发前连接检查的重试间隔（秒）
+}
+
+// 任务钩子配置
+export interface HookConfig {
+  allowHooks: boolean; // 是否允许执行任务的前置/后置命令
+  timeoutSecs: number; // 单个钩子命令的超时时间（秒）
+}
+
+// 扫描得到的文件条目（scan_endpoint 返回，字段为 snake_case）
+export interface FileInfo {
+  path: string;
+  size: number;
+  modified_time: number;
+  is_dir: boolean;
+  checksum: string | null;
+  hidden: boolean;
+}
+
+// 单文件传输结果（诊断用）
+export interface SingleFileResult {
+  path: string;
+  success: boolean;
+  size: number;
+  bytesTransferred: number;
+  hash?: string | null;
+  retries: number;
+  durationMs: number;
+  error?: string | null;
+}
+
+// 重复文件组
+export interface DuplicateGroup {
+  hash: string;
+  size: number;
+  paths: string[];
+  wastedBytes: number; // 保留一份后可节省的字节数
+}
+
+// 重复文件检测结果
+export interface DuplicateReport {
+  groups: DuplicateGroup[];
+  filesChecked: number;
+  totalWastedBytes: number;
+}
+
+// 目标与上次同步不一致的文件
+export interface DriftedFile {
+  path: string;
+  kind: "missing" | "size_mismatch" | "checksum_mismatch" | "unreadable";
+  expectedSize: number;
+  actualSize?: number | null;
+  detail?: string | null;
+}
+
+// 目标校验结果
+export interface VerifyReport {
+  filesChecked: number;
+  sizeOnly: number; // 没有校验和记录、只比较了大小的文件数
+  drifted: DriftedFile[];
+}
+
+// 重建文件状态的结果
+export interface RehashReport {
+  filesChecked: number;
+  unchanged: number;
+  updated: number; // 按当前内容更新的记录数
+  removed: number; // 删除的记录数（源已删除、两边不一致或无法读取）
+}
+
+// 首次上传量估算（目标视为空）
+export interface UploadEstimate {
+  sourceName: string;
+  fileCount: number;
+  dirCount: number;
+  totalBytes: number;
+  sourceCachedAt: number; // 源缓存时间（0 表示未使用缓存）
+}
+
+// 同步报告
+export interface SyncReport {
+  jobId: string;
+  startTime: number;
+  endTime: number;
+  status: SyncProgress["status"];
+  filesScanned: number;
+  filesCopied: number;
+  filesCopiedToSource: number; // 从目标复制到源（双向同步的反向复制）
+  filesCopiedToDest: number; // 从源复制到目标
+  filesDeleted: number;
+  filesSkipped: number;
+  filesFailed: number;
+  bytesTransferred: number;
+  duration: number;
+  errors: string[];
+  skippedLocked: string[]; // 因被其他程序占用而跳过的文件
+  logId?: number | null; // 对应的同步历史记录 ID
 }
@@ -25,12 +25,25 @@ export function formatTime(timestamp: number): string {
   });
 }
 
+// 格式化剩余时间（秒）
+export function formatEta(seconds: number): string {
+  if (seconds < 60) return `${seconds} 秒`;
+  if (seconds < 3600) return `${Math.floor(seconds / 60)} 分 ${seconds % 60} 秒`;
+  return `${Math.floor(seconds / 3600)} 小时 ${Math.floor((seconds % 3600) / 60)} 分`;
+}
+
 // 存储类型标签
 export function getStorageTypeLabel(type: StorageType): string {
   const labels: Record<StorageType, string> = {
     local: "本地",
     s3: "S3",
     webdav: "WebDAV",
+    b2: "B2",
+    http: "HTTP",
+    onedrive: "OneDrive",
+    dropbox: "Dropbox",
+    azblob: "Azure Blob",
+    gcs: "GCS",
   };
   return labels[type] || type;
 }